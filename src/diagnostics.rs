@@ -0,0 +1,91 @@
+use oxc_diagnostics::Error;
+use oxc_span::Span;
+
+/// Stable error codes. The string form is part of the public output so never renumber these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticCode {
+    /// The input could not be parsed at all
+    ParseError,
+    /// The input contains syntax that this tool does not support (TS, JSX, `using`, etc)
+    UnsupportedSyntax,
+    /// A transform bumped into a construct that it can not (yet) rewrite. The node is left as is.
+    UnsupportedTransform,
+    /// A transform found a node that an earlier transform should have eliminated
+    UnexpectedNode,
+    /// Something went wrong that we did not anticipate at all
+    InternalError,
+    /// The code uses syntax that the validation profile does not allow (see `validator.rs`)
+    ProfileViolation,
+    /// The generated code does not parse. That's a bug in a transform.
+    InvalidOutput,
+    /// A visitor kept asking the mapper to revisit the same node (see `Mapper::set_max_revisits`)
+    RevisitLimit,
+    /// The input is nested deeper than the mapper goes (see `check_nesting`), so it is not transformed. When a
+    /// transform nests deeper than that, the mapper leaves the deeper part as is (see `Mapper::set_max_depth`).
+    NestingLimit,
+    /// The passes are in an order that can't work, or they kept changing the code (see `PassManager`)
+    PassPipeline,
+    /// An option has a value that can't work (see `TransformOptions::check`). Nothing is transformed.
+    InvalidOption,
+}
+
+impl DiagnosticCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::ParseError => "ZS001",
+            DiagnosticCode::UnsupportedSyntax => "ZS002",
+            DiagnosticCode::UnsupportedTransform => "ZS003",
+            DiagnosticCode::UnexpectedNode => "ZS004",
+            DiagnosticCode::InternalError => "ZS005",
+            DiagnosticCode::ProfileViolation => "ZS006",
+            DiagnosticCode::InvalidOutput => "ZS007",
+            DiagnosticCode::RevisitLimit => "ZS008",
+            DiagnosticCode::NestingLimit => "ZS009",
+            DiagnosticCode::PassPipeline => "ZS010",
+            DiagnosticCode::InvalidOption => "ZS011",
+        }
+    }
+}
+
+/// A problem found while parsing or transforming. Only knows about the source span. Line and
+/// column are resolved against the source once at the end (see `resolve_line_column`).
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub code: DiagnosticCode,
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn new(code: DiagnosticCode, message: String, span: Span) -> Self {
+        Self { code, message, span }
+    }
+
+    /// Convert an oxc parser error. The span is that of its first label, if any.
+    pub fn from_parse_error(code: DiagnosticCode, error: &Error) -> Self {
+        let span = error.labels()
+            .and_then(|mut labels| labels.next())
+            .map(|label| Span::new(label.offset() as u32, (label.offset() + label.len()) as u32))
+            .unwrap_or_default();
+        Self::new(code, error.to_string(), span)
+    }
+}
+
+/// Convert a byte offset into a one-based line and column. The column counts chars, not bytes.
+pub fn resolve_line_column(source: &str, offset: u32) -> (u32, u32) {
+    let offset = (offset as usize).min(source.len());
+    let mut line = 1;
+    let mut column = 1;
+    for (index, c) in source.char_indices() {
+        if index >= offset {
+            break;
+        }
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
@@ -0,0 +1,206 @@
+use oxc_allocator::Allocator;
+use oxc_allocator::Vec as OxcVec;
+use oxc_ast::ast::*;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+use wasm_bindgen::prelude::*;
+
+use crate::source_map::count_statements;
+use crate::source_map::json_string;
+
+/// Some transforms replace syntax with a call to a small runtime function. These are those functions.
+/// The transforms record which ones they used in the `MapperState` (see `use_helper`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Helper {
+    /// `$forIn(obj)`: an iterator over the keys that a `for-in` would visit. Keys that are deleted before
+    /// they are reached are skipped, like `for-in` does, but keys that are added are not visited.
+    ForIn,
+    /// `$forOf(obj)`: the iterator that a `for-of` would use. Without `Symbol` it iterates obj as an array.
+    ForOf,
+    /// `$rest(obj, keys)`: a copy of the own enumerable props of obj, except for keys. For object rest patterns.
+    Rest,
+}
+
+impl Helper {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Helper::ForIn => "$forIn",
+            Helper::ForOf => "$forOf",
+            Helper::Rest => "$rest",
+        }
+    }
+
+    /// The JS source of the function declaration for this helper, declared as `name`
+    pub fn declaration(&self, name: &str) -> String {
+        // The first occurrence is the name of the function
+        self.source().replacen(self.name(), name, 1)
+    }
+
+    /// The JS source of the function declaration for this helper. The helpers are injected after the transforms
+    /// ran so they only use what every preset allows: `function`, `var` and `while (true)` with block bodies.
+    /// They only call ES5 builtins, and use `Symbol` only when it exists, so they run in an ES5 engine.
+    pub fn source(&self) -> &'static str {
+        match self {
+            Helper::ForIn => r#"
+                function $forIn(obj) {
+                    var keys = [];
+                    var from = obj === null || obj === undefined ? null : Object(obj);
+                    var seen = Object.create(null);
+                    var target = from;
+                    while (true) {
+                        if (target === null) {
+                            break;
+                        }
+                        var names = Object.getOwnPropertyNames(target);
+                        var i = 0;
+                        while (true) {
+                            if (i >= names.length) {
+                                break;
+                            }
+                            if (seen[names[i]] !== true) {
+                                seen[names[i]] = true;
+                                if (Object.prototype.propertyIsEnumerable.call(target, names[i])) {
+                                    keys.push(names[i]);
+                                }
+                            }
+                            i++;
+                        }
+                        target = Object.getPrototypeOf(target);
+                    }
+                    var index = 0;
+                    return {
+                        next: function () {
+                            while (true) {
+                                if (index >= keys.length) {
+                                    return { done: true, value: undefined };
+                                }
+                                var key = keys[index];
+                                index++;
+                                if (key in from) {
+                                    return { done: false, value: key };
+                                }
+                            }
+                        }
+                    };
+                }
+            "#,
+            Helper::ForOf => r#"
+                function $forOf(obj) {
+                    if (typeof Symbol === "function") {
+                        return obj[Symbol.iterator]();
+                    }
+                    var from = Object(obj);
+                    var index = 0;
+                    return {
+                        next: function () {
+                            if (index >= from.length) {
+                                return { done: true, value: undefined };
+                            }
+                            index++;
+                            return { done: false, value: from[index - 1] };
+                        }
+                    };
+                }
+            "#,
+            Helper::Rest => r#"
+                function $rest(obj, keys) {
+                    if (obj === null || obj === undefined) {
+                        throw new TypeError("Cannot destructure '" + obj + "' as it is " + obj + ".");
+                    }
+                    var from = Object(obj);
+                    var excluded = [];
+                    var i = 0;
+                    while (true) {
+                        if (i >= keys.length) {
+                            break;
+                        }
+                        excluded.push(typeof keys[i] === "symbol" ? keys[i] : String(keys[i]));
+                        i++;
+                    }
+                    var result = {};
+                    var own = Object.getOwnPropertyNames(from);
+                    if (typeof Object.getOwnPropertySymbols === "function") {
+                        own = own.concat(Object.getOwnPropertySymbols(from));
+                    }
+                    var j = 0;
+                    while (true) {
+                        if (j >= own.length) {
+                            break;
+                        }
+                        if (excluded.indexOf(own[j]) === -1 && Object.prototype.propertyIsEnumerable.call(from, own[j])) {
+                            result[own[j]] = from[own[j]];
+                        }
+                        j++;
+                    }
+                    return result;
+                }
+            "#,
+        }
+    }
+}
+
+/// How to make the helpers that the output depends on available
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelperMode {
+    /// Add a function declaration for each helper to the top of the program
+    Inline,
+    /// Add an `import { ... } from "<helpers_module>"` to the top of the program
+    Import,
+    /// Don't add anything. The caller makes sure they are available at runtime.
+    None,
+}
+
+/// Inject the given helpers at the start of the program body (after the directives). Each helper is
+/// declared or imported under the name it was given by `MapperState::use_helper`. A script can't
+/// `import` so for scripts the import mode does a `var` with a `require()` for each helper instead.
+///
+/// Returns the number of statements that were injected, counting nested ones. The source map needs to skip those.
+/// Returns an error, and injects nothing, when the prelude does not parse.
+pub fn inject_helpers<'a>(
+    program: &mut Program<'a>,
+    helpers: &[(Helper, String)],
+    mode: HelperMode,
+    module: &str,
+    source_type: SourceType,
+    allocator: &'a Allocator,
+) -> Result<usize, String> {
+    if helpers.is_empty() {
+        return Ok(0);
+    }
+
+    // Any string is a valid module specifier, as long as it's escaped
+    let module = json_string(module);
+    let prelude = match mode {
+        HelperMode::None => return Ok(0),
+        HelperMode::Inline => helpers.iter().map(|(helper, name)| helper.declaration(name)).collect::<Vec<_>>().join("\n"),
+        HelperMode::Import if source_type.is_module() => format!(
+            "import {{ {} }} from {};",
+            helpers.iter().map(|(helper, name)| {
+                if helper.name() == name { name.clone() } else { format!("{} as {}", helper.name(), name) }
+            }).collect::<Vec<_>>().join(", "),
+            module
+        ),
+        // Not a destructuring `const` since that is not allowed in the output either
+        HelperMode::Import => helpers.iter().map(|(helper, name)| {
+            format!("var {} = require({}).{};", name, module, helper.name())
+        }).collect::<Vec<_>>().join("\n"),
+    };
+
+    // Parsing our own source is simpler than building these with the builder. The spans point into the
+    // prelude, not the input, which is why the source map must skip these statements.
+    let prelude: &'a str = allocator.alloc_str(&prelude);
+    let parsed = Parser::new(allocator, prelude, source_type).parse();
+    if let Some(error) = parsed.errors.first() {
+        return Err(format!("The helper prelude does not parse: {}", error));
+    }
+
+    let injected_count = count_statements(&parsed.program);
+
+    let mut body = OxcVec::with_capacity_in(parsed.program.body.len() + program.body.len(), allocator);
+    body.extend(parsed.program.body);
+    body.extend(std::mem::replace(&mut program.body, OxcVec::new_in(allocator)));
+    program.body = body;
+
+    Ok(injected_count)
+}
@@ -0,0 +1,566 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_parser::Parser;
+use oxc_span::GetSpan;
+use oxc_span::Span;
+use oxc_syntax::operator::{AssignmentOperator, BinaryOperator, LogicalOperator};
+
+use crate::diagnostics::{resolve_line_column, Diagnostic, DiagnosticCode};
+use crate::mapper::DEFAULT_MAX_DEPTH;
+use crate::options::{Preset, TransformOptions};
+use crate::source_map::json_string;
+use crate::walker::{check_nesting, create_walker, Node};
+use crate::TransformError;
+
+/// The syntax that the inventory counts. Mostly what ES2015 and later added, and whatever the presets
+/// eliminate. The order is the order of the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Feature {
+    Switch,
+    Continue,
+    Finally,
+    ForLoop,
+    ForIn,
+    ForOf,
+    ForAwait,
+    DoWhile,
+    While,
+    WhileTrue,
+    Label,
+    With,
+    Debugger,
+    Let,
+    Const,
+    DestructuringDeclaration,
+    DestructuringAssignment,
+    DestructuringParameter,
+    DefaultParameter,
+    Rest,
+    Spread,
+    Arrow,
+    Class,
+    Async,
+    Await,
+    Generator,
+    Yield,
+    OptionalChaining,
+    NullishCoalescing,
+    LogicalAssignment,
+    Exponentiation,
+    TemplateLiteral,
+    TaggedTemplate,
+    Eval,
+    DynamicImport,
+    MetaProperty,
+    BigInt,
+    Import,
+    Export,
+}
+
+impl Feature {
+    /// The id in the report, like "for-of"
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Feature::Switch => "switch",
+            Feature::Continue => "continue",
+            Feature::Finally => "finally",
+            Feature::ForLoop => "for",
+            Feature::ForIn => "for-in",
+            Feature::ForOf => "for-of",
+            Feature::ForAwait => "for-await",
+            Feature::DoWhile => "do-while",
+            Feature::While => "while",
+            Feature::WhileTrue => "while-true",
+            Feature::Label => "label",
+            Feature::With => "with",
+            Feature::Debugger => "debugger",
+            Feature::Let => "let",
+            Feature::Const => "const",
+            Feature::DestructuringDeclaration => "destructuring-declaration",
+            Feature::DestructuringAssignment => "destructuring-assignment",
+            Feature::DestructuringParameter => "destructuring-parameter",
+            Feature::DefaultParameter => "default-parameter",
+            Feature::Rest => "rest",
+            Feature::Spread => "spread",
+            Feature::Arrow => "arrow",
+            Feature::Class => "class",
+            Feature::Async => "async",
+            Feature::Await => "await",
+            Feature::Generator => "generator",
+            Feature::Yield => "yield",
+            Feature::OptionalChaining => "optional-chaining",
+            Feature::NullishCoalescing => "nullish-coalescing",
+            Feature::LogicalAssignment => "logical-assignment",
+            Feature::Exponentiation => "exponentiation",
+            Feature::TemplateLiteral => "template-literal",
+            Feature::TaggedTemplate => "tagged-template",
+            Feature::Eval => "eval",
+            Feature::DynamicImport => "dynamic-import",
+            Feature::MetaProperty => "meta-property",
+            Feature::BigInt => "bigint",
+            Feature::Import => "import",
+            Feature::Export => "export",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Feature::Switch => "`switch` statement",
+            Feature::Continue => "`continue` statement",
+            Feature::Finally => "`finally` block",
+            Feature::ForLoop => "`for` loop",
+            Feature::ForIn => "`for-in` loop",
+            Feature::ForOf => "`for-of` loop",
+            Feature::ForAwait => "`for await` loop",
+            Feature::DoWhile => "`do-while` loop",
+            Feature::While => "`while` loop with a test other than `true`",
+            Feature::WhileTrue => "`while (true)` loop",
+            Feature::Label => "Labeled statement",
+            Feature::With => "`with` statement",
+            Feature::Debugger => "`debugger` statement",
+            Feature::Let => "`let` declaration",
+            Feature::Const => "`const` declaration",
+            Feature::DestructuringDeclaration => "Destructuring pattern in a variable declaration",
+            Feature::DestructuringAssignment => "Destructuring pattern in an assignment",
+            Feature::DestructuringParameter => "Destructuring pattern in a parameter or catch clause",
+            Feature::DefaultParameter => "Parameter with a default value",
+            Feature::Rest => "Rest element in a pattern or the parameters",
+            Feature::Spread => "Spread in an array, object or arguments",
+            Feature::Arrow => "Arrow function",
+            Feature::Class => "Class",
+            Feature::Async => "Async function, arrow or method",
+            Feature::Await => "`await` expression",
+            Feature::Generator => "Generator function or method",
+            Feature::Yield => "`yield` expression",
+            Feature::OptionalChaining => "Optional chaining (`a?.b`)",
+            Feature::NullishCoalescing => "Nullish coalescing (`a ?? b`)",
+            Feature::LogicalAssignment => "Logical assignment (`a ||= b`)",
+            Feature::Exponentiation => "Exponentiation (`a ** b`)",
+            Feature::TemplateLiteral => "Template literal",
+            Feature::TaggedTemplate => "Tagged template",
+            Feature::Eval => "Call to `eval`",
+            Feature::DynamicImport => "Dynamic `import()`",
+            Feature::MetaProperty => "`new.target` or `import.meta`",
+            Feature::BigInt => "BigInt literal",
+            Feature::Import => "`import` declaration",
+            Feature::Export => "`export` declaration",
+        }
+    }
+
+    /// The least aggressive preset that eliminates this feature, if any does
+    pub fn preset(&self) -> Option<Preset> {
+        match self {
+            Feature::ForOf | Feature::DestructuringDeclaration => Some(Preset::Es5),
+            Feature::Switch | Feature::Continue | Feature::Finally | Feature::ForLoop | Feature::ForIn | Feature::DoWhile => Some(Preset::Js0),
+            Feature::While => Some(Preset::Misc),
+            _ => None,
+        }
+    }
+}
+
+/// Every occurrence of a feature in the program, in walk order
+pub fn find_features(program: &Program) -> Vec<(Feature, Span)> {
+    let found: Rc<RefCell<Vec<(Feature, Span)>>> = Rc::new(RefCell::new(vec!()));
+    let found_closure = Rc::clone(&found);
+
+    let mut walker = create_walker();
+    walker.add_visitor_before(move |node| {
+        let mut found = found_closure.borrow_mut();
+        let mut add = |feature: Feature, span: Span| found.push((feature, span));
+        match node {
+            Node::Statement(stmt) => statement_features(stmt, &mut add),
+            Node::Expression(expr) => expression_features(expr, &mut add),
+        }
+    });
+    walker.walk(program);
+
+    let found = found.borrow().clone();
+    found
+}
+
+// The walker only has nodes for statements and expressions. Whatever is in the declarations, patterns and
+// functions that are part of a statement (or expression) is found from there.
+fn statement_features(stmt: &Statement, add: &mut impl FnMut(Feature, Span)) {
+    match stmt {
+        Statement::SwitchStatement(switch_stmt) => add(Feature::Switch, switch_stmt.span),
+        Statement::ContinueStatement(continue_stmt) => add(Feature::Continue, continue_stmt.span),
+        Statement::TryStatement(try_stmt) => {
+            if let Some(param) = try_stmt.handler.as_ref().and_then(|handler| handler.param.as_ref()) {
+                if is_destructuring(param) {
+                    add(Feature::DestructuringParameter, param.kind.span());
+                }
+                pattern_features(param, add);
+            }
+            if let Some(finalizer) = &try_stmt.finalizer {
+                add(Feature::Finally, finalizer.span);
+            }
+        }
+        Statement::ForStatement(for_stmt) => {
+            add(Feature::ForLoop, for_stmt.span);
+            if let Some(ForStatementInit::VariableDeclaration(decl)) = &for_stmt.init {
+                variable_declaration_features(decl, add);
+            }
+        }
+        Statement::ForInStatement(for_in) => {
+            add(Feature::ForIn, for_in.span);
+            for_left_features(&for_in.left, add);
+        }
+        Statement::ForOfStatement(for_of) => {
+            add(if for_of.r#await { Feature::ForAwait } else { Feature::ForOf }, for_of.span);
+            for_left_features(&for_of.left, add);
+        }
+        Statement::DoWhileStatement(do_while) => add(Feature::DoWhile, do_while.span),
+        Statement::WhileStatement(while_stmt) => match &while_stmt.test {
+            Expression::BooleanLiteral(bool_lit) if bool_lit.value => add(Feature::WhileTrue, while_stmt.span),
+            _ => add(Feature::While, while_stmt.span),
+        },
+        Statement::LabeledStatement(labeled) => add(Feature::Label, labeled.span),
+        Statement::WithStatement(with_stmt) => add(Feature::With, with_stmt.span),
+        Statement::DebuggerStatement(debugger) => add(Feature::Debugger, debugger.span),
+        Statement::Declaration(decl) => declaration_features(decl, add),
+        Statement::ModuleDeclaration(module_decl) => match &**module_decl {
+            ModuleDeclaration::ImportDeclaration(import) => add(Feature::Import, import.span),
+            ModuleDeclaration::ExportAllDeclaration(export) => add(Feature::Export, export.span),
+            ModuleDeclaration::ExportDefaultDeclaration(export) => {
+                add(Feature::Export, export.span);
+                match &export.declaration {
+                    ExportDefaultDeclarationKind::FunctionDeclaration(func) => function_features(func, add),
+                    ExportDefaultDeclarationKind::ClassDeclaration(class) => class_features(class, add),
+                    _ => (),
+                }
+            }
+            ModuleDeclaration::ExportNamedDeclaration(export) => {
+                add(Feature::Export, export.span);
+                if let Some(decl) = &export.declaration {
+                    declaration_features(decl, add);
+                }
+            }
+            ModuleDeclaration::TSExportAssignment(_) => (),
+            ModuleDeclaration::TSNamespaceExportDeclaration(_) => (),
+        },
+        _ => (),
+    }
+}
+
+fn expression_features(expr: &Expression, add: &mut impl FnMut(Feature, Span)) {
+    match expr {
+        Expression::ArrowExpression(arrow) => {
+            add(Feature::Arrow, arrow.span);
+            if arrow.r#async {
+                add(Feature::Async, arrow.span);
+            }
+            parameter_features(&arrow.params, add);
+        }
+        Expression::FunctionExpression(func) => function_features(func, add),
+        Expression::ClassExpression(class) => class_features(class, add),
+        Expression::AwaitExpression(await_expr) => add(Feature::Await, await_expr.span),
+        Expression::YieldExpression(yield_expr) => add(Feature::Yield, yield_expr.span),
+        Expression::ChainExpression(chain) => add(Feature::OptionalChaining, chain.span),
+        Expression::LogicalExpression(logical) if logical.operator == LogicalOperator::Coalesce => add(Feature::NullishCoalescing, logical.span),
+        Expression::BinaryExpression(binary) if binary.operator == BinaryOperator::Exponential => add(Feature::Exponentiation, binary.span),
+        Expression::AssignmentExpression(assign) => {
+            if assign.operator.is_logical() {
+                add(Feature::LogicalAssignment, assign.span);
+            } else if assign.operator == AssignmentOperator::Exponential {
+                add(Feature::Exponentiation, assign.span);
+            }
+            if let AssignmentTarget::AssignmentTargetPattern(pattern) = &assign.left {
+                add(Feature::DestructuringAssignment, pattern.span());
+            }
+        }
+        Expression::ArrayExpression(array) => {
+            for elem in &array.elements {
+                if let ArrayExpressionElement::SpreadElement(spread) = elem {
+                    add(Feature::Spread, spread.span);
+                }
+            }
+        }
+        Expression::ObjectExpression(object) => {
+            for prop in &object.properties {
+                if let ObjectPropertyKind::SpreadProperty(spread) = prop {
+                    add(Feature::Spread, spread.span);
+                }
+            }
+        }
+        Expression::CallExpression(call) => {
+            if matches!(&call.callee, Expression::Identifier(ident) if ident.name == "eval") {
+                add(Feature::Eval, call.span);
+            }
+            argument_features(&call.arguments, add);
+        }
+        Expression::NewExpression(new_expr) => argument_features(&new_expr.arguments, add),
+        Expression::TemplateLiteral(template) => add(Feature::TemplateLiteral, template.span),
+        Expression::TaggedTemplateExpression(tagged) => add(Feature::TaggedTemplate, tagged.span),
+        Expression::ImportExpression(import) => add(Feature::DynamicImport, import.span),
+        Expression::MetaProperty(meta) => add(Feature::MetaProperty, meta.span),
+        Expression::BigintLiteral(bigint) => add(Feature::BigInt, bigint.span),
+        _ => (),
+    }
+}
+
+fn declaration_features(decl: &Declaration, add: &mut impl FnMut(Feature, Span)) {
+    match decl {
+        Declaration::VariableDeclaration(var_decl) => variable_declaration_features(var_decl, add),
+        Declaration::FunctionDeclaration(func) => function_features(func, add),
+        Declaration::ClassDeclaration(class) => class_features(class, add),
+        _ => (),
+    }
+}
+
+fn variable_declaration_features(decl: &VariableDeclaration, add: &mut impl FnMut(Feature, Span)) {
+    match decl.kind {
+        VariableDeclarationKind::Let => add(Feature::Let, decl.span),
+        VariableDeclarationKind::Const => add(Feature::Const, decl.span),
+        VariableDeclarationKind::Var => (),
+    }
+    for declarator in &decl.declarations {
+        if is_destructuring(&declarator.id) {
+            add(Feature::DestructuringDeclaration, declarator.id.kind.span());
+        }
+        pattern_features(&declarator.id, add);
+    }
+}
+
+fn for_left_features(left: &ForStatementLeft, add: &mut impl FnMut(Feature, Span)) {
+    match left {
+        ForStatementLeft::VariableDeclaration(decl) => variable_declaration_features(decl, add),
+        ForStatementLeft::AssignmentTarget(AssignmentTarget::AssignmentTargetPattern(pattern)) => add(Feature::DestructuringAssignment, pattern.span()),
+        _ => (),
+    }
+}
+
+fn function_features(func: &Function, add: &mut impl FnMut(Feature, Span)) {
+    if func.r#async {
+        add(Feature::Async, func.span);
+    }
+    if func.generator {
+        add(Feature::Generator, func.span);
+    }
+    parameter_features(&func.params, add);
+}
+
+fn class_features(class: &Class, add: &mut impl FnMut(Feature, Span)) {
+    add(Feature::Class, class.span);
+    // The walker goes into the methods but they are not expressions, so they have no node of their own
+    for element in &class.body.body {
+        if let ClassElement::MethodDefinition(method) = element {
+            function_features(&method.value, add);
+        }
+    }
+}
+
+fn parameter_features(params: &FormalParameters, add: &mut impl FnMut(Feature, Span)) {
+    for param in &params.items {
+        if is_destructuring(&param.pattern) {
+            add(Feature::DestructuringParameter, param.pattern.kind.span());
+        }
+        if let BindingPatternKind::AssignmentPattern(_) = &param.pattern.kind {
+            add(Feature::DefaultParameter, param.span);
+        }
+        pattern_features(&param.pattern, add);
+    }
+    if let Some(rest) = &params.rest {
+        add(Feature::Rest, rest.span);
+        pattern_features(&rest.argument, add);
+    }
+}
+
+fn argument_features(arguments: &[Argument], add: &mut impl FnMut(Feature, Span)) {
+    for arg in arguments {
+        if let Argument::SpreadElement(spread) = arg {
+            add(Feature::Spread, spread.span);
+        }
+    }
+}
+
+// The rest elements in the pattern, at any depth
+fn pattern_features(pattern: &BindingPattern, add: &mut impl FnMut(Feature, Span)) {
+    match &pattern.kind {
+        BindingPatternKind::ObjectPattern(obj_pattern) => {
+            for prop in &obj_pattern.properties {
+                pattern_features(&prop.value, add);
+            }
+            if let Some(rest) = &obj_pattern.rest {
+                add(Feature::Rest, rest.span);
+                pattern_features(&rest.argument, add);
+            }
+        }
+        BindingPatternKind::ArrayPattern(array_pattern) => {
+            for elem in array_pattern.elements.iter().flatten() {
+                pattern_features(elem, add);
+            }
+            if let Some(rest) = &array_pattern.rest {
+                add(Feature::Rest, rest.span);
+                pattern_features(&rest.argument, add);
+            }
+        }
+        BindingPatternKind::AssignmentPattern(assign_pattern) => pattern_features(&assign_pattern.left, add),
+        BindingPatternKind::BindingIdentifier(_) => (),
+    }
+}
+
+// An object or array pattern, with or without a default
+fn is_destructuring(pattern: &BindingPattern) -> bool {
+    match &pattern.kind {
+        BindingPatternKind::ObjectPattern(_) | BindingPatternKind::ArrayPattern(_) => true,
+        BindingPatternKind::AssignmentPattern(assign_pattern) => is_destructuring(&assign_pattern.left),
+        BindingPatternKind::BindingIdentifier(_) => false,
+    }
+}
+
+/// Where a feature occurs. The span is in bytes. Line and column are one-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub start: u32,
+    pub end: u32,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// The features of one file
+#[derive(Debug, Clone)]
+pub struct FileInventory {
+    pub name: String,
+    /// Only the features that occur, each with where it occurs in walk order
+    pub features: BTreeMap<Feature, Vec<Occurrence>>,
+    /// The parse errors, or that the file is nested too deep. A file with errors has no features.
+    pub errors: Vec<TransformError>,
+}
+
+impl FileInventory {
+    /// Parse the source, with the source type in the options, and find its features
+    pub fn from_source(name: &str, source: &str, options: &TransformOptions) -> Self {
+        let allocator = Allocator::default();
+        let parsed = Parser::new(&allocator, source, options.source_type()).parse();
+
+        let mut inventory = Self { name: name.to_string(), features: BTreeMap::new(), errors: vec!() };
+        if !parsed.errors.is_empty() {
+            inventory.errors = parsed.errors.iter()
+                .map(|error| TransformError::from_diagnostic(Diagnostic::from_parse_error(DiagnosticCode::ParseError, error), source))
+                .collect();
+            return inventory;
+        }
+        if let Err(diagnostic) = check_nesting(&parsed.program, DEFAULT_MAX_DEPTH) {
+            inventory.errors.push(TransformError::from_diagnostic(diagnostic, source));
+            return inventory;
+        }
+
+        for (feature, span) in find_features(&parsed.program) {
+            let (line, column) = resolve_line_column(source, span.start);
+            inventory.features.entry(feature).or_default().push(Occurrence { start: span.start, end: span.end, line, column });
+        }
+        inventory
+    }
+}
+
+/// Which features a set of files use, per file and in total. See `to_json` and `to_table` for the report.
+#[derive(Debug, Clone, Default)]
+pub struct Inventory {
+    pub files: Vec<FileInventory>,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self { files: vec!() }
+    }
+
+    /// Find the features of this file and add it to the report
+    pub fn add_source(&mut self, name: &str, source: &str, options: &TransformOptions) -> &FileInventory {
+        self.files.push(FileInventory::from_source(name, source, options));
+        self.files.last().unwrap()
+    }
+
+    /// For each feature that occurs anywhere: how often it occurs in total and in how many files
+    pub fn totals(&self) -> BTreeMap<Feature, (usize, usize)> {
+        let mut totals: BTreeMap<Feature, (usize, usize)> = BTreeMap::new();
+        for file in &self.files {
+            for (feature, occurrences) in &file.features {
+                let total = totals.entry(*feature).or_default();
+                total.0 += occurrences.len();
+                total.1 += 1;
+            }
+        }
+        totals
+    }
+
+    /// The report as JSON. Each file has its features (by id, see `Feature::as_str`) with the count and
+    /// every occurrence, and its parse errors. The total has the count and the number of files per feature.
+    pub fn to_json(&self) -> String {
+        let files: Vec<String> = self.files.iter().map(|file| {
+            let errors: Vec<String> = file.errors.iter().map(|error| format!(
+                "{{ \"code\": {}, \"message\": {}, \"line\": {}, \"column\": {} }}",
+                json_string(&error.code), json_string(&error.message), error.line, error.column
+            )).collect();
+            let features: Vec<String> = file.features.iter().map(|(feature, occurrences)| {
+                let occurrences: Vec<String> = occurrences.iter().map(|occurrence| format!(
+                    "{{ \"start\": {}, \"end\": {}, \"line\": {}, \"column\": {} }}",
+                    occurrence.start, occurrence.end, occurrence.line, occurrence.column
+                )).collect();
+                format!(
+                    "        {}: {{ \"count\": {}, \"occurrences\": [{}] }}",
+                    json_string(feature.as_str()), occurrences.len(), occurrences.join(", ")
+                )
+            }).collect();
+            format!(
+                "    {{\n      \"name\": {},\n      \"errors\": [{}],\n      \"features\": {{{}}}\n    }}",
+                json_string(&file.name), errors.join(", "), json_block(&features, "      ")
+            )
+        }).collect();
+
+        let totals: Vec<String> = self.totals().iter().map(|(feature, (count, files))| {
+            format!("    {}: {{ \"count\": {}, \"files\": {} }}", json_string(feature.as_str()), count, files)
+        }).collect();
+
+        format!("{{\n  \"files\": [{}],\n  \"total\": {{{}}}\n}}\n", json_block(&files, "  "), json_block(&totals, "  "))
+    }
+
+    /// The report as a table per file, with where each feature occurs (line:column), and one for the total.
+    /// The preset column is the least aggressive preset that eliminates the feature.
+    pub fn to_table(&self) -> String {
+        let width = self.totals().keys().map(|feature| feature.as_str().len()).max().unwrap_or(0).max("Feature".len());
+        let mut out = String::new();
+
+        for file in &self.files {
+            out.push_str(&format!("{}\n", file.name));
+            if !file.errors.is_empty() {
+                out.push_str("  Does not parse\n\n");
+                continue;
+            }
+            if file.features.is_empty() {
+                out.push_str("  No features\n\n");
+                continue;
+            }
+            out.push_str(&format!("  {:<width$}  {:>5}  Where\n", "Feature", "Count"));
+            for (feature, occurrences) in &file.features {
+                let places: Vec<String> = occurrences.iter().map(|occurrence| format!("{}:{}", occurrence.line, occurrence.column)).collect();
+                out.push_str(&format!("  {:<width$}  {:>5}  {}\n", feature.as_str(), occurrences.len(), places.join(", ")));
+            }
+            out.push('\n');
+        }
+
+        out.push_str(&format!("Total ({} {})\n", self.files.len(), if self.files.len() == 1 { "file" } else { "files" }));
+        out.push_str(&format!("  {:<width$}  {:>5}  {:>5}  Preset\n", "Feature", "Files", "Count"));
+        for (feature, (count, files)) in self.totals() {
+            let preset = match feature.preset() {
+                Some(Preset::Es5) => "es5",
+                Some(Preset::Js0) => "js0",
+                Some(Preset::Misc) => "misc",
+                None => "-",
+            };
+            out.push_str(&format!("  {:<width$}  {:>5}  {:>5}  {}\n", feature.as_str(), files, count, preset));
+        }
+        out
+    }
+}
+
+// The entries one per line, or nothing at all when there are none
+fn json_block(entries: &[String], indent: &str) -> String {
+    if entries.is_empty() {
+        return "".to_string();
+    }
+    format!("\n{}\n{}", entries.join(",\n"), indent)
+}
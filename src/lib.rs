@@ -3,37 +3,126 @@ pub mod walker;
 pub mod mapper;
 pub mod get_stmt_span;
 pub mod mapper_state;
-pub mod utils;
-
-use mapper::create_mapper_with_debug_id;
-use transforms::stmt_continue::apply_continue_transform_updates;
-use transforms::stmt_for_in::transform_for_in_statement;
-use transforms::stmt_for_of::transform_for_of_statement;
-use transforms::stmt_switch::transform_switch_statement;
-use transforms::stmt_var_decl::transform_var_decl_statement;
+pub mod mapper_context;
+pub mod mapper_prelude;
+pub mod scope;
+pub mod diagnostics;
+pub mod options;
+pub mod helpers;
+pub mod names;
+pub mod source_map;
+pub mod trace;
+pub mod logger;
+pub mod validator;
+pub mod passes;
+pub mod transformer;
+pub mod inventory;
+
 use wasm_bindgen::prelude::*;
 
 use oxc_allocator::Allocator;
+use oxc_allocator::Vec as OxcVec;
 use oxc_parser::Parser;
-use oxc_span::SourceType;
 use oxc_ast::ast::*;
-use oxc_span::Span;
+use oxc_span::{SourceType, Span};
 use oxc_codegen::{Codegen, CodegenOptions};
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
 
-use crate::mapper::MapperAction;
-use crate::transforms::stmt_do_while::transform_do_while_statement;
-use crate::transforms::stmt_for_n::transform_for_n_statement;
-use crate::transforms::stmt_finally::transform_finally_statement;
-use crate::transforms::stmt_continue::transform_continue_statement;
+use crate::diagnostics::{resolve_line_column, Diagnostic, DiagnosticCode};
+pub use crate::options::{Preset, SourceKind, SourceLanguage, TransformOptions};
+pub use crate::helpers::HelperMode;
+pub use crate::logger::{set_log_level, set_log_sink, LogLevel};
+pub use crate::validator::Profile;
+pub use crate::transformer::{Transform, TransformOutput, Transformer};
+pub use crate::inventory::Inventory;
+use crate::helpers::inject_helpers;
+use crate::mapper::DEFAULT_MAX_DEPTH;
+use crate::mapper_state::MapperState;
+use crate::names::collect_used_names;
+use crate::source_map::pair_statement_spans;
+use crate::trace::{resolve_generated_spans, TraceEntry};
+use crate::validator::{validate_program, validate_source, Violation};
+use crate::walker::check_nesting;
 
 #[wasm_bindgen(getter_with_clone)]
 pub struct TransformResult {
     pub transformed_ast: String,
     pub transformed_code: String,
+    // A v3 source map (JSON) from `transformed_code` back to the input. Empty when there is no output.
+    pub source_map: String,
     pub had_error: bool,
+    // The message of the first error, if any. See `errors` for all of them.
     pub error_message: Option<String>,
+    pub errors: Vec<TransformError>,
+    // The rules that were applied, in the order they were applied
+    pub trace: Vec<TransformTrace>,
+}
+
+#[wasm_bindgen]
+impl TransformResult {
+    /// How often the rule with this id (see `RuleId::as_str`) was applied
+    pub fn rule_count(&self, rule: &str) -> usize {
+        self.trace.iter().filter(|entry| entry.rule == rule).count()
+    }
+}
+
+/// A parse error or a node that could not be transformed. The code is stable (see `DiagnosticCode`).
+/// The span is in bytes. Line and column are one-based.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TransformError {
+    pub code: String,
+    pub message: String,
+    pub start: u32,
+    pub end: u32,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl TransformError {
+    fn from_diagnostic(diagnostic: Diagnostic, source: &str) -> Self {
+        let (line, column) = resolve_line_column(source, diagnostic.span.start);
+        Self {
+            code: diagnostic.code.as_str().to_string(),
+            message: diagnostic.message,
+            start: diagnostic.span.start,
+            end: diagnostic.span.end,
+            line,
+            column,
+        }
+    }
+}
+
+/// One rule that was applied. The rule is the id of a `RuleId`, like "for-of". The spans are in bytes.
+/// `start`/`end` point into the input and `generated_start`/`generated_end` into the output. The latter
+/// are absent when the output could not be matched to the input.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TransformTrace {
+    pub rule: String,
+    pub start: u32,
+    pub end: u32,
+    pub generated_start: Option<u32>,
+    pub generated_end: Option<u32>,
 }
 
+impl TransformTrace {
+    fn from_entry(entry: &TraceEntry) -> Self {
+        Self {
+            rule: entry.rule.as_str().to_string(),
+            start: entry.span.start,
+            end: entry.span.end,
+            generated_start: entry.generated_span.map(|span| span.start),
+            generated_end: entry.generated_span.map(|span| span.end),
+        }
+    }
+}
+
+// The name of the input in the source map when the caller doesn't tell us where the source came from.
+const SOURCE_NAME: &str = "input.js";
+
 fn _span_tofix() -> Span {
     Span::default()
 }
@@ -44,102 +133,183 @@ extern "C" {
     fn log(s: &str);
 }
 
+/// Log a message at `LogLevel::Info`, regardless of where it's running
 pub fn console_log(s: String) {
-    #[cfg(not(target_arch = "wasm32"))]
-    println!("{}", s);
-
-    #[cfg(target_arch = "wasm32")]
-    log(&format!("[Rust]: {}", s));
+    logger::emit(LogLevel::Info, &s);
 }
 
-// Simple wrapper for `log(format!())` into `log!()`
-// This will println!() in CLI and console.log(format!()) in nodejs etc
+// `log!(Level, "fmt", args)` sends `format!("fmt", args)` to the log sink (see `logger.rs`) when that level
+// is enabled. The message is not formatted at all otherwise, which matters for the mapper trace.
 #[macro_export]
 macro_rules! log {
-    ($fmt_str:literal) => {
-        #[cfg(not(target_arch = "wasm32"))]
-        println!($fmt_str);
-
-        #[cfg(target_arch = "wasm32")]
-        $crate::log(&format!("[Rust]: {}", $fmt_str));
+    ($level:ident, $($args:tt)*) => {
+        if $crate::logger::log_level_enabled($crate::logger::LogLevel::$level) {
+            $crate::logger::emit($crate::logger::LogLevel::$level, &format!($($args)*));
+        }
     };
+}
 
-    ($fmt_str:literal, $($args:expr),*) => {
-        #[cfg(not(target_arch = "wasm32"))]
-        println!($fmt_str, $($args),*);
+/// Check that this code parses (with the source type in the options) and only uses syntax that the profile
+/// allows. Returns all problems, so an empty list means the code is fine.
+#[wasm_bindgen]
+pub fn validate_code(source: &str, options: &TransformOptions, profile: &Profile) -> Vec<TransformError> {
+    validate_source(source, options.source_type(), *profile).into_iter()
+        .map(|diagnostic| TransformError::from_diagnostic(diagnostic, source))
+        .collect()
+}
 
-        #[cfg(target_arch = "wasm32")]
-        $crate::log(&format!("[Rust]: {}", format!($fmt_str, $($args),*)));
-    };
+/// Which syntax features this code uses and where, as JSON (see `Inventory::to_json`). Parse errors
+/// are in the report as well.
+#[wasm_bindgen]
+pub fn inventory_code(source: &str, options: &TransformOptions) -> String {
+    let mut inventory = Inventory::new();
+    inventory.add_source(SOURCE_NAME, source, options);
+    inventory.to_json()
 }
 
 #[wasm_bindgen]
 pub fn transform_code(source: &str) -> Result<TransformResult, JsValue> {
+    transform_code_with_options(source, &TransformOptions::default())
+}
+
+#[wasm_bindgen]
+pub fn transform_code_with_options(source: &str, options: &TransformOptions) -> Result<TransformResult, JsValue> {
+    Ok(transform_source(source, SOURCE_NAME, options))
+}
+
+/// Same as `transform_code_with_options` but for native callers, like the CLI. The `source_name`
+/// is what the source map will refer to.
+pub fn transform_source(source: &str, source_name: &str, options: &TransformOptions) -> TransformResult {
+    Transformer::new(options).transform_source(source, source_name)
+}
+
+fn run_transformer(transformer: &Transformer, source: &str, source_name: &str) -> TransformResult {
     let allocator = Allocator::default();
-    let source_str = Box::leak(Box::new(source.to_string()));
-    let (transformed_program, transformed_code) = parse_and_map(source_str, &allocator);
 
-    Ok(TransformResult {
-        transformed_ast: format!("{:#?}", transformed_program),
+    // Transforms report problems as diagnostics rather than panic. This is a last line of defense for
+    // anything we missed. (In wasm a panic aborts regardless so that's on us to prevent.)
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        let output = parse_and_map(transformer, &allocator, source);
+        let source_map = output.source_map(source, source_name);
+        // The dump grows with the square of the nesting depth (indentation) so skip it when the input is that deep
+        let too_deep = output.diagnostics.iter().any(|diagnostic| diagnostic.code == DiagnosticCode::NestingLimit);
+        let transformed_ast = if too_deep { "".to_string() } else { format!("{:#?}", output.program) };
+        (transformed_ast, output.code, source_map, output.diagnostics, output.trace)
+    }));
+
+    let (transformed_ast, transformed_code, source_map, diagnostics, trace) = match outcome {
+        Ok(outcome) => outcome,
+        Err(payload) => (
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            vec!(internal_error(payload)),
+            vec!(),
+        ),
+    };
+
+    let errors: Vec<TransformError> = diagnostics.into_iter().map(|diagnostic| TransformError::from_diagnostic(diagnostic, source)).collect();
+
+    TransformResult {
+        transformed_ast,
         transformed_code,
-        had_error: false,
-        error_message: None,
-    })
+        source_map,
+        had_error: !errors.is_empty(),
+        error_message: errors.first().map(|error| error.message.clone()),
+        errors,
+        trace: trace.iter().map(TransformTrace::from_entry).collect(),
+    }
+}
+
+fn internal_error(payload: Box<dyn std::any::Any + Send>) -> Diagnostic {
+    let message = payload.downcast_ref::<String>().cloned()
+        .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+        .unwrap_or_else(|| "Unknown panic".to_string());
+    Diagnostic::new(DiagnosticCode::InternalError, format!("Internal error: {}", message), Span::default())
 }
 
-fn parse_and_map<'a>(source: &'static str, allocator: &'a Allocator) -> (Program<'a>, String) {
-    let source_type = SourceType::default().with_module(true);
-    let parser = Parser::new(allocator, &source, source_type);
+/// Same last line of defense as in `run_transformer`, for `Transformer::transform_str` and
+/// `Transformer::transform_program`. The program is lost after a panic so that returns an empty one.
+fn catch_internal_error<'a>(allocator: &'a Allocator, source_type: SourceType, span: Span, run: impl FnOnce() -> TransformOutput<'a>) -> TransformOutput<'a> {
+    match panic::catch_unwind(AssertUnwindSafe(run)) {
+        Ok(output) => output,
+        Err(payload) => {
+            let program = Program {
+                span,
+                source_type,
+                directives: OxcVec::new_in(allocator),
+                hashbang: None,
+                body: OxcVec::new_in(allocator),
+            };
+            TransformOutput::new(program, "".to_string(), vec!(internal_error(payload)), vec!(), None)
+        }
+    }
+}
+
+fn parse_and_map<'a>(transformer: &Transformer, allocator: &'a Allocator, source: &'a str) -> TransformOutput<'a> {
+    let parser = Parser::new(allocator, source, transformer.options().source_type());
     let parsed = parser.parse();
 
     if !parsed.errors.is_empty() {
-        panic!("Input code could not be parsed: {:?}", parsed.errors);
+        // Don't try to transform a broken AST. Just report what the parser found.
+        let diagnostics = parsed.errors.iter().map(|error| Diagnostic::from_parse_error(DiagnosticCode::ParseError, error)).collect();
+        return TransformOutput::new(parsed.program, "".to_string(), diagnostics, vec!(), None);
+    }
+
+    map_program(transformer, allocator, parsed.program)
+}
+
+fn map_program<'a>(transformer: &Transformer, allocator: &'a Allocator, program: Program<'a>) -> TransformOutput<'a> {
+    let options = transformer.options();
+    if let Err(message) = options.check() {
+        return TransformOutput::new(program, "".to_string(), vec!(Diagnostic::new(DiagnosticCode::InvalidOption, message, Span::default())), vec!(), None);
+    }
+    if let Err(diagnostic) = check_nesting(&program, DEFAULT_MAX_DEPTH) {
+        return TransformOutput::new(program, "".to_string(), vec!(diagnostic), vec!(), None);
+    }
+    let source_type = program.source_type;
+
+    let state = Rc::new(RefCell::new(MapperState::new()));
+    {
+        // Generated names must not clash with anything in the input
+        let mut state = state.borrow_mut();
+        state.name_prefix = options.name_prefix.clone();
+        state.var_temporaries = options.var_temporaries;
+        state.reserved_names = collect_used_names(&program);
+        state.source_type = source_type;
+        state.check_preconditions = options.validate;
     }
+    let (helper_mode, helpers_module) = (options.helpers, options.helpers_module.clone());
+
+    let mut transformed = transformer.passes().run(program, allocator, &state);
 
-    let mut mapper = create_mapper_with_debug_id(allocator, "root".to_string());
-    let state = mapper.state.clone();
-
-    mapper.add_visitor_stmt(move |stmt, allocator, before: bool| {
-        log!("  Visitor call: before: {}", before);
-        // This part purely deals with wrapping loop bodies in a labeled statement for the sake of eliminating continue statements.
-        let stmt = apply_continue_transform_updates(stmt, before, allocator, &mut state.borrow_mut());
-
-        match ( before, stmt ) {
-            (false, Statement::DoWhileStatement(do_while)) => {
-                transform_do_while_statement(do_while.unbox(), allocator, &mut state.borrow_mut())
-            }
-            (false, Statement::ForStatement(for_stmt)) => {
-                transform_for_n_statement(for_stmt.unbox(), allocator, &mut state.borrow_mut())
-            }
-            (false, Statement::ForInStatement(for_stmt)) => {
-                transform_for_in_statement(for_stmt.unbox(), allocator, &mut state.borrow_mut())
-            }
-            (false, Statement::ForOfStatement(for_stmt)) => {
-                transform_for_of_statement(for_stmt.unbox(), allocator, &mut state.borrow_mut())
-            }
-            (false, Statement::TryStatement(try_stmt)) => {
-                transform_finally_statement(try_stmt.unbox(), allocator, &mut state.borrow_mut())
-            }
-            (false, Statement::ContinueStatement(continue_stmt)) => {
-                transform_continue_statement(continue_stmt.unbox(), allocator, &mut state.borrow_mut())
-            }
-            (false, Statement::SwitchStatement(switch_stmt)) => {
-                transform_switch_statement(switch_stmt.unbox(), allocator, &mut state.borrow_mut())
-            }
-            (true, Statement::BlockStatement(block_stmt)) => {
-                // Do this on-enter rather than on-exit
-                transform_var_decl_statement(block_stmt.unbox(), allocator, &mut state.borrow_mut())
-            }
-            (false, other) => (MapperAction::Normal, other),
-            (true, stmt) => (MapperAction::Normal, stmt),
+    let helpers: Vec<_> = state.borrow().helpers.clone().into_iter().collect();
+    let injected_count = match inject_helpers(&mut transformed, &helpers, helper_mode, &helpers_module, source_type, allocator) {
+        Ok(injected_count) => injected_count,
+        Err(message) => {
+            state.borrow_mut().report(DiagnosticCode::InternalError, message, Span::default());
+            0
         }
-    });
+    };
 
-    let transformed = mapper.map(parsed.program);
+    // After the helpers are injected, they must not use anything the transforms eliminate either
+    let violations = if options.validate { validate_program(&transformed, Profile::from_options(options)) } else { vec!() };
 
-    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions::default());
+    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions);
     let transformed_code = codegen.build(&transformed);
 
-    (transformed, transformed_code)
-}
+    let statement_spans = pair_statement_spans(&transformed, &transformed_code, source_type, injected_count);
 
+    let mut diagnostics = std::mem::take(&mut state.borrow_mut().diagnostics);
+    if options.validate {
+        diagnostics.extend(violations.iter().map(Violation::to_diagnostic));
+        // The spans of these point into the output, not the input, so only the message is useful
+        diagnostics.extend(validate_source(&transformed_code, source_type, Profile::any()).into_iter().map(|diagnostic| {
+            Diagnostic::new(DiagnosticCode::InvalidOutput, format!("The generated code does not parse: {}", diagnostic.message), Span::default())
+        }));
+    }
+    let mut trace = std::mem::take(&mut state.borrow_mut().trace);
+    resolve_generated_spans(&mut trace, &statement_spans);
+
+    TransformOutput::new(transformed, transformed_code, diagnostics, trace, Some(statement_spans))
+}
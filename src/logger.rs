@@ -0,0 +1,123 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, RwLock};
+
+use wasm_bindgen::prelude::*;
+
+/// How much debug output `log!()` produces. Each level includes the ones before it.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Nothing at all. This is the default.
+    Off = 0,
+    /// One line per rule that was applied
+    Info = 1,
+    /// What the transforms are doing internally
+    Debug = 2,
+    /// Every node the mapper enters, leaves or revisits. This is a lot and it's slow.
+    Trace = 3,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    pub fn parse(level: &str) -> Option<Self> {
+        match level {
+            "off" => Some(LogLevel::Off),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            "trace" => Some(LogLevel::Trace),
+            _ => None,
+        }
+    }
+
+    fn from_u8(level: u8) -> Self {
+        match level {
+            0 => LogLevel::Off,
+            1 => LogLevel::Info,
+            2 => LogLevel::Debug,
+            _ => LogLevel::Trace,
+        }
+    }
+}
+
+/// Receives every message at or below the current level
+pub type LogSink = Arc<dyn Fn(LogLevel, &str) + Send + Sync>;
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Off as u8);
+static LOG_SINK: RwLock<Option<LogSink>> = RwLock::new(None);
+
+#[wasm_bindgen]
+extern "C" {
+    /// A JS function that is called as `sink(level, message)`, where level is the name of the level
+    #[wasm_bindgen(js_name = Function)]
+    pub type JsLogSink;
+
+    #[wasm_bindgen(method, js_name = call)]
+    fn call2(this: &JsLogSink, context: &JsValue, level: &str, message: &str);
+}
+
+thread_local! {
+    // A JS value can't be shared between threads. There's only one thread in wasm anyways.
+    static JS_LOG_SINK: RefCell<Option<JsLogSink>> = const { RefCell::new(None) };
+}
+
+/// Set the level for all `log!()` output. It's `Off` by default.
+#[wasm_bindgen]
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn log_level() -> LogLevel {
+    LogLevel::from_u8(LOG_LEVEL.load(Ordering::Relaxed))
+}
+
+/// Whether messages of this level are currently emitted. `log!()` checks this before formatting.
+pub fn log_level_enabled(level: LogLevel) -> bool {
+    level != LogLevel::Off && level <= log_level()
+}
+
+/// Send log messages to this callback instead of stderr (native) or `console.log` (wasm). Pass `None`
+/// to restore the default. Setting a sink does not change the level.
+pub fn set_log_sink(sink: Option<LogSink>) {
+    *LOG_SINK.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = sink;
+}
+
+/// Same as `set_log_sink` for JS callers. A sink set from Rust takes precedence.
+#[wasm_bindgen(js_name = set_log_sink)]
+pub fn set_js_log_sink(sink: Option<JsLogSink>) {
+    JS_LOG_SINK.with(|cell| *cell.borrow_mut() = sink);
+}
+
+/// Pass a message on to the sink. Use `log!()` instead, which skips the formatting when the level is off.
+pub fn emit(level: LogLevel, message: &str) {
+    if !log_level_enabled(level) {
+        return;
+    }
+
+    let sink = LOG_SINK.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+    if let Some(sink) = sink {
+        sink(level, message);
+        return;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        let handled = JS_LOG_SINK.with(|cell| {
+            cell.borrow().as_ref().map(|sink| sink.call2(&JsValue::NULL, level.as_str(), message)).is_some()
+        });
+        if !handled {
+            crate::log(&format!("[Rust]: {}", message));
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("{}", message);
+}
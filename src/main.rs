@@ -0,0 +1,415 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use zero_sugar::{set_log_level, transform_source, validate_code, HelperMode, Inventory, LogLevel, Preset, Profile, SourceKind, SourceLanguage, TransformError, TransformOptions};
+
+const USAGE: &str = "\
+Usage: zero-sugar [options] [paths...]
+
+Desugars the given JS files. Directories are searched recursively for .js, .mjs and .cjs files.
+Without paths, or with `-`, the source is read from stdin and the result is written to stdout.
+
+Options:
+  -o, --out-dir <dir>   Write the output into this dir instead of next to the input
+      --suffix <ext>    Suffix for output files written next to the input (default: .zs)
+      --source-map      Also write a source map (<output>.map) for each output file (not with stdin)
+      --preset <name>   The target level: js0 (default), es5 (only the ES6 statements) or misc (js0, plus
+                        every loop a `while (true)` and every sub-statement a block)
+      --helpers <mode>  How to provide the runtime helpers: inline (default), import or none
+      --helpers-module <module>
+                        The module to import the helpers from (default: zero-sugar/helpers)
+      --name-prefix <prefix>
+                        Prefix for generated variables and labels (default: $zeroSugar)
+      --source-type <type>
+                        Parse the input as a script or module. Default: .cjs/.cts are scripts, the rest modules
+      --language <lang> Parse the input as js, jsx, ts or tsx. Default: from the extension, js for stdin
+      --validate        Check the output for syntax that should have been eliminated, and that it parses
+      --check           Don't transform, only report syntax that the transforms would eliminate (is it JS0?)
+      --inventory <format>
+                        Don't transform, report which syntax features the input uses, per file and in
+                        total: table or json. Written to stdout.
+      --log-level <level>
+                        Print debug output to stderr: off (default), info, debug or trace
+      --verbose         Same as `--log-level info`, prints each rule that was applied
+  -h, --help            Show this help
+";
+
+struct Args {
+    paths: Vec<String>,
+    out_dir: Option<PathBuf>,
+    suffix: String,
+    source_map: bool,
+    check: bool,
+    // Print the features as a table (false) or as JSON (true)
+    inventory: Option<bool>,
+    log_level: LogLevel,
+    // Only set when given explicitly. Otherwise these are inferred from the file extension.
+    source_kind: Option<SourceKind>,
+    language: Option<SourceLanguage>,
+    options: TransformOptions,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        paths: vec!(),
+        out_dir: None,
+        suffix: ".zs".to_string(),
+        source_map: false,
+        check: false,
+        inventory: None,
+        log_level: LogLevel::Off,
+        source_kind: None,
+        language: None,
+        options: TransformOptions::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Err("".to_string()),
+            "-o" | "--out-dir" => {
+                let dir = args.next().ok_or_else(|| format!("Missing value for `{}`", arg))?;
+                parsed.out_dir = Some(PathBuf::from(dir));
+            }
+            "--suffix" => {
+                parsed.suffix = args.next().ok_or_else(|| format!("Missing value for `{}`", arg))?;
+            }
+            "--source-map" => parsed.source_map = true,
+            "--preset" => {
+                let preset = args.next().ok_or_else(|| format!("Missing value for `{}`", arg))?;
+                parsed.options.apply_preset(match preset.as_str() {
+                    "es5" => Preset::Es5,
+                    "js0" => Preset::Js0,
+                    "misc" => Preset::Misc,
+                    _ => return Err(format!("Unknown preset `{}`, expecting es5, js0 or misc", preset)),
+                });
+            }
+            "--validate" => parsed.options.validate = true,
+            "--check" => parsed.check = true,
+            "--inventory" => {
+                let format = args.next().ok_or_else(|| format!("Missing value for `{}`", arg))?;
+                parsed.inventory = Some(match format.as_str() {
+                    "table" => false,
+                    "json" => true,
+                    _ => return Err(format!("Unknown inventory format `{}`, expecting table or json", format)),
+                });
+            }
+            "--helpers" => {
+                let mode = args.next().ok_or_else(|| format!("Missing value for `{}`", arg))?;
+                parsed.options.helpers = match mode.as_str() {
+                    "inline" => HelperMode::Inline,
+                    "import" => HelperMode::Import,
+                    "none" => HelperMode::None,
+                    _ => return Err(format!("Unknown helpers mode `{}`, expecting inline, import or none", mode)),
+                };
+            }
+            "--helpers-module" => {
+                parsed.options.helpers_module = args.next().ok_or_else(|| format!("Missing value for `{}`", arg))?;
+            }
+            "--name-prefix" => {
+                parsed.options.name_prefix = args.next().ok_or_else(|| format!("Missing value for `{}`", arg))?;
+            }
+            "--source-type" => {
+                let kind = args.next().ok_or_else(|| format!("Missing value for `{}`", arg))?;
+                parsed.source_kind = Some(match kind.as_str() {
+                    "script" => SourceKind::Script,
+                    "module" => SourceKind::Module,
+                    _ => return Err(format!("Unknown source type `{}`, expecting script or module", kind)),
+                });
+            }
+            "--language" => {
+                let language = args.next().ok_or_else(|| format!("Missing value for `{}`", arg))?;
+                parsed.language = Some(match language.as_str() {
+                    "js" => SourceLanguage::JavaScript,
+                    "jsx" => SourceLanguage::Jsx,
+                    "ts" => SourceLanguage::TypeScript,
+                    "tsx" => SourceLanguage::Tsx,
+                    _ => return Err(format!("Unknown language `{}`, expecting js, jsx, ts or tsx", language)),
+                });
+            }
+            "--log-level" => {
+                let level = args.next().ok_or_else(|| format!("Missing value for `{}`", arg))?;
+                parsed.log_level = LogLevel::parse(&level)
+                    .ok_or_else(|| format!("Unknown log level `{}`, expecting off, info, debug or trace", level))?;
+            }
+            "--verbose" => parsed.log_level = LogLevel::Info,
+            "-" => parsed.paths.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option `{}`", arg)),
+            _ => parsed.paths.push(arg),
+        }
+    }
+
+    parsed.options.check()?;
+    if parsed.suffix.is_empty() && parsed.out_dir.is_none() {
+        return Err("An empty `--suffix` needs an `--out-dir`, the output would overwrite the input".to_string());
+    }
+
+    Ok(parsed)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            if message.is_empty() {
+                print!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    set_log_level(args.log_level);
+
+    if let Some(json) = args.inventory {
+        return if report_inventory(&args, json) { ExitCode::FAILURE } else { ExitCode::SUCCESS };
+    }
+
+    let mut failed = false;
+
+    if args.paths.is_empty() || args.paths.iter().any(|path| path == "-") {
+        if args.paths.len() > 1 {
+            eprintln!("error: Can not combine stdin with other paths");
+            return ExitCode::from(2);
+        }
+        if args.source_map && !args.check {
+            eprintln!("error: Can not write a source map for stdin, pass a file instead");
+            return ExitCode::from(2);
+        }
+        let mut source = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut source) {
+            eprintln!("error: Failed to read stdin: {}", err);
+            return ExitCode::FAILURE;
+        }
+        if args.check {
+            failed |= report_errors("<stdin>", &check_source(&source, &options_for(None, &args)));
+            return if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS };
+        }
+        let result = transform_source(&source, "<stdin>", &options_for(None, &args));
+        failed |= report_errors("<stdin>", &result.errors);
+        if !result.transformed_code.is_empty() || !result.had_error {
+            print!("{}", result.transformed_code);
+            io::stdout().flush().ok();
+        }
+        return if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS };
+    }
+
+    let mut files = vec!();
+    for path in &args.paths {
+        let path = Path::new(path);
+        let path_files = match input_files(path, &args) {
+            Ok(files) => files,
+            Err(err) => {
+                eprintln!("{}: error: {}", path.display(), err);
+                failed = true;
+                continue;
+            }
+        };
+        // Files in a dir are written to the same relative path in the out dir
+        let base = if path.is_dir() { path } else { path.parent().unwrap_or(Path::new("")) };
+        files.extend(path_files.into_iter().map(|file| (file, base)));
+    }
+
+    // Check this before writing anything, an input that was already overwritten is lost
+    if !args.check {
+        if let Some((file, _)) = files.iter().find(|(file, base)| is_same_file(file, &output_path(file, base, &args))) {
+            eprintln!("error: The output for {} would overwrite it, pick another `--out-dir` or `--suffix`", file.display());
+            return ExitCode::from(2);
+        }
+    }
+
+    for (file, base) in files {
+        failed |= transform_file(&file, base, &args);
+    }
+
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+/// Returns whether there was a problem with this file
+fn transform_file(file: &Path, base: &Path, args: &Args) -> bool {
+    let display_name = file.display().to_string();
+    let source = match fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("{}: error: {}", display_name, err);
+            return true;
+        }
+    };
+
+    if args.check {
+        return report_errors(&display_name, &check_source(&source, &options_for(Some(file), args)));
+    }
+
+    let result = transform_source(&source, &display_name, &options_for(Some(file), args));
+    let failed = report_errors(&display_name, &result.errors);
+    if result.transformed_code.is_empty() && result.had_error {
+        // Nothing to write, the file did not parse
+        return failed;
+    }
+
+    let out_file = output_path(file, base, args);
+    if let Some(parent) = out_file.parent() {
+        if let Err(err) = fs::create_dir_all(parent) {
+            eprintln!("{}: error: {}", parent.display(), err);
+            return true;
+        }
+    }
+
+    let mut code = result.transformed_code;
+    if args.source_map {
+        let map_file = PathBuf::from(format!("{}.map", out_file.display()));
+        if let Err(err) = fs::write(&map_file, &result.source_map) {
+            eprintln!("{}: error: {}", map_file.display(), err);
+            return true;
+        }
+        code.push_str(&format!("\n//# sourceMappingURL={}\n", map_file.file_name().unwrap().to_string_lossy()));
+    }
+
+    if let Err(err) = fs::write(&out_file, code) {
+        eprintln!("{}: error: {}", out_file.display(), err);
+        return true;
+    }
+
+    failed
+}
+
+/// The options for this file (`None` for stdin), with the source type inferred from the extension
+/// unless it was given explicitly
+fn options_for(file: Option<&Path>, args: &Args) -> TransformOptions {
+    let mut options = args.options.clone();
+    if let Some(file) = file {
+        options.infer_source_type(&file.to_string_lossy());
+    }
+    if let Some(source_kind) = args.source_kind {
+        options.source_kind = source_kind;
+    }
+    if let Some(language) = args.language {
+        options.language = language;
+    }
+    options
+}
+
+/// Print the features of stdin or all files in the paths. Returns whether there was a problem with any of them.
+fn report_inventory(args: &Args, json: bool) -> bool {
+    let mut inventory = Inventory::new();
+    let mut failed = false;
+
+    if args.paths.is_empty() || args.paths.iter().any(|path| path == "-") {
+        if args.paths.len() > 1 {
+            eprintln!("error: Can not combine stdin with other paths");
+            return true;
+        }
+        let mut source = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut source) {
+            eprintln!("error: Failed to read stdin: {}", err);
+            return true;
+        }
+        failed |= report_errors("<stdin>", &inventory.add_source("<stdin>", &source, &options_for(None, args)).errors);
+    }
+
+    for path in args.paths.iter().filter(|path| *path != "-") {
+        let path = Path::new(path);
+        let files = match input_files(path, args) {
+            Ok(files) => files,
+            Err(err) => {
+                eprintln!("{}: error: {}", path.display(), err);
+                failed = true;
+                continue;
+            }
+        };
+        for file in files {
+            let display_name = file.display().to_string();
+            match fs::read_to_string(&file) {
+                Ok(source) => failed |= report_errors(&display_name, &inventory.add_source(&display_name, &source, &options_for(Some(&file), args)).errors),
+                Err(err) => {
+                    eprintln!("{}: error: {}", display_name, err);
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    print!("{}", if json { inventory.to_json() } else { inventory.to_table() });
+    io::stdout().flush().ok();
+    failed
+}
+
+/// Everything in the source that the enabled transforms would eliminate (see `Profile::from_options`)
+fn check_source(source: &str, options: &TransformOptions) -> Vec<TransformError> {
+    validate_code(source, options, &Profile::from_options(options))
+}
+
+/// Print the errors as `file:line:col: error[code]: message`. Returns whether there were any.
+fn report_errors(name: &str, errors: &[TransformError]) -> bool {
+    for error in errors {
+        eprintln!("{}:{}:{}: error[{}]: {}", name, error.line, error.column, error.code, error.message);
+    }
+    !errors.is_empty()
+}
+
+/// `a/b.js` -> `a/b.zs.js`, or `<out_dir>/b.js` when there is an out dir
+fn output_path(file: &Path, base: &Path, args: &Args) -> PathBuf {
+    match &args.out_dir {
+        Some(out_dir) => out_dir.join(file.strip_prefix(base).unwrap_or(file)),
+        None => {
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+            let name = match file.extension() {
+                Some(ext) => format!("{}{}.{}", stem, args.suffix, ext.to_string_lossy()),
+                None => format!("{}{}", stem, args.suffix),
+            };
+            file.with_file_name(name)
+        }
+    }
+}
+
+/// Whether both paths point to the same existing file, however they are spelled
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// The files to transform for this path. Don't pick up our own output, whether it's written next to the
+/// input or into an out dir inside of the input dir.
+fn input_files(path: &Path, args: &Args) -> io::Result<Vec<PathBuf>> {
+    match &args.out_dir {
+        Some(out_dir) => collect_files(path, None, fs::canonicalize(out_dir).ok().as_deref()),
+        None => collect_files(path, Some(args.suffix.as_str()), None),
+    }
+}
+
+/// A file as is, or all JS files in a dir (recursively). Skips files with the output suffix, the out dir, hidden dirs and node_modules.
+fn collect_files(path: &Path, skip_suffix: Option<&str>, skip_dir: Option<&Path>) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        fs::metadata(path)?;
+        return Ok(vec!(path.to_path_buf()));
+    }
+
+    let mut files = vec!();
+    let mut entries: Vec<_> = fs::read_dir(path)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let entry_path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry_path.is_dir() {
+            let is_out_dir = skip_dir.is_some_and(|skip_dir| fs::canonicalize(&entry_path).is_ok_and(|dir| dir == skip_dir));
+            if !name.starts_with('.') && name != "node_modules" && !is_out_dir {
+                files.extend(collect_files(&entry_path, skip_suffix, skip_dir)?);
+            }
+        } else if is_js_file(&name) && !skip_suffix.is_some_and(|suffix| is_output_file(&name, suffix)) {
+            files.push(entry_path);
+        }
+    }
+    Ok(files)
+}
+
+fn is_js_file(name: &str) -> bool {
+    name.ends_with(".js") || name.ends_with(".mjs") || name.ends_with(".cjs")
+}
+
+fn is_output_file(name: &str, suffix: &str) -> bool {
+    [".js", ".mjs", ".cjs"].iter().any(|ext| name.ends_with(&format!("{}{}", suffix, ext)))
+}
@@ -1,13 +1,21 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
 use oxc_ast::ast::*;
 use oxc_allocator::Vec as OxcVec;
 use oxc_allocator::Box as OxcBox;
 use oxc_allocator::Allocator;
+use oxc_span::GetSpan;
+use oxc_span::Span;
+use oxc_syntax::operator::{AssignmentOperator, UnaryOperator};
 
+use crate::diagnostics::DiagnosticCode;
+use crate::get_stmt_span::get_stmt_span;
 use crate::log;
+use crate::mapper_context::{has_use_strict, AncestorKind, LoopKind};
+use crate::mapper_prelude::{has_own_effects, Prelude, PreludeFrame};
 use crate::mapper_state::MapperState;
+use crate::scope::{ScopeKind, ScopeTree};
 
 #[derive(PartialEq)]
 pub enum MapperAction {
@@ -21,14 +29,46 @@ pub enum MapperAction {
     // This value is only useful in the before phase. It means that the node is not entered
     // but all visitors are called as normal. That's why it has no effect in the after phase.
     Skip,
+    // Only valid when the returned statement is a BlockStatement. Its statements replace the
+    // node in the enclosing statement list and each of them is visited as if it had been there
+    // all along. Where a single statement is expected (like an `if` or loop body) the block is
+    // kept and this acts like Revisit.
+    Splice,
 }
 
+// How often a single node may be revisited before the mapper assumes a visitor is stuck
+pub const DEFAULT_MAX_REVISITS: usize = 100;
+// How many statements and expressions deep the mapper goes. Each level costs stack, roughly one to
+// ten KB depending on the build, and the stack of a wasm module is only one MB by default.
+pub const DEFAULT_MAX_DEPTH: usize = 500;
+
+type StatementVisitor<'a> = Box<dyn Fn(Statement<'a>, &'a Allocator, bool) -> (MapperAction, Statement<'a>)>;
+type ExpressionVisitor<'a> = Box<dyn Fn(Expression<'a>, &'a Allocator, bool, &mut Prelude<'a>) -> (MapperAction, Expression<'a>)>;
+// For the other node kinds that have hooks. `Splice` only means something for statements, here it acts like `Revisit`.
+type NodeVisitor<'a, T> = Box<dyn Fn(T, &'a Allocator, bool) -> (MapperAction, T)>;
+
 pub struct Mapper<'a> {
     debug_id: String,
     allocator: &'a Allocator,
-    visitors_stmt: Vec<Box<dyn Fn(Statement<'a>, &'a Allocator, bool) -> (MapperAction, Statement<'a>)>>,
-    visitors_expr: Vec<Box<dyn Fn(Expression<'a>, &'a Allocator, bool) -> (MapperAction, Expression<'a>)>>,
+    visitors_stmt: Vec<StatementVisitor<'a>>,
+    visitors_expr: Vec<ExpressionVisitor<'a>>,
+    // Functions in declarations, expressions, methods, getters and setters
+    visitors_function: Vec<NodeVisitor<'a, Function<'a>>>,
+    visitors_arrow: Vec<NodeVisitor<'a, ArrowExpression<'a>>>,
+    // Class declarations and class expressions
+    visitors_class: Vec<NodeVisitor<'a, Class<'a>>>,
+    visitors_params: Vec<NodeVisitor<'a, FormalParameters<'a>>>,
+    // Every pattern, including the nested ones and the plain identifiers
+    visitors_binding_pattern: Vec<NodeVisitor<'a, BindingPattern<'a>>>,
+    visitors_object_property: Vec<NodeVisitor<'a, ObjectProperty<'a>>>,
+    visitors_switch_case: Vec<NodeVisitor<'a, SwitchCase<'a>>>,
     pub state: Rc<RefCell<MapperState>>,
+    // One frame per statement (or loop test, or arrow expression body) that is being mapped, innermost last
+    preludes: RefCell<Vec<PreludeFrame<'a>>>,
+    max_revisits: usize,
+    max_depth: usize,
+    // The number of statements and expressions that are being mapped right now
+    depth: Cell<usize>,
 }
 
 #[derive(Debug)]
@@ -44,14 +84,103 @@ impl<'a> Mapper<'a> {
             allocator,
             visitors_stmt: Vec::new(),
             visitors_expr: Vec::new(),
-            state: Rc::new(RefCell::new(MapperState { id_counter: 0, continue_targets: vec![] })),
+            visitors_function: Vec::new(),
+            visitors_arrow: Vec::new(),
+            visitors_class: Vec::new(),
+            visitors_params: Vec::new(),
+            visitors_binding_pattern: Vec::new(),
+            visitors_object_property: Vec::new(),
+            visitors_switch_case: Vec::new(),
+            state: Rc::new(RefCell::new(MapperState::new())),
+            preludes: RefCell::new(Vec::new()),
+            max_revisits: DEFAULT_MAX_REVISITS,
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: Cell::new(0),
         }
     }
 
+    /// Same as `OxcBox(self.allocator.alloc(value))`. The allocator is inlined even in debug builds,
+    /// and every inlined copy adds to the stack frame. That frame is paid for each level of nesting.
+    fn boxed<T>(&self, value: T) -> OxcBox<'a, T> {
+        OxcBox(self.allocator.alloc(value))
+    }
+
+    /// Record that we found something we can't (or won't) walk. The node is kept as is.
+    fn report_unsupported(&self, what: &str, span: Span) {
+        self.state.borrow_mut().report(DiagnosticCode::UnsupportedSyntax, format!("{} is not supported", what), span);
+    }
+
+    /// Push an ancestor onto the context in the state. Every `enter` must be paired with a `leave`.
+    fn enter(&self, kind: AncestorKind, span: Span, use_strict: bool) {
+        self.state.borrow_mut().context.push(kind, span, use_strict);
+    }
+
+    fn leave(&self) {
+        self.state.borrow_mut().context.pop();
+    }
+
+    /// Enter the scope of this node. That's the scope from the analysis when the node is from the input, or a new one.
+    fn enter_scope(&self, kind: ScopeKind, span: Span) {
+        let mut state = self.state.borrow_mut();
+        let parent = state.context.current_scope().unwrap_or(state.scopes.root());
+        let scope = state.scopes.find_or_add_scope(parent, kind, span);
+        state.context.push_scope(scope);
+    }
+
+    fn leave_scope(&self) {
+        self.state.borrow_mut().context.pop_scope();
+    }
+
     pub fn set_debug_id(&mut self, debug_id: String) {
         self.debug_id = debug_id;
     }
 
+    /// How often a visitor may ask to revisit the same node. After that the mapper reports which
+    /// visitor it was and moves on with the node as that visitor returned it.
+    pub fn set_max_revisits(&mut self, max_revisits: usize) {
+        self.max_revisits = max_revisits;
+    }
+
+    /// How deep statements and expressions may be nested. Anything deeper is reported and left as is,
+    /// rather than running out of stack.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Count a revisit of a node. Returns false, after reporting it, when the node was revisited too often.
+    fn allow_revisit(&self, revisits: &mut usize, visitor_kind: &str, visitor_index: usize, span: Span) -> bool {
+        *revisits += 1;
+        if *revisits <= self.max_revisits {
+            return true;
+        }
+        self.state.borrow_mut().report(
+            DiagnosticCode::RevisitLimit,
+            format!("The {} visitor at index {} kept asking to revisit this node. Stopped after {} revisits.", visitor_kind, visitor_index, self.max_revisits),
+            span,
+        );
+        false
+    }
+
+    /// Count a level of nesting. Returns false, after reporting it, when the node is too deep to map.
+    /// Every `enter_nesting` that returns true must be paired with a `leave_nesting`.
+    fn enter_nesting(&self, span: Span) -> bool {
+        let depth = self.depth.get() + 1;
+        if depth > self.max_depth {
+            self.state.borrow_mut().report(
+                DiagnosticCode::NestingLimit,
+                format!("This is nested more than {} levels deep. It is left as is.", self.max_depth),
+                span,
+            );
+            return false;
+        }
+        self.depth.set(depth);
+        true
+    }
+
+    fn leave_nesting(&self) {
+        self.depth.set(self.depth.get() - 1);
+    }
+
     pub fn add_visitor_stmt<F>(&mut self, visitor: F)
     where
         F: Fn(Statement<'a>, &'a Allocator, bool) -> (MapperAction, Statement<'a>) + 'static,
@@ -63,56 +192,343 @@ impl<'a> Mapper<'a> {
     pub fn add_visitor_expr<F>(&mut self, visitor: F)
     where
         F: Fn(Expression<'a>, &'a Allocator, bool) -> (MapperAction, Expression<'a>) + 'static,
+    {
+        self.visitors_expr.push(Box::new(move |expr, allocator, before, _prelude: &mut Prelude<'a>| visitor(expr, allocator, before)));
+    }
+
+    /// Like `add_visitor_expr` but the visitor can also queue statements to run before the statement
+    /// that contains the expression, with `Prelude::hoist`. Loop tests and arrow expression
+    /// bodies are restructured to make room for them. Other positions that are not evaluated exactly
+    /// once (branches, defaults, `do-while` tests, ...) or that come after side effects can't hoist.
+    pub fn add_visitor_expr_with_prelude<F>(&mut self, visitor: F)
+    where
+        F: Fn(Expression<'a>, &'a Allocator, bool, &mut Prelude<'a>) -> (MapperAction, Expression<'a>) + 'static,
     {
         self.visitors_expr.push(Box::new(visitor));
     }
 
+    pub fn add_visitor_function<F>(&mut self, visitor: F)
+    where
+        F: Fn(Function<'a>, &'a Allocator, bool) -> (MapperAction, Function<'a>) + 'static,
+    {
+        self.visitors_function.push(Box::new(visitor));
+    }
+
+    pub fn add_visitor_arrow<F>(&mut self, visitor: F)
+    where
+        F: Fn(ArrowExpression<'a>, &'a Allocator, bool) -> (MapperAction, ArrowExpression<'a>) + 'static,
+    {
+        self.visitors_arrow.push(Box::new(visitor));
+    }
+
+    pub fn add_visitor_class<F>(&mut self, visitor: F)
+    where
+        F: Fn(Class<'a>, &'a Allocator, bool) -> (MapperAction, Class<'a>) + 'static,
+    {
+        self.visitors_class.push(Box::new(visitor));
+    }
+
+    pub fn add_visitor_params<F>(&mut self, visitor: F)
+    where
+        F: Fn(FormalParameters<'a>, &'a Allocator, bool) -> (MapperAction, FormalParameters<'a>) + 'static,
+    {
+        self.visitors_params.push(Box::new(visitor));
+    }
+
+    pub fn add_visitor_binding_pattern<F>(&mut self, visitor: F)
+    where
+        F: Fn(BindingPattern<'a>, &'a Allocator, bool) -> (MapperAction, BindingPattern<'a>) + 'static,
+    {
+        self.visitors_binding_pattern.push(Box::new(visitor));
+    }
+
+    pub fn add_visitor_object_property<F>(&mut self, visitor: F)
+    where
+        F: Fn(ObjectProperty<'a>, &'a Allocator, bool) -> (MapperAction, ObjectProperty<'a>) + 'static,
+    {
+        self.visitors_object_property.push(Box::new(visitor));
+    }
+
+    pub fn add_visitor_switch_case<F>(&mut self, visitor: F)
+    where
+        F: Fn(SwitchCase<'a>, &'a Allocator, bool) -> (MapperAction, SwitchCase<'a>) + 'static,
+    {
+        self.visitors_switch_case.push(Box::new(visitor));
+    }
+
+    /// Call the before visitors, map the children and call the after visitors, like for statements
+    fn visit_node<T>(&self, kind: &str, visitors: &[NodeVisitor<'a, T>], mut node: T, span: Span, map_children: impl Fn(T) -> T) -> T {
+        let mut revisits = 0;
+        loop {
+            let mut enter_node = true;
+            let mut visit_again = false;
+
+            for (index, visitor) in visitors.iter().enumerate() {
+                let (action, new_node) = visitor(node, self.allocator, true);
+                node = new_node;
+                match action {
+                    MapperAction::Revisit | MapperAction::Splice => {
+                        if !self.allow_revisit(&mut revisits, kind, index, span) {
+                            return node;
+                        }
+                        visit_again = true;
+                        break;
+                    }
+                    MapperAction::Skip => enter_node = false,
+                    MapperAction::Normal => (),
+                }
+            }
+            if visit_again {
+                continue;
+            }
+
+            if enter_node {
+                node = map_children(node);
+            }
+
+            for (index, visitor) in visitors.iter().enumerate() {
+                let (action, new_node) = visitor(node, self.allocator, false);
+                node = new_node;
+                if matches!(action, MapperAction::Revisit | MapperAction::Splice) {
+                    visit_again = self.allow_revisit(&mut revisits, kind, index, span);
+                    break;
+                }
+            }
+            if !visit_again {
+                return node;
+            }
+        }
+    }
+
+    fn push_prelude_frame(&self) {
+        self.preludes.borrow_mut().push(PreludeFrame::default());
+    }
+
+    fn pop_prelude_frame(&self) -> Vec<Statement<'a>> {
+        self.preludes.borrow_mut().pop().map(|frame| frame.statements).unwrap_or_default()
+    }
+
+    /// Map something that is not evaluated exactly once when its parent is. Prelude statements can't leave it.
+    fn with_barrier<T>(&self, barrier: &'static str, map: impl FnOnce() -> T) -> T {
+        if let Some(frame) = self.preludes.borrow_mut().last_mut() {
+            frame.barriers.push(barrier);
+        }
+        let result = map();
+        if let Some(frame) = self.preludes.borrow_mut().last_mut() {
+            frame.barriers.pop();
+        }
+        result
+    }
+
+    fn prelude_blocked_by(&self) -> Option<&'static str> {
+        match self.preludes.borrow().last() {
+            Some(frame) => frame.blocked_by(),
+            None => Some("code outside of a statement"),
+        }
+    }
+
+    /// Move the statements that visitors queued so far into the current frame
+    fn hoist_prelude(&self, prelude: &mut Prelude<'a>, span: Span) {
+        if let Some(blocked_by) = prelude.take_refused() {
+            // The expression was left as is
+            self.state.borrow_mut().report(DiagnosticCode::UnsupportedTransform, format!("Cannot move statements out of {}", blocked_by), span);
+        }
+        if prelude.is_empty() {
+            return;
+        }
+        if let Some(frame) = self.preludes.borrow_mut().last_mut() {
+            frame.statements.extend(prelude.take());
+        }
+    }
+
+    /// Record that this expression was evaluated, so statements can't be moved before it anymore
+    fn note_effects(&self, expr: &Expression<'a>) {
+        if has_own_effects(expr) {
+            if let Some(frame) = self.preludes.borrow_mut().last_mut() {
+                frame.effects = true;
+            }
+        }
+    }
+
+    /// `while (test) body` -> `while (true) { prelude; if (!test) break; body }`, also used for `for` loops.
+    /// A `continue` in the body still evaluates the prelude and test again.
+    fn loop_body_with_test_prelude(&self, prelude: Vec<Statement<'a>>, test: Expression<'a>, body: Statement<'a>, span: Span) -> Statement<'a> {
+        let mut new_body = self.map_statements(OxcVec::from_iter_in(prelude, self.allocator));
+        let test_span = test.span();
+        new_body.push(Statement::IfStatement(self.boxed(IfStatement {
+            test: Expression::UnaryExpression(OxcBox(self.allocator.alloc(UnaryExpression { operator: UnaryOperator::LogicalNot, argument: test, span: test_span }))),
+            consequent: Statement::BreakStatement(OxcBox(self.allocator.alloc(BreakStatement { label: None, span: test_span }))),
+            alternate: None,
+            span: test_span,
+        })));
+        new_body.push(body);
+        Statement::BlockStatement(self.boxed(BlockStatement { body: new_body, span }))
+    }
+
 
     pub fn map(&self, program: Program<'a>) -> Program<'a> {
+        let applied = {
+            let mut state = self.state.borrow_mut();
+            if !state.scopes_analyzed {
+                state.scopes = ScopeTree::analyze(&program);
+                state.scopes_analyzed = true;
+            }
+            let root = state.scopes.root();
+            state.context.push_scope(root);
+            state.trace.len()
+        };
         let Program { body,  span, source_type, directives, hashbang } = program;
 
+        self.enter(AncestorKind::Program, span, source_type.is_module() || has_use_strict(&directives));
+
         // The program body is not a BlockStatement, so we need to wrap it in one to traverse
         // it as usual. This will serve our purpose although it may not be generic.
-        let block = self.map_statement(Statement::BlockStatement(OxcBox(self.allocator.alloc(BlockStatement { body, span }))));
-        let Statement::BlockStatement(block) = block else {
-            panic!("Expecting a block back from the program step");
+        let block = self.map_statement(Statement::BlockStatement(self.boxed(BlockStatement { body, span })));
+        let body = match block {
+            Statement::BlockStatement(block) => block.unbox().body,
+            // A visitor replaced the root block with something else. Make that the sole statement of the program.
+            other => OxcVec::from_iter_in([other], self.allocator),
         };
-        let BlockStatement { body, span: _span } = block.unbox();
+        self.leave();
+        self.leave_scope();
+        // The bindings don't move along with their nodes, so once a rule moved some the next traversal
+        // has to analyze the program again
+        let mut state = self.state.borrow_mut();
+        if state.trace.len() > applied {
+            state.scopes_analyzed = false;
+        }
         Program { body, span, source_type, directives, hashbang }
     }
 
-    pub fn map_statement(&self, mut stmt: Statement<'a>) -> Statement<'a> {
+    pub fn map_statement(&self, stmt: Statement<'a>) -> Statement<'a> {
+        let (stmt, _, prelude) = self.visit_statement_with_prelude(stmt, false);
+        if prelude.is_empty() {
+            return stmt;
+        }
+
+        // Only one statement fits here so the prelude and the statement go in a block
+        let span = stmt.span();
+        let mut body = self.map_statements(OxcVec::from_iter_in(prelude, self.allocator));
+        body.push(stmt);
+        Statement::BlockStatement(self.boxed(BlockStatement { body, span }))
+    }
+
+    /// Map the statements of a block, function body, switch case, etc. This is where spliced statements end up.
+    pub fn map_statements(&self, stmts: OxcVec<'a, Statement<'a>>) -> OxcVec<'a, Statement<'a>> {
+        let mut new_stmts = OxcVec::with_capacity_in(stmts.len(), self.allocator);
+        for stmt in stmts {
+            self.map_statement_into(stmt, &mut new_stmts);
+        }
+        new_stmts
+    }
+
+    fn map_statement_into(&self, stmt: Statement<'a>, new_stmts: &mut OxcVec<'a, Statement<'a>>) {
+        let (stmt, splice, prelude) = self.visit_statement_with_prelude(stmt, true);
+        for stmt in prelude {
+            self.map_statement_into(stmt, new_stmts);
+        }
+        match (stmt, splice) {
+            (Statement::BlockStatement(block), true) => {
+                for stmt in block.unbox().body {
+                    self.map_statement_into(stmt, new_stmts);
+                }
+            }
+            (stmt, _) => new_stmts.push(stmt),
+        }
+    }
+
+    /// Like `visit_statement` and also returns the prelude that expressions in the statement queued.
+    /// These still have to be visited.
+    fn visit_statement_with_prelude(&self, stmt: Statement<'a>, in_list: bool) -> (Statement<'a>, bool, Vec<Statement<'a>>) {
+        self.push_prelude_frame();
+        let (stmt, splice) = self.visit_statement(stmt, in_list);
+        (stmt, splice, self.pop_prelude_frame())
+    }
+
+    /// Returns the mapped statement and whether a visitor asked to splice it, which is only honored
+    /// when the statement is part of a statement list and the visitor returned a block.
+    fn visit_statement(&self, stmt: Statement<'a>, in_list: bool) -> (Statement<'a>, bool) {
+        if !self.enter_nesting(get_stmt_span(&stmt)) {
+            return (stmt, false);
+        }
+        let result = self.visit_statement_unchecked(stmt, in_list);
+        self.leave_nesting();
+        result
+    }
+
+    fn visit_statement_unchecked(&self, mut stmt: Statement<'a>, in_list: bool) -> (Statement<'a>, bool) {
+        let mut revisits = 0;
         // Apply before visitors first
         let mut visit_again = true;
         let mut enter_node;
         while visit_again {
-            log!("{}Enter statement {:?}", if self.debug_id.len() > 0 { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", stmt).split(' ').next().unwrap_or(format!("{:?}", stmt).as_str()));
+            log!(Trace, "{}Enter statement {:?}", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", stmt).split(' ').next().unwrap_or(format!("{:?}", stmt).as_str()));
             enter_node = true;
             visit_again = false;
+            // The expressions are visited again, that does not make them run twice
+            if let Some(frame) = self.preludes.borrow_mut().last_mut() {
+                frame.effects = false;
+            }
 
-            for visitor in &self.visitors_stmt {
+            for (index, visitor) in self.visitors_stmt.iter().enumerate() {
                 let (action, new_stmt) = visitor(stmt, self.allocator, true);
                 stmt = new_stmt;
-                if action == MapperAction::Revisit {
+                if action == MapperAction::Splice && in_list && matches!(stmt, Statement::BlockStatement(_)) {
+                    log!(Trace, "{}Splice statement", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() });
+                    return (stmt, true);
+                }
+                if action == MapperAction::Revisit || action == MapperAction::Splice {
+                    if !self.allow_revisit(&mut revisits, "statement", index, get_stmt_span(&stmt)) {
+                        return (stmt, false);
+                    }
                     visit_again = true;
-                    log!("{}Revisit statement {:?}", if self.debug_id.len() > 0 { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", stmt).split(' ').next().unwrap_or(format!("{:?}", stmt).as_str()));
+                    log!(Trace, "{}Revisit statement {:?}", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", stmt).split(' ').next().unwrap_or(format!("{:?}", stmt).as_str()));
                     break;
                 }
-                enter_node |= action == MapperAction::Normal;
+                if action == MapperAction::Skip {
+                    enter_node = false;
+                }
+            }
+            if visit_again {
+                continue;
             }
 
             // Only map children if we're not skipping the visit
             if enter_node {
+                let ancestor = match &stmt {
+                    Statement::DoWhileStatement(s) => Some((AncestorKind::Loop(LoopKind::DoWhile), s.span)),
+                    Statement::ForStatement(s) => Some((AncestorKind::Loop(LoopKind::For), s.span)),
+                    Statement::ForInStatement(s) => Some((AncestorKind::Loop(LoopKind::ForIn), s.span)),
+                    Statement::ForOfStatement(s) => Some((AncestorKind::Loop(LoopKind::ForOf), s.span)),
+                    Statement::WhileStatement(s) => Some((AncestorKind::Loop(LoopKind::While), s.span)),
+                    Statement::LabeledStatement(s) => Some((AncestorKind::Label(s.label.name.to_string()), s.span)),
+                    Statement::SwitchStatement(s) => Some((AncestorKind::Switch, s.span)),
+                    Statement::TryStatement(s) => Some((AncestorKind::Try, s.span)),
+                    _ => None,
+                };
+                let has_ancestor = ancestor.is_some();
+                if let Some((kind, span)) = ancestor {
+                    self.enter(kind, span, false);
+                }
+                let scope = match &stmt {
+                    Statement::BlockStatement(s) => Some((ScopeKind::Block, s.span)),
+                    Statement::ForStatement(s) if s.init.as_ref().is_some_and(ForStatementInit::is_lexical_declaration) => Some((ScopeKind::For, s.span)),
+                    Statement::ForInStatement(s) if s.left.is_lexical_declaration() => Some((ScopeKind::For, s.span)),
+                    Statement::ForOfStatement(s) if s.left.is_lexical_declaration() => Some((ScopeKind::For, s.span)),
+                    _ => None,
+                };
+                let has_scope = scope.is_some();
+                if let Some((kind, span)) = scope {
+                    self.enter_scope(kind, span);
+                }
+
                 stmt = match stmt {
                     Statement::BlockStatement(block) => {
                         let BlockStatement { body, span } = block.unbox();
 
-                        let mut new_body = OxcVec::with_capacity_in(body.len(), self.allocator);
-                        for stmt in body {
-                            new_body.push(self.map_statement(stmt));
-                        }
+                        let new_body = self.map_statements(body);
 
-                        Statement::BlockStatement(OxcBox(self.allocator.alloc(BlockStatement { body: new_body, span })))
+                        Statement::BlockStatement(self.boxed(BlockStatement { body: new_body, span }))
                     }
                     Statement::BreakStatement(_) => stmt, // No children to visit
                     Statement::ContinueStatement(_) => stmt, // No children to visit
@@ -121,9 +537,9 @@ impl<'a> Mapper<'a> {
                         let DoWhileStatement { body, test, span } = do_while.unbox();
 
                         let body = self.map_statement(body);
-                        let test = self.map_expression(test);
+                        let test = self.with_barrier("a `do-while` test", || self.map_expression(test));
 
-                        Statement::DoWhileStatement(OxcBox(self.allocator.alloc(DoWhileStatement { body, test, span })))
+                        Statement::DoWhileStatement(self.boxed(DoWhileStatement { body, test, span }))
                     }
                     Statement::EmptyStatement(_) => stmt, // No children to visit
                     Statement::ExpressionStatement(expr_stmt) => {
@@ -131,41 +547,49 @@ impl<'a> Mapper<'a> {
 
                         let expr = self.map_expression(expression);
 
-                        Statement::ExpressionStatement(OxcBox(self.allocator.alloc(ExpressionStatement { expression: expr, span })))
+                        Statement::ExpressionStatement(self.boxed(ExpressionStatement { expression: expr, span }))
                     }
                     Statement::ForInStatement(for_in) => {
                         let ForInStatement { left, right, body, span } = for_in.unbox();
 
-                        let left = match left {
+                        let right = self.map_expression(right);
+                        // The left side is assigned for every iteration, after the right side was evaluated
+                        let left = self.with_barrier("the left side of a `for-in` or `for-of` loop", || match left {
                             ForStatementLeft::VariableDeclaration(decl) => {
-                                ForStatementLeft::VariableDeclaration(OxcBox(self.allocator.alloc(self.map_variable_declaration(decl.unbox()))))
+                                ForStatementLeft::VariableDeclaration(self.boxed(self.map_variable_declaration(decl.unbox())))
                             }
                             ForStatementLeft::AssignmentTarget(target) => {
                                 ForStatementLeft::AssignmentTarget(self.map_assignment_target(target))
                             }
-                            ForStatementLeft::UsingDeclaration(_) => panic!("UsingDeclaration (stage 3) is not supported"),
-                        };
-                        let right = self.map_expression(right);
+                            ForStatementLeft::UsingDeclaration(using) => {
+                                self.report_unsupported("UsingDeclaration (stage 3)", using.span);
+                                ForStatementLeft::UsingDeclaration(using)
+                            }
+                        });
                         let body = self.map_statement(body);
 
-                        Statement::ForInStatement(OxcBox(self.allocator.alloc(ForInStatement { left, right, body, span })))
+                        Statement::ForInStatement(self.boxed(ForInStatement { left, right, body, span }))
                     }
                     Statement::ForOfStatement(for_of) => {
                         let ForOfStatement { left, right, body, span, r#await } = for_of.unbox();
 
-                        let left = match left {
+                        let right = self.map_expression(right);
+                        // The left side is assigned for every iteration, after the right side was evaluated
+                        let left = self.with_barrier("the left side of a `for-in` or `for-of` loop", || match left {
                             ForStatementLeft::VariableDeclaration(decl) => {
-                                ForStatementLeft::VariableDeclaration(OxcBox(self.allocator.alloc(self.map_variable_declaration(decl.unbox()))))
+                                ForStatementLeft::VariableDeclaration(self.boxed(self.map_variable_declaration(decl.unbox())))
                             }
                             ForStatementLeft::AssignmentTarget(target) => {
                                 ForStatementLeft::AssignmentTarget(self.map_assignment_target(target))
                             }
-                            ForStatementLeft::UsingDeclaration(_) => panic!("UsingDeclaration (stage 3) is not supported"),
-                        };
-                        let right = self.map_expression(right);
+                            ForStatementLeft::UsingDeclaration(using) => {
+                                self.report_unsupported("UsingDeclaration (stage 3)", using.span);
+                                ForStatementLeft::UsingDeclaration(using)
+                            }
+                        });
                         let body = self.map_statement(body);
 
-                        Statement::ForOfStatement(OxcBox(self.allocator.alloc(ForOfStatement { left, right, body, span, r#await })))
+                        Statement::ForOfStatement(self.boxed(ForOfStatement { left, right, body, span, r#await }))
                     }
                     Statement::ForStatement(for_stmt) => {
                         let ForStatement { init, test, update, body, span } = for_stmt.unbox();
@@ -173,17 +597,27 @@ impl<'a> Mapper<'a> {
                         let init = match init {
                             Some(ForStatementInit::Expression(expr)) => Some(ForStatementInit::Expression(self.map_expression(expr))),
                             Some(ForStatementInit::VariableDeclaration(decl)) => {
-                                Some(ForStatementInit::VariableDeclaration(OxcBox(self.allocator.alloc(self.map_variable_declaration(decl.unbox())))))
+                                Some(ForStatementInit::VariableDeclaration(self.boxed(self.map_variable_declaration(decl.unbox()))))
+                            }
+                            Some(ForStatementInit::UsingDeclaration(using)) => {
+                                self.report_unsupported("UsingDeclaration (stage 3)", using.span);
+                                Some(ForStatementInit::UsingDeclaration(using))
                             }
-                            Some(ForStatementInit::UsingDeclaration(_)) => panic!("UsingDeclaration (stage 3) is not supported"),
                             None => None,
                         };
 
+                        self.push_prelude_frame();
                         let test = test.map(|test| self.map_expression(test));
-                        let update = update.map(|update| self.map_expression(update));
+                        let test_prelude = self.pop_prelude_frame();
+                        let update = update.map(|update| self.with_barrier("a `for` loop update", || self.map_expression(update)));
                         let body = self.map_statement(body);
 
-                        Statement::ForStatement(OxcBox(self.allocator.alloc(ForStatement { init, test, update, body, span })))
+                        let (test, body) = match test {
+                            Some(test) if !test_prelude.is_empty() => (None, self.loop_body_with_test_prelude(test_prelude, test, body, span)),
+                            test => (test, body),
+                        };
+
+                        Statement::ForStatement(self.boxed(ForStatement { init, test, update, body, span }))
                     }
                     Statement::IfStatement(if_stmt) => {
                         let IfStatement { test, consequent, alternate, span } = if_stmt.unbox();
@@ -192,82 +626,95 @@ impl<'a> Mapper<'a> {
                         let consequent = self.map_statement(consequent);
                         let alternate = alternate.map(|alt| self.map_statement(alt));
 
-                        Statement::IfStatement(OxcBox(self.allocator.alloc(IfStatement { test, consequent, alternate, span })))
+                        Statement::IfStatement(self.boxed(IfStatement { test, consequent, alternate, span }))
                     }
                     Statement::LabeledStatement(labeled) => {
                         let LabeledStatement { label, body, span } = labeled.unbox();
 
-                        let body = self.map_statement(body);
+                        // The prelude of the body goes before the label so a labeled loop stays labeled
+                        let (body, _) = self.visit_statement(body, false);
 
-                        Statement::LabeledStatement(OxcBox(self.allocator.alloc(LabeledStatement { label, body, span })))
+                        Statement::LabeledStatement(self.boxed(LabeledStatement { label, body, span }))
                     }
                     Statement::ReturnStatement(ret) => {
                         let ReturnStatement { argument, span } = ret.unbox();
 
                         let argument = argument.map(|arg| self.map_expression(arg));
 
-                        Statement::ReturnStatement(OxcBox(self.allocator.alloc(ReturnStatement { argument, span })))
+                        Statement::ReturnStatement(self.boxed(ReturnStatement { argument, span }))
                     }
                     Statement::SwitchStatement(switch) => {
                         let SwitchStatement { discriminant, cases, span } = self.map_switch_statement(switch.unbox());
 
-                        Statement::SwitchStatement(OxcBox(self.allocator.alloc(SwitchStatement { discriminant, cases, span })))
+                        Statement::SwitchStatement(self.boxed(SwitchStatement { discriminant, cases, span }))
                     }
                     Statement::ThrowStatement(throw) => {
                         let ThrowStatement { argument, span } = throw.unbox();
 
                         let argument = self.map_expression(argument);
 
-                        Statement::ThrowStatement(OxcBox(self.allocator.alloc(ThrowStatement { argument, span })))
+                        Statement::ThrowStatement(self.boxed(ThrowStatement { argument, span }))
                     }
                     Statement::TryStatement(try_stmt) => {
                         let TryStatement { block, handler, finalizer, span } = try_stmt.unbox();
                         let BlockStatement { body, span: block_span } = block.unbox();
 
-                        let mut new_block_body = OxcVec::with_capacity_in(body.len(), self.allocator);
-                        for stmt in body {
-                            new_block_body.push(self.map_statement(stmt));
-                        }
+                        self.enter_scope(ScopeKind::Block, block_span);
+                        let new_block_body = self.map_statements(body);
+                        self.leave_scope();
                         let block = BlockStatement { body: new_block_body, span: block_span };
 
                         let handler = handler.map(|h| {
                             let CatchClause { param, body, span } = h.unbox();
-
-                            let mut new_body_stmts = OxcVec::with_capacity_in(body.body.len(), self.allocator);
-                            for stmt in body.unbox().body {
-                                new_body_stmts.push(self.map_statement(stmt));
-                            }
-                            let body = BlockStatement { body: new_body_stmts, span };
+                            self.enter(AncestorKind::Catch, span, false);
+                            self.enter_scope(ScopeKind::Catch, span);
+                            let param = param.map(|p| self.map_binding_pattern(p));
+                            let BlockStatement { body, span: body_span } = body.unbox();
+
+                            let new_body_stmts = self.map_statements(body);
+                            self.leave_scope();
+                            self.leave();
+                            let body = BlockStatement { body: new_body_stmts, span: body_span };
                             CatchClause {
-                                param: param.map(|p| self.map_binding_pattern(p)),
-                                body: OxcBox(self.allocator.alloc(body)),
+                                param,
+                                body: self.boxed(body),
                                 span,
                             }
                         });
 
                         let finalizer = finalizer.map(|f| {
                             let BlockStatement { body, span } = f.unbox();
-                            let mut new_finalizer_body = OxcVec::with_capacity_in(body.len(), self.allocator);
-                            for stmt in body {
-                                new_finalizer_body.push(self.map_statement(stmt));
-                            }
+                            self.enter(AncestorKind::Finally, span, false);
+                            self.enter_scope(ScopeKind::Block, span);
+                            let new_finalizer_body = self.map_statements(body);
+                            self.leave_scope();
+                            self.leave();
                             BlockStatement { body: new_finalizer_body, span }
                         });
 
-                        Statement::TryStatement(OxcBox(self.allocator.alloc(TryStatement {
+                        Statement::TryStatement(self.boxed(TryStatement {
                             block: OxcBox(self.allocator.alloc(block)),
                             handler: handler.map(|h| OxcBox(self.allocator.alloc(h))),
                             finalizer: finalizer.map(|f| OxcBox(self.allocator.alloc(f))),
                             span,
-                        })))
+                        }))
                     }
                     Statement::WhileStatement(while_stmt) => {
                         let WhileStatement { test, body, span } = while_stmt.unbox();
 
+                        self.push_prelude_frame();
                         let test = self.map_expression(test);
+                        let test_prelude = self.pop_prelude_frame();
                         let body = self.map_statement(body);
 
-                        Statement::WhileStatement(OxcBox(self.allocator.alloc(WhileStatement { test, body, span })))
+                        let (test, body) = if test_prelude.is_empty() {
+                            (test, body)
+                        } else {
+                            let body = self.loop_body_with_test_prelude(test_prelude, test, body, span);
+                            (Expression::BooleanLiteral(self.boxed(BooleanLiteral { value: true, span })), body)
+                        };
+
+                        Statement::WhileStatement(self.boxed(WhileStatement { test, body, span }))
                     }
                     Statement::WithStatement(with) => {
                         let WithStatement { object, body, span } = with.unbox();
@@ -275,57 +722,44 @@ impl<'a> Mapper<'a> {
                         let object = self.map_expression(object);
                         let body = self.map_statement(body);
 
-                        Statement::WithStatement(OxcBox(self.allocator.alloc(WithStatement { object, body, span })))
+                        Statement::WithStatement(self.boxed(WithStatement { object, body, span }))
                     }
-                    Statement::Declaration(decl) => Statement::Declaration(match decl {
-                        Declaration::VariableDeclaration(var_decl) => {
-                            Declaration::VariableDeclaration(OxcBox(self.allocator.alloc(self.map_variable_declaration(var_decl.unbox()))))
-                        }
-                        Declaration::FunctionDeclaration(func_decl) => {
-                            let Function { body, span, id, expression, generator, r#async, params, type_parameters, return_type, modifiers, r#type } = func_decl.unbox();
-                            let FunctionBody { statements, directives, .. } = body.unwrap().unbox();
-                            let mut new_body_stmts = OxcVec::with_capacity_in(statements.len(), self.allocator);
-                            for stmt in statements {
-                                new_body_stmts.push(self.map_statement(stmt));
-                            }
-                            let new_body = OxcBox(self.allocator.alloc(FunctionBody { statements: new_body_stmts, directives, span }));
-                            Declaration::FunctionDeclaration(OxcBox(self.allocator.alloc(Function {
-                                body: Some(new_body), span, id, expression, generator, r#async, params, type_parameters, return_type, modifiers, r#type,
-                            })))
-                        }
-                        Declaration::ClassDeclaration(class_decl) => {
-                            // Reuse existing visit_class logic
-                            let class_decl = self.map_class(class_decl.unbox());
-                            Declaration::ClassDeclaration(OxcBox(self.allocator.alloc(class_decl)))
-                        }
-                        Declaration::UsingDeclaration(_) => panic!("UsingDeclaration (stage 3) is not supported"),
-                        Declaration::TSTypeAliasDeclaration(_) => panic!("TSTypeAliasDeclaration is not supported"),
-                        Declaration::TSInterfaceDeclaration(_) => panic!("TSInterfaceDeclaration is not supported"),
-                        Declaration::TSModuleDeclaration(_) => panic!("TSModuleDeclaration is not supported"),
-                        Declaration::TSEnumDeclaration(_) => panic!("TSEnumDeclaration is not supported"),
-                        Declaration::TSImportEqualsDeclaration(_) => panic!("TSImportEqualsDeclaration is not supported"),
-                    }),
+                    Statement::Declaration(decl) => Statement::Declaration(self.map_declaration(decl)),
                     Statement::ModuleDeclaration(module_decl) => {
-                        Statement::ModuleDeclaration(module_decl)
+                        Statement::ModuleDeclaration(self.boxed(self.map_module_declaration(module_decl.unbox())))
                     },
                 };
+
+                if has_scope {
+                    self.leave_scope();
+                }
+                if has_ancestor {
+                    self.leave();
+                }
             }
 
             // Apply after visitors and potentially revisit
-            for visitor in &self.visitors_stmt {
+            for (index, visitor) in self.visitors_stmt.iter().enumerate() {
                 let (action, new_stmt) = visitor(stmt, self.allocator, false);
                 stmt = new_stmt;
-                if action == MapperAction::Revisit {
+                if action == MapperAction::Splice && in_list && matches!(stmt, Statement::BlockStatement(_)) {
+                    log!(Trace, "{}Splice statement", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() });
+                    return (stmt, true);
+                }
+                if action == MapperAction::Revisit || action == MapperAction::Splice {
+                    if !self.allow_revisit(&mut revisits, "statement", index, get_stmt_span(&stmt)) {
+                        return (stmt, false);
+                    }
                     visit_again = true;
-                    log!("{}Revisiting statement {:?}", if self.debug_id.len() > 0 { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", stmt).split(' ').next().unwrap_or(format!("{:?}", stmt).as_str()));
+                    log!(Trace, "{}Revisiting statement {:?}", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", stmt).split(' ').next().unwrap_or(format!("{:?}", stmt).as_str()));
                     break;
                 }
             }
 
-            log!("{}Leave statement {:?}", if self.debug_id.len() > 0 { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", stmt).split(' ').next().unwrap_or(format!("{:?}", stmt).as_str()));
+            log!(Trace, "{}Leave statement {:?}", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", stmt).split(' ').next().unwrap_or(format!("{:?}", stmt).as_str()));
         }
 
-        stmt
+        (stmt, false)
     }
 
     pub fn map_switch_statement(&self, stmt: SwitchStatement<'a>) -> SwitchStatement<'a> {
@@ -334,24 +768,36 @@ impl<'a> Mapper<'a> {
         let discriminant = self.map_expression(discriminant);
         let mut new_cases = OxcVec::with_capacity_in(cases.len(), self.allocator);
 
-        let mut case_index = 0;
         let case_count = cases.len();
-        for case in cases {
-            log!("{}Enter switch case {} of {}", if self.debug_id.len() > 0 { format!("{}: ", self.debug_id) } else { "".to_string() }, case_index, case_count);
-            let test = case.test.map(|test| self.map_expression(test));
-            let mut new_consequent = OxcVec::with_capacity_in(case.consequent.len(), self.allocator);
-            for stmt in case.consequent {
-                new_consequent.push(self.map_statement(stmt));
-            }
-            new_cases.push(SwitchCase { test, consequent: new_consequent, span: case.span });
-            case_index += 1;
+        for (case_index, case) in cases.into_iter().enumerate() {
+            log!(Trace, "{}Enter switch case {} of {}", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() }, case_index, case_count);
+            new_cases.push(self.map_switch_case(case));
         }
 
         SwitchStatement { discriminant, cases: new_cases, span }
     }
 
-    fn map_expression(&self, mut expr: Expression<'a>) -> Expression<'a> {
-        log!("{}Enter expression {:?} {}", if self.debug_id.len() > 0 { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", expr).split(' ').next().unwrap_or(format!("{:?}", expr).as_str()),
+    fn map_switch_case(&self, case: SwitchCase<'a>) -> SwitchCase<'a> {
+        let span = case.span;
+        self.visit_node("switch case", &self.visitors_switch_case, case, span, |case| {
+            let SwitchCase { test, consequent, span } = case;
+            let test = test.map(|test| self.with_barrier("a `case` test", || self.map_expression(test)));
+            let consequent = self.map_statements(consequent);
+            SwitchCase { test, consequent, span }
+        })
+    }
+
+    fn map_expression(&self, expr: Expression<'a>) -> Expression<'a> {
+        if !self.enter_nesting(expr.span()) {
+            return expr;
+        }
+        let expr = self.map_expression_unchecked(expr);
+        self.leave_nesting();
+        expr
+    }
+
+    fn map_expression_unchecked(&self, mut expr: Expression<'a>) -> Expression<'a> {
+        log!(Trace, "{}Enter expression {:?} {}", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", expr).split(' ').next().unwrap_or(format!("{:?}", expr).as_str()),
             if let Expression::Identifier(id) = &expr {
                 format!("id: {}", id.name)
             } else {
@@ -359,6 +805,12 @@ impl<'a> Mapper<'a> {
             }
         );
 
+        // Whether statements can be moved to before the statement is decided by what came before this expression
+        let mut prelude = Prelude::new(self.prelude_blocked_by());
+
+        let mut revisits = 0;
+        // Set when a visitor asked for too many revisits in the before phase. The node is not entered then.
+        let mut gave_up = false;
         // Apply before visitors first
         let mut visit_again = true;
         let mut enter_node;
@@ -366,509 +818,472 @@ impl<'a> Mapper<'a> {
             enter_node = true;
             visit_again = false;
 
-            for visitor in &self.visitors_expr {
-                let (action, new_expr) = visitor(expr, self.allocator, true);
+            for (index, visitor) in self.visitors_expr.iter().enumerate() {
+                let (action, new_expr) = visitor(expr, self.allocator, true, &mut prelude);
                 expr = new_expr;
                 if action == MapperAction::Revisit {
+                    if !self.allow_revisit(&mut revisits, "expression", index, expr.span()) {
+                        gave_up = true;
+                        break;
+                    }
                     visit_again = true;
-                    log!("{}Revisit expression {:?}", if self.debug_id.len() > 0 { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", expr).split(' ').next().unwrap_or(format!("{:?}", expr).as_str()));
+                    log!(Trace, "{}Revisit expression {:?}", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", expr).split(' ').next().unwrap_or(format!("{:?}", expr).as_str()));
                     break;
                 }
-                enter_node |= action == MapperAction::Normal;
+                if action == MapperAction::Skip {
+                    enter_node = false;
+                }
+            }
+            self.hoist_prelude(&mut prelude, expr.span());
+            if visit_again || gave_up {
+                continue;
             }
 
             // Only map children if we're not skipping the visit
             if enter_node {
-                expr = match expr {
-                    Expression::ArrayExpression(array) => {
-                        let ArrayExpression { elements, span, trailing_comma } = array.unbox();
-
-                        let mut new_elements = OxcVec::with_capacity_in(elements.len(), self.allocator);
-
-                        for elem in elements {
-                            match elem {
-                                ArrayExpressionElement::Expression(expr) => new_elements.push(ArrayExpressionElement::Expression(self.map_expression(expr))),
-                                ArrayExpressionElement::SpreadElement(spread) => {
-                                    let SpreadElement { argument, span } = spread.unbox();
-                                    new_elements.push(ArrayExpressionElement::SpreadElement(
-                                        OxcBox(self.allocator.alloc(SpreadElement { argument: self.map_expression(argument), span }))
-                                    ))
-                                }
-                                ArrayExpressionElement::Elision(e) => new_elements.push(ArrayExpressionElement::Elision(e)),
-                            }
-                        }
+                expr = self.map_expression_children(expr);
+            }
 
-                        Expression::ArrayExpression(OxcBox(self.allocator.alloc(ArrayExpression { elements: new_elements, span, trailing_comma })))
-                    }
-                    Expression::ArrowExpression(arrow) => {
-                        let ArrowExpression {
-                            params, body, span: arrow_span, r#async, expression, generator, type_parameters, return_type
-                        } = arrow.unbox();
-
-                        let FormalParameters { items, span, kind, rest } = params.unbox();
-                        let mut new_params = OxcVec::with_capacity_in(items.len(), self.allocator);
-                        for param in items {
-                            let FormalParameter { pattern, accessibility, readonly, decorators, span } = param;
-                            let pattern = self.map_binding_pattern(pattern);
-                            new_params.push(FormalParameter { pattern, accessibility, readonly, decorators, span });
-                        }
-                        let new_params = OxcBox(self.allocator.alloc(FormalParameters { items: new_params, span, kind, rest }));
-
-                        // Note: for a function expression they still create a whole function body. The first statement is an expression statement that gets unboxed.
-                        let FunctionBody { statements, directives, span: body_span } = body.unbox();
-
-                        if expression {
-                            let first = statements.into_iter().next().unwrap();
-                            let expr = if let Statement::ExpressionStatement(expr_stmt) = first {
-                                expr_stmt.unbox().expression
-                            } else {
-                                panic!("Arrow Function expression did not start with an expression statement...");
-                            };
-
-                            let mut new_body = OxcVec::with_capacity_in(1, self.allocator);
-                            new_body.push(Statement::ExpressionStatement(OxcBox(self.allocator.alloc(ExpressionStatement { expression: expr, span: body_span }))));
-                            let body = OxcBox(self.allocator.alloc(FunctionBody { statements: new_body, directives, span: body_span }));
-
-                            Expression::ArrowExpression(OxcBox(self.allocator.alloc(ArrowExpression {
-                                params: new_params,
-                                expression: true,
-                                body,
-                                span: arrow_span,
-                                r#async,
-                                generator,
-                                type_parameters,
-                                return_type,
-                            })))
+            // Apply after visitors and potentially revisit
+            for (index, visitor) in self.visitors_expr.iter().enumerate() {
+                let (action, new_expr) = visitor(expr, self.allocator, false, &mut prelude);
+                expr = new_expr;
+                if action == MapperAction::Revisit {
+                    visit_again = self.allow_revisit(&mut revisits, "expression", index, expr.span());
+                    log!(Trace, "{}Revisiting expression {:?}", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", expr).split(' ').next().unwrap_or(format!("{:?}", expr).as_str()));
+                    break;
+                }
+            }
+            self.hoist_prelude(&mut prelude, expr.span());
+        }
+        self.note_effects(&expr);
 
-                        } else {
-                            let mut new_body_stmts = OxcVec::with_capacity_in(statements.len(), self.allocator);
-                            for stmt in statements {
-                                new_body_stmts.push(self.map_statement(stmt));
-                            }
-                            let body = OxcBox(self.allocator.alloc(FunctionBody { statements: new_body_stmts, directives, span }));
+        log!(Trace, "{}Leave expression {:?} {}", if !self.debug_id.is_empty() { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", expr).split(' ').next().unwrap_or(format!("{:?}", expr).as_str()),
+            if let Expression::Identifier(id) = &expr {
+                format!("id: {}", id.name)
+            } else {
+                "".to_string()
+            }
+        );
+        expr
+    }
 
-                            Expression::ArrowExpression(OxcBox(self.allocator.alloc(ArrowExpression {
-                                params: new_params,
-                                body,
-                                expression: false,
-                                span,
-                                r#async,
-                                generator,
-                                type_parameters,
-                                return_type,
-                            })))
-                        }
-                    }
-                    Expression::AssignmentExpression(assign) => {
-                        let AssignmentExpression { left, right, span, operator } = assign.unbox();
+    /// Map the children of an expression. Kept out of `map_expression`, and with the bigger arms in
+    /// functions of their own, because the stack frame of a function with one large match is the sum
+    /// of all of its arms in debug builds. That frame is paid for every level of nesting.
+    fn map_expression_children(&self, expr: Expression<'a>) -> Expression<'a> {
+        match expr {
+            Expression::ArrayExpression(array) => {
+                Expression::ArrayExpression(self.boxed(self.map_array_expression(array.unbox())))
+            }
+            Expression::ArrowExpression(arrow) => {
+                Expression::ArrowExpression(self.boxed(self.map_arrow(arrow.unbox())))
+            }
+            Expression::AssignmentExpression(assign) => {
+                Expression::AssignmentExpression(self.boxed(self.map_assignment_expression(assign.unbox())))
+            }
+            Expression::AwaitExpression(await_expr) => {
+                let AwaitExpression { argument, span } = await_expr.unbox();
+                Expression::AwaitExpression(self.boxed(AwaitExpression { argument: self.map_expression(argument), span }))
+            }
+            Expression::BinaryExpression(binary) => {
+                Expression::BinaryExpression(self.boxed(self.map_binary_expression(binary.unbox())))
+            }
+            Expression::CallExpression(call) => {
+                Expression::CallExpression(self.boxed(self.map_call_expression(call.unbox())))
+            }
+            Expression::ChainExpression(chain) => {
+                Expression::ChainExpression(self.boxed(self.map_chain_expression(chain.unbox())))
+            }
+            Expression::ClassExpression(class) => {
+                Expression::ClassExpression(self.boxed(self.map_class(class.unbox())))
+            }
+            Expression::ConditionalExpression(cond) => {
+                Expression::ConditionalExpression(self.boxed(self.map_conditional_expression(cond.unbox())))
+            }
+            Expression::FunctionExpression(func) => {
+                Expression::FunctionExpression(self.boxed(self.map_function(func.unbox())))
+            }
+            Expression::LogicalExpression(logical) => {
+                Expression::LogicalExpression(self.boxed(self.map_logical_expression(logical.unbox())))
+            }
+            Expression::MemberExpression(member) => {
+                Expression::MemberExpression(self.boxed(self.map_member_expression(member.unbox())))
+            }
+            Expression::NewExpression(new_expr) => {
+                Expression::NewExpression(self.boxed(self.map_new_expression(new_expr.unbox())))
+            }
+            Expression::ObjectExpression(object) => {
+                Expression::ObjectExpression(self.boxed(self.map_object_expression(object.unbox())))
+            }
+            Expression::SequenceExpression(seq) => {
+                let SequenceExpression { expressions, span } = seq.unbox();
+                Expression::SequenceExpression(self.boxed(SequenceExpression { expressions: self.map_expressions(expressions), span }))
+            }
+            Expression::TaggedTemplateExpression(tagged) => {
+                Expression::TaggedTemplateExpression(self.boxed(self.map_tagged_template_expression(tagged.unbox())))
+            }
+            Expression::UnaryExpression(unary) => {
+                let UnaryExpression { argument, span, operator } = unary.unbox();
+                Expression::UnaryExpression(self.boxed(UnaryExpression { argument: self.map_expression(argument), span, operator }))
+            }
+            Expression::UpdateExpression(update) => {
+                Expression::UpdateExpression(self.boxed(self.map_update_expression(update.unbox())))
+            }
+            Expression::YieldExpression(yield_expr) => {
+                let YieldExpression { argument, span, delegate } = yield_expr.unbox();
+                Expression::YieldExpression(self.boxed(YieldExpression { argument: argument.map(|arg| self.map_expression(arg)), span, delegate }))
+            }
+            Expression::TemplateLiteral(template) => {
+                Expression::TemplateLiteral(self.boxed(self.map_template_literal(template.unbox())))
+            }
+            Expression::ParenthesizedExpression(expr) => {
+                let ParenthesizedExpression { expression, span } = expr.unbox();
+                Expression::ParenthesizedExpression(self.boxed(ParenthesizedExpression { expression: self.map_expression(expression), span }))
+            }
+            Expression::ImportExpression(import) => {
+                let ImportExpression { source, arguments, span } = import.unbox();
+                let source = self.map_expression(source);
+                Expression::ImportExpression(self.boxed(ImportExpression { source, arguments: self.map_expressions(arguments), span }))
+            }
+            // This represents `#field in obj` in private class fields
+            Expression::PrivateInExpression(private_in) => {
+                let PrivateInExpression { left, right, operator, span } = private_in.unbox();
+                Expression::PrivateInExpression(self.boxed(PrivateInExpression { left, right: self.map_expression(right), operator, span }))
+            }
 
-                        let left = self.map_assignment_target(left);
-                        let right = self.map_expression(right);
+            // No children to visit
+            | Expression::ThisExpression(_)
+            | Expression::BooleanLiteral(_)
+            | Expression::NullLiteral(_)
+            | Expression::NumberLiteral(_)
+            | Expression::StringLiteral(_)
+            | Expression::BigintLiteral(_)
+            | Expression::RegExpLiteral(_)
+            | Expression::Identifier(_)
+            | Expression::MetaProperty(_) // import.meta
+            | Expression::Super(_)
+            => expr,
+
+            Expression::JSXElement(jsx) => {
+                self.report_unsupported("JSXElement", jsx.span);
+                Expression::JSXElement(jsx)
+            }
+            Expression::JSXFragment(jsx) => {
+                self.report_unsupported("JSXFragment", jsx.span);
+                Expression::JSXFragment(jsx)
+            }
+            Expression::TSAsExpression(ts) => {
+                self.report_unsupported("TSAsExpression", ts.span);
+                Expression::TSAsExpression(ts)
+            }
+            Expression::TSSatisfiesExpression(ts) => {
+                self.report_unsupported("TSSatisfiesExpression", ts.span);
+                Expression::TSSatisfiesExpression(ts)
+            }
+            Expression::TSTypeAssertion(ts) => {
+                self.report_unsupported("TSTypeAssertion", ts.span);
+                Expression::TSTypeAssertion(ts)
+            }
+            Expression::TSNonNullExpression(ts) => {
+                self.report_unsupported("TSNonNullExpression", ts.span);
+                Expression::TSNonNullExpression(ts)
+            }
+            Expression::TSInstantiationExpression(ts) => {
+                self.report_unsupported("TSInstantiationExpression", ts.span);
+                Expression::TSInstantiationExpression(ts)
+            }
+        }
+    }
 
-                        Expression::AssignmentExpression(OxcBox(self.allocator.alloc(AssignmentExpression { left, right, span, operator })))
-                    }
-                    Expression::AwaitExpression(await_expr) => {
-                        let AwaitExpression { argument, span } = await_expr.unbox();
+    fn map_expressions(&self, expressions: OxcVec<'a, Expression<'a>>) -> OxcVec<'a, Expression<'a>> {
+        let mut new_expressions = OxcVec::with_capacity_in(expressions.len(), self.allocator);
+        for expr in expressions {
+            new_expressions.push(self.map_expression(expr));
+        }
+        new_expressions
+    }
 
-                        let argument = self.map_expression(argument);
+    fn map_assignment_expression(&self, assign: AssignmentExpression<'a>) -> AssignmentExpression<'a> {
+        let AssignmentExpression { left, right, span, operator } = assign;
 
-                        Expression::AwaitExpression(OxcBox(self.allocator.alloc(AwaitExpression { argument, span })))
-                    }
-                    Expression::BinaryExpression(binary) => {
-                        let BinaryExpression { left, right, span, operator } = binary.unbox();
+        let left = self.map_assignment_target(left);
+        let right = if matches!(operator, AssignmentOperator::LogicalAnd | AssignmentOperator::LogicalOr | AssignmentOperator::LogicalNullish) {
+            self.with_barrier("the right side of a logical assignment", || self.map_expression(right))
+        } else {
+            self.map_expression(right)
+        };
 
-                        let left = self.map_expression(left);
-                        let right = self.map_expression(right);
+        AssignmentExpression { left, right, span, operator }
+    }
 
-                        Expression::BinaryExpression(OxcBox(self.allocator.alloc(BinaryExpression { left, right, span, operator })))
-                    }
-                    Expression::CallExpression(call) => {
-                        let CallExpression { callee, arguments, span, optional, type_parameters } = call.unbox();
-
-                        let callee = self.map_expression(callee);
-                        let mut new_arguments = OxcVec::with_capacity_in(arguments.len(), self.allocator);
-                        for arg in arguments {
-                            match arg {
-                                Argument::Expression(expr) => new_arguments.push(Argument::Expression(self.map_expression(expr))),
-                                Argument::SpreadElement(spread) => {
-                                    let SpreadElement { argument, span } = spread.unbox();
-                                    new_arguments.push(Argument::SpreadElement(OxcBox(self.allocator.alloc(SpreadElement {
-                                        argument: self.map_expression(argument),
-                                        span,
-                                    }))))
-                                }
-                            }
-                        }
+    fn map_binary_expression(&self, binary: BinaryExpression<'a>) -> BinaryExpression<'a> {
+        let BinaryExpression { left, right, span, operator } = binary;
 
-                        Expression::CallExpression(OxcBox(self.allocator.alloc(CallExpression { callee, arguments: new_arguments, span, optional, type_parameters })))
-                    }
-                    Expression::ChainExpression(chain) => {
-                        let ChainExpression { expression, span } = chain.unbox();
-
-                        let ce = match expression {
-                            ChainElement::CallExpression(call) => {
-                                let CallExpression { callee, arguments, span, optional, type_parameters } = call.unbox();
-
-                                let callee = self.map_expression(callee);
-                                let mut new_arguments = OxcVec::with_capacity_in(arguments.len(), self.allocator);
-                                for arg in arguments {
-                                    match arg {
-                                        Argument::Expression(expr) => new_arguments.push(Argument::Expression(self.map_expression(expr))),
-                                        Argument::SpreadElement(spread) => {
-                                            let SpreadElement { argument, span } = spread.unbox();
-                                            new_arguments.push(Argument::SpreadElement(OxcBox(self.allocator.alloc(SpreadElement { argument: self.map_expression(argument), span }))))
-                                        }
-                                    }
-                                }
-
-                                ChainElement::CallExpression(OxcBox(self.allocator.alloc(CallExpression { callee, arguments: new_arguments, span, optional, type_parameters })))
-                            }
-                            ChainElement::MemberExpression(member) => {
-                                match member.unbox() {
-                                    MemberExpression::ComputedMemberExpression(computed) => {
-                                        let ComputedMemberExpression { object, expression, span, optional } = computed;
-
-                                        let object = self.map_expression(object);
-                                        let expression = self.map_expression(expression);
-
-                                        ChainElement::MemberExpression(OxcBox(self.allocator.alloc(
-                                            MemberExpression::ComputedMemberExpression(ComputedMemberExpression { object, expression, span, optional })
-                                        )))
-                                    }
-                                    MemberExpression::StaticMemberExpression(static_member) => {
-                                        // "static" being the opposite of computed, not related to the "static" keyword
-                                        let StaticMemberExpression { object, property, span, optional } = static_member;
-
-                                        let object = self.map_expression(object);
-
-                                        ChainElement::MemberExpression(OxcBox(self.allocator.alloc(
-                                            MemberExpression::StaticMemberExpression(StaticMemberExpression { object, property, span, optional })
-                                        )))
-                                    }
-                                    MemberExpression::PrivateFieldExpression(_private_field) => {
-                                        todo!("TODO: not sure how to walk this properly :D");
-                                        // self.visit_expression(&private_field.object);
-                                    }
-                                }
-                            }
-                        };
+        let left = self.map_expression(left);
+        let right = self.map_expression(right);
 
-                        Expression::ChainExpression(OxcBox(self.allocator.alloc(ChainExpression { expression: ce, span })))
-                    }
-                    Expression::ClassExpression(class) => {
-                        Expression::ClassExpression(OxcBox(self.allocator.alloc(self.map_class(class.unbox()))))
-                    }
-                    Expression::ConditionalExpression(cond) => {
-                        let ConditionalExpression { test, consequent, alternate, span } = cond.unbox();
+        BinaryExpression { left, right, span, operator }
+    }
 
-                        let test = self.map_expression(test);
-                        let consequent = self.map_expression(consequent);
-                        let alternate = self.map_expression(alternate);
+    fn map_chain_expression(&self, chain: ChainExpression<'a>) -> ChainExpression<'a> {
+        let ChainExpression { expression, span } = chain;
 
-                        Expression::ConditionalExpression(OxcBox(self.allocator.alloc(ConditionalExpression { test, consequent, alternate, span })))
-                    }
-                    Expression::FunctionExpression(func) => {
-                        Expression::FunctionExpression(OxcBox(self.allocator.alloc(self.map_function(func.unbox()))))
-                    }
-                    Expression::LogicalExpression(logical) => {
-                        let LogicalExpression { left, right, span, operator } = logical.unbox();
+        let expression = self.with_barrier("an optional chain", || match expression {
+            ChainElement::CallExpression(call) => {
+                ChainElement::CallExpression(self.boxed(self.map_call_expression(call.unbox())))
+            }
+            ChainElement::MemberExpression(member) => {
+                ChainElement::MemberExpression(self.boxed(self.map_member_expression(member.unbox())))
+            }
+        });
 
-                        let left = self.map_expression(left);
-                        let right = self.map_expression(right);
+        ChainExpression { expression, span }
+    }
 
-                        Expression::LogicalExpression(OxcBox(self.allocator.alloc(LogicalExpression { left, right, span, operator })))
-                    }
-                    Expression::MemberExpression(member) => {
-                        match member.unbox() {
-                            MemberExpression::ComputedMemberExpression(computed) => {
-                                let ComputedMemberExpression { object, expression, span, optional } = computed;
+    fn map_conditional_expression(&self, cond: ConditionalExpression<'a>) -> ConditionalExpression<'a> {
+        let ConditionalExpression { test, consequent, alternate, span } = cond;
 
-                                let object = self.map_expression(object);
-                                let expression = self.map_expression(expression);
+        let test = self.map_expression(test);
+        let consequent = self.with_barrier("a branch of a conditional expression", || self.map_expression(consequent));
+        let alternate = self.with_barrier("a branch of a conditional expression", || self.map_expression(alternate));
 
-                                Expression::MemberExpression(OxcBox(self.allocator.alloc(MemberExpression::ComputedMemberExpression(ComputedMemberExpression { object, expression, span, optional }))))
-                            }
-                            MemberExpression::StaticMemberExpression(static_member) => {
-                                let StaticMemberExpression { object, property, span, optional } = static_member;
+        ConditionalExpression { test, consequent, alternate, span }
+    }
 
-                                let object = self.map_expression(object);
-                                // let property = self.map_expression(property);
+    fn map_logical_expression(&self, logical: LogicalExpression<'a>) -> LogicalExpression<'a> {
+        let LogicalExpression { left, right, span, operator } = logical;
 
-                                Expression::MemberExpression(OxcBox(self.allocator.alloc(MemberExpression::StaticMemberExpression(StaticMemberExpression { object, property, span, optional }))))
-                            }
-                            MemberExpression::PrivateFieldExpression(private_field) => {
-                                let PrivateFieldExpression { object, span, optional, field } = private_field;
+        let left = self.map_expression(left);
+        let right = self.with_barrier("the right side of a logical operator", || self.map_expression(right));
 
-                                let object = self.map_expression(object);
-                                // let property = self.map_expression(field.name);
+        LogicalExpression { left, right, span, operator }
+    }
 
-                                Expression::MemberExpression(OxcBox(self.allocator.alloc(MemberExpression::PrivateFieldExpression(PrivateFieldExpression { object, field, span, optional }))))
-                            }
-                        }
-                    }
-                    Expression::NewExpression(new_expr) => {
-                        let NewExpression { callee, arguments, span, type_parameters } = new_expr.unbox();
-
-                        let callee = self.map_expression(callee);
-                        let mut new_arguments = OxcVec::with_capacity_in(arguments.len(), self.allocator);
-                        for arg in arguments {
-                            match arg {
-                                Argument::Expression(expr) => new_arguments.push(Argument::Expression(self.map_expression(expr))),
-                                Argument::SpreadElement(spread) => {
-                                    let SpreadElement { argument, span } = spread.unbox();
-                                    new_arguments.push(Argument::SpreadElement(OxcBox(self.allocator.alloc(SpreadElement { argument: self.map_expression(argument), span }))))
-                                }
-                            }
-                        }
+    fn map_new_expression(&self, new_expr: NewExpression<'a>) -> NewExpression<'a> {
+        let NewExpression { callee, arguments, span, type_parameters } = new_expr;
 
-                        Expression::NewExpression(OxcBox(self.allocator.alloc(NewExpression { callee, arguments: new_arguments, span, type_parameters })))
-                    }
-                    Expression::ObjectExpression(object) => {
-                        let ObjectExpression { properties, span, trailing_comma } = object.unbox();
-
-                        let mut new_properties: OxcVec<'a, ObjectPropertyKind<'a>> = OxcVec::with_capacity_in(properties.len(), self.allocator);
-                        for prop in properties {
-                            match prop {
-                                ObjectPropertyKind::ObjectProperty(prop) => {
-                                    let ObjectProperty { kind, key, value, span, method, shorthand, computed, init } = prop.unbox();
-
-                                    match key {
-                                        PropertyKey::Expression(expr) => new_properties.push(
-                                            ObjectPropertyKind::ObjectProperty(OxcBox(self.allocator.alloc(ObjectProperty {
-                                                kind,
-                                                key: PropertyKey::Expression(self.map_expression(expr)),
-                                                value: self.map_expression(value),
-                                                span,
-                                                method,
-                                                shorthand,
-                                                computed,
-                                                init,
-                                            })))
-                                        ),
-                                        PropertyKey::Identifier(ident) => new_properties.push(
-                                            ObjectPropertyKind::ObjectProperty(OxcBox(self.allocator.alloc(ObjectProperty {
-                                                kind,
-                                                key: PropertyKey::Identifier(ident),
-                                                value: self.map_expression(value),
-                                                span,
-                                                method,
-                                                shorthand,
-                                                computed,
-                                                init,
-                                            })))
-                                        ),
-                                        PropertyKey::PrivateIdentifier(ident) => new_properties.push(
-                                            ObjectPropertyKind::ObjectProperty(OxcBox(self.allocator.alloc(ObjectProperty {
-                                                kind,
-                                                key: PropertyKey::PrivateIdentifier(ident),
-                                                value: self.map_expression(value),
-                                                span,
-                                                method,
-                                                shorthand,
-                                                computed,
-                                                init,
-                                            })))
-                                        ),
-                                    }
-                                }
-                                ObjectPropertyKind::SpreadProperty(spread) => {
-
-                                    let SpreadElement { argument, span } = spread.unbox();
-
-                                    new_properties.push(
-                                        ObjectPropertyKind::SpreadProperty(OxcBox(self.allocator.alloc(SpreadElement {
-                                            argument: self.map_expression(argument),
-                                            span,
-                                        })))
-                                    );
-                                }
-                            }
-                        }
+        let callee = self.map_expression(callee);
+        let arguments = self.map_arguments(arguments);
 
-                        Expression::ObjectExpression(OxcBox(self.allocator.alloc(ObjectExpression { properties: new_properties, span, trailing_comma })))
-                    }
-                    Expression::SequenceExpression(seq) => {
-                        let SequenceExpression { expressions, span } = seq.unbox();
+        NewExpression { callee, arguments, span, type_parameters }
+    }
 
-                        let mut new_expressions = OxcVec::with_capacity_in(expressions.len(), self.allocator);
-                        for expr in expressions {
-                            new_expressions.push(self.map_expression(expr));
-                        }
+    fn map_tagged_template_expression(&self, tagged: TaggedTemplateExpression<'a>) -> TaggedTemplateExpression<'a> {
+        let TaggedTemplateExpression { tag, quasi, span, type_parameters } = tagged;
 
-                        Expression::SequenceExpression(OxcBox(self.allocator.alloc(SequenceExpression { expressions: new_expressions, span })))
-                    }
-                    Expression::TaggedTemplateExpression(tagged) => {
-                        let TaggedTemplateExpression { tag, quasi, span, type_parameters } = tagged.unbox();
+        // FIXME: this is not correct, we need to visit the tag expressions as a vec
+        let tag = self.map_expression(tag);
+        let quasi = self.map_template_literal(quasi);
+
+        TaggedTemplateExpression { tag, quasi, span, type_parameters }
+    }
 
-                        // FIXME: this is not correct, we need to visit the tag expressions as a vec
-                        let tag = self.map_expression(tag);
-                        let quasi = self.map_template_literal(quasi);
+    fn map_update_expression(&self, update: UpdateExpression<'a>) -> UpdateExpression<'a> {
+        let UpdateExpression { argument, span, operator, prefix } = update;
 
-                        Expression::TaggedTemplateExpression(OxcBox(self.allocator.alloc(TaggedTemplateExpression { tag, quasi, span, type_parameters })))
-                    }
-                    Expression::ThisExpression(this_node) => {
-                        Expression::ThisExpression(OxcBox(self.allocator.alloc(ThisExpression { span: this_node.span }))) // No children to visit
-                    }
-                    Expression::UnaryExpression(unary) => {
-                        let UnaryExpression { argument, span, operator } = unary.unbox();
+        let argument = match argument {
+            // Simple being the `x` in `x = y`, but it's not actually an expression, so ... visit? no visit? meh.
+            SimpleAssignmentTarget::AssignmentTargetIdentifier(ident) => SimpleAssignmentTarget::AssignmentTargetIdentifier(ident),
+            SimpleAssignmentTarget::MemberAssignmentTarget(member) => {
+                SimpleAssignmentTarget::MemberAssignmentTarget(self.boxed(self.map_member_expression(member.unbox())))
+            }
+            // let tp = self.map_type_parameter(tp);
+            ts @ (SimpleAssignmentTarget::TSAsExpression(_)
+            | SimpleAssignmentTarget::TSSatisfiesExpression(_)
+            | SimpleAssignmentTarget::TSNonNullExpression(_)
+            | SimpleAssignmentTarget::TSTypeAssertion(_)) => ts,
+        };
 
-                        let argument = self.map_expression(argument);
+        UpdateExpression { argument, span, operator, prefix }
+    }
 
-                        Expression::UnaryExpression(OxcBox(self.allocator.alloc(UnaryExpression { argument, span, operator })))
-                    }
-                    Expression::UpdateExpression(update) => {
-                        let UpdateExpression { argument, span, operator, prefix } = update.unbox();
-
-                        match argument {
-                            SimpleAssignmentTarget::AssignmentTargetIdentifier(ident) => {
-                                // Simple being the `x` in `x = y`, but it's not actually an expression, so ... visit? no visit? meh.
-
-                                Expression::UpdateExpression(OxcBox(self.allocator.alloc(UpdateExpression {
-                                    argument: SimpleAssignmentTarget::AssignmentTargetIdentifier(ident),
-                                    span,
-                                    operator,
-                                    prefix,
-                                })))
-                            }
-                            SimpleAssignmentTarget::MemberAssignmentTarget(member) => {
-                                // We definitely visit the expression of a computed member expression
-                                // but do we visit the object of a static member expression?
-                                // self.visit_expression(&member.object);
-
-                                match member.unbox() {
-                                    MemberExpression::ComputedMemberExpression(computed) => {
-                                        let ComputedMemberExpression { object, expression, span, optional } = computed;
-
-                                        let object = self.map_expression(object);
-                                        let expression = self.map_expression(expression);
-
-                                        Expression::UpdateExpression(OxcBox(self.allocator.alloc(UpdateExpression {
-                                            argument: SimpleAssignmentTarget::MemberAssignmentTarget(OxcBox(self.allocator.alloc(
-                                                MemberExpression::ComputedMemberExpression(
-                                                    ComputedMemberExpression {
-                                                        object,
-                                                        expression,
-                                                        span,
-                                                        optional,
-                                                    }
-                                            )))),
-                                            span,
-                                            operator,
-                                            prefix,
-                                        })))
-                                    }
-                                    MemberExpression::StaticMemberExpression(static_member) => {
-                                        // Do we visit the object of a static member expression when it's an assignment target?
-                                        // self.visit_expression(&static_member.property);
-
-                                        let StaticMemberExpression { object, property, span, optional } = static_member;
-
-                                        let object = self.map_expression(object);
-
-                                        Expression::UpdateExpression(OxcBox(self.allocator.alloc(UpdateExpression {
-                                            argument: SimpleAssignmentTarget::MemberAssignmentTarget(OxcBox(self.allocator.alloc(
-                                                MemberExpression::StaticMemberExpression(
-                                                    StaticMemberExpression {
-                                                        object,
-                                                        property,
-                                                        span,
-                                                        optional,
-                                                    }
-                                            )))),
-                                            span,
-                                            operator,
-                                            prefix,
-                                        })))
-                                    }
-                                    MemberExpression::PrivateFieldExpression(_private_field) => {
-                                        todo!("TODO: not sure how to walk this properly :D");
-                                        // self.visit_expression(&private_field.object);
-                                    }
-                                }
-                            }
-                            SimpleAssignmentTarget::TSAsExpression(tp) => {
-                                // let tp = self.map_type_parameter(tp);
-                                Expression::UpdateExpression(OxcBox(self.allocator.alloc(UpdateExpression { argument: SimpleAssignmentTarget::TSAsExpression(tp), span, operator, prefix })))
-                            }
-                            SimpleAssignmentTarget::TSSatisfiesExpression(tp) => {
-                                // let tp = self.map_type_parameter(tp);
-                                Expression::UpdateExpression(OxcBox(self.allocator.alloc(UpdateExpression { argument: SimpleAssignmentTarget::TSSatisfiesExpression(tp), span, operator, prefix })))
-                            }
-                            SimpleAssignmentTarget::TSNonNullExpression(tp) => {
-                                // let tp = self.map_type_parameter(tp);
-                                Expression::UpdateExpression(OxcBox(self.allocator.alloc(UpdateExpression { argument: SimpleAssignmentTarget::TSNonNullExpression(tp), span, operator, prefix })))
-                            }
-                            SimpleAssignmentTarget::TSTypeAssertion(tp) => {
-                                // let tp = self.map_type_parameter(tp);
-                                Expression::UpdateExpression(OxcBox(self.allocator.alloc(UpdateExpression { argument: SimpleAssignmentTarget::TSTypeAssertion(tp), span, operator, prefix })))
-                            }
-                        }
-                    }
-                    Expression::YieldExpression(yield_expr) => {
-                        let YieldExpression { mut argument, span, delegate } = yield_expr.unbox();
+    fn map_array_expression(&self, array: ArrayExpression<'a>) -> ArrayExpression<'a> {
+        let ArrayExpression { elements, span, trailing_comma } = array;
 
-                        if let Some(arg) = argument {
-                            let arg = self.map_expression(arg);
-                            argument = Some(arg);
-                        }
+        let mut new_elements = OxcVec::with_capacity_in(elements.len(), self.allocator);
 
-                        Expression::YieldExpression(OxcBox(self.allocator.alloc(YieldExpression { argument, span, delegate })))
-                    }
-                    Expression::TemplateLiteral(template) => Expression::TemplateLiteral(template),
-                    Expression::BooleanLiteral(literal) => Expression::BooleanLiteral(literal),
-                    Expression::NullLiteral(literal) => Expression::NullLiteral(literal),
-                    Expression::NumberLiteral(literal) => Expression::NumberLiteral(literal),
-                    Expression::StringLiteral(literal) => Expression::StringLiteral(literal),
-                    Expression::RegExpLiteral(literal) => Expression::RegExpLiteral(literal),
-                    Expression::Identifier(ident) => Expression::Identifier(ident),
-                    Expression::MetaProperty(meta) => Expression::MetaProperty(meta), // import.meta
-                    Expression::Super(superrrr) => Expression::Super(superrrr),
-                    Expression::ParenthesizedExpression(expr) => {
-                        let ParenthesizedExpression { expression, span } = expr.unbox();
-                        Expression::ParenthesizedExpression(OxcBox(self.allocator.alloc(ParenthesizedExpression { expression: self.map_expression(expression), span })))
-                    }
-                    Expression::ImportExpression(import) => {
-                        let ImportExpression { source, arguments, span } = import.unbox();
-                        Expression::ImportExpression(OxcBox(self.allocator.alloc(ImportExpression { source: self.map_expression(source), arguments, span })))
-                    }
-                    Expression::BigintLiteral(literal) => {
-                        let BigintLiteral { value, base, span } = literal.unbox();
-                        Expression::BigintLiteral(OxcBox(self.allocator.alloc(BigintLiteral { value, base, span })))
-                    }
+        for elem in elements {
+            match elem {
+                ArrayExpressionElement::Expression(expr) => new_elements.push(ArrayExpressionElement::Expression(self.map_expression(expr))),
+                ArrayExpressionElement::SpreadElement(spread) => {
+                    let SpreadElement { argument, span } = spread.unbox();
+                    new_elements.push(ArrayExpressionElement::SpreadElement(
+                        self.boxed(SpreadElement { argument: self.map_expression(argument), span })
+                    ))
+                }
+                ArrayExpressionElement::Elision(e) => new_elements.push(ArrayExpressionElement::Elision(e)),
+            }
+        }
 
-                    // This represents `#field in obj` in private class fields
-                    Expression::PrivateInExpression(_) => panic!("PrivateInExpression (stage 3) is not supported"),
+        ArrayExpression { elements: new_elements, span, trailing_comma }
+    }
 
-                    Expression::JSXElement(_) => panic!("JSXElement is not supported"),
-                    Expression::JSXFragment(_) => panic!("JSXFragment is not supported"),
-                    Expression::TSAsExpression(_) => panic!("TSAsExpression is not supported"),
-                    Expression::TSSatisfiesExpression(_) => panic!("TSSatisfiesExpression is not supported"),
-                    Expression::TSTypeAssertion(_) => panic!("TSTypeAssertion is not supported"),
-                    Expression::TSNonNullExpression(_) => panic!("TSNonNullExpression is not supported"),
-                    Expression::TSInstantiationExpression(_) => panic!("TSInstantiationExpression is not supported"),
-                };
+    fn map_object_expression(&self, object: ObjectExpression<'a>) -> ObjectExpression<'a> {
+        let ObjectExpression { properties, span, trailing_comma } = object;
+
+        let mut new_properties: OxcVec<'a, ObjectPropertyKind<'a>> = OxcVec::with_capacity_in(properties.len(), self.allocator);
+        for prop in properties {
+            match prop {
+                ObjectPropertyKind::ObjectProperty(prop) => {
+                    new_properties.push(ObjectPropertyKind::ObjectProperty(self.boxed(self.map_object_property(prop.unbox()))));
+                }
+                ObjectPropertyKind::SpreadProperty(spread) => {
+
+                    let SpreadElement { argument, span } = spread.unbox();
+
+                    new_properties.push(
+                        ObjectPropertyKind::SpreadProperty(self.boxed(SpreadElement {
+                            argument: self.map_expression(argument),
+                            span,
+                        }))
+                    );
+                }
             }
+        }
 
-            // Apply after visitors and potentially revisit
-            for visitor in &self.visitors_expr {
-                let (action, new_expr) = visitor(expr, self.allocator, false);
-                expr = new_expr;
-                if action == MapperAction::Revisit {
-                    visit_again = true;
-                    log!("{}Revisiting expression {:?}", if self.debug_id.len() > 0 { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", expr).split(' ').next().unwrap_or(format!("{:?}", expr).as_str()));
-                    break;
+        ObjectExpression { properties: new_properties, span, trailing_comma }
+    }
+
+    fn map_call_expression(&self, call: CallExpression<'a>) -> CallExpression<'a> {
+        let CallExpression { callee, arguments, span, optional, type_parameters } = call;
+
+        let callee = self.map_expression(callee);
+        let arguments = self.map_arguments(arguments);
+
+        CallExpression { callee, arguments, span, optional, type_parameters }
+    }
+
+    fn map_arguments(&self, arguments: OxcVec<'a, Argument<'a>>) -> OxcVec<'a, Argument<'a>> {
+        let mut new_arguments = OxcVec::with_capacity_in(arguments.len(), self.allocator);
+        for arg in arguments {
+            match arg {
+                Argument::Expression(expr) => new_arguments.push(Argument::Expression(self.map_expression(expr))),
+                Argument::SpreadElement(spread) => {
+                    let SpreadElement { argument, span } = spread.unbox();
+                    new_arguments.push(Argument::SpreadElement(self.boxed(SpreadElement { argument: self.map_expression(argument), span })))
                 }
             }
         }
+        new_arguments
+    }
 
-        log!("{}Leave expression {:?} {}", if self.debug_id.len() > 0 { format!("{}: ", self.debug_id) } else { "".to_string() }, format!("{:?}", expr).split(' ').next().unwrap_or(format!("{:?}", expr).as_str()),
-            if let Expression::Identifier(id) = &expr {
-                format!("id: {}", id.name)
-            } else {
-                "".to_string()
+    /// Member expressions, also when they are the target of an assignment or update. The property of a
+    /// static member and the field of a private member are names, not expressions, so they're not visited.
+    fn map_member_expression(&self, member: MemberExpression<'a>) -> MemberExpression<'a> {
+        match member {
+            MemberExpression::ComputedMemberExpression(computed) => {
+                let ComputedMemberExpression { object, expression, span, optional } = computed;
+
+                let object = self.map_expression(object);
+                let expression = self.map_expression(expression);
+
+                MemberExpression::ComputedMemberExpression(ComputedMemberExpression { object, expression, span, optional })
             }
-        );
-        expr
+            MemberExpression::StaticMemberExpression(static_member) => {
+                // "static" being the opposite of computed, not related to the "static" keyword
+                let StaticMemberExpression { object, property, span, optional } = static_member;
+
+                let object = self.map_expression(object);
+
+                MemberExpression::StaticMemberExpression(StaticMemberExpression { object, property, span, optional })
+            }
+            MemberExpression::PrivateFieldExpression(private_field) => {
+                let PrivateFieldExpression { object, field, span, optional } = private_field;
+
+                let object = self.map_expression(object);
+
+                MemberExpression::PrivateFieldExpression(PrivateFieldExpression { object, field, span, optional })
+            }
+        }
+    }
+
+    fn map_declaration(&self, decl: Declaration<'a>) -> Declaration<'a> {
+        match decl {
+            Declaration::VariableDeclaration(var_decl) => {
+                Declaration::VariableDeclaration(self.boxed(self.map_variable_declaration(var_decl.unbox())))
+            }
+            Declaration::FunctionDeclaration(func_decl) if func_decl.body.is_none() => {
+                // Only TS overloads and `declare function` have no body
+                self.report_unsupported("Function declaration without body", func_decl.span);
+                Declaration::FunctionDeclaration(func_decl)
+            }
+            Declaration::FunctionDeclaration(func_decl) => {
+                Declaration::FunctionDeclaration(self.boxed(self.map_function(func_decl.unbox())))
+            }
+            Declaration::ClassDeclaration(class_decl) => {
+                // Reuse existing visit_class logic
+                let class_decl = self.map_class(class_decl.unbox());
+                Declaration::ClassDeclaration(self.boxed(class_decl))
+            }
+            Declaration::UsingDeclaration(decl) => {
+                self.report_unsupported("UsingDeclaration (stage 3)", decl.span);
+                Declaration::UsingDeclaration(decl)
+            }
+            Declaration::TSTypeAliasDeclaration(decl) => {
+                self.report_unsupported("TSTypeAliasDeclaration", decl.span);
+                Declaration::TSTypeAliasDeclaration(decl)
+            }
+            Declaration::TSInterfaceDeclaration(decl) => {
+                self.report_unsupported("TSInterfaceDeclaration", decl.span);
+                Declaration::TSInterfaceDeclaration(decl)
+            }
+            Declaration::TSModuleDeclaration(decl) => {
+                self.report_unsupported("TSModuleDeclaration", decl.span);
+                Declaration::TSModuleDeclaration(decl)
+            }
+            Declaration::TSEnumDeclaration(decl) => {
+                self.report_unsupported("TSEnumDeclaration", decl.span);
+                Declaration::TSEnumDeclaration(decl)
+            }
+            Declaration::TSImportEqualsDeclaration(decl) => {
+                self.report_unsupported("TSImportEqualsDeclaration", decl.span);
+                Declaration::TSImportEqualsDeclaration(decl)
+            }
+        }
+    }
+
+    fn map_module_declaration(&self, module_decl: ModuleDeclaration<'a>) -> ModuleDeclaration<'a> {
+        match module_decl {
+            ModuleDeclaration::ExportNamedDeclaration(export_decl) => {
+                let ExportNamedDeclaration { span, declaration, specifiers, source, export_kind } = export_decl.unbox();
+                let declaration = declaration.map(|decl| self.map_declaration(decl));
+                ModuleDeclaration::ExportNamedDeclaration(self.boxed(ExportNamedDeclaration { span, declaration, specifiers, source, export_kind }))
+            }
+            ModuleDeclaration::ExportDefaultDeclaration(export_decl) => {
+                let ExportDefaultDeclaration { span, declaration, exported } = export_decl.unbox();
+                let declaration = match declaration {
+                    ExportDefaultDeclarationKind::Expression(expr) => {
+                        ExportDefaultDeclarationKind::Expression(self.map_expression(expr))
+                    }
+                    ExportDefaultDeclarationKind::FunctionDeclaration(func) => {
+                        ExportDefaultDeclarationKind::FunctionDeclaration(self.boxed(self.map_function(func.unbox())))
+                    }
+                    ExportDefaultDeclarationKind::ClassDeclaration(class) => {
+                        ExportDefaultDeclarationKind::ClassDeclaration(self.boxed(self.map_class(class.unbox())))
+                    }
+                    ExportDefaultDeclarationKind::TSInterfaceDeclaration(decl) => {
+                        self.report_unsupported("TSInterfaceDeclaration", decl.span);
+                        ExportDefaultDeclarationKind::TSInterfaceDeclaration(decl)
+                    }
+                    ExportDefaultDeclarationKind::TSEnumDeclaration(decl) => {
+                        self.report_unsupported("TSEnumDeclaration", decl.span);
+                        ExportDefaultDeclarationKind::TSEnumDeclaration(decl)
+                    }
+                };
+                ModuleDeclaration::ExportDefaultDeclaration(self.boxed(ExportDefaultDeclaration { span, declaration, exported }))
+            }
+            ModuleDeclaration::TSExportAssignment(decl) => {
+                self.report_unsupported("TSExportAssignment", decl.span);
+                ModuleDeclaration::TSExportAssignment(decl)
+            }
+            ModuleDeclaration::TSNamespaceExportDeclaration(decl) => {
+                self.report_unsupported("TSNamespaceExportDeclaration", decl.span);
+                ModuleDeclaration::TSNamespaceExportDeclaration(decl)
+            }
+            // No code in these
+            ModuleDeclaration::ImportDeclaration(_) | ModuleDeclaration::ExportAllDeclaration(_) => module_decl,
+        }
     }
 
     fn map_variable_declaration(&self, decl: VariableDeclaration<'a>) -> VariableDeclaration<'a> {
@@ -879,11 +1294,7 @@ impl<'a> Mapper<'a> {
             let VariableDeclarator { id, init, kind, span, definite } = declarator;
 
             let id = self.map_binding_pattern(id);
-            let init = if let Some(init) = init {
-                Some(self.map_expression(init))
-            } else {
-                None
-            };
+            let init = init.map(|init| self.map_expression(init));
 
             let declarator = VariableDeclarator { id, init, kind, span, definite };
             new_declarations.push(declarator);
@@ -893,6 +1304,11 @@ impl<'a> Mapper<'a> {
     }
 
     fn map_binding_pattern(&self, pattern: BindingPattern<'a>) -> BindingPattern<'a> {
+        let span = pattern.kind.span();
+        self.visit_node("binding pattern", &self.visitors_binding_pattern, pattern, span, |pattern| self.map_binding_pattern_children(pattern))
+    }
+
+    fn map_binding_pattern_children(&self, pattern: BindingPattern<'a>) -> BindingPattern<'a> {
         let BindingPattern { kind, type_annotation, optional } = pattern;
 
         match kind {
@@ -904,18 +1320,18 @@ impl<'a> Mapper<'a> {
                     let BindingProperty { span, key, value, shorthand, computed } = prop;
                     new_properties.push(BindingProperty {
                         span,
-                        key,
-                        value,
+                        key: self.map_property_key(key),
+                        value: self.map_binding_pattern(value),
                         shorthand,
                         computed,
                     });
                 }
                 BindingPattern {
-                    kind: BindingPatternKind::ObjectPattern(OxcBox(self.allocator.alloc(ObjectPattern {
+                    kind: BindingPatternKind::ObjectPattern(self.boxed(ObjectPattern {
                         properties: new_properties,
-                        span: span,
-                        rest,
-                    }))),
+                        span,
+                        rest: rest.map(|rest| self.map_rest_element(rest)),
+                    })),
                     type_annotation,
                     optional,
                 }
@@ -933,7 +1349,7 @@ impl<'a> Mapper<'a> {
                 }
 
                 BindingPattern {
-                    kind: BindingPatternKind::ArrayPattern(OxcBox(self.allocator.alloc(ArrayPattern { elements: new_elements, span, rest }))),
+                    kind: BindingPatternKind::ArrayPattern(self.boxed(ArrayPattern { elements: new_elements, span, rest: rest.map(|rest| self.map_rest_element(rest)) })),
                     type_annotation,
                     optional,
                 }
@@ -942,9 +1358,9 @@ impl<'a> Mapper<'a> {
                 let AssignmentPattern { left, right, span } = assign_pattern.unbox();
 
                 let left = self.map_binding_pattern(left);
-                let right = self.map_expression(right);
+                let right = self.with_barrier("a default value", || self.map_expression(right));
 
-                BindingPattern { kind: BindingPatternKind::AssignmentPattern(OxcBox(self.allocator.alloc(AssignmentPattern { left, right, span }))), type_annotation, optional }
+                BindingPattern { kind: BindingPatternKind::AssignmentPattern(self.boxed(AssignmentPattern { left, right, span })), type_annotation, optional }
             }
             BindingPatternKind::BindingIdentifier(ident) => BindingPattern {
                 kind: BindingPatternKind::BindingIdentifier(ident),
@@ -958,143 +1374,251 @@ impl<'a> Mapper<'a> {
         match target {
             AssignmentTarget::SimpleAssignmentTarget(simple) => match simple {
                 SimpleAssignmentTarget::MemberAssignmentTarget(member) => {
-                    match member.unbox() {
-                        MemberExpression::ComputedMemberExpression(computed) => {
-                            let ComputedMemberExpression { object, expression, span, optional } = computed;
-
-                            let object = self.map_expression(object);
-                            let expression = self.map_expression(expression);
-
-                            AssignmentTarget::SimpleAssignmentTarget(
-                                SimpleAssignmentTarget::MemberAssignmentTarget(OxcBox(self.allocator.alloc(
-                                    MemberExpression::ComputedMemberExpression(
-                                        ComputedMemberExpression {
-                                            object,
-                                            expression,
-                                            span,
-                                            optional,
-                                        }
-                                    )
-                                )))
-                            )
-                        }
-                        MemberExpression::StaticMemberExpression(static_member) => {
-                            let StaticMemberExpression { object, property, span, optional } = static_member;
-
-                            let object = self.map_expression(object);
-
-                            AssignmentTarget::SimpleAssignmentTarget(
-                                SimpleAssignmentTarget::MemberAssignmentTarget(OxcBox(self.allocator.alloc(
-                                    MemberExpression::StaticMemberExpression(
-                                        StaticMemberExpression {
-                                            object,
-                                            property,
-                                            span,
-                                            optional,
-                                        }
-                                    )
-                                )))
-                            )
-                        }
-                        MemberExpression::PrivateFieldExpression(private_field) => {
-                            let PrivateFieldExpression { object, field, span, optional } = private_field;
-
-                            let object = self.map_expression(object);
-
-                            AssignmentTarget::SimpleAssignmentTarget(
-                                SimpleAssignmentTarget::MemberAssignmentTarget(OxcBox(self.allocator.alloc(
-                                    MemberExpression::PrivateFieldExpression(
-                                        PrivateFieldExpression {
-                                            object,
-                                            field,
-                                            span,
-                                            optional,
-                                        }
-                                    )
-                                )))
-                            )
-                        }
-                    }
+                    AssignmentTarget::SimpleAssignmentTarget(
+                        SimpleAssignmentTarget::MemberAssignmentTarget(self.boxed(self.map_member_expression(member.unbox())))
+                    )
                 }
                 SimpleAssignmentTarget::AssignmentTargetIdentifier(_) => {
                     AssignmentTarget::SimpleAssignmentTarget(simple)
                 }
-                SimpleAssignmentTarget::TSAsExpression(_) => panic!("TSAsExpression is not supported"),
-                SimpleAssignmentTarget::TSSatisfiesExpression(_) => panic!("TSSatisfiesExpression is not supported"),
-                SimpleAssignmentTarget::TSNonNullExpression(_) => panic!("TSNonNullExpression is not supported"),
-                SimpleAssignmentTarget::TSTypeAssertion(_) => panic!("TSTypeAssertion is not supported"),
+                SimpleAssignmentTarget::TSAsExpression(ts) => {
+                    self.report_unsupported("TSAsExpression", ts.span);
+                    AssignmentTarget::SimpleAssignmentTarget(SimpleAssignmentTarget::TSAsExpression(ts))
+                }
+                SimpleAssignmentTarget::TSSatisfiesExpression(ts) => {
+                    self.report_unsupported("TSSatisfiesExpression", ts.span);
+                    AssignmentTarget::SimpleAssignmentTarget(SimpleAssignmentTarget::TSSatisfiesExpression(ts))
+                }
+                SimpleAssignmentTarget::TSNonNullExpression(ts) => {
+                    self.report_unsupported("TSNonNullExpression", ts.span);
+                    AssignmentTarget::SimpleAssignmentTarget(SimpleAssignmentTarget::TSNonNullExpression(ts))
+                }
+                SimpleAssignmentTarget::TSTypeAssertion(ts) => {
+                    self.report_unsupported("TSTypeAssertion", ts.span);
+                    AssignmentTarget::SimpleAssignmentTarget(SimpleAssignmentTarget::TSTypeAssertion(ts))
+                }
             },
             AssignmentTarget::AssignmentTargetPattern(pattern) => match pattern {
                 AssignmentTargetPattern::ObjectAssignmentTarget(obj_pattern) => {
                     let ObjectAssignmentTarget { properties, span, rest } = obj_pattern.unbox();
 
-                    // let mut new_properties = OxcVec::with_capacity_in(properties.len(), self.allocator);
-                    // for prop in properties {
-                    //     new_properties.push(prop);
-                    // }
+                    let mut new_properties = OxcVec::with_capacity_in(properties.len(), self.allocator);
+                    for prop in properties {
+                        match prop {
+                            AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(ident) => {
+                                // `{x = y} = obj`, the default is an expression
+                                let AssignmentTargetPropertyIdentifier { span, binding, init } = ident.unbox();
+                                let init = init.map(|init| self.map_expression(init));
+                                new_properties.push(AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(self.boxed(
+                                    AssignmentTargetPropertyIdentifier { span, binding, init }
+                                )));
+                            }
+                            AssignmentTargetProperty::AssignmentTargetPropertyProperty(property) => {
+                                let AssignmentTargetPropertyProperty { span, name, binding } = property.unbox();
+                                let name = self.map_property_key(name);
+                                let binding = self.map_assignment_target_maybe_default(binding);
+                                new_properties.push(AssignmentTargetProperty::AssignmentTargetPropertyProperty(self.boxed(
+                                    AssignmentTargetPropertyProperty { span, name, binding }
+                                )));
+                            }
+                        }
+                    }
 
-                    AssignmentTarget::AssignmentTargetPattern(AssignmentTargetPattern::ObjectAssignmentTarget(OxcBox(self.allocator.alloc(ObjectAssignmentTarget {
-                        // properties: new_properties,
-                        properties,
+                    AssignmentTarget::AssignmentTargetPattern(AssignmentTargetPattern::ObjectAssignmentTarget(self.boxed(ObjectAssignmentTarget {
+                        properties: new_properties,
                         span,
-                        rest,
-                    }))))
+                        rest: rest.map(|rest| self.map_assignment_target(rest)),
+                    })))
                 }
                 AssignmentTargetPattern::ArrayAssignmentTarget(array_pattern) => {
                     let ArrayAssignmentTarget { elements, span, rest, trailing_comma } = array_pattern.unbox();
 
-                    // let mut new_elements = OxcVec::with_capacity_in(elements.len(), self.allocator);
-                    // for elem in elements {
-                    //     new_elements.push(elem);
-                    // }
+                    let mut new_elements = OxcVec::with_capacity_in(elements.len(), self.allocator);
+                    for elem in elements {
+                        new_elements.push(elem.map(|elem| self.map_assignment_target_maybe_default(elem)));
+                    }
 
-                    AssignmentTarget::AssignmentTargetPattern(AssignmentTargetPattern::ArrayAssignmentTarget(OxcBox(self.allocator.alloc(
+                    AssignmentTarget::AssignmentTargetPattern(AssignmentTargetPattern::ArrayAssignmentTarget(self.boxed(
                         ArrayAssignmentTarget {
-                            // elements: new_elements,
-                            elements,
+                            elements: new_elements,
                             span,
-                            rest,
+                            rest: rest.map(|rest| self.map_assignment_target(rest)),
                             trailing_comma,
                         }
-                    ))))
+                    )))
                 }
             },
         }
     }
 
-    fn map_function(&self, func: Function<'a>) -> Function<'a> {
-        let Function { params, body, span: func_span, r#type, id, expression, generator, r#async, type_parameters, return_type, modifiers } = func;
+    fn map_assignment_target_maybe_default(&self, target: AssignmentTargetMaybeDefault<'a>) -> AssignmentTargetMaybeDefault<'a> {
+        match target {
+            AssignmentTargetMaybeDefault::AssignmentTarget(target) => {
+                AssignmentTargetMaybeDefault::AssignmentTarget(self.map_assignment_target(target))
+            }
+            AssignmentTargetMaybeDefault::AssignmentTargetWithDefault(with_default) => {
+                let AssignmentTargetWithDefault { span, binding, init } = with_default.unbox();
+                let binding = self.map_assignment_target(binding);
+                let init = self.with_barrier("a default value", || self.map_expression(init));
+                AssignmentTargetMaybeDefault::AssignmentTargetWithDefault(self.boxed(AssignmentTargetWithDefault { span, binding, init }))
+            }
+        }
+    }
+
+    fn map_rest_element(&self, rest: OxcBox<'a, RestElement<'a>>) -> OxcBox<'a, RestElement<'a>> {
+        let RestElement { span, argument } = rest.unbox();
+        self.boxed(RestElement { span, argument: self.map_binding_pattern(argument) })
+    }
+
+    fn map_object_property(&self, prop: ObjectProperty<'a>) -> ObjectProperty<'a> {
+        let span = prop.span;
+        self.visit_node("object property", &self.visitors_object_property, prop, span, |prop| {
+            let ObjectProperty { kind, key, value, span, method, shorthand, computed, init } = prop;
+            let key = self.map_property_key(key);
+            let value = self.map_expression(value);
+            ObjectProperty { kind, key, value, span, method, shorthand, computed, init }
+        })
+    }
+
+    /// Only computed keys (`[expr]`) contain code. Identifiers and private names are kept as is.
+    fn map_property_key(&self, key: PropertyKey<'a>) -> PropertyKey<'a> {
+        match key {
+            PropertyKey::Expression(expr) => PropertyKey::Expression(self.map_expression(expr)),
+            PropertyKey::Identifier(_) | PropertyKey::PrivateIdentifier(_) => key,
+        }
+    }
+
+    /// Parameters are evaluated when the function is called, not where it's defined, so nothing can be hoisted out
+    fn map_formal_parameters(&self, params: FormalParameters<'a>) -> FormalParameters<'a> {
+        self.with_barrier("a function parameter", || {
+            let span = params.span;
+            self.visit_node("params", &self.visitors_params, params, span, |params| self.map_formal_parameters_children(params))
+        })
+    }
+
+    fn map_formal_parameters_children(&self, params: FormalParameters<'a>) -> FormalParameters<'a> {
+        let FormalParameters { items, span, kind, rest } = params;
 
-        let FormalParameters { items, span: param_span, kind, rest } = params.unbox();
         let mut new_items = OxcVec::with_capacity_in(items.len(), self.allocator);
         for param in items {
             let FormalParameter { pattern, span, accessibility, readonly, decorators } = param;
-            let BindingPattern { kind, type_annotation, optional } = pattern;
-            new_items.push(FormalParameter {
-                pattern: BindingPattern { kind, type_annotation, optional },
-                span,
-                accessibility,
-                readonly,
-                decorators,
-            });
+            let decorators = self.map_decorators(decorators);
+            let pattern = self.map_binding_pattern(pattern);
+            new_items.push(FormalParameter { pattern, span, accessibility, readonly, decorators });
         }
 
-        // The body may be None for an arrow that is an expression but I'm not sure if that would reach here at all?
-        if body.is_none() {
-            panic!("Function body is None? Was this an arrow?");
+        FormalParameters { items: new_items, span, kind, rest: rest.map(|rest| self.map_rest_element(rest)) }
+    }
+
+    fn map_arrow(&self, arrow: ArrowExpression<'a>) -> ArrowExpression<'a> {
+        let span = arrow.span;
+        self.visit_node("arrow", &self.visitors_arrow, arrow, span, |arrow| self.map_arrow_children(arrow))
+    }
+
+    fn map_arrow_children(&self, arrow: ArrowExpression<'a>) -> ArrowExpression<'a> {
+        let ArrowExpression {
+            params, body, span: arrow_span, r#async, expression, generator, type_parameters, return_type
+        } = arrow;
+
+        self.enter(AncestorKind::Function { is_async: r#async, is_generator: generator, is_arrow: true }, arrow_span, has_use_strict(&body.directives));
+        self.enter_scope(ScopeKind::Function, arrow_span);
+        let new_params = self.boxed(self.map_formal_parameters(params.unbox()));
+
+        // Note: for a function expression they still create a whole function body. The first statement is an expression statement that gets unboxed.
+        let FunctionBody { statements, directives, span: body_span } = body.unbox();
+
+        if expression {
+            let Some(Statement::ExpressionStatement(expr_stmt)) = statements.into_iter().next() else {
+                unreachable!("The parser always wraps the expression body of an arrow in an expression statement");
+            };
+            self.push_prelude_frame();
+            let expr = self.map_expression(expr_stmt.unbox().expression);
+            let prelude = self.pop_prelude_frame();
+
+            // With a prelude the body becomes a block: `() => x` -> `() => { prelude; return x; }`
+            let expression = prelude.is_empty();
+            let mut new_body = self.map_statements(OxcVec::from_iter_in(prelude, self.allocator));
+            if expression {
+                new_body.push(Statement::ExpressionStatement(self.boxed(ExpressionStatement { expression: expr, span: body_span })));
+            } else {
+                new_body.push(Statement::ReturnStatement(self.boxed(ReturnStatement { argument: Some(expr), span: body_span })));
+            }
+            let body = self.boxed(FunctionBody { statements: new_body, directives, span: body_span });
+            self.leave_scope();
+            self.leave();
+
+            ArrowExpression {
+                params: new_params,
+                expression,
+                body,
+                span: arrow_span,
+                r#async,
+                generator,
+                type_parameters,
+                return_type,
+            }
+
+        } else {
+            let new_body_stmts = self.map_statements(statements);
+            let body = self.boxed(FunctionBody { statements: new_body_stmts, directives, span: body_span });
+            self.leave_scope();
+            self.leave();
+
+            ArrowExpression {
+                params: new_params,
+                body,
+                expression: false,
+                span: arrow_span,
+                r#async,
+                generator,
+                type_parameters,
+                return_type,
+            }
         }
+    }
+
+    fn map_function(&self, func: Function<'a>) -> Function<'a> {
+        let span = func.span;
+        self.visit_node("function", &self.visitors_function, func, span, |func| self.map_function_children(func))
+    }
+
+    fn map_function_children(&self, func: Function<'a>) -> Function<'a> {
+        let Function { params, body, span: func_span, r#type, id, expression, generator, r#async, type_parameters, return_type, modifiers } = func;
+
+        let use_strict = body.as_ref().is_some_and(|body| has_use_strict(&body.directives));
+        self.enter(AncestorKind::Function { is_async: r#async, is_generator: generator, is_arrow: false }, func_span, use_strict);
+        self.enter_scope(ScopeKind::Function, func_span);
+        let params = self.boxed(self.map_formal_parameters(params.unbox()));
+
+        // Only TS overloads and `declare` functions have no body. Nothing to walk there.
+        let Some(body) = body else {
+            self.report_unsupported("Function without body", func_span);
+            self.leave_scope();
+            self.leave();
+            return Function {
+                params,
+                body: None,
+                span: func_span,
+                r#type,
+                id,
+                expression,
+                generator,
+                r#async,
+                type_parameters,
+                return_type,
+                modifiers,
+            };
+        };
 
-        let body = body.unwrap();
         let FunctionBody { statements, span: body_span, directives } = body.unbox();
 
-        let mut new_statements = OxcVec::with_capacity_in(statements.len(), self.allocator);
-        for stmt in statements {
-            new_statements.push(self.map_statement(stmt));
-        }
+        let new_statements = self.map_statements(statements);
+        self.leave_scope();
+        self.leave();
 
         Function {
-            params: OxcBox(self.allocator.alloc(FormalParameters { items: new_items, span: param_span, kind, rest })),
-            body: Some(OxcBox(self.allocator.alloc(FunctionBody { statements: new_statements, span: body_span, directives }))),
+            params,
+            body: Some(self.boxed(FunctionBody { statements: new_statements, span: body_span, directives })),
             span: func_span,
             r#type,
             id,
@@ -1108,6 +1632,19 @@ impl<'a> Mapper<'a> {
     }
 
     fn map_class(&self, class: Class<'a>) -> Class<'a> {
+        let span = class.span;
+        self.visit_node("class", &self.visitors_class, class, span, |class| self.map_class_children(class))
+    }
+
+    fn map_decorators(&self, decorators: OxcVec<'a, Decorator<'a>>) -> OxcVec<'a, Decorator<'a>> {
+        let mut new_decorators = OxcVec::with_capacity_in(decorators.len(), self.allocator);
+        for Decorator { span, expression } in decorators {
+            new_decorators.push(Decorator { span, expression: self.map_expression(expression) });
+        }
+        new_decorators
+    }
+
+    fn map_class_children(&self, class: Class<'a>) -> Class<'a> {
         let Class {
             mut super_class,
             body,
@@ -1121,6 +1658,16 @@ impl<'a> Mapper<'a> {
             super_type_parameters,
         } = class;
 
+        // These are evaluated before the class, in the scope around it
+        let decorators = self.map_decorators(decorators);
+
+        self.enter(AncestorKind::Class, span, false);
+        // Only a class expression has a scope of its own, for its name
+        let is_expression = r#type == ClassType::ClassExpression;
+        if is_expression {
+            self.enter_scope(ScopeKind::Class, span);
+        }
+
         if let Some(sclass) = super_class {
             super_class = Some(self.map_expression(sclass));
         }
@@ -1146,14 +1693,12 @@ impl<'a> Mapper<'a> {
                         type_annotation,
                     } = prop.unbox();
 
-                    let value = if let Some(value) = value {
-                        Some(self.map_expression(value))
-                    } else {
-                        None
-                    };
+                    let decorators = self.map_decorators(decorators);
+                    let key = self.map_property_key(key);
+                    let value = value.map(|value| self.with_barrier("a class field initializer", || self.map_expression(value)));
 
                     new_body.push(
-                        ClassElement::PropertyDefinition(OxcBox(self.allocator.alloc(PropertyDefinition {
+                        ClassElement::PropertyDefinition(self.boxed(PropertyDefinition {
                             key,
                             value,
                             span,
@@ -1167,7 +1712,7 @@ impl<'a> Mapper<'a> {
                             definite,
                             readonly,
                             type_annotation,
-                        })))
+                        }))
                     );
                 }
                 ClassElement::MethodDefinition(method) => {
@@ -1183,11 +1728,13 @@ impl<'a> Mapper<'a> {
                         r#override,
                         optional,
                     } = method.unbox();
+                    let decorators = self.map_decorators(decorators);
+                    let key = self.map_property_key(key);
                     let value = self.map_function(value.unbox());
                     new_body.push(
-                        ClassElement::MethodDefinition(OxcBox(self.allocator.alloc(MethodDefinition {
+                        ClassElement::MethodDefinition(self.boxed(MethodDefinition {
                             key,
-                            value: OxcBox(self.allocator.alloc(self.map_function(value))),
+                            value: OxcBox(self.allocator.alloc(value)),
                             span,
                             kind,
                             accessibility,
@@ -1196,14 +1743,27 @@ impl<'a> Mapper<'a> {
                             r#static,
                             r#override,
                             optional,
-                        })))
+                        }))
                     );
                 }
-                ClassElement::StaticBlock(_) => {
-                    new_body.push(element);
+                ClassElement::StaticBlock(block) => {
+                    let StaticBlock { span, body } = block.unbox();
+
+                    self.enter(AncestorKind::StaticBlock, span, false);
+                    self.enter_scope(ScopeKind::StaticBlock, span);
+                    let new_statements = self.map_statements(body);
+                    self.leave_scope();
+                    self.leave();
+
+                    new_body.push(ClassElement::StaticBlock(self.boxed(StaticBlock { span, body: new_statements })));
                 }
-                ClassElement::AccessorProperty(_) => {
-                    new_body.push(element);
+                ClassElement::AccessorProperty(accessor) => {
+                    let AccessorProperty { span, key, value, computed, r#static } = accessor.unbox();
+
+                    let key = self.map_property_key(key);
+                    let value = value.map(|value| self.with_barrier("a class field initializer", || self.map_expression(value)));
+
+                    new_body.push(ClassElement::AccessorProperty(self.boxed(AccessorProperty { span, key, value, computed, r#static })));
                 }
                 ClassElement::TSAbstractMethodDefinition(_) => {
                     new_body.push(element);
@@ -1216,10 +1776,14 @@ impl<'a> Mapper<'a> {
                 }
             }
         }
+        if is_expression {
+            self.leave_scope();
+        }
+        self.leave();
 
         Class {
             super_class,
-            body: OxcBox(self.allocator.alloc(ClassBody { body: new_body, span: body_span })),
+            body: self.boxed(ClassBody { body: new_body, span: body_span }),
             span,
             id,
             type_parameters,
@@ -1255,3 +1819,16 @@ pub fn create_mapper_with_debug_id<'a>(allocator: &'a Allocator, debug_id: Strin
     mapper.set_debug_id(debug_id);
     mapper
 }
+
+/// Run a separate traversal over a subtree from inside a visitor or transform. The closure gets a
+/// fresh mapper to add visitors to and call one of the `map_*` methods on (not `map`, that starts
+/// over with a new scope analysis). For the duration of the closure that mapper owns `state`, so
+/// generated names, diagnostics, scopes and the ancestor context are shared with the caller. Its
+/// visitors get to the state through `mapper.state`. The state is handed back when the closure returns.
+pub fn with_sub_mapper<'a, R>(allocator: &'a Allocator, state: &mut MapperState, debug_id: &str, f: impl FnOnce(&mut Mapper<'a>) -> R) -> R {
+    let mut mapper = create_mapper_with_debug_id(allocator, debug_id.to_string());
+    mapper.state = Rc::new(RefCell::new(std::mem::take(state)));
+    let result = f(&mut mapper);
+    *state = std::mem::take(&mut *mapper.state.borrow_mut());
+    result
+}
@@ -0,0 +1,132 @@
+use oxc_ast::ast::Directive;
+use oxc_span::Span;
+use oxc_syntax::scope::ScopeId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopKind {
+    While,
+    DoWhile,
+    For,
+    ForIn,
+    ForOf,
+}
+
+/// The kinds of nodes that are tracked as ancestors. Other nodes (blocks, ifs, expressions, ...) are
+/// walked through without being recorded because they don't change the context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AncestorKind {
+    Program,
+    // Also arrows, methods, getters and setters
+    Function { is_async: bool, is_generator: bool, is_arrow: bool },
+    Class,
+    StaticBlock,
+    Loop(LoopKind),
+    Label(String),
+    Switch,
+    // The `try` statement as a whole. The catch and finally blocks are nested in it.
+    Try,
+    Catch,
+    Finally,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ancestor {
+    pub kind: AncestorKind,
+    pub span: Span,
+    // Whether the code inside this node is strict mode code
+    pub strict: bool,
+}
+
+impl Ancestor {
+    /// Functions, static blocks and class bodies are a boundary for labels, loops, `yield` and `await`
+    pub fn is_function_boundary(&self) -> bool {
+        matches!(self.kind, AncestorKind::Function { .. } | AncestorKind::StaticBlock | AncestorKind::Class)
+    }
+}
+
+/// The chain of ancestors of the node that the mapper is currently visiting, outermost first.
+/// The mapper keeps this in sync while it walks. The node itself is not part of it, so the
+/// before and after visitors of a loop see the same context as the statement that holds the loop.
+#[derive(Debug, Clone, Default)]
+pub struct MapperContext {
+    ancestors: Vec<Ancestor>,
+    // The scopes (in `MapperState::scopes`) that the mapper is in, outermost first
+    scopes: Vec<ScopeId>,
+}
+
+impl MapperContext {
+    pub fn new() -> Self {
+        Self { ancestors: vec!(), scopes: vec!() }
+    }
+
+    pub fn push_scope(&mut self, scope: ScopeId) {
+        self.scopes.push(scope);
+    }
+
+    pub fn pop_scope(&mut self) -> Option<ScopeId> {
+        self.scopes.pop()
+    }
+
+    /// The scope of the node being visited. `None` outside of `Mapper::map`.
+    pub fn current_scope(&self) -> Option<ScopeId> {
+        self.scopes.last().copied()
+    }
+
+    /// Enter a node. Strictness is inherited from the parent, or set by a directive or class body.
+    pub fn push(&mut self, kind: AncestorKind, span: Span, use_strict: bool) {
+        let strict = use_strict || matches!(kind, AncestorKind::Class) || self.is_strict();
+        self.ancestors.push(Ancestor { kind, span, strict });
+    }
+
+    pub fn pop(&mut self) -> Option<Ancestor> {
+        self.ancestors.pop()
+    }
+
+    pub fn ancestors(&self) -> &[Ancestor] {
+        &self.ancestors
+    }
+
+    pub fn parent(&self) -> Option<&Ancestor> {
+        self.ancestors.last()
+    }
+
+    /// The ancestors up to the nearest function boundary, innermost first
+    fn in_current_function(&self) -> impl Iterator<Item = &Ancestor> {
+        self.ancestors.iter().rev().take_while(|ancestor| !ancestor.is_function_boundary())
+    }
+
+    /// The nearest function, arrow or method. `None` at the top level and directly in a static block or class body.
+    pub fn enclosing_function(&self) -> Option<&Ancestor> {
+        self.ancestors.iter().rev().find(|ancestor| ancestor.is_function_boundary()).filter(|ancestor| matches!(ancestor.kind, AncestorKind::Function { .. }))
+    }
+
+    /// The nearest loop that a `break` or `continue` here could target
+    pub fn nearest_loop(&self) -> Option<&Ancestor> {
+        self.in_current_function().find(|ancestor| matches!(ancestor.kind, AncestorKind::Loop(_)))
+    }
+
+    /// The labels that a `break` here could target, innermost first
+    pub fn labels(&self) -> Vec<&str> {
+        self.in_current_function().filter_map(|ancestor| match &ancestor.kind {
+            AncestorKind::Label(name) => Some(name.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn in_generator(&self) -> bool {
+        matches!(self.enclosing_function(), Some(Ancestor { kind: AncestorKind::Function { is_generator: true, .. }, .. }))
+    }
+
+    /// Whether `await` is allowed here because of an async function. Does not consider top level await.
+    pub fn in_async(&self) -> bool {
+        matches!(self.enclosing_function(), Some(Ancestor { kind: AncestorKind::Function { is_async: true, .. }, .. }))
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.ancestors.last().is_some_and(|ancestor| ancestor.strict)
+    }
+}
+
+pub fn has_use_strict(directives: &[Directive]) -> bool {
+    directives.iter().any(|directive| directive.directive == "use strict")
+}
@@ -0,0 +1,100 @@
+use oxc_ast::ast::*;
+use oxc_syntax::operator::UnaryOperator;
+
+/// Statements that an expression visitor wants to run before the statement that contains its
+/// expression. For example to put an operand in a temporary variable first.
+/// See `Mapper::add_visitor_expr_with_prelude`.
+pub struct Prelude<'a> {
+    statements: Vec<Statement<'a>>,
+    // Why statements can not be moved to before the statement from here, if they can't
+    blocked_by: Option<&'static str>,
+    // Whether a visitor tried to hoist while that was blocked
+    refused: bool,
+}
+
+impl<'a> Prelude<'a> {
+    pub fn new(blocked_by: Option<&'static str>) -> Self {
+        Self { statements: vec!(), blocked_by, refused: false }
+    }
+
+    /// Take the statements that were pushed so far
+    pub fn take(&mut self) -> Vec<Statement<'a>> {
+        std::mem::take(&mut self.statements)
+    }
+
+    /// Whether statements hoisted here run right before the expression does, and only then. When this
+    /// is false `hoist` leaves the expression alone, the visitor can still transform it without a prelude.
+    pub fn can_hoist(&self) -> bool {
+        self.blocked_by.is_none()
+    }
+
+    /// A description of the position that prevents hoisting, like "a `do-while` test"
+    pub fn blocked_by(&self) -> Option<&'static str> {
+        self.blocked_by
+    }
+
+    /// Rewrite the expression with statements that run before the statement that contains it. `rewrite`
+    /// returns those statements, in the order they run, and the new expression. The mapper visits the
+    /// statements like any other statement. When `can_hoist()` is false `rewrite` is not called and the
+    /// expression is returned as is. The mapper reports that as an error.
+    pub fn hoist(&mut self, expr: Expression<'a>, rewrite: impl FnOnce(Expression<'a>) -> (Vec<Statement<'a>>, Expression<'a>)) -> Expression<'a> {
+        if self.blocked_by.is_some() {
+            self.refused = true;
+            return expr;
+        }
+        let (statements, expr) = rewrite(expr);
+        self.statements.extend(statements);
+        expr
+    }
+
+    /// Why the last `hoist` did nothing, if it was called where that is blocked. Clears it.
+    pub fn take_refused(&mut self) -> Option<&'static str> {
+        if std::mem::take(&mut self.refused) { self.blocked_by } else { None }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+}
+
+/// The prelude statements that were collected for the statement being mapped. Loop tests and arrow
+/// expression bodies get a frame of their own because the mapper can move those into a block.
+#[derive(Default)]
+pub struct PreludeFrame<'a> {
+    pub statements: Vec<Statement<'a>>,
+    // Whether an expression that was evaluated so far may have side effects. Hoisting past that would change the order.
+    pub effects: bool,
+    // The positions that the mapper is in that are not evaluated exactly once, innermost last
+    pub barriers: Vec<&'static str>,
+}
+
+impl<'a> PreludeFrame<'a> {
+    pub fn blocked_by(&self) -> Option<&'static str> {
+        match self.barriers.last() {
+            Some(barrier) => Some(barrier),
+            None if self.effects => Some("an expression that must run after other side effects in the same statement"),
+            None => None,
+        }
+    }
+}
+
+/// Whether evaluating this node, not counting its children, may have side effects. Property reads
+/// and implicit conversions are assumed to be free of side effects.
+pub fn has_own_effects(expr: &Expression) -> bool {
+    match expr {
+        Expression::CallExpression(_)
+        | Expression::NewExpression(_)
+        | Expression::AssignmentExpression(_)
+        | Expression::UpdateExpression(_)
+        | Expression::AwaitExpression(_)
+        | Expression::YieldExpression(_)
+        | Expression::TaggedTemplateExpression(_)
+        | Expression::ImportExpression(_)
+        // Static blocks and static fields run when the class is created
+        | Expression::ClassExpression(_) => true,
+        // The calls in a chain are not mapped as expressions of their own
+        Expression::ChainExpression(chain) => matches!(chain.expression, ChainElement::CallExpression(_)),
+        Expression::UnaryExpression(unary) => unary.operator == UnaryOperator::Delete,
+        _ => false,
+    }
+}
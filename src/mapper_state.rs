@@ -1,45 +1,204 @@
+use oxc_ast::ast::VariableDeclarationKind;
+use oxc_span::SourceType;
+use oxc_span::Span;
+
+use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::helpers::Helper;
+use crate::log;
+use crate::mapper_context::MapperContext;
+use crate::scope::{BindingKind, ScopeKind, ScopeTree};
+use crate::options::DEFAULT_NAME_PREFIX;
+use crate::trace::{RuleId, TraceEntry};
+
 pub struct MapperState {
     pub id_counter: usize,
 
-    // We need to track while loops and any parent labels
-    // For each we need to track whether they are targeted
-    // by a continue statement.
-    //
-    // If they are, we need to generate a new label for the break
-    // statement that will replace the continue statement.
-    //
-    // We also need to track the label of the continue statement
-    // so we can generate the correct break statement.
-    //
-    // Since labels and while nesting is hierarchical, we can
-    // use a stack to track the current while loop and any parent
-    // labels and keep their "used" state in sync. Since we need
-    // to know where a certain label points to, we use the same
-    // trick as the finally transform and use "#loop" as a token
-    // to indicate a while loop statement versus its parent label.
-    //
-    // When we enter a label that is a direct parent of a while
-    // loop, or enter a while loop, we push a new entry onto the
-    // stack. We pop it when we leave either. This should keep
-    // the stack in sync with the while loop and label nesting.
-    //
-    // When leaving a while loop, we need to check if the continue
-    // statement is targeting it or a parent label. It is when the
-    // second element of the tuple is not empty. In that case it
-    // will contain a generated label. This way multiple continues
-    // targeting the same loop can use the same generated label.
-    // Labels should always check the generated label of the next
-    // entry in the stack, which must be a while loop as per syntax.
-    //
-    // So this vector means: Vec<(while_label_name, Option<generated_label_name>)>
-    pub continue_targets: Vec<(String, Option<String>)>,
+    // How the input was parsed. Transforms only look at it to see whether a temporary would end up in the
+    // global scope of a script (see `in_script_top_level`), otherwise it just affects parsing.
+    pub source_type: SourceType,
+
+    // Generated names are this prefix followed by the id_counter
+    pub name_prefix: String,
+
+    // Declare every temporary with `var`, see `TransformOptions::var_temporaries`
+    pub var_temporaries: bool,
+
+    // Names that we must not generate because the input already uses them (see `collect_used_names`)
+    pub reserved_names: HashSet<String>,
+
+    // The ancestors of the node being visited. The mapper keeps this up to date, transforms can
+    // query it for the enclosing function, loop, labels, generator/async-ness and strict mode.
+    pub context: MapperContext,
+
+    // Scopes, bindings and references of the input. The mapper computes this when it starts and adds
+    // the scopes of generated nodes as it finds them. Generated variables are added by `next_var_name`.
+    pub scopes: ScopeTree,
+
+    // Whether `scopes` is the analysis of the program that is being transformed. When it is, the next
+    // traversal keeps using it instead of analyzing the program again. The bindings of the input are not
+    // moved along with their nodes, so `Mapper::map` sets this to false when a rule was applied in its
+    // traversal. Within a traversal they are only up to date for the nodes that were not moved yet.
+    pub scopes_analyzed: bool,
+
+    // Whether the built-in passes check their preconditions on each statement they transform (see
+    // `passes::check_precondition`). That walks the statement, so the transformer only does it with the
+    // `validate` option. The order of the passes is checked up front regardless.
+    pub check_preconditions: bool,
+
+    // Problems found while transforming. Transforms should report here and leave the node as is
+    // rather than panic. These end up in the TransformResult.
+    pub diagnostics: Vec<Diagnostic>,
+
+    // The runtime helpers that the transformed code calls, and the name they are called by.
+    // These get injected after the transforms.
+    pub helpers: BTreeMap<Helper, String>,
+
+    // Every rule that was applied, in the order they were applied. Ends up in the TransformResult.
+    pub trace: Vec<TraceEntry>,
+
+    // State that transforms keep to themselves, one value per type. See `extension_mut`.
+    extensions: HashMap<TypeId, Box<dyn Any>>,
 }
 
 impl MapperState {
+    pub fn new() -> Self {
+        Self {
+            id_counter: 0,
+            source_type: SourceType::default().with_module(true),
+            name_prefix: DEFAULT_NAME_PREFIX.to_string(),
+            var_temporaries: false,
+            reserved_names: HashSet::new(),
+            context: MapperContext::new(),
+            scopes: ScopeTree::new(),
+            scopes_analyzed: false,
+            check_preconditions: true,
+            diagnostics: vec![],
+            helpers: BTreeMap::new(),
+            trace: vec![],
+            extensions: HashMap::new(),
+        }
+    }
+
+    /// The value of this type that a transform stored with `insert_extension` or `extension_mut`, if any
+    pub fn extension<T: Any>(&self) -> Option<&T> {
+        self.extensions.get(&TypeId::of::<T>()).and_then(|value| value.downcast_ref::<T>())
+    }
+
+    /// The value of this type, starting from its default when there is none yet. Transforms that are
+    /// defined outside this crate keep their state here by declaring a type for it.
+    pub fn extension_mut<T: Any + Default>(&mut self) -> &mut T {
+        self.extensions
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_mut::<T>()
+            .expect("extensions are keyed by their type")
+    }
+
+    /// Store a value for its type. Returns the value it replaces, if any.
+    pub fn insert_extension<T: Any>(&mut self, value: T) -> Option<T> {
+        self.extensions.insert(TypeId::of::<T>(), Box::new(value)).and_then(|old| old.downcast::<T>().ok()).map(|old| *old)
+    }
+
+    pub fn remove_extension<T: Any>(&mut self) -> Option<T> {
+        self.extensions.remove(&TypeId::of::<T>()).and_then(|old| old.downcast::<T>().ok()).map(|old| *old)
+    }
+
+    /// Record that the output calls this helper. Returns the name to call it by. That's the name of
+    /// the helper unless the input already uses that name, in which case a number is added to it.
+    pub fn use_helper(&mut self, helper: Helper) -> String {
+        if let Some(name) = self.helpers.get(&helper) {
+            return name.clone();
+        }
+
+        let mut name = helper.name().to_string();
+        let mut suffix = 1;
+        while self.reserved_names.contains(&name) {
+            name = format!("{}{}", helper.name(), suffix);
+            suffix += 1;
+        }
+        self.reserved_names.insert(name.clone());
+        self.helpers.insert(helper, name.clone());
+        name
+    }
+
+    pub fn report(&mut self, code: DiagnosticCode, message: String, span: Span) {
+        // A node can be visited more than once when a transform asks for a revisit. Report it once.
+        if self.diagnostics.iter().any(|diagnostic| diagnostic.code == code && diagnostic.span == span && diagnostic.message == message) {
+            return;
+        }
+        self.diagnostics.push(Diagnostic::new(code, message, span));
+    }
+
+    /// Record that a transform rewrote the construct at `span`. Call this after any checks that
+    /// could make the transform bail, so the trace only lists rewrites that actually happened.
+    pub fn record_rule(&mut self, rule: RuleId, span: Span) {
+        log!(Info, "Rule: {}", rule.description());
+        self.trace.push(TraceEntry { rule, span, generated_span: None });
+    }
+
     pub fn next_ident_name(&mut self) -> String {
-        let id = self.id_counter;
-        self.id_counter += 1;
-        // Legit variable name AND label name :D
-        format!("$zeroSugar{}", id)
+        let (id, name) = self.find_free_name();
+        self.id_counter = id + 1;
+        name
+    }
+
+    /// A new name for a variable that the transform declares in the current scope. Use `next_ident_name`
+    /// for labels and other names that are not bindings.
+    pub fn next_var_name(&mut self, span: Span) -> String {
+        let name = self.next_ident_name();
+        let scope = self.context.current_scope().unwrap_or(self.scopes.root());
+        self.scopes.declare(scope, &name, BindingKind::Generated, span);
+        name
+    }
+
+    /// Whether the node being visited is directly in the body of a script, not in a block or function.
+    /// All scripts on a page share that scope, so a `let` or `const` for a temporary there would clash
+    /// with the same name in another transformed script. Transforms keep those in a block instead.
+    pub fn in_script_top_level(&self) -> bool {
+        let scope = self.context.current_scope().unwrap_or(self.scopes.root());
+        self.source_type.is_script() && self.scopes.scope(scope).kind == ScopeKind::Program
+    }
+
+    /// How to declare a temporary that is spliced into the current statement list. That's a `let`, except
+    /// at the top level of a script where a `var` can't clash with the same name in another script.
+    pub fn temp_var_kind(&self) -> VariableDeclarationKind {
+        if self.in_script_top_level() {
+            VariableDeclarationKind::Var
+        } else {
+            self.local_temp_var_kind(VariableDeclarationKind::Let)
+        }
+    }
+
+    /// How to declare a temporary of a transform that keeps it in a block of its own. That's the given
+    /// kind, or `var` when the output must not contain `let` or `const` (`var_temporaries`).
+    pub fn local_temp_var_kind(&self, kind: VariableDeclarationKind) -> VariableDeclarationKind {
+        if self.var_temporaries {
+            VariableDeclarationKind::Var
+        } else {
+            kind
+        }
+    }
+
+    fn find_free_name(&self) -> (usize, String) {
+        let mut id = self.id_counter;
+        loop {
+            // Legit variable name AND label name :D
+            let name = format!("{}{}", self.name_prefix, id);
+            if !self.reserved_names.contains(&name) {
+                return (id, name);
+            }
+            id += 1;
+        }
+    }
+}
+
+impl Default for MapperState {
+    fn default() -> Self {
+        Self::new()
     }
 }
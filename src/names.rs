@@ -0,0 +1,53 @@
+use std::collections::HashSet;
+
+use oxc_ast::ast::*;
+use oxc_ast::Visit;
+
+use crate::mapper::DEFAULT_MAX_DEPTH;
+
+/// Collect every binding, identifier reference and label name in the program.
+///
+/// Generated names must not be any of these. We don't care about scoping here, a name that is used
+/// anywhere in the input is off limits everywhere. Property names are not included since they
+/// can't collide with a binding. Names nested deeper than the mapper goes are not collected (see `check_nesting`).
+pub fn collect_used_names(program: &Program) -> HashSet<String> {
+    let mut collector = NameCollector { names: HashSet::new(), depth: 0 };
+    collector.visit_program(program);
+    collector.names
+}
+
+struct NameCollector {
+    names: HashSet<String>,
+    // The number of statements and expressions that are being visited right now
+    depth: usize,
+}
+
+impl<'a> Visit<'a> for NameCollector {
+    fn visit_statement(&mut self, stmt: &Statement<'a>) {
+        if self.depth < DEFAULT_MAX_DEPTH {
+            self.depth += 1;
+            self.visit_statement_match(stmt);
+            self.depth -= 1;
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &Expression<'a>) {
+        if self.depth < DEFAULT_MAX_DEPTH {
+            self.depth += 1;
+            self.visit_expression_match(expr);
+            self.depth -= 1;
+        }
+    }
+
+    fn visit_binding_identifier(&mut self, ident: &BindingIdentifier) {
+        self.names.insert(ident.name.to_string());
+    }
+
+    fn visit_identifier_reference(&mut self, ident: &IdentifierReference) {
+        self.names.insert(ident.name.to_string());
+    }
+
+    fn visit_label_identifier(&mut self, ident: &LabelIdentifier) {
+        self.names.insert(ident.name.to_string());
+    }
+}
@@ -0,0 +1,236 @@
+use oxc_span::SourceType;
+use oxc_syntax::identifier::is_identifier_name;
+use wasm_bindgen::prelude::*;
+
+use crate::helpers::HelperMode;
+
+pub const DEFAULT_HELPERS_MODULE: &str = "zero-sugar/helpers";
+pub const DEFAULT_NAME_PREFIX: &str = "$zeroSugar";
+
+/// Whether the input is a classic script or an ES module. Scripts are parsed in sloppy mode, and the
+/// temporaries that end up at their top level are declared with `var` since scripts share that scope.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceKind {
+    Script,
+    Module,
+}
+
+/// The syntax flavor of the input. JSX and TS parse fine but their syntax is not transformed (it's reported instead).
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceLanguage {
+    JavaScript,
+    Jsx,
+    TypeScript,
+    Tsx,
+}
+
+/// A target level: which transforms to run and what the validator allows in the output (see
+/// `Profile::from_preset`). Pick one with `TransformOptions::from_preset` or `apply_preset`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// Only what ES6 added to the statements this tool knows about: `for-of` loops and destructuring
+    /// in var decls, and `continue` since the `for-of` transform needs that gone. The rest is valid ES5
+    /// already. Temporaries are declared with `var` and the helpers only use ES5. Other ES6 syntax in
+    /// the input (`let`, arrows, classes) is left as is.
+    Es5,
+    /// The statement-level desugaring: `switch`, `continue`, `finally`, the loops other than `while`
+    /// and patterns in var decls. This is the default.
+    Js0,
+    /// The "minimal instruction set" of JS: JS0, then every `while` becomes `while (true)` and every
+    /// sub-statement a block.
+    Misc,
+}
+
+/// Toggle each desugaring transform individually. The default is the JS0 preset, which leaves
+/// `while_true` and `block_bodies` off.
+///
+/// Some transforms rely on others. These dependencies are applied by `resolve()`, which
+/// the pipeline calls for you. A disabled transform leaves its syntax in the output as is.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransformOptions {
+    /// `do { } while (x)` -> `while`
+    pub do_while: bool,
+    /// `for (a; b; c)` -> `while`
+    pub for_loop: bool,
+    /// `for (x in y)` -> `while` with `$forIn`
+    pub for_in: bool,
+    /// `for (x of y)` -> `while` with `$forOf`
+    pub for_of: bool,
+    /// `try { } finally { }` -> `try { } catch { }`
+    pub finally: bool,
+    /// `continue` -> labeled `break`
+    pub continue_stmt: bool,
+    /// `switch` -> `if`-`else` chain
+    pub switch: bool,
+    /// Split up var decls with multiple declarators, patterns, or without init
+    pub var_decl: bool,
+    /// `while (x)` -> `while (true)` with `if (!x) break;` (MISC)
+    pub while_true: bool,
+    /// `if (x) y;` -> `if (x) { y; }`, same for the bodies of loops and `with` (MISC)
+    pub block_bodies: bool,
+
+    /// How the runtime helpers (`$forIn`, `$forOf`, `$rest`) end up in the output. Inline by default.
+    pub helpers: HelperMode,
+    /// The module to import the helpers from when `helpers` is `HelperMode::Import`
+    pub helpers_module: String,
+    /// Generated variables and labels are this prefix followed by a number. Must be a valid identifier
+    /// (see `check`). Names that already occur in the input are skipped.
+    pub name_prefix: String,
+    /// Declare the generated temporaries with `var` rather than `let` and `const`. Only the ES5 preset
+    /// enables this.
+    pub var_temporaries: bool,
+
+    /// Script or module. Module by default. See also `infer_source_type`.
+    pub source_kind: SourceKind,
+    /// JS, JSX, TS or TSX. JS by default.
+    pub language: SourceLanguage,
+
+    /// Check the output afterwards. Reports anything the enabled transforms should have eliminated (see
+    /// `Profile::from_options`) and whether the output parses. Also has the passes check their
+    /// preconditions as they go (see `passes::check_precondition`). Off by default.
+    pub validate: bool,
+}
+
+#[wasm_bindgen]
+impl TransformOptions {
+    /// The transforms of the JS0 preset enabled
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            do_while: true,
+            for_loop: true,
+            for_in: true,
+            for_of: true,
+            finally: true,
+            continue_stmt: true,
+            switch: true,
+            var_decl: true,
+            while_true: false,
+            block_bodies: false,
+            helpers: HelperMode::Inline,
+            helpers_module: DEFAULT_HELPERS_MODULE.to_string(),
+            name_prefix: DEFAULT_NAME_PREFIX.to_string(),
+            var_temporaries: false,
+            source_kind: SourceKind::Module,
+            language: SourceLanguage::JavaScript,
+            validate: false,
+        }
+    }
+
+    /// All transforms disabled. Enable the ones you want from here.
+    pub fn none() -> Self {
+        Self {
+            do_while: false,
+            for_loop: false,
+            for_in: false,
+            for_of: false,
+            finally: false,
+            continue_stmt: false,
+            switch: false,
+            var_decl: false,
+            while_true: false,
+            block_bodies: false,
+            helpers: HelperMode::Inline,
+            helpers_module: DEFAULT_HELPERS_MODULE.to_string(),
+            name_prefix: DEFAULT_NAME_PREFIX.to_string(),
+            var_temporaries: false,
+            source_kind: SourceKind::Module,
+            language: SourceLanguage::JavaScript,
+            validate: false,
+        }
+    }
+
+    /// The transforms of this preset enabled, the other options are the defaults
+    pub fn from_preset(preset: Preset) -> Self {
+        let mut options = Self::new();
+        options.apply_preset(preset);
+        options
+    }
+
+    /// Enable the transforms of this preset and disable the others. The ES5 preset also declares the
+    /// temporaries with `var`. Leaves the other options alone.
+    pub fn apply_preset(&mut self, preset: Preset) {
+        let (js0, misc) = match preset {
+            Preset::Es5 => (false, false),
+            Preset::Js0 => (true, false),
+            Preset::Misc => (true, true),
+        };
+        self.do_while = js0;
+        self.for_loop = js0;
+        self.for_in = js0;
+        self.for_of = true;
+        self.finally = js0;
+        self.continue_stmt = js0;
+        self.switch = js0;
+        self.var_decl = true;
+        self.while_true = misc;
+        self.block_bodies = misc;
+        self.var_temporaries = preset == Preset::Es5;
+    }
+
+    /// Set the source kind and language from the extension of this file name. Returns false, and changes
+    /// nothing, when the extension is not one we know.
+    ///
+    /// `.cjs` is a script and `.mjs` a module. For `.js` (and the others) the source kind is left as is
+    /// since that depends on the `package.json`, which we don't look at.
+    pub fn infer_source_type(&mut self, file_name: &str) -> bool {
+        let extension = file_name.rsplit_once('.').map(|(_, extension)| extension).unwrap_or("");
+        let (source_kind, language) = match extension {
+            "js" => (self.source_kind, SourceLanguage::JavaScript),
+            "cjs" => (SourceKind::Script, SourceLanguage::JavaScript),
+            "mjs" => (SourceKind::Module, SourceLanguage::JavaScript),
+            "jsx" => (self.source_kind, SourceLanguage::Jsx),
+            "ts" => (self.source_kind, SourceLanguage::TypeScript),
+            "cts" => (SourceKind::Script, SourceLanguage::TypeScript),
+            "mts" => (SourceKind::Module, SourceLanguage::TypeScript),
+            "tsx" => (self.source_kind, SourceLanguage::Tsx),
+            _ => return false,
+        };
+        self.source_kind = source_kind;
+        self.language = language;
+        true
+    }
+
+    /// Enable the transforms that the enabled transforms depend on.
+    ///
+    /// The for-loop and do-while transforms move the update/test into the body of the new `while`.
+    /// A `continue` in that body would skip it so those must be eliminated as well. The for-in and
+    /// for-of transforms put a labeled loop in a block, where a `continue` to that label is not allowed.
+    /// The other transforms deal with whatever the disabled transforms leave behind.
+    pub fn resolve(&self) -> Self {
+        let mut resolved = self.clone();
+        if resolved.for_loop || resolved.do_while || resolved.for_in || resolved.for_of {
+            resolved.continue_stmt = true;
+        }
+        resolved
+    }
+}
+
+impl TransformOptions {
+    /// Whether these options can work. The error says what's wrong with them.
+    pub fn check(&self) -> Result<(), String> {
+        // The generated names are the prefix with a number so the prefix itself must be an identifier
+        if !is_identifier_name(&self.name_prefix) {
+            return Err(format!("The name prefix `{}` is not a valid identifier", self.name_prefix));
+        }
+        Ok(())
+    }
+
+    /// The oxc source type to parse (and reparse) the code with
+    pub fn source_type(&self) -> SourceType {
+        SourceType::default()
+            .with_module(self.source_kind == SourceKind::Module)
+            .with_typescript(matches!(self.language, SourceLanguage::TypeScript | SourceLanguage::Tsx))
+            .with_jsx(matches!(self.language, SourceLanguage::Jsx | SourceLanguage::Tsx))
+    }
+}
+
+impl Default for TransformOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
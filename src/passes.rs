@@ -0,0 +1,479 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_span::Span;
+
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::log;
+use crate::mapper::{create_mapper_with_debug_id, Mapper, MapperAction};
+use crate::mapper_state::MapperState;
+use crate::options::TransformOptions;
+use crate::transformer::Transform;
+use crate::transforms::stmt_block_body::transform_block_bodies;
+use crate::transforms::stmt_continue::{apply_continue_transform_updates, transform_continue_statement};
+use crate::transforms::stmt_do_while::transform_do_while_statement;
+use crate::transforms::stmt_finally::transform_finally_statement;
+use crate::transforms::stmt_for_in::transform_for_in_statement;
+use crate::transforms::stmt_for_n::transform_for_n_statement;
+use crate::transforms::stmt_for_of::transform_for_of_statement;
+use crate::transforms::stmt_switch::transform_switch_statement;
+use crate::transforms::stmt_var_decl::transform_var_decl_statement;
+use crate::transforms::stmt_while::transform_while_statement;
+use crate::validator::{find_constructs, validate_statement, Construct, Profile};
+
+/// Gets every statement of the program, on the way down (`before` is true) and on the way up, and
+/// returns what to replace it with. Same as a statement visitor of the mapper, plus the state.
+pub type PassVisitor = for<'a> fn(Statement<'a>, bool, &'a Allocator, &mut MapperState) -> (MapperAction, Statement<'a>);
+
+/// One of the built-in transforms and what it expects of the code it gets. See `PassManager`.
+#[derive(Clone, Copy)]
+pub struct Pass {
+    /// Shows up in diagnostics and the log, like "for-loop"
+    pub name: &'static str,
+    /// What must be gone by the time this pass runs. Checked for every statement that this pass
+    /// removes. When such a statement still contains one of these, the pass leaves it alone.
+    pub requires_absent: &'static [Construct],
+    /// What this pass may add to the code
+    pub introduces: &'static [Construct],
+    /// What this pass eliminates
+    pub removes: &'static [Construct],
+    /// Whether `visit` transforms a statement when it enters it rather than when it leaves it. The
+    /// precondition is checked on that visit only.
+    pub on_enter: bool,
+    pub visit: PassVisitor,
+}
+
+pub const FINALLY_PASS: Pass = Pass {
+    name: "finally",
+    requires_absent: &[],
+    // A `continue` that leaves the `try` is moved to after the `finally` body, in an `if` without a block
+    introduces: &[Construct::Continue, Construct::NonBlockBody],
+    removes: &[Construct::Finally],
+    on_enter: false,
+    visit: visit_finally,
+};
+
+pub const CONTINUE_PASS: Pass = Pass {
+    name: "continue",
+    requires_absent: &[],
+    // The labeled body of the loop
+    introduces: &[Construct::NonBlockBody],
+    removes: &[Construct::Continue],
+    on_enter: false,
+    visit: visit_continue,
+};
+
+pub const DO_WHILE_PASS: Pass = Pass {
+    name: "do-while",
+    // The test moves into the body, where a `continue` would skip it
+    requires_absent: &[Construct::Continue],
+    introduces: &[Construct::ConditionalWhile],
+    removes: &[Construct::DoWhile],
+    on_enter: false,
+    visit: visit_do_while,
+};
+
+pub const FOR_LOOP_PASS: Pass = Pass {
+    name: "for-loop",
+    // The update moves into the body, where a `continue` would skip it
+    requires_absent: &[Construct::Continue],
+    // The init is moved out of the header as is
+    introduces: &[Construct::DeclarationPattern, Construct::ConditionalWhile],
+    removes: &[Construct::ForLoop],
+    on_enter: false,
+    visit: visit_for_loop,
+};
+
+pub const FOR_IN_PASS: Pass = Pass {
+    name: "for-in",
+    requires_absent: &[],
+    // A pattern in the header is assigned in the body. The loop ends with `if ($next.done) break;`.
+    introduces: &[Construct::DeclarationPattern, Construct::ConditionalWhile, Construct::NonBlockBody],
+    removes: &[Construct::ForIn],
+    on_enter: false,
+    visit: visit_for_in,
+};
+
+pub const FOR_OF_PASS: Pass = Pass {
+    name: "for-of",
+    requires_absent: &[],
+    // A pattern in the header is assigned in the body. The loop ends with `if ($next.done) break;`.
+    introduces: &[Construct::DeclarationPattern, Construct::ConditionalWhile, Construct::NonBlockBody],
+    removes: &[Construct::ForOf],
+    on_enter: false,
+    visit: visit_for_of,
+};
+
+pub const VAR_DECL_PASS: Pass = Pass {
+    name: "var-decl",
+    requires_absent: &[],
+    introduces: &[],
+    removes: &[Construct::DeclarationPattern],
+    on_enter: true,
+    visit: visit_var_decl,
+};
+
+pub const SWITCH_PASS: Pass = Pass {
+    name: "switch",
+    // The let/const decls of a case are hoisted out of the switch by name
+    requires_absent: &[Construct::DeclarationPattern],
+    // The if-else chain that picks the case
+    introduces: &[Construct::NonBlockBody],
+    removes: &[Construct::Switch],
+    on_enter: false,
+    visit: visit_switch,
+};
+
+pub const WHILE_TRUE_PASS: Pass = Pass {
+    name: "while-true",
+    requires_absent: &[],
+    // The `if (!x) break;`
+    introduces: &[Construct::NonBlockBody],
+    removes: &[Construct::ConditionalWhile],
+    on_enter: false,
+    visit: visit_while,
+};
+
+pub const BLOCK_BODY_PASS: Pass = Pass {
+    name: "block-body",
+    requires_absent: &[],
+    introduces: &[],
+    removes: &[Construct::NonBlockBody],
+    on_enter: false,
+    visit: visit_block_body,
+};
+
+fn visit_finally<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> (MapperAction, Statement<'a>) {
+    match (before, stmt) {
+        (false, Statement::TryStatement(try_stmt)) => transform_finally_statement(try_stmt.unbox(), allocator, state),
+        (_, stmt) => (MapperAction::Normal, stmt),
+    }
+}
+
+fn visit_continue<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> (MapperAction, Statement<'a>) {
+    // This part wraps loop bodies in a labeled statement for the continues that were transformed in it.
+    let stmt = apply_continue_transform_updates(stmt, before, allocator, state);
+    match (before, stmt) {
+        (false, Statement::ContinueStatement(continue_stmt)) => transform_continue_statement(continue_stmt.unbox(), allocator, state),
+        (_, stmt) => (MapperAction::Normal, stmt),
+    }
+}
+
+fn visit_do_while<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> (MapperAction, Statement<'a>) {
+    match (before, stmt) {
+        (false, Statement::DoWhileStatement(do_while)) => transform_do_while_statement(do_while.unbox(), allocator, state),
+        (_, stmt) => (MapperAction::Normal, stmt),
+    }
+}
+
+fn visit_for_loop<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> (MapperAction, Statement<'a>) {
+    match (before, stmt) {
+        (false, Statement::ForStatement(for_stmt)) => transform_for_n_statement(for_stmt.unbox(), allocator, state),
+        (_, stmt) => (MapperAction::Normal, stmt),
+    }
+}
+
+fn visit_for_in<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> (MapperAction, Statement<'a>) {
+    match (before, stmt) {
+        (false, Statement::ForInStatement(for_stmt)) => transform_for_in_statement(for_stmt.unbox(), allocator, state),
+        (_, stmt) => (MapperAction::Normal, stmt),
+    }
+}
+
+fn visit_for_of<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> (MapperAction, Statement<'a>) {
+    match (before, stmt) {
+        (false, Statement::ForOfStatement(for_stmt)) => transform_for_of_statement(for_stmt.unbox(), allocator, state),
+        (_, stmt) => (MapperAction::Normal, stmt),
+    }
+}
+
+fn visit_var_decl<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> (MapperAction, Statement<'a>) {
+    match (before, stmt) {
+        // Do this on-enter rather than on-exit
+        (true, stmt @ (Statement::Declaration(Declaration::VariableDeclaration(_)) | Statement::ModuleDeclaration(_))) => {
+            transform_var_decl_statement(stmt, allocator, state)
+        }
+        (_, stmt) => (MapperAction::Normal, stmt),
+    }
+}
+
+fn visit_switch<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> (MapperAction, Statement<'a>) {
+    match (before, stmt) {
+        (false, Statement::SwitchStatement(switch_stmt)) => transform_switch_statement(switch_stmt.unbox(), allocator, state),
+        (_, stmt) => (MapperAction::Normal, stmt),
+    }
+}
+
+fn visit_while<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> (MapperAction, Statement<'a>) {
+    match (before, stmt) {
+        (false, Statement::WhileStatement(while_stmt)) => transform_while_statement(while_stmt.unbox(), allocator, state),
+        (_, stmt) => (MapperAction::Normal, stmt),
+    }
+}
+
+fn visit_block_body<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> (MapperAction, Statement<'a>) {
+    if before {
+        return (MapperAction::Normal, stmt);
+    }
+    transform_block_bodies(stmt, allocator, state)
+}
+
+impl Transform for Pass {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn requires_absent(&self) -> &[Construct] {
+        self.requires_absent
+    }
+
+    fn introduces(&self) -> &[Construct] {
+        self.introduces
+    }
+
+    fn removes(&self) -> &[Construct] {
+        self.removes
+    }
+
+    fn install<'a>(&self, mapper: &mut Mapper<'a>) {
+        let pass = *self;
+        let state = Rc::clone(&mapper.state);
+        mapper.add_visitor_stmt(move |stmt, allocator, before: bool| {
+            let mut state = state.borrow_mut();
+            if before == pass.on_enter && state.check_preconditions && !check_precondition(&pass, &stmt, &mut state) {
+                return (MapperAction::Normal, stmt);
+            }
+            (pass.visit)(stmt, before, allocator, &mut state)
+        });
+    }
+}
+
+/// Runs a list of transforms over a program, in order. Each transform declares what it needs gone, what
+/// it removes and what it may add (in terms of the `Construct`s of the validator). From that the manager
+/// checks the order up front (see `validate`). The built-in passes also check their preconditions as
+/// they run (see `check_precondition` and `MapperState::check_preconditions`), so a transform never gets
+/// a node it can't deal with. Those problems are reported as diagnostics.
+///
+/// Every transform gets its own traversal of the program, unless it's added with `add_to_last_traversal` or
+/// `add_grouped`. The built-in passes are grouped into as few traversals as their dependencies allow.
+pub struct PassManager {
+    // The transforms that share a traversal, per traversal in the order they run
+    traversals: Vec<Vec<Box<dyn Transform>>>,
+    // Run all traversals again, until a round applies no rule or this many rounds ran
+    max_rounds: usize,
+}
+
+impl Default for PassManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PassManager {
+    /// No transforms. Runs them once.
+    pub fn new() -> Self {
+        Self { traversals: vec!(), max_rounds: 1 }
+    }
+
+    /// The passes for the transforms that are enabled in these options, in an order that works. Call
+    /// `options.resolve()` first, or the for-loop and do-while passes may be missing their dependency.
+    pub fn from_options(options: &TransformOptions) -> Self {
+        let mut manager = Self::new();
+        // The finally pass goes first because it can introduce continues. The loop passes need
+        // those gone. The loops can leave patterns behind, which the switch pass can't hoist. The
+        // MISC passes go last so they also get the loops and ifs that the others generate.
+        let passes = [
+            (options.finally, FINALLY_PASS),
+            (options.continue_stmt, CONTINUE_PASS),
+            (options.do_while, DO_WHILE_PASS),
+            (options.for_loop, FOR_LOOP_PASS),
+            (options.for_in, FOR_IN_PASS),
+            (options.for_of, FOR_OF_PASS),
+            (options.var_decl, VAR_DECL_PASS),
+            (options.switch, SWITCH_PASS),
+            (options.while_true, WHILE_TRUE_PASS),
+            (options.block_bodies, BLOCK_BODY_PASS),
+        ];
+        for (enabled, pass) in passes {
+            if enabled {
+                manager.add_grouped(pass);
+            }
+        }
+        manager
+    }
+
+    /// Add a transform to the traversal of the last added transform when that is safe, otherwise in a traversal
+    /// of its own. It's safe when it does not depend on what the transforms in that traversal remove or
+    /// introduce, and they don't depend on what it introduces.
+    pub fn add_grouped(&mut self, transform: impl Transform + 'static) {
+        let shares = self.traversals.last().is_some_and(|traversal| {
+            traversal.iter().all(|other| !conflicts(other.as_ref(), &transform))
+        });
+        if shares {
+            self.add_to_last_traversal(transform);
+        } else {
+            self.add(transform);
+        }
+    }
+
+    /// Add a transform that runs in a traversal of its own, after the transforms added so far
+    pub fn add(&mut self, transform: impl Transform + 'static) {
+        self.traversals.push(vec!(Box::new(transform)));
+    }
+
+    /// Add a transform to the traversal of the last added transform. Its visitors are called right after
+    /// theirs, for each node. That saves a traversal, but it can't rely on what those transforms remove.
+    pub fn add_to_last_traversal(&mut self, transform: impl Transform + 'static) {
+        match self.traversals.last_mut() {
+            Some(traversal) => traversal.push(Box::new(transform)),
+            None => self.add(transform),
+        }
+    }
+
+    /// All transforms, in the order they run
+    pub fn transforms(&self) -> impl Iterator<Item = &dyn Transform> {
+        self.traversals.iter().flatten().map(|transform| transform.as_ref())
+    }
+
+    /// The names of the transforms in each traversal, joined with a `+`, in the order they run
+    pub fn traversal_names(&self) -> Vec<String> {
+        self.traversals.iter().map(|transforms| traversal_name(transforms)).collect()
+    }
+
+    /// Keep running all passes until a round applies no rule at all (see `MapperState::record_rule`), at
+    /// most `max_rounds` times. The default of 1 runs every pass once.
+    pub fn set_max_rounds(&mut self, max_rounds: usize) {
+        self.max_rounds = max_rounds.max(1);
+    }
+
+    /// Check that every construct a transform requires to be absent is removed in an earlier traversal
+    /// and not introduced again in between. A construct that no other transform removes is fine, the
+    /// input may not have it. Returns a diagnostic for each problem.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        // Each transform with the index of its traversal
+        let transforms: Vec<(usize, &dyn Transform)> = self.traversals.iter().enumerate()
+            .flat_map(|(traversal, transforms)| transforms.iter().map(move |transform| (traversal, transform.as_ref())))
+            .collect();
+
+        let mut problems = vec!();
+        for (index, &(traversal, transform)) in transforms.iter().enumerate() {
+            for &construct in transform.requires_absent() {
+                // When no other transform removes it then it's up to the input
+                let removed_elsewhere = transforms.iter().enumerate().any(|(other, (_, other_transform))| other != index && other_transform.removes().contains(&construct));
+                if !removed_elsewhere {
+                    continue;
+                }
+                let last_change = transforms[..index].iter().rev()
+                    .filter(|(earlier_traversal, _)| *earlier_traversal < traversal)
+                    .find(|(_, earlier)| earlier.introduces().contains(&construct) || earlier.removes().contains(&construct));
+                let message = match last_change {
+                    Some((_, earlier)) if earlier.introduces().contains(&construct) => format!(
+                        "{} must be gone before the `{}` pass but the `{}` pass before it may introduce one",
+                        construct.description(), transform.name(), earlier.name()
+                    ),
+                    Some(_) => continue,
+                    None => match transforms.iter().enumerate().find(|(other, (_, later))| *other != index && later.removes().contains(&construct)) {
+                        Some((_, (later_traversal, later))) if *later_traversal == traversal => format!(
+                            "{} must be gone before the `{}` pass so that can't share a traversal with the `{}` pass",
+                            construct.description(), transform.name(), later.name()
+                        ),
+                        Some((_, (_, later))) => format!(
+                            "{} must be gone before the `{}` pass so that must run after the `{}` pass",
+                            construct.description(), transform.name(), later.name()
+                        ),
+                        None => continue,
+                    },
+                };
+                problems.push(Diagnostic::new(DiagnosticCode::PassPipeline, message, Span::default()));
+            }
+        }
+        problems
+    }
+
+    /// Run the transforms over the program. When the order does not validate, that's reported and the
+    /// program is returned as is. All transforms share the state, so generated names are unique across them.
+    pub fn run<'a>(&self, program: Program<'a>, allocator: &'a Allocator, state: &Rc<RefCell<MapperState>>) -> Program<'a> {
+        let problems = self.validate();
+        if !problems.is_empty() {
+            state.borrow_mut().diagnostics.extend(problems);
+            return program;
+        }
+
+        let mut program = program;
+        for round in 0..self.max_rounds {
+            let applied = state.borrow().trace.len();
+            for traversal in &self.traversals {
+                program = run_traversal(traversal, program, allocator, state, round);
+            }
+            if state.borrow().trace.len() == applied {
+                return program;
+            }
+        }
+        if self.max_rounds > 1 {
+            state.borrow_mut().report(
+                DiagnosticCode::PassPipeline,
+                format!("The passes still changed the code after {} rounds. Stopped there.", self.max_rounds),
+                Span::default()
+            );
+        }
+        program
+    }
+}
+
+/// Whether `later` has to run in a later traversal than `earlier`, rather than in the same one
+fn conflicts(earlier: &dyn Transform, later: &dyn Transform) -> bool {
+    let overlaps = |a: &[Construct], b: &[Construct]| a.iter().any(|construct| b.contains(construct));
+    // It needs them gone, which is only certain after the traversal that removes them
+    overlaps(later.requires_absent(), earlier.removes())
+        || overlaps(later.requires_absent(), earlier.introduces())
+        // The other way around, the earlier one would get what the later one introduces
+        || overlaps(earlier.requires_absent(), later.introduces())
+        // It cleans up after the earlier one, which may add those where it was already done
+        || overlaps(later.removes(), earlier.introduces())
+}
+
+fn traversal_name(transforms: &[Box<dyn Transform>]) -> String {
+    transforms.iter().map(|transform| transform.name()).collect::<Vec<&str>>().join("+")
+}
+
+fn run_traversal<'a>(transforms: &[Box<dyn Transform>], program: Program<'a>, allocator: &'a Allocator, state: &Rc<RefCell<MapperState>>, round: usize) -> Program<'a> {
+    let names = traversal_name(transforms);
+    log!(Debug, "Running {} (round {})", names, round + 1);
+    let mut mapper = create_mapper_with_debug_id(allocator, names);
+    mapper.state = Rc::clone(state);
+    for transform in transforms {
+        transform.install(&mut mapper);
+    }
+    mapper.map(program)
+}
+
+/// When this statement is something that the transform removes, check that it contains nothing that
+/// the transform requires to be absent. Reports each of those and returns false when there are any.
+/// The built-in passes call this before they touch a statement. Transforms from elsewhere can do the same.
+pub fn check_precondition(transform: &dyn Transform, stmt: &Statement, state: &mut MapperState) -> bool {
+    if transform.requires_absent().is_empty() {
+        return true;
+    }
+    let mut removed = None;
+    find_constructs(stmt, &mut |construct, _span| {
+        if transform.removes().contains(&construct) {
+            removed = Some(construct);
+        }
+    });
+    let Some(removed) = removed else { return true };
+
+    let violations = validate_statement(stmt, Profile::any_except(transform.requires_absent()));
+    for violation in &violations {
+        state.report(
+            DiagnosticCode::UnexpectedNode,
+            format!(
+                "{} must be gone before the `{}` pass. This one is still here so the {} around it is left as is.",
+                violation.construct.description(), transform.name(), removed.description()
+            ),
+            violation.span
+        );
+    }
+    violations.is_empty()
+}
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+
+use oxc_ast::ast::*;
+use oxc_ast::{AstKind, Visit};
+use oxc_span::{GetSpan, Span};
+use oxc_syntax::reference::ReferenceId;
+use oxc_syntax::scope::{ScopeFlags, ScopeId};
+use oxc_syntax::symbol::SymbolId;
+
+use crate::mapper::DEFAULT_MAX_DEPTH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScopeKind {
+    Program,
+    // Functions, methods and arrows. Holds the params and the body.
+    Function,
+    StaticBlock,
+    Block,
+    // The catch param and the catch body
+    Catch,
+    // A `for`, `for-in` or `for-of` with a `let` or `const` in the header
+    For,
+    // Only class expressions get a scope, for their own name
+    Class,
+}
+
+impl ScopeKind {
+    /// Whether `var` declarations are hoisted up to this scope
+    pub fn is_var_scope(&self) -> bool {
+        matches!(self, ScopeKind::Program | ScopeKind::Function | ScopeKind::StaticBlock)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Var,
+    Let,
+    Const,
+    Function,
+    Class,
+    Param,
+    CatchParam,
+    Import,
+    // A variable that a transform introduced
+    Generated,
+}
+
+impl BindingKind {
+    /// Whether the binding can't be accessed before its declaration (temporal dead zone)
+    pub fn has_tdz(&self) -> bool {
+        matches!(self, BindingKind::Let | BindingKind::Const | BindingKind::Class)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub span: Span,
+    pub parent: Option<ScopeId>,
+    // The bindings declared in this scope, by name
+    pub bindings: HashMap<String, SymbolId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: String,
+    pub kind: BindingKind,
+    // The span of the identifier that declared it (the first one, for redeclared `var`s)
+    pub span: Span,
+    pub scope: ScopeId,
+    pub references: Vec<ReferenceId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub span: Span,
+    // The scope that the reference appears in
+    pub scope: ScopeId,
+    // `None` for globals and anything else that is not declared in the program
+    pub binding: Option<SymbolId>,
+    // Assigned to (`x = 1`, `x++`, `[x] = arr`). This may also be a read, like `x += 1`.
+    pub is_write: bool,
+}
+
+/// The scopes, bindings and references of a program.
+///
+/// `analyze` builds this from the input and stamps the `symbol_id` of every `BindingIdentifier` and
+/// the `reference_id` of every `IdentifierReference` it finds, so a transform can go from a node to its
+/// binding. Those ids are kept when a node is moved into the output. Nodes created by the builders have
+/// no ids, and generated names are only known by name (see `MapperState::next_var_name`).
+#[derive(Debug, Clone)]
+pub struct ScopeTree {
+    scopes: Vec<Scope>,
+    bindings: Vec<Binding>,
+    references: Vec<Reference>,
+    // The child scopes by parent, kind and span (start and end, `Span` is not `Hash`), for `find_or_add_scope`
+    children: HashMap<ChildKey, ScopeId>,
+}
+
+type ChildKey = (ScopeId, ScopeKind, u32, u32);
+
+fn child_key(parent: ScopeId, kind: ScopeKind, span: Span) -> ChildKey {
+    (parent, kind, span.start, span.end)
+}
+
+impl ScopeTree {
+    /// A tree with only an empty program scope
+    pub fn new() -> Self {
+        Self {
+            scopes: vec!(Scope { kind: ScopeKind::Program, span: Span::default(), parent: None, bindings: HashMap::new() }),
+            bindings: vec!(),
+            references: vec!(),
+            children: HashMap::new(),
+        }
+    }
+
+    pub fn analyze(program: &Program) -> Self {
+        let mut builder = ScopeBuilder {
+            tree: ScopeTree { scopes: vec!(), bindings: vec!(), references: vec!(), children: HashMap::new() },
+            current: None,
+            pending_scope: None,
+            binding_kind: None,
+            depth: 0,
+        };
+        builder.visit_program(program);
+
+        let mut tree = builder.tree;
+        // The kind and span of a scope are only known after it was added, when the node that owns it is entered
+        tree.children.clear();
+        for index in 0..tree.scopes.len() {
+            let scope = &tree.scopes[index];
+            if let Some(parent) = scope.parent {
+                tree.children.entry(child_key(parent, scope.kind, scope.span)).or_insert(ScopeId::new(index));
+            }
+        }
+        for index in 0..tree.references.len() {
+            let reference = &tree.references[index];
+            if let Some(symbol_id) = tree.resolve(reference.scope, &reference.name) {
+                tree.references[index].binding = Some(symbol_id);
+                tree.bindings[symbol_id.index()].references.push(ReferenceId::new(index));
+            }
+        }
+        tree
+    }
+
+    pub fn root(&self) -> ScopeId {
+        ScopeId::new(0)
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.index()]
+    }
+
+    pub fn binding(&self, id: SymbolId) -> &Binding {
+        &self.bindings[id.index()]
+    }
+
+    pub fn reference(&self, id: ReferenceId) -> &Reference {
+        &self.references[id.index()]
+    }
+
+    pub fn scopes(&self) -> impl Iterator<Item = (ScopeId, &Scope)> {
+        self.scopes.iter().enumerate().map(|(index, scope)| (ScopeId::new(index), scope))
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (SymbolId, &Binding)> {
+        self.bindings.iter().enumerate().map(|(index, binding)| (SymbolId::new(index), binding))
+    }
+
+    pub fn references(&self) -> impl Iterator<Item = (ReferenceId, &Reference)> {
+        self.references.iter().enumerate().map(|(index, reference)| (ReferenceId::new(index), reference))
+    }
+
+    /// The binding that `name` refers to in this scope, searching outward
+    pub fn resolve(&self, scope: ScopeId, name: &str) -> Option<SymbolId> {
+        let mut current = Some(scope);
+        while let Some(id) = current {
+            let scope = self.scope(id);
+            if let Some(symbol_id) = scope.bindings.get(name) {
+                return Some(*symbol_id);
+            }
+            current = scope.parent;
+        }
+        None
+    }
+
+    /// Whether `name` in this scope refers to a different binding than it does in `outer`, an ancestor scope
+    pub fn is_shadowed(&self, scope: ScopeId, outer: ScopeId, name: &str) -> bool {
+        self.resolve(scope, name) != self.resolve(outer, name)
+    }
+
+    /// The binding of an identifier from the input
+    pub fn symbol_of(&self, ident: &BindingIdentifier) -> Option<&Binding> {
+        ident.symbol_id.get().map(|id| self.binding(id))
+    }
+
+    /// The binding that an identifier from the input refers to. `None` for globals and generated nodes.
+    pub fn binding_of(&self, ident: &IdentifierReference) -> Option<&Binding> {
+        ident.reference_id.get().and_then(|id| self.reference(id).binding).map(|id| self.binding(id))
+    }
+
+    /// The nearest scope that a `var` in this scope would be hoisted to
+    pub fn var_scope(&self, scope: ScopeId) -> ScopeId {
+        let mut id = scope;
+        while let (false, Some(parent)) = (self.scope(id).kind.is_var_scope(), self.scope(id).parent) {
+            id = parent;
+        }
+        id
+    }
+
+    pub fn add_scope(&mut self, parent: Option<ScopeId>, kind: ScopeKind, span: Span) -> ScopeId {
+        self.scopes.push(Scope { kind, span, parent, bindings: HashMap::new() });
+        let id = ScopeId::new(self.scopes.len() - 1);
+        if let Some(parent) = parent {
+            self.children.entry(child_key(parent, kind, span)).or_insert(id);
+        }
+        id
+    }
+
+    /// A child scope of `parent` for this node. Nodes that were moved into the output keep their span so they
+    /// find their scope from the input. Anything else is generated and gets a new scope.
+    pub fn find_or_add_scope(&mut self, parent: ScopeId, kind: ScopeKind, span: Span) -> ScopeId {
+        // The mapper walks the program body as a block with the span of the program. That's the program scope.
+        if kind == ScopeKind::Block && self.scope(parent).kind == ScopeKind::Program && self.scope(parent).span == span {
+            return parent;
+        }
+        match self.children.get(&child_key(parent, kind, span)) {
+            Some(id) => *id,
+            None => self.add_scope(Some(parent), kind, span),
+        }
+    }
+
+    /// Declare a binding in this scope, or the var scope above it for `var`. Redeclaring a name in the
+    /// same scope returns the existing binding.
+    pub fn declare(&mut self, scope: ScopeId, name: &str, kind: BindingKind, span: Span) -> SymbolId {
+        let scope = if kind == BindingKind::Var { self.var_scope(scope) } else { scope };
+        if let Some(symbol_id) = self.scope(scope).bindings.get(name) {
+            return *symbol_id;
+        }
+
+        let symbol_id = SymbolId::new(self.bindings.len());
+        self.bindings.push(Binding { name: name.to_string(), kind, span, scope, references: vec!() });
+        self.scopes[scope.index()].bindings.insert(name.to_string(), symbol_id);
+        symbol_id
+    }
+
+    fn add_reference(&mut self, ident: &IdentifierReference, scope: ScopeId, is_write: bool) {
+        let reference_id = ReferenceId::new(self.references.len());
+        self.references.push(Reference { name: ident.name.to_string(), span: ident.span, scope, binding: None, is_write });
+        ident.reference_id.set(Some(reference_id));
+    }
+}
+
+impl Default for ScopeTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct ScopeBuilder {
+    tree: ScopeTree,
+    current: Option<ScopeId>,
+    // A scope that was just entered. The node that owns it comes next, which tells us its span and kind.
+    pending_scope: Option<ScopeId>,
+    // What the binding identifiers that we're visiting declare
+    binding_kind: Option<BindingKind>,
+    // The number of statements and expressions that are being visited right now
+    depth: usize,
+}
+
+impl ScopeBuilder {
+    fn current(&self) -> ScopeId {
+        self.current.expect("the program scope is entered first")
+    }
+
+    fn declare(&mut self, ident: &BindingIdentifier, kind: BindingKind) {
+        let symbol_id = self.tree.declare(self.current(), &ident.name, kind, ident.span);
+        ident.symbol_id.set(Some(symbol_id));
+    }
+
+    fn with_binding_kind(&mut self, kind: BindingKind, f: impl FnOnce(&mut Self)) {
+        let outer = self.binding_kind.replace(kind);
+        f(self);
+        self.binding_kind = outer;
+    }
+}
+
+impl<'a> Visit<'a> for ScopeBuilder {
+    // Anything nested deeper than the mapper goes is not analyzed, rather than running out of stack.
+    // The transforms reject such input before they start (see `check_nesting`).
+    fn visit_statement(&mut self, stmt: &Statement<'a>) {
+        if self.depth < DEFAULT_MAX_DEPTH {
+            self.depth += 1;
+            self.visit_statement_match(stmt);
+            self.depth -= 1;
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &Expression<'a>) {
+        if self.depth < DEFAULT_MAX_DEPTH {
+            self.depth += 1;
+            self.visit_expression_match(expr);
+            self.depth -= 1;
+        }
+    }
+
+    fn enter_scope(&mut self, flags: ScopeFlags) {
+        let kind = if flags.contains(ScopeFlags::Top) {
+            ScopeKind::Program
+        } else if flags.contains(ScopeFlags::Function) {
+            ScopeKind::Function
+        } else if flags.contains(ScopeFlags::ClassStaticBlock) {
+            ScopeKind::StaticBlock
+        } else {
+            ScopeKind::Block
+        };
+        let id = self.tree.add_scope(self.current, kind, Span::default());
+        self.current = Some(id);
+        self.pending_scope = Some(id);
+    }
+
+    fn leave_scope(&mut self) {
+        self.current = self.tree.scope(self.current()).parent;
+    }
+
+    fn enter_node(&mut self, kind: AstKind<'a>) {
+        let Some(id) = self.pending_scope.take() else { return };
+        let scope = &mut self.tree.scopes[id.index()];
+        scope.span = kind.span();
+        match kind {
+            AstKind::CatchClause(_) => scope.kind = ScopeKind::Catch,
+            AstKind::ForStatement(_) | AstKind::ForInStatement(_) | AstKind::ForOfStatement(_) => scope.kind = ScopeKind::For,
+            AstKind::Class(_) => scope.kind = ScopeKind::Class,
+            _ => (),
+        }
+    }
+
+    fn visit_binding_identifier(&mut self, ident: &BindingIdentifier) {
+        if let Some(kind) = self.binding_kind {
+            self.declare(ident, kind);
+        }
+    }
+
+    fn visit_identifier_reference(&mut self, ident: &IdentifierReference) {
+        let scope = self.current();
+        self.tree.add_reference(ident, scope, false);
+    }
+
+    fn visit_simple_assignment_target(&mut self, target: &SimpleAssignmentTarget<'a>) {
+        match target {
+            SimpleAssignmentTarget::AssignmentTargetIdentifier(ident) => {
+                let scope = self.current();
+                self.tree.add_reference(ident, scope, true);
+            }
+            SimpleAssignmentTarget::MemberAssignmentTarget(expr) => self.visit_member_expression(expr),
+            SimpleAssignmentTarget::TSAsExpression(expr) => self.visit_expression(&expr.expression),
+            SimpleAssignmentTarget::TSSatisfiesExpression(expr) => self.visit_expression(&expr.expression),
+            SimpleAssignmentTarget::TSNonNullExpression(expr) => self.visit_expression(&expr.expression),
+            SimpleAssignmentTarget::TSTypeAssertion(expr) => self.visit_expression(&expr.expression),
+        }
+    }
+
+    fn visit_assignment_target_property_identifier(&mut self, ident: &AssignmentTargetPropertyIdentifier<'a>) {
+        let scope = self.current();
+        self.tree.add_reference(&ident.binding, scope, true);
+        if let Some(expr) = &ident.init {
+            self.visit_expression(expr);
+        }
+    }
+
+    fn visit_variable_declarator(&mut self, declarator: &VariableDeclarator<'a>) {
+        let kind = match declarator.kind {
+            VariableDeclarationKind::Var => BindingKind::Var,
+            VariableDeclarationKind::Let => BindingKind::Let,
+            VariableDeclarationKind::Const => BindingKind::Const,
+        };
+        self.with_binding_kind(kind, |builder| builder.visit_binding_pattern(&declarator.id));
+        if let Some(init) = &declarator.init {
+            self.visit_expression(init);
+        }
+    }
+
+    fn visit_function(&mut self, func: &Function<'a>, flags: Option<ScopeFlags>) {
+        // A declaration binds its name outside of the function, an expression only inside of it
+        let is_declaration = func.r#type == FunctionType::FunctionDeclaration;
+        if let (true, Some(id)) = (is_declaration, &func.id) {
+            self.declare(id, BindingKind::Function);
+        }
+
+        let kind = AstKind::Function(self.alloc(func));
+        self.enter_scope(flags.unwrap_or(ScopeFlags::empty()) | ScopeFlags::Function);
+        self.enter_node(kind);
+        if let (false, Some(id)) = (is_declaration, &func.id) {
+            self.declare(id, BindingKind::Function);
+        }
+        self.visit_formal_parameters(&func.params);
+        if let Some(body) = &func.body {
+            self.visit_function_body(body);
+        }
+        self.leave_node(kind);
+        self.leave_scope();
+    }
+
+    fn visit_formal_parameters(&mut self, params: &FormalParameters<'a>) {
+        self.with_binding_kind(BindingKind::Param, |builder| {
+            for param in &params.items {
+                builder.visit_binding_pattern(&param.pattern);
+            }
+            if let Some(rest) = &params.rest {
+                builder.visit_rest_element(rest);
+            }
+        });
+    }
+
+    fn visit_catch_clause(&mut self, clause: &CatchClause<'a>) {
+        let kind = AstKind::CatchClause(self.alloc(clause));
+        self.enter_scope(ScopeFlags::empty());
+        self.enter_node(kind);
+        if let Some(param) = &clause.param {
+            self.with_binding_kind(BindingKind::CatchParam, |builder| builder.visit_binding_pattern(param));
+        }
+        self.visit_statements(&clause.body.body);
+        self.leave_node(kind);
+        self.leave_scope();
+    }
+
+    fn visit_class(&mut self, class: &Class<'a>) {
+        for decorator in &class.decorators {
+            self.visit_decorator(decorator);
+        }
+
+        // Same as functions, but a class expression gets its own scope for its name
+        let is_expression = class.r#type == ClassType::ClassExpression;
+        let kind = AstKind::Class(self.alloc(class));
+        if is_expression {
+            self.enter_scope(ScopeFlags::empty());
+        }
+        self.enter_node(kind);
+        if let Some(id) = &class.id {
+            self.declare(id, BindingKind::Class);
+        }
+        if let Some(super_class) = &class.super_class {
+            self.visit_class_heritage(super_class);
+        }
+        self.visit_class_body(&class.body);
+        self.leave_node(kind);
+        if is_expression {
+            self.leave_scope();
+        }
+    }
+
+    fn visit_import_declaration(&mut self, decl: &ImportDeclaration<'a>) {
+        if let Some(specifiers) = &decl.specifiers {
+            self.with_binding_kind(BindingKind::Import, |builder| {
+                for specifier in specifiers {
+                    builder.visit_import_declaration_specifier(specifier);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_parser::Parser;
+use oxc_span::GetSpan;
+use oxc_span::SourceType;
+use oxc_span::Span;
+
+use crate::log;
+use crate::walker::{create_walker, Node};
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Generate a v3 source map (as JSON) for the generated `output` of `source`.
+///
+/// The `statement_spans` pair each statement's span in the input with its span in the output. See
+/// `pair_statement_spans`.
+pub fn generate_source_map(source: &str, source_name: &str, output: &str, statement_spans: &[(Span, Span)]) -> String {
+    let source_lines = LineIndex::new(source);
+    let output_lines = LineIndex::new(output);
+
+    let mut mappings: Vec<Mapping> = statement_spans.iter().map(|(original_span, output_span)| {
+        let (generated_line, generated_column) = output_lines.line_column(output, output_span.start);
+        let (original_line, original_column) = source_lines.line_column(source, original_span.start);
+        Mapping { generated_line, generated_column, original_line, original_column }
+    }).collect();
+
+    // Nested statements may start at the same position (`a: b: while (x) ...`). Keep the outer one.
+    mappings.sort_by_key(|mapping| (mapping.generated_line, mapping.generated_column));
+    mappings.dedup_by_key(|mapping| (mapping.generated_line, mapping.generated_column));
+
+    format!(
+        r#"{{"version":3,"sources":[{}],"sourcesContent":[{}],"names":[],"mappings":"{}"}}"#,
+        json_string(source_name),
+        json_string(source),
+        encode_mappings(&mappings)
+    )
+}
+
+/// Pair the span of each statement in the `transformed` AST with the span of that statement in the
+/// `output` that the codegen generated for it.
+///
+/// The codegen in oxc doesn't track positions so we get them another way. Every node in the transformed
+/// AST still carries the span of the input construct it was created for. So we parse the generated code
+/// again and walk both ASTs in lockstep. The Nth statement of one is the Nth statement of the other.
+///
+/// The first `injected_count` statements (in walk order) did not come from the input, like the helpers.
+/// They are skipped.
+pub fn pair_statement_spans(transformed: &Program, output: &str, source_type: SourceType, injected_count: usize) -> Vec<(Span, Span)> {
+    let original_spans = collect_statement_spans(transformed);
+
+    let allocator = Allocator::default();
+    let reparsed = Parser::new(&allocator, output, source_type).parse();
+    let output_spans = collect_statement_spans(&reparsed.program);
+
+    if !reparsed.errors.is_empty() || original_spans.len() != output_spans.len() {
+        // This means the codegen did not produce something that parses back to the same AST. That would be
+        // a bug somewhere but we'd rather have no mappings than wrong ones.
+        log!(Debug, "Source map: generated code does not match the transformed AST, skipping mappings ({} vs {} statements, {} errors)", original_spans.len(), output_spans.len(), reparsed.errors.len());
+        return vec!();
+    }
+
+    original_spans.into_iter().zip(output_spans).skip(injected_count).collect()
+}
+
+struct Mapping {
+    generated_line: u32,
+    generated_column: u32,
+    original_line: u32,
+    original_column: u32,
+}
+
+/// The number of statements in the program, counting nested ones
+pub fn count_statements(program: &Program) -> usize {
+    collect_statement_spans(program).len()
+}
+
+/// Collect the spans of all statements, in walk order.
+fn collect_statement_spans(program: &Program) -> Vec<Span> {
+    let spans: Rc<RefCell<Vec<Span>>> = Rc::new(RefCell::new(vec!()));
+    let spans_closure = Rc::clone(&spans);
+
+    let mut walker = create_walker();
+    walker.add_visitor_before(move |node| {
+        if let Node::Statement(stmt) = node {
+            spans_closure.borrow_mut().push(stmt.span());
+        }
+    });
+    walker.walk(program);
+
+    let spans = spans.borrow().clone();
+    spans
+}
+
+/// Zero based line and column lookup. Columns are in UTF-16 code units, as source map consumers expect.
+struct LineIndex {
+    line_starts: Vec<u32>,
+}
+
+impl LineIndex {
+    fn new(text: &str) -> Self {
+        let mut line_starts = vec!(0);
+        for (index, c) in text.char_indices() {
+            if c == '\n' {
+                line_starts.push(index as u32 + 1);
+            }
+        }
+        Self { line_starts }
+    }
+
+    fn line_column(&self, text: &str, offset: u32) -> (u32, u32) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let line_start = self.line_starts[line] as usize;
+        let offset = (offset as usize).min(text.len());
+        let column = text.get(line_start..offset).map_or(0, |prefix| prefix.encode_utf16().count());
+        (line as u32, column as u32)
+    }
+}
+
+fn encode_mappings(mappings: &[Mapping]) -> String {
+    let mut result = String::new();
+    let mut current_line = 0;
+    let mut prev_generated_column = 0;
+    let mut prev_original_line = 0;
+    let mut prev_original_column = 0;
+    let mut first_in_line = true;
+
+    for mapping in mappings {
+        while current_line < mapping.generated_line {
+            result.push(';');
+            current_line += 1;
+            prev_generated_column = 0;
+            first_in_line = true;
+        }
+        if !first_in_line {
+            result.push(',');
+        }
+        first_in_line = false;
+
+        // Fields: generated column, source index (always 0), original line, original column. All relative to the previous segment.
+        encode_vlq(&mut result, mapping.generated_column as i64 - prev_generated_column as i64);
+        encode_vlq(&mut result, 0);
+        encode_vlq(&mut result, mapping.original_line as i64 - prev_original_line as i64);
+        encode_vlq(&mut result, mapping.original_column as i64 - prev_original_column as i64);
+
+        prev_generated_column = mapping.generated_column;
+        prev_original_line = mapping.original_line;
+        prev_original_column = mapping.original_column;
+    }
+
+    result
+}
+
+fn encode_vlq(out: &mut String, value: i64) {
+    // The sign goes in the lowest bit, then groups of five bits with a continuation bit
+    let mut vlq = if value < 0 { ((-value) << 1) | 1 } else { value << 1 };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64_CHARS[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
@@ -0,0 +1,123 @@
+use oxc_span::Span;
+
+/// The rewrite rules. Each time a transform rewrites a construct it records one of these in the
+/// `MapperState` (see `record_rule`). The string form is part of the public output so don't change it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RuleId {
+    DoWhile,
+    ForLoop,
+    ForIn,
+    ForOf,
+    TryCatchFinally,
+    TryFinally,
+    Continue,
+    Switch,
+    VarDeclDeclarators,
+    VarDeclObjectPattern,
+    VarDeclArrayPattern,
+    WhileTrue,
+    BlockBody,
+    /// A rule of a transform from outside this crate (see `Transform`). The string is its id.
+    Custom(&'static str),
+}
+
+impl RuleId {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleId::DoWhile => "do-while",
+            RuleId::ForLoop => "for-loop",
+            RuleId::ForIn => "for-in",
+            RuleId::ForOf => "for-of",
+            RuleId::TryCatchFinally => "try-catch-finally",
+            RuleId::TryFinally => "try-finally",
+            RuleId::Continue => "continue",
+            RuleId::Switch => "switch",
+            RuleId::VarDeclDeclarators => "var-decl-declarators",
+            RuleId::VarDeclObjectPattern => "var-decl-object-pattern",
+            RuleId::VarDeclArrayPattern => "var-decl-array-pattern",
+            RuleId::WhileTrue => "while-true",
+            RuleId::BlockBody => "block-body",
+            RuleId::Custom(id) => id,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            RuleId::DoWhile => "Eliminate do-while in favor of regular while",
+            RuleId::ForLoop => "Eliminate regular for-loop in favor of regular while",
+            RuleId::ForIn => "Eliminate for-in loop in favor of regular while",
+            RuleId::ForOf => "Eliminate for-of loop in favor of regular while",
+            RuleId::TryCatchFinally => "Eliminate try/catch/finally in favor of a try/catch/try/catch statement",
+            RuleId::TryFinally => "Eliminate try/finally statement in favor of a try/catch statement",
+            RuleId::Continue => "Eliminate continue statement in favor of a break statement",
+            RuleId::Switch => "Eliminate switch statement in favor of if-else chain",
+            RuleId::VarDeclDeclarators => "Transform var decl with multiple declarators into multiple var decls with a single declarator",
+            RuleId::VarDeclObjectPattern => "Transform var decl object pattern to var decls without binding pattern",
+            RuleId::VarDeclArrayPattern => "Transform var decl array pattern to var decls without binding pattern",
+            RuleId::WhileTrue => "Move the test of a while loop into its body so every loop is a while (true)",
+            RuleId::BlockBody => "Wrap the sub-statement of an if, loop or with statement in a block",
+            RuleId::Custom(id) => id,
+        }
+    }
+
+    /// A (simplified) example of the input and output of this rule
+    pub fn example(&self) -> (&'static str, &'static str) {
+        match self {
+            RuleId::DoWhile => ("do { x }; while (y);", "let tmp = true; while (test) { { x; } test = y; }"),
+            RuleId::ForLoop => ("for (x; y; z) { body; }", "x; while (y) { { body; } z; }"),
+            RuleId::ForIn => (
+                "for (x in y) { body; }",
+                "let $tmp = $forIn(y); let $next; while ($next = $tmp.next()) { if ($next.done) break; x = $next.value; { body; } }",
+            ),
+            RuleId::ForOf => (
+                "for (x of y) { body; }",
+                "let $tmp = $forOf(y); let $next; while ($next = $tmp.next()) { if ($next.done) break; x = $next.value; { body; } }",
+            ),
+            RuleId::TryCatchFinally => (
+                "try { a(); } catch (e) { b(e); } finally { c(); }",
+                "let thrown = false; let val; let action = 0; try { a(); } catch (e) { try { b(e); } catch (e2) { thrown = true; val = e2; } } c(); if (thrown) throw val;",
+            ),
+            RuleId::TryFinally => (
+                "try { a(); } finally { c(); }",
+                "let thrown = false; let val; try { a(); } catch (e) { thrown = true; val = e; } c(); if (thrown) throw val;",
+            ),
+            RuleId::Continue => ("while (x) { if (y) continue; z; }", "while (x) again: { { if (y) break again; z; } }"),
+            RuleId::Switch => (
+                "switch (test) { case a: x; break; case b: y; default: z; }",
+                "let result = 3; if (test === a) { result = 1; } else if (test === b) { result = 2; } else { result = 3; } root: { if (result <= 0) { x; break root; } if (result <= 1) { y; } if (result <= 2) { z; } }",
+            ),
+            RuleId::VarDeclDeclarators => ("let x = a, y = b;", "let x = a; let y = b;"),
+            RuleId::VarDeclObjectPattern => ("let {x} = a;", "let x = a.x;"),
+            RuleId::VarDeclArrayPattern => ("let [x] = a;", "let x = a[0];"),
+            RuleId::WhileTrue => ("while (x) { y; }", "while (true) { if (!x) break; { y; } }"),
+            RuleId::BlockBody => ("if (x) y; else z;", "if (x) { y; } else { z; }"),
+            // We don't know what these do
+            RuleId::Custom(_) => ("", ""),
+        }
+    }
+}
+
+/// One application of a rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub rule: RuleId,
+    /// The span of the construct in the input that was rewritten
+    pub span: Span,
+    /// The span of the code that replaced it, in the output. Resolved after codegen (see `resolve_generated_spans`).
+    /// This can be `None` when the output could not be matched to the transformed AST.
+    pub generated_span: Option<Span>,
+}
+
+/// Fill in the `generated_span` of each entry.
+///
+/// `statement_spans` pairs the original span of each statement in the transformed AST with its span in
+/// the output (see `source_map::pair_statement_spans`). The generated code for a rule is everything that
+/// came from inside the construct it rewrote.
+pub fn resolve_generated_spans(trace: &mut [TraceEntry], statement_spans: &[(Span, Span)]) {
+    for entry in trace.iter_mut() {
+        entry.generated_span = statement_spans.iter()
+            .filter(|(original, _)| original.start < original.end && entry.span.start <= original.start && original.end <= entry.span.end)
+            .map(|(_, generated)| *generated)
+            .reduce(|a, b| Span::new(a.start.min(b.start), a.end.max(b.end)));
+    }
+}
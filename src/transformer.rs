@@ -0,0 +1,135 @@
+use oxc_allocator::Allocator;
+use oxc_ast::ast::Program;
+use oxc_span::Span;
+
+use crate::diagnostics::Diagnostic;
+use crate::mapper::Mapper;
+use crate::options::TransformOptions;
+use crate::passes::PassManager;
+use crate::source_map::generate_source_map;
+use crate::trace::TraceEntry;
+use crate::validator::Construct;
+use crate::TransformResult;
+
+/// A desugaring step, built-in (see `passes::Pass`) or from elsewhere. It adds visitors to a mapper,
+/// which then runs over the program. Several transforms can share that traversal (see
+/// `Transformer::add_to_last_traversal`).
+///
+/// The visitors reach the state through `mapper.state`. Anything a transform needs to keep track of
+/// goes in there as an extension (see `MapperState::extension_mut`). Use `RuleId::Custom` to put a
+/// rewrite in the trace.
+pub trait Transform {
+    /// Shows up in diagnostics and the log
+    fn name(&self) -> &str;
+
+    /// What must be gone by the time this transform runs. See `passes::check_precondition`.
+    fn requires_absent(&self) -> &[Construct] {
+        &[]
+    }
+
+    /// What this transform may add to the code
+    fn introduces(&self) -> &[Construct] {
+        &[]
+    }
+
+    /// What this transform eliminates
+    fn removes(&self) -> &[Construct] {
+        &[]
+    }
+
+    /// Add the visitors of this transform to the mapper
+    fn install<'a>(&self, mapper: &mut Mapper<'a>);
+}
+
+/// The options and the transforms to run. Starts with the built-in transforms that the options enable.
+/// Add your own with `add` or `add_to_last_traversal`.
+pub struct Transformer {
+    options: TransformOptions,
+    passes: PassManager,
+}
+
+impl Transformer {
+    /// The built-in transforms that are enabled in these options (after `resolve()`)
+    pub fn new(options: &TransformOptions) -> Self {
+        let options = options.resolve();
+        Self { passes: PassManager::from_options(&options), options }
+    }
+
+    /// No transforms at all, only the other options apply. The built-in ones are in `passes`.
+    pub fn empty(options: &TransformOptions) -> Self {
+        Self { options: options.resolve(), passes: PassManager::new() }
+    }
+
+    pub fn options(&self) -> &TransformOptions {
+        &self.options
+    }
+
+    pub fn passes(&self) -> &PassManager {
+        &self.passes
+    }
+
+    /// Run this transform in a traversal of its own, after the transforms added so far
+    pub fn add(&mut self, transform: impl Transform + 'static) {
+        self.passes.add(transform);
+    }
+
+    /// Run this transform in the same traversal as the last one added. With the default options that
+    /// is the switch pass.
+    pub fn add_to_last_traversal(&mut self, transform: impl Transform + 'static) {
+        self.passes.add_to_last_traversal(transform);
+    }
+
+    /// See `PassManager::set_max_rounds`
+    pub fn set_max_rounds(&mut self, max_rounds: usize) {
+        self.passes.set_max_rounds(max_rounds);
+    }
+
+    /// Transform this code. The `source_name` is what the source map will refer to. Like the wasm
+    /// entry points, this returns an internal error rather than panic.
+    pub fn transform_source(&self, source: &str, source_name: &str) -> TransformResult {
+        crate::run_transformer(self, source, source_name)
+    }
+
+    /// Parse this code (with the source type in the options) and transform it. The program lives in
+    /// the allocator, which the caller owns, so nothing outlives it. After a parse error the
+    /// diagnostics hold the errors, the program is what the parser made of it and there is no code.
+    pub fn transform_str<'a>(&self, allocator: &'a Allocator, source: &'a str) -> TransformOutput<'a> {
+        let span = Span::new(0, source.len() as u32);
+        crate::catch_internal_error(allocator, self.options.source_type(), span, || crate::parse_and_map(self, allocator, source))
+    }
+
+    /// Transform a program that was parsed elsewhere, in this allocator. The spans in the
+    /// diagnostics and the trace point into the source it was parsed from. Like `transform_source`
+    /// this returns an internal error rather than panic, the program is empty in that case.
+    pub fn transform_program<'a>(&self, allocator: &'a Allocator, program: Program<'a>) -> TransformOutput<'a> {
+        let (source_type, span) = (program.source_type, program.span);
+        crate::catch_internal_error(allocator, source_type, span, || crate::map_program(self, allocator, program))
+    }
+}
+
+/// What `Transformer::transform_str` and `Transformer::transform_program` return
+pub struct TransformOutput<'a> {
+    pub program: Program<'a>,
+    /// The generated code, with the runtime helpers. Empty when the input did not parse.
+    pub code: String,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The rules that were applied, in the order they were applied
+    pub trace: Vec<TraceEntry>,
+    // The original span of each statement in the output with its span in `code`. None without output.
+    statement_spans: Option<Vec<(Span, Span)>>,
+}
+
+impl<'a> TransformOutput<'a> {
+    pub fn new(program: Program<'a>, code: String, diagnostics: Vec<Diagnostic>, trace: Vec<TraceEntry>, statement_spans: Option<Vec<(Span, Span)>>) -> Self {
+        Self { program, code, diagnostics, trace, statement_spans }
+    }
+
+    /// A v3 source map (JSON) from `code` back to the source the program was parsed from. The
+    /// `source_name` is what it refers to. Empty when there is no output.
+    pub fn source_map(&self, source: &str, source_name: &str) -> String {
+        match &self.statement_spans {
+            Some(statement_spans) => generate_source_map(source, source_name, &self.code, statement_spans),
+            None => "".to_string(),
+        }
+    }
+}
@@ -128,8 +128,8 @@ pub fn create_binary_expression<'alloc>(
     })))
 }
 
-pub fn create_binding_identifier<'alloc>(
-    _allocator: &'alloc Allocator,
+pub fn create_binding_identifier(
+    _allocator: &Allocator,
     name: String,
     span: Span
 ) -> BindingIdentifier {
@@ -180,6 +180,17 @@ pub fn create_break_statement<'alloc>(
     })))
 }
 
+pub fn create_continue_statement<'alloc>(
+    allocator: &'alloc Allocator,
+    label: Option<LabelIdentifier>,
+    span: Span
+) -> Statement<'alloc> {
+    Statement::ContinueStatement(OxcBox(allocator.alloc(ContinueStatement {
+        label,
+        span,
+    })))
+}
+
 pub fn create_catch_clause<'alloc>(
     allocator: &'alloc Allocator,
     param: Option<BindingPattern<'alloc>>,
@@ -205,7 +216,7 @@ pub fn create_call_expression<'alloc>(
         OxcBox(allocator.alloc(CallExpression {
             callee,
             arguments: OxcVec::from_iter_in(
-                arguments.into_iter().map(|expr| Argument::Expression(expr)),
+                arguments.into_iter().map(Argument::Expression),
                 allocator
             ),
             optional,
@@ -215,6 +226,30 @@ pub fn create_call_expression<'alloc>(
     )
 }
 
+/// `export { a, b };`
+pub fn create_export_named_statement<'alloc>(
+    allocator: &'alloc Allocator,
+    names: Vec<String>,
+    span: Span
+) -> Statement<'alloc> {
+    let specifiers = names.into_iter().map(|name| ExportSpecifier {
+        local: ModuleExportName::Identifier(IdentifierName { name: Atom::from(name.clone()), span }),
+        exported: ModuleExportName::Identifier(IdentifierName { name: Atom::from(name), span }),
+        export_kind: ImportOrExportKind::Value,
+        span,
+    });
+
+    Statement::ModuleDeclaration(
+        OxcBox(allocator.alloc(ModuleDeclaration::ExportNamedDeclaration(OxcBox(allocator.alloc(ExportNamedDeclaration {
+            span,
+            declaration: None,
+            specifiers: OxcVec::from_iter_in(specifiers, allocator),
+            source: None,
+            export_kind: ImportOrExportKind::Value,
+        })))))
+    )
+}
+
 pub fn create_expression_statement<'alloc>(
     allocator: &'alloc Allocator,
     expression: Expression<'alloc>,
@@ -243,7 +278,7 @@ pub fn create_identifier_expression<'alloc>(
     )
 }
 
-pub fn create_identifier_reference<'alloc>(
+pub fn create_identifier_reference(
     name: String,
     span: Span
 ) -> IdentifierReference {
@@ -345,10 +380,10 @@ pub fn create_number_literal<'alloc>(
 pub fn create_number_literal_str<'alloc>(
     allocator: &'alloc Allocator,
     value: f64,
-    value_str: &'alloc String,
+    value_str: &'alloc str,
     span: Span
 ) -> Expression<'alloc> {
-    create_number_literal(allocator, value, value_str.as_str(), span)
+    create_number_literal(allocator, value, value_str, span)
 }
 
 pub fn create_return_statement<'alloc>(
@@ -498,7 +533,8 @@ pub fn create_variable_declaration_kind<'alloc>(
     init: Option<Expression<'alloc>>,
     span: Span
 ) -> Statement<'alloc> {
-    let declr = create_variable_declarator(allocator, name, init, span);
+    let mut declr = create_variable_declarator(allocator, name, init, span);
+    declr.kind = kind;
     let decl = VariableDeclaration {
         kind,
         declarations: OxcVec::from_iter_in([declr], allocator),
@@ -514,9 +550,11 @@ pub fn create_variable_declaration_kind<'alloc>(
 pub fn create_variable_declaration_kind_declr<'alloc>(
     allocator: &'alloc Allocator,
     kind: VariableDeclarationKind,
-    declr: VariableDeclarator<'alloc>,
+    mut declr: VariableDeclarator<'alloc>,
     span: Span
 ) -> Statement<'alloc> {
+    // The declarator repeats the kind. Transforms that split up declarations take it from there.
+    declr.kind = kind;
     let decl = VariableDeclaration {
         kind,
         declarations: OxcVec::from_iter_in([declr], allocator),
@@ -545,49 +583,56 @@ fn convert_binding_identifier_to_assignment_target<'alloc>(
 ///      ^^^           ^^^
 /// `let {x = def} = y` -> `({x = def} = y);`
 ///      ^^^^^^^^^           ^^^^^^^^^
+/// A shorthand always has an identifier key and an identifier value, with or without default. Anything
+/// else can only come from a hand-built AST and becomes a regular property, which means the same.
 fn convert_binding_pattern_obj_shorthand_to_assignment_target<'alloc>(
     allocator: &'alloc Allocator,
     key: PropertyKey<'alloc>,
     value: BindingPattern<'alloc>,
+    span: Span,
 ) -> AssignmentTargetProperty<'alloc> {
+    let is_shorthand = match (&key, &value.kind) {
+        (PropertyKey::Identifier(_), BindingPatternKind::BindingIdentifier(_)) => true,
+        (PropertyKey::Identifier(_), BindingPatternKind::AssignmentPattern(assign)) => matches!(assign.left.kind, BindingPatternKind::BindingIdentifier(_)),
+        _ => false,
+    };
+    if !is_shorthand {
+        return convert_binding_property_to_assignment_target(allocator, key, value, span);
+    }
     let key_span = key.span();
-    let name = match key {
-        PropertyKey::Identifier(ident) => ident.name.clone(),
-        _ => panic!("Expected identifier key in shorthand"),
+    let PropertyKey::Identifier(ident) = key else { unreachable!("checked above") };
+    let name = ident.name.to_string();
+    // `let {x} = y` -> `({x} = y);`
+    //      ^^^           ^^^
+    // `let {x = def} = y` -> `({x = def} = y);`
+    //      ^^^^^^^^^           ^^^^^^^^^
+    let init = match value.kind {
+        BindingPatternKind::AssignmentPattern(assign) => Some(assign.unbox().right),
+        _ => None,
     };
-    // We must still check the value to determine the difference between `let {x} = y` and `let {x = def} = y`
-    match &value.kind {
-        BindingPatternKind::BindingIdentifier(_) => {
-            // `let {x} = y` -> `({x} = y);`
-            //      ^^^           ^^^
-            AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(OxcBox(allocator.alloc(
-                AssignmentTargetPropertyIdentifier {
-                    init: None,
-                    span: key_span,
-                    binding: create_identifier_reference(name.to_string(), key_span),
-                }
-            )))
-        }
-        BindingPatternKind::AssignmentPattern(_) => {
-            // `let {x = def} = y` -> `({x = def} = y);`
-            //      ^^^^^^^^^           ^^^^^^^^^
-            AssignmentTargetProperty::AssignmentTargetPropertyProperty(OxcBox(allocator.alloc(AssignmentTargetPropertyProperty {
-                name: PropertyKey::Identifier(OxcBox(allocator.alloc(IdentifierName {
-                    name: Atom::from(name),
-                    span: key_span,
-                }))),
-                binding: AssignmentTargetMaybeDefault::AssignmentTarget(convert_binding_pattern_to_assignment_target(allocator, value)),
-                span: key_span,
-            })))
+    AssignmentTargetProperty::AssignmentTargetPropertyIdentifier(OxcBox(allocator.alloc(
+        AssignmentTargetPropertyIdentifier {
+            init,
+            span: key_span,
+            binding: create_identifier_reference(name, key_span),
         }
-        BindingPatternKind::ObjectPattern(_) => {
-            // I don't think this is possible
-            todo!("I dont think you can have an object pattern value in a shorthand pattern...");
-        }
-        BindingPatternKind::ArrayPattern(_) => {
-            todo!("I dont think you can have an array pattern value in a shorthand pattern...");
-        }
-    }
+    )))
+}
+
+/// `let {a: x = def} = y` -> `({a: x = def} = y);`
+///      ^^^^^^^^^^             ^^^^^^^^^^
+fn convert_binding_property_to_assignment_target<'alloc>(
+    allocator: &'alloc Allocator,
+    key: PropertyKey<'alloc>,
+    value: BindingPattern<'alloc>,
+    span: Span,
+) -> AssignmentTargetProperty<'alloc> {
+    // key can be any form; ident, computed, private but we can move that node
+    AssignmentTargetProperty::AssignmentTargetPropertyProperty(OxcBox(allocator.alloc(AssignmentTargetPropertyProperty {
+        name: key,
+        binding: convert_binding_pattern_to_assignment_target_maybe_default(allocator, value),
+        span,
+    })))
 }
 
 /// `let {a: x} = y` -> `({a: x} = y);
@@ -604,23 +649,15 @@ fn convert_binding_pattern_obj_to_assignment_target<'alloc>(
             if shorthand {
                 // `let {x} = y` -> `({x} = y);`
                 // `let {x = def} = y` -> `({x = def} = y);`
-                convert_binding_pattern_obj_shorthand_to_assignment_target(allocator, key, value)
+                convert_binding_pattern_obj_shorthand_to_assignment_target(allocator, key, value, span)
             } else {
-                // key can be any form; ident, computed, private but we can move that node
-                AssignmentTargetProperty::AssignmentTargetPropertyProperty(OxcBox(allocator.alloc(AssignmentTargetPropertyProperty {
-                    name: key,
-                    binding: AssignmentTargetMaybeDefault::AssignmentTarget(convert_binding_pattern_to_assignment_target(allocator, value)),
-                    span,
-                })))
+                convert_binding_property_to_assignment_target(allocator, key, value, span)
             }
         }),
         allocator
     );
 
-    let rest = match pattern.rest {
-        Some(rest) => Some(convert_binding_pattern_to_assignment_target(allocator, rest.unbox().argument)),
-        None => None,
-    };
+    let rest = pattern.rest.map(|rest| convert_binding_pattern_to_assignment_target(allocator, rest.unbox().argument));
 
     AssignmentTarget::AssignmentTargetPattern(
         AssignmentTargetPattern::ObjectAssignmentTarget(
@@ -648,16 +685,13 @@ fn convert_binding_pattern_arr_to_assignment_target<'alloc>(
                 None => return None, // elided elements
             };
 
-            // key can be any form; ident, computed, private but we can move that node
-            Some(AssignmentTargetMaybeDefault::AssignmentTarget(convert_binding_pattern_to_assignment_target(allocator, elem)))
+            // `let [x = def] = y` -> `([x = def] = y);`
+            Some(convert_binding_pattern_to_assignment_target_maybe_default(allocator, elem))
         }),
         allocator
     );
 
-    let rest = match rest {
-        Some(rest) => Some(convert_binding_pattern_to_assignment_target(allocator, rest.unbox().argument)),
-        None => None,
-    };
+    let rest = rest.map(|rest| convert_binding_pattern_to_assignment_target(allocator, rest.unbox().argument));
 
     AssignmentTarget::AssignmentTargetPattern(
         AssignmentTargetPattern::ArrayAssignmentTarget(
@@ -690,11 +724,35 @@ fn convert_binding_pattern_to_assignment_target<'alloc>(
         BindingPatternKind::ArrayPattern(arr) => {
             convert_binding_pattern_arr_to_assignment_target(allocator, arr.unbox())
         },
-        // This is only the default part of a pattern (`let [x = def] = y`)
-        // Unfortunately, Oxc chose to use this enum for binding patterns but AssignmentTargetMaybeDefault::AssignmentTargetWithDefault
-        // for assignment patterns (there may be a reason but it's not obvious to me)
-        BindingPatternKind::AssignmentPattern(_assign) => {
-            todo!("zucht");
+        // This is only the default part of a pattern (`let [x = def] = y`). Elements and property values
+        // go through `convert_binding_pattern_to_assignment_target_maybe_default` so this can only be the
+        // argument of a rest (`let [...x = def] = y`). That's a syntax error which the parser lets through,
+        // an assignment target can't express it so the default is dropped.
+        BindingPatternKind::AssignmentPattern(assign) => {
+            convert_binding_pattern_to_assignment_target(allocator, assign.unbox().left)
         },
     }
 }
+
+/// Like `convert_binding_pattern_to_assignment_target` but for a spot that can have a default, like an
+/// element or a property value. Unfortunately, Oxc chose to use `BindingPatternKind::AssignmentPattern`
+/// for binding patterns but `AssignmentTargetMaybeDefault::AssignmentTargetWithDefault` for assignment
+/// patterns (there may be a reason but it's not obvious to me)
+/// `let [x = def] = y` -> `([x = def] = y);`
+///       ^^^^^^^            ^^^^^^^
+fn convert_binding_pattern_to_assignment_target_maybe_default<'alloc>(
+    allocator: &'alloc Allocator,
+    pattern: BindingPattern<'alloc>,
+) -> AssignmentTargetMaybeDefault<'alloc> {
+    match pattern.kind {
+        BindingPatternKind::AssignmentPattern(assign) => {
+            let AssignmentPattern { left, right, span } = assign.unbox();
+            AssignmentTargetMaybeDefault::AssignmentTargetWithDefault(OxcBox(allocator.alloc(AssignmentTargetWithDefault {
+                binding: convert_binding_pattern_to_assignment_target(allocator, left),
+                init: right,
+                span,
+            })))
+        }
+        kind => AssignmentTargetMaybeDefault::AssignmentTarget(convert_binding_pattern_to_assignment_target(allocator, BindingPattern { kind, ..pattern })),
+    }
+}
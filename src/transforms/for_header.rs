@@ -15,8 +15,6 @@
 //
 // This is useful for other transforms like `stmt_for_in` and `stmt_for_of` which assume a simple lhs.
 
-use oxc_allocator::Vec as OxcVec;
-use oxc_allocator::Box as OxcBox;
 use oxc_ast::ast::*;
 use oxc_allocator::Allocator;
 use oxc_span::Span;
@@ -25,43 +23,44 @@ use oxc_syntax::operator::AssignmentOperator;
 use crate::mapper_state::MapperState;
 use crate::transforms::builder::*;
 
-// Transform the for-in and for-of lhs in the header to make sure it's simple
+/// The lhs of a for-in or for-of header after `transform_for_header`
+pub enum SimpleForLeft {
+    /// ie: `for (let x in obj)`, with the span of the declaration
+    Declaration(VariableDeclarationKind, String, Span),
+    /// ie: `for (x in obj)`
+    Identifier(IdentifierReference),
+}
+
+// Transform the for-in and for-of lhs in the header to make sure it's simple.
+// Returns the lhs as is when it's something we can't simplify, nothing was changed in that case.
 pub fn transform_for_header<'a>(
     left: ForStatementLeft<'a>,
     allocator: &'a Allocator,
     state: &mut MapperState,
     span: Span,
-) -> (ForStatementLeft<'a>, Option<Statement<'a>>) {
+) -> Result<(SimpleForLeft, Option<Statement<'a>>), ForStatementLeft<'a>> {
     match left {
         ForStatementLeft::VariableDeclaration(decl) => {
-            let VariableDeclaration { declarations, span: decl_span, kind, modifiers } = decl.unbox();
+            // Should only have one declaration, without init. The init is only allowed in sloppy
+            // scripts (`for (var a = 1 in b)`) and we don't support that.
+            if decl.declarations.len() != 1 || decl.declarations[0].init.is_some() {
+                return Err(ForStatementLeft::VariableDeclaration(decl));
+            }
 
-            // Should only have one declaration
-            let decl = declarations.into_iter().next().unwrap();
-            let VariableDeclarator { id, init, definite: _, span: var_span, kind: _ } = decl;
+            let VariableDeclaration { mut declarations, span: decl_span, kind, modifiers: _ } = decl.unbox();
+            let VariableDeclarator { id, init: _, definite: _, span: var_span, kind: _ } = declarations.remove(0);
 
             match id.kind {
+                BindingPatternKind::BindingIdentifier(id) => {
+                    // ie: `for (let x in obj)`, nothing to do
+                    Ok((SimpleForLeft::Declaration(kind, id.name.to_string(), decl_span), None))
+                },
                 BindingPatternKind::ArrayPattern(_) |
                 BindingPatternKind::ObjectPattern(_) |
                 BindingPatternKind::AssignmentPattern(_) => {
                     // Create temporary variable
-                    let tmp_name = state.next_ident_name();
-
-                    // Create new variable declaration with temporary variable
                     // ie: `for (let [x] in obj) { ... }` becomes `for (let $zeroSugar0 in obj)`
-                    let new_left = ForStatementLeft::VariableDeclaration(OxcBox(allocator.alloc(VariableDeclaration {
-                        declarations: OxcVec::from_iter_in([
-                            create_variable_declarator(
-                                allocator,
-                                tmp_name.clone(),
-                                init,
-                                var_span
-                            )
-                        ], allocator),
-                        span: decl_span,
-                        kind,
-                        modifiers
-                    })));
+                    let tmp_name = state.next_var_name(span);
 
                     // Create pattern assignment statement
                     // ie: `for (let [x] in obj)` becomes `let [x] = $zeroSugar0;`
@@ -69,105 +68,59 @@ pub fn transform_for_header<'a>(
                         allocator,
                         kind,
                         tmp_name.clone(),
-                        Some(create_identifier_expression(allocator, tmp_name, var_span)),
+                        Some(create_identifier_expression(allocator, tmp_name.clone(), var_span)),
                         span
                     );
 
-                    (new_left, Some(pattern_stmt))
+                    Ok((SimpleForLeft::Declaration(kind, tmp_name, decl_span), Some(pattern_stmt)))
                 },
-                _ => {
-                    (
-                        ForStatementLeft::VariableDeclaration(OxcBox(allocator.alloc(VariableDeclaration {
-                            declarations: OxcVec::from_iter_in([VariableDeclarator {
-                                id,
-                                init,
-                                definite: false,
-                                span: var_span,
-                                kind,
-                            }], allocator),
-                            span: decl_span,
-                            kind,
-                            modifiers
-                        }))),
-                        None
-                    )
-                }
             }
         },
-        ForStatementLeft::AssignmentTarget(target) => {
-            match target {
-                AssignmentTarget::AssignmentTargetPattern(_) => {
-                    // ie: `for ([x] in obj)` becomes `for (let $zeroSugar0 in obj) { [x] = $zeroSugar0; }`
-
-                    // Create temporary variable
-                    let tmp_name = state.next_ident_name();
-
-                    // Create new assignment target with temporary variable
-                    let new_left = ForStatementLeft::AssignmentTarget(
-                        AssignmentTarget::SimpleAssignmentTarget(
-                            SimpleAssignmentTarget::AssignmentTargetIdentifier(
-                                OxcBox(allocator.alloc(create_identifier_reference(
-                                    tmp_name.clone(),
-                                    span
-                                )))
-                            )
-                        )
-                    );
-
-                    // Create pattern assignment statement
-                    let pattern_stmt = create_expression_statement(
-                        allocator,
-                        create_assignment_expression_name(
-                            allocator,
-                            tmp_name.clone(),
-                            create_identifier_expression(allocator, tmp_name, span),
-                            span
-                        ),
-                        span
-                    );
-
-                    (new_left, Some(pattern_stmt))
-                },
-                AssignmentTarget::SimpleAssignmentTarget(SimpleAssignmentTarget::MemberAssignmentTarget(me)) => {
-                    // ie: `for (a.x in b) x`
-
-                    // Create temporary variable
-                    let tmp_name = state.next_ident_name();
-
-                    // Create new assignment target with temporary variable
-                    let new_left = ForStatementLeft::AssignmentTarget(
-                        AssignmentTarget::SimpleAssignmentTarget(
-                            SimpleAssignmentTarget::AssignmentTargetIdentifier(
-                                OxcBox(allocator.alloc(create_identifier_reference(
-                                    tmp_name.clone(),
-                                    span
-                                )))
-                            )
-                        )
-                    );
-
-                    let pattern_stmt = create_expression_statement(
-                        allocator,
-                        create_assignment_expression_member(
-                            allocator,
-                            AssignmentOperator::Assign,
-                            me.unbox(),
-                            create_identifier_expression(allocator, tmp_name, span),
-                            span
-                        ),
-                        span
-                    );
-
-                    (new_left, Some(pattern_stmt))
-                },
-                _ => {
-                    (ForStatementLeft::AssignmentTarget(target), None)
-                }
-            }
+        ForStatementLeft::AssignmentTarget(AssignmentTarget::SimpleAssignmentTarget(SimpleAssignmentTarget::AssignmentTargetIdentifier(id))) => {
+            // ie: `for (x in obj)`, nothing to do
+            Ok((SimpleForLeft::Identifier(id.unbox()), None))
+        },
+        ForStatementLeft::AssignmentTarget(AssignmentTarget::AssignmentTargetPattern(_)) => {
+            // ie: `for ([x] in obj)` becomes `for ($zeroSugar0 in obj) { [x] = $zeroSugar0; }`
+
+            // Create temporary variable
+            let tmp_name = state.next_var_name(span);
+
+            // Create pattern assignment statement
+            let pattern_stmt = create_expression_statement(
+                allocator,
+                create_assignment_expression_name(
+                    allocator,
+                    tmp_name.clone(),
+                    create_identifier_expression(allocator, tmp_name.clone(), span),
+                    span
+                ),
+                span
+            );
+
+            Ok((SimpleForLeft::Identifier(create_identifier_reference(tmp_name, span)), Some(pattern_stmt)))
         },
-        ForStatementLeft::UsingDeclaration(_) => {
-            panic!("UsingDeclaration is not supported by this tool");
+        ForStatementLeft::AssignmentTarget(AssignmentTarget::SimpleAssignmentTarget(SimpleAssignmentTarget::MemberAssignmentTarget(me))) => {
+            // ie: `for (a.x in b) x` becomes `for ($zeroSugar0 in b) { a.x = $zeroSugar0; x }`
+
+            // Create temporary variable
+            let tmp_name = state.next_var_name(span);
+
+            let pattern_stmt = create_expression_statement(
+                allocator,
+                create_assignment_expression_member(
+                    allocator,
+                    AssignmentOperator::Assign,
+                    me.unbox(),
+                    create_identifier_expression(allocator, tmp_name.clone(), span),
+                    span
+                ),
+                span
+            );
+
+            Ok((SimpleForLeft::Identifier(create_identifier_reference(tmp_name, span)), Some(pattern_stmt)))
         },
+        // TS assertions (`for (a! in b)`) and `using` declarations
+        _ => Err(left),
     }
 }
-
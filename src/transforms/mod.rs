@@ -8,4 +8,6 @@ pub mod stmt_finally;
 pub mod stmt_continue;
 pub mod stmt_var_decl;
 pub mod stmt_switch;
+pub mod stmt_while;
+pub mod stmt_block_body;
 
@@ -0,0 +1,78 @@
+use oxc_allocator::Vec as OxcVec;
+use oxc_allocator::Box as OxcBox;
+use oxc_ast::ast::*;
+use oxc_allocator::Allocator;
+use oxc_span::GetSpan;
+
+use crate::mapper::MapperAction;
+use crate::mapper_state::MapperState;
+use crate::trace::RuleId;
+
+use super::builder::create_block_statement;
+use super::builder::create_if_statement;
+use super::builder::create_while_statement;
+
+pub fn transform_block_bodies<'a>(
+    stmt: Statement<'a>,
+    allocator: &'a Allocator,
+    state: &mut MapperState
+) -> (MapperAction, Statement<'a>) {
+    // `if (x) y; else z;` -> `if (x) { y; } else { z; }`, same for the loops and `with`.
+    //
+    // An `else if` is left as is, that's how an if-else chain is written. The body of a labeled
+    // statement is left as is too, a `continue` can't target a label on a block.
+
+    let stmt = match stmt {
+        Statement::IfStatement(if_stmt) => {
+            let IfStatement { test, consequent, alternate, span } = if_stmt.unbox();
+            let alternate = match alternate {
+                Some(alternate @ Statement::IfStatement(_)) => Some(alternate),
+                Some(alternate) => Some(wrap_in_block(alternate, allocator, state)),
+                None => None,
+            };
+            create_if_statement(allocator, test, wrap_in_block(consequent, allocator, state), alternate, span)
+        }
+        Statement::WhileStatement(while_stmt) => {
+            let WhileStatement { test, body, span } = while_stmt.unbox();
+            create_while_statement(allocator, test, wrap_in_block(body, allocator, state), span)
+        }
+        Statement::DoWhileStatement(do_while) => {
+            let DoWhileStatement { body, test, span } = do_while.unbox();
+            let body = wrap_in_block(body, allocator, state);
+            Statement::DoWhileStatement(OxcBox(allocator.alloc(DoWhileStatement { body, test, span })))
+        }
+        Statement::ForStatement(for_stmt) => {
+            let ForStatement { init, test, update, body, span } = for_stmt.unbox();
+            let body = wrap_in_block(body, allocator, state);
+            Statement::ForStatement(OxcBox(allocator.alloc(ForStatement { init, test, update, body, span })))
+        }
+        Statement::ForInStatement(for_in) => {
+            let ForInStatement { left, right, body, span } = for_in.unbox();
+            let body = wrap_in_block(body, allocator, state);
+            Statement::ForInStatement(OxcBox(allocator.alloc(ForInStatement { left, right, body, span })))
+        }
+        Statement::ForOfStatement(for_of) => {
+            let ForOfStatement { r#await, left, right, body, span } = for_of.unbox();
+            let body = wrap_in_block(body, allocator, state);
+            Statement::ForOfStatement(OxcBox(allocator.alloc(ForOfStatement { r#await, left, right, body, span })))
+        }
+        Statement::WithStatement(with_stmt) => {
+            let WithStatement { object, body, span } = with_stmt.unbox();
+            let body = wrap_in_block(body, allocator, state);
+            Statement::WithStatement(OxcBox(allocator.alloc(WithStatement { object, body, span })))
+        }
+        other => other,
+    };
+
+    (MapperAction::Normal, stmt)
+}
+
+fn wrap_in_block<'a>(body: Statement<'a>, allocator: &'a Allocator, state: &mut MapperState) -> Statement<'a> {
+    if let Statement::BlockStatement(_) = body {
+        return body;
+    }
+
+    let span = body.span();
+    state.record_rule(RuleId::BlockBody, span);
+    create_block_statement(allocator, OxcVec::from_iter_in([body], allocator), span)
+}
@@ -1,15 +1,16 @@
-use std::cell::RefMut;
 
 use oxc_allocator::Box as OxcBox;
 use oxc_ast::ast::*;
 use oxc_allocator::Allocator;
 use oxc_span::Atom;
 
+use crate::diagnostics::DiagnosticCode;
 use crate::log;
 use crate::mapper::MapperAction;
+use crate::mapper_context::{AncestorKind, MapperContext};
 use crate::mapper_state::MapperState;
 use crate::transforms::builder::create_labeled_stmt;
-use crate::utils::{example, rule};
+use crate::trace::RuleId;
 
 /// Transform a continue statement into a labeled block with a break.
 /// There are two cases to deal with: labeled and unlabeled continue.
@@ -68,14 +69,11 @@ use crate::utils::{example, rule};
 /// }
 /// ```
 ///
-/// The tricky part is tracking loops and labels and wrapping loop bodies in a label when necessary.
-/// For this purpose we've added a `MapperState` field `loop_stack` which is a stack of loop headers.
-///
-/// The stack is either a label or a loop marker combined with an optional string. The optional string
-/// is populated once a continue is found to target this loop. If the continue targets a label then it
-/// should populate the child loop instead. This way, if multiple continues target the same label, it
-/// only generates one label wrapping. This is also how the toplevel handler knows to wrap the body
-/// and what label to use when doing so.
+/// The tricky part is finding the loop that a continue targets and wrapping its body in a label. The
+/// mapper context has the loops and labels around the continue. When the first continue to a loop is
+/// found a label is generated for it, see `ContinueLabels`, so other continues to the same loop can
+/// use the same label. When the mapper leaves that loop `apply_continue_transform_updates` wraps its
+/// body in that label.
 ///
 /// So here we find the target loop, use the generated label or generate a new one, and transform
 /// the continue into a break to that label.
@@ -84,42 +82,31 @@ pub fn transform_continue_statement<'a>(
     allocator: &'a Allocator,
     state: &mut MapperState
 ) -> (MapperAction, Statement<'a>) {
-    rule("Eliminate continue statement in favor of a break statement");
-    example("while (x) { if (y) continue; z; }", "while (x) again: { { if (y) break again; z; } }");
+    // `while (x) { if (y) continue; z; }` -> `while (x) again: { { if (y) break again; z; } }`
 
-    log!("transform_continue_statement");
+    log!(Debug, "transform_continue_statement");
     let ContinueStatement { label: target_label, span } = continue_stmt;
-    let target_label = match target_label {
-        Some(LabelIdentifier { name, span: _ }) => name.to_string(),
-        None => "#loop".to_string(),
-    };
 
-    // Find the nearest target loop from the top. Remember the index because if it's a label we'll need to
-    // replace it with the child loop instead, which is the next index.
-    let target_loop = state.continue_targets.iter().enumerate().rev().find(|(_i, (label, _generated))| *label == target_label);
-    let (i, generated) = if let Some((i, (_, generated))) = target_loop {
-        (i, generated)
-    } else {
-        panic!("Syntactically each continue should have a target label or loop so this should never happen. Searching for target label: {} in stack: {:?}", target_label, state.continue_targets);
+    let Some(depth) = target_loop_depth(&state.context, target_label.as_ref().map(|label| label.name.as_str())) else {
+        // Syntactically each continue should have a target label or loop so this should never happen.
+        let message = format!("Could not find the loop targeted by this continue statement. Searching for target label: {:?} in ancestors: {:?}", target_label, state.context.ancestors());
+        state.report(DiagnosticCode::UnexpectedNode, message, span);
+        return (MapperAction::Normal, Statement::ContinueStatement(OxcBox(allocator.alloc(ContinueStatement { label: target_label, span }))));
     };
 
-    let generated =
-        if let Some(generated) = generated {
-            generated.clone()
-        } else {
-            let generated = state.next_ident_name();
-
-            // Store the generated label;
-            // - other continues targeting the same loop can use the same label
-            // - toplevel handler will wrap the loop body in a label with this name, making the transform work
-            if target_label == "#loop" {
-                state.continue_targets[i].1 = Some(generated.clone());
-            } else {
-                state.continue_targets[i + 1].1 = Some(generated.clone());
-            }
+    state.record_rule(RuleId::Continue, span);
 
+    let existing = state.extension_mut::<ContinueLabels>().0.iter().find(|(loop_depth, _)| *loop_depth == depth).map(|(_, label)| label.clone());
+    let generated = match existing {
+        Some(generated) => generated,
+        None => {
+            // Other continues targeting the same loop can use the same label, and the body of the loop
+            // gets wrapped in a label with this name when the mapper leaves it
+            let generated = state.next_ident_name();
+            state.extension_mut::<ContinueLabels>().0.push((depth, generated.clone()));
             generated
-        };
+        }
+    };
 
     (
         MapperAction::Revisit,
@@ -133,67 +120,70 @@ pub fn transform_continue_statement<'a>(
     )
 }
 
-// This maintains the continue target stack in sync and wraps a loop body in a label when a continue targets it.
-pub fn apply_continue_transform_updates<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut RefMut<'_, MapperState>) -> Statement<'a> {
-    match stmt {
-        | Statement::DoWhileStatement(_)
-        | Statement::ForStatement(_)
-        | Statement::ForInStatement(_)
-        | Statement::ForOfStatement(_)
-        | Statement::WhileStatement(_)
-        => {
-            if before {
-                state.continue_targets.push(("#loop".to_string(), None));
-                println!("pushed #loop to continue_targets: {:?}", state.continue_targets);
-                stmt
-            } else {
-                println!("popping #loop from continue_targets: {:?}", state.continue_targets);
-                let (_, used) = state.continue_targets.pop().unwrap();
-                if let Some(used) = used {
-                    // Wrap body of loop in label with this name
-                    // At least one continue was replaced with a break targeting this label
-                    // so we need to wrap the body of the loop in a label with this name.
+/// The labels generated for the loops that a continue targets, with the depth of the loop in the
+/// ancestors of the mapper context. That identifies the loop for as long as the mapper is inside it.
+#[derive(Default)]
+struct ContinueLabels(Vec<(usize, String)>);
 
-                    match stmt {
-                        Statement::DoWhileStatement(do_while) => {
-                            let DoWhileStatement { test, body, span } = do_while.unbox();
-                            Statement::DoWhileStatement(OxcBox(allocator.alloc(DoWhileStatement { test, body: create_labeled_stmt(allocator, used, body, span), span })))
-                        }
-                        Statement::ForStatement(for_stmt) => {
-                            let ForStatement { init, test, update, body, span } = for_stmt.unbox();
-                            Statement::ForStatement(OxcBox(allocator.alloc(ForStatement { init, test, update, body: create_labeled_stmt(allocator, used, body, span), span })))
-                        }
-                        Statement::ForInStatement(for_stmt) => {
-                            let ForInStatement { left, right, body, span } = for_stmt.unbox();
-                            Statement::ForInStatement(OxcBox(allocator.alloc(ForInStatement { left, right, body: create_labeled_stmt(allocator, used, body, span), span })))
-                        }
-                        Statement::ForOfStatement(for_stmt) => {
-                            let ForOfStatement { left, right, body, span, r#await } = for_stmt.unbox();
-                            Statement::ForOfStatement(OxcBox(allocator.alloc(ForOfStatement { left, right, body: create_labeled_stmt(allocator, used, body, span), span, r#await })))
-                        }
-                        Statement::WhileStatement(while_stmt) => {
-                            let WhileStatement { test, body, span } = while_stmt.unbox();
-                            Statement::WhileStatement(OxcBox(allocator.alloc(WhileStatement { test, body: create_labeled_stmt(allocator, used, body, span), span })))
-                        }
-                        _ => panic!("Unexpected statement type: {:?}", stmt)
-                    }
-                } else {
-                    stmt
-                }
+/// The index in the ancestors of the loop that a continue with this label (or without) targets
+fn target_loop_depth(context: &MapperContext, label: Option<&str>) -> Option<usize> {
+    let ancestors = context.ancestors();
+    match label {
+        None => {
+            let target = context.nearest_loop()?;
+            ancestors.iter().rposition(|ancestor| std::ptr::eq(ancestor, target))
+        }
+        Some(label) => {
+            if !context.labels().contains(&label) {
+                return None;
             }
+            let label_depth = ancestors.iter().rposition(|ancestor| matches!(&ancestor.kind, AncestorKind::Label(name) if name == label))?;
+            // The label is on the loop, maybe with more labels in between: `a: b: while (x) continue a;`
+            let depth = label_depth + 1 + ancestors[label_depth + 1..].iter().position(|ancestor| !matches!(ancestor.kind, AncestorKind::Label(_)))?;
+            matches!(ancestors[depth].kind, AncestorKind::Loop(_)).then_some(depth)
         }
+    }
+}
 
-        Statement::LabeledStatement(labeled_stmt) => {
-            if before {
-                state.continue_targets.push((labeled_stmt.label.name.clone().to_string(), None));
-            } else {
-                // Pop and drop. Any "target" should be registered in the
-                // child loop so we should never need to do anything here.
-                state.continue_targets.pop().unwrap();
-            }
+// This wraps a loop body in a label when a continue targets it.
+pub fn apply_continue_transform_updates<'a>(stmt: Statement<'a>, before: bool, allocator: &'a Allocator, state: &mut MapperState) -> Statement<'a> {
+    if !matches!(stmt, Statement::DoWhileStatement(_) | Statement::ForStatement(_) | Statement::ForInStatement(_) | Statement::ForOfStatement(_) | Statement::WhileStatement(_)) {
+        return stmt;
+    }
+    // The loop itself is not in the context while its visitors run, so it will be at this depth inside of it
+    let depth = state.context.ancestors().len();
+    let labels = &mut state.extension_mut::<ContinueLabels>().0;
+    if before {
+        // Labels of a loop that was at this depth before are of no use anymore
+        labels.retain(|(loop_depth, _)| *loop_depth < depth);
+        return stmt;
+    }
+    let Some(index) = labels.iter().position(|(loop_depth, _)| *loop_depth == depth) else { return stmt };
+    let (_, used) = labels.remove(index);
 
-            Statement::LabeledStatement(labeled_stmt)
+    // At least one continue was replaced with a break targeting this label
+    // so we need to wrap the body of the loop in a label with this name.
+    match stmt {
+        Statement::DoWhileStatement(do_while) => {
+            let DoWhileStatement { test, body, span } = do_while.unbox();
+            Statement::DoWhileStatement(OxcBox(allocator.alloc(DoWhileStatement { test, body: create_labeled_stmt(allocator, used, body, span), span })))
+        }
+        Statement::ForStatement(for_stmt) => {
+            let ForStatement { init, test, update, body, span } = for_stmt.unbox();
+            Statement::ForStatement(OxcBox(allocator.alloc(ForStatement { init, test, update, body: create_labeled_stmt(allocator, used, body, span), span })))
+        }
+        Statement::ForInStatement(for_stmt) => {
+            let ForInStatement { left, right, body, span } = for_stmt.unbox();
+            Statement::ForInStatement(OxcBox(allocator.alloc(ForInStatement { left, right, body: create_labeled_stmt(allocator, used, body, span), span })))
+        }
+        Statement::ForOfStatement(for_stmt) => {
+            let ForOfStatement { left, right, body, span, r#await } = for_stmt.unbox();
+            Statement::ForOfStatement(OxcBox(allocator.alloc(ForOfStatement { left, right, body: create_labeled_stmt(allocator, used, body, span), span, r#await })))
+        }
+        Statement::WhileStatement(while_stmt) => {
+            let WhileStatement { test, body, span } = while_stmt.unbox();
+            Statement::WhileStatement(OxcBox(allocator.alloc(WhileStatement { test, body: create_labeled_stmt(allocator, used, body, span), span })))
         }
-        _ => stmt
+        _ => unreachable!("Only loops get here: {:?}", stmt)
     }
 }
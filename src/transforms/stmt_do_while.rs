@@ -6,33 +6,34 @@ use std::cell::Cell;
 use oxc_syntax::operator::*;
 use oxc_syntax::reference::*;
 use oxc_allocator::Allocator;
+use oxc_span::GetSpan;
 
 use crate::mapper::MapperAction;
 use crate::mapper_state::MapperState;
-use crate::utils::example;
-use crate::utils::rule;
+use crate::trace::RuleId;
 
 pub fn transform_do_while_statement<'a>(
     do_while: DoWhileStatement<'a>,
     allocator: &'a Allocator,
     state: &mut MapperState
 ) -> (MapperAction, Statement<'a>) {
-    rule("Eliminate d-while in favor of regular while");
-    example("do { x }; while (y);", "let tmp = true; while (test) { { x; } test = y; }");
+    // `do { x }; while (y);` -> `let tmp = true; while (test) { { x; } test = y; }`
+    state.record_rule(RuleId::DoWhile, do_while.span);
 
-    let loop_test_ident = state.next_ident_name();
+    let loop_test_ident = state.next_var_name(do_while.span);
+    let loop_test_kind = state.local_temp_var_kind(VariableDeclarationKind::Let);
 
     let DoWhileStatement { body, test, span } = do_while;
     // Create a block with test variable and while loop
-    let mut outer_body = OxcVec::with_capacity_in(2, &allocator);
+    let mut outer_body = OxcVec::with_capacity_in(2, allocator);
 
     // Add test variable declaration. Init to `true` to enter the loop at least once (do-while)
     // `var $tmp = true;`
     let test_decl = Statement::Declaration(
         Declaration::VariableDeclaration(OxcBox(allocator.alloc(VariableDeclaration {
-            kind: VariableDeclarationKind::Let,
+            kind: loop_test_kind,
             declarations: {
-                let mut decls = OxcVec::with_capacity_in(1, &allocator);
+                let mut decls = OxcVec::with_capacity_in(1, allocator);
                 decls.push(VariableDeclarator {
                     id: BindingPattern {
                         kind: BindingPatternKind::BindingIdentifier(OxcBox(allocator.alloc(BindingIdentifier {
@@ -49,7 +50,7 @@ pub fn transform_do_while_statement<'a>(
                     })))),
                     definite: false,
                     span,
-                    kind: VariableDeclarationKind::Let,
+                    kind: loop_test_kind,
                 });
                 decls
             },
@@ -59,8 +60,9 @@ pub fn transform_do_while_statement<'a>(
     );
     outer_body.push(test_decl);
 
-    // Create the while loop body
-    let mut while_body = OxcVec::with_capacity_in(2, &allocator);
+    // Create the while loop body. The test assignment maps back to the original test.
+    let test_span = test.span();
+    let mut while_body = OxcVec::with_capacity_in(2, allocator);
     while_body.push(body);
     while_body.push(Statement::ExpressionStatement(OxcBox(allocator.alloc(ExpressionStatement {
         expression: Expression::AssignmentExpression(OxcBox(allocator.alloc(AssignmentExpression {
@@ -74,9 +76,9 @@ pub fn transform_do_while_statement<'a>(
                 })))
             ),
             right: test,
-            span,
+            span: test_span,
         }))),
-        span,
+        span: test_span,
     }))));
 
     // Create the while statement
@@ -95,9 +97,10 @@ pub fn transform_do_while_statement<'a>(
     })));
     outer_body.push(while_stmt);
 
-    // Return the block containing everything
+    // The test variable is generated so the statements can go straight into the parent. Not at the
+    // top level of a script though, where all scripts would share it.
     (
-        MapperAction::Revisit,
+        if state.in_script_top_level() { MapperAction::Revisit } else { MapperAction::Splice },
         Statement::BlockStatement(OxcBox(allocator.alloc(BlockStatement {
             body: outer_body,
             span,
@@ -8,11 +8,11 @@ use std::cell::Cell;
 use oxc_allocator::Allocator;
 use oxc_span::Span;
 
+use crate::diagnostics::DiagnosticCode;
 use crate::mapper::MapperAction;
 use crate::mapper_state::MapperState;
 use crate::transforms::builder::*;
-use crate::utils::example;
-use crate::utils::rule;
+use crate::trace::RuleId;
 
 const NO_ACTION_ID: f64 = 0.0;
 const NO_ACTION_ID_STR: &str = "0";
@@ -36,19 +36,28 @@ pub fn transform_finally_statement<'a>(
 ) -> (MapperAction, Statement<'a>) {
     if try_stmt.finalizer.is_none() {
         ( MapperAction::Normal, Statement::TryStatement(OxcBox(allocator.alloc(try_stmt))) )
-    } else if try_stmt.handler.is_some() {
-        rule("Eliminate try/catch/finally in favor of a try/catch/try/catch statement");
-        example(
-            "try { a(); } catch (e) { b(e); } finally { c(); }",
-            "let thrown = false; let val; let action = 0; try { a(); } catch (e) { try { b(e); } catch (e2) { thrown = true; val = e2; } } c(); if (thrown) throw val;"
+    } else if let Some(span) = find_unconverted_finally(&try_stmt) {
+        state.report(
+            DiagnosticCode::UnexpectedNode,
+            "Cannot eliminate this finally block because it contains a nested finally block that could not be eliminated".to_string(),
+            span
         );
-
-        transform_try_catch_finally(try_stmt, allocator, state)
+        ( MapperAction::Normal, Statement::TryStatement(OxcBox(allocator.alloc(try_stmt))) )
     } else {
-        rule("Eliminate try/finally statement in favor of a try/catch statement");
-        example("try { a(); } finally { c(); }", "let thrown = false; let val; try { a(); } catch (e) { thrown = true; val = e; } c(); if (thrown) throw val;");
-
-        transform_try_finally(try_stmt, allocator, state)
+        let (action, stmt) = if try_stmt.handler.is_some() {
+            state.record_rule(RuleId::TryCatchFinally, try_stmt.span);
+            transform_try_catch_finally(try_stmt, allocator, state)
+        } else {
+            state.record_rule(RuleId::TryFinally, try_stmt.span);
+            transform_try_finally(try_stmt, allocator, state)
+        };
+        // The action and value vars are generated so they can go into the parent, except at the top
+        // level of a script (see `MapperState::in_script_top_level`)
+        if action == MapperAction::Splice && state.in_script_top_level() {
+            (MapperAction::Revisit, stmt)
+        } else {
+            (action, stmt)
+        }
     }
 }
 
@@ -101,7 +110,7 @@ fn transform_try_catch_finally<'a>(
         None => return ( MapperAction::Normal, create_try_statement(allocator, block.unbox(), handler, None, try_span) )
     };
     let CatchClause { param: catch_param, body: catch_body, span: catch_clause_span } = handler.unwrap().unbox(); // The handler was asserted before calling this function...
-    let catch_block_span = catch_body.span.clone();
+    let catch_block_span = catch_body.span;
     let BlockStatement { body: block_body, span: block_span } = block.unbox();
     let BlockStatement { body: finalizer_body, span: finalizer_span } = finalizer.unbox();
 
@@ -110,9 +119,9 @@ fn transform_try_catch_finally<'a>(
 
     // Create state variables
     // Action is what to do after the finally block (throw, return, break, continue, nothing)
-    let action_var = state.next_ident_name();
+    let action_var = state.next_var_name(try_span);
     // Use is the arg of the action to take (return x)
-    let use_var = state.next_ident_name();
+    let use_var = state.next_var_name(try_span);
     // Label is the new parent label of the try (may not be used)
     let new_try_label = state.next_ident_name();
 
@@ -128,7 +137,8 @@ fn transform_try_catch_finally<'a>(
                 &use_var,
                 &new_try_label,
                 block_span,
-                &target_labels
+                &target_labels,
+                &mut vec!()
             );
             has_return = has_return || has_return2;
             new_try_body.push(stmt);
@@ -143,7 +153,7 @@ fn transform_try_catch_finally<'a>(
             catch_body
         },
         handler: {
-            let catch_var = state.next_ident_name();
+            let catch_var = state.next_var_name(catch_clause_span);
             // This is the `thrown=true; thrown_value=e` part. We need to know whether the catch
             // handler threw (throws inside the try block are caught by definition) and propagate that.
 
@@ -220,6 +230,7 @@ fn transform_try_catch_finally<'a>(
         finalizer_span,
         action_var,
         use_var,
+        state.local_temp_var_kind(VariableDeclarationKind::Let),
         new_try_label,
         target_labels,
         has_return
@@ -274,9 +285,9 @@ fn transform_try_finally<'a>(
 
     // Create state variables
     // Action is what to do after the finally block (throw, return, break, continue, nothing)
-    let action_var = state.next_ident_name();
+    let action_var = state.next_var_name(span);
     // Use is the arg of the action to take (return x)
-    let use_var = state.next_ident_name();
+    let use_var = state.next_var_name(span);
     // Label is the new parent label of the try (may not be used)
     let new_try_label = state.next_ident_name();
 
@@ -292,7 +303,8 @@ fn transform_try_finally<'a>(
                 &use_var,
                 &new_try_label,
                 block_span,
-                &target_labels
+                &target_labels,
+                &mut vec!()
             );
             has_return = has_return || has_return2;
             new_try_body.push(stmt);
@@ -347,12 +359,14 @@ fn transform_try_finally<'a>(
         finalizer_span,
         action_var,
         use_var,
+        state.local_temp_var_kind(VariableDeclarationKind::Let),
         new_try_label,
         target_labels,
         has_return
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 fn transform_finally_wrap<'a>(
     allocator: &'a Allocator,
     try_span: Span,
@@ -361,6 +375,7 @@ fn transform_finally_wrap<'a>(
     finalizer_span: Span,
     action_var: String,
     use_var: String,
+    temp_kind: VariableDeclarationKind,
     new_try_label: String,
     target_labels: Vec<String>,
     has_return: bool
@@ -373,8 +388,8 @@ fn transform_finally_wrap<'a>(
 
     let mut new_body = vec![
         // `var thrown = false; var thrown_value = undefined;`
-        create_variable_declaration_let(allocator, action_var.clone(), Some(create_number_literal(allocator, NO_ACTION_ID, NO_ACTION_ID_STR, try_span)), try_span),
-        create_variable_declaration_let(allocator, use_var.clone(), None, try_span),
+        create_variable_declaration_kind(allocator, temp_kind, action_var.clone(), Some(create_number_literal(allocator, NO_ACTION_ID, NO_ACTION_ID_STR, try_span)), try_span),
+        create_variable_declaration_kind(allocator, temp_kind, use_var.clone(), None, try_span),
 
         // The labeled block representing the `new_label: finally { ... }`
         Statement::LabeledStatement(OxcBox(allocator.alloc(LabeledStatement {
@@ -401,7 +416,7 @@ fn transform_finally_wrap<'a>(
             ),
             create_throw_statement(allocator, create_identifier_expression(allocator, use_var.clone(), finalizer_span), finalizer_span),
             None,
-            try_span,
+            finalizer_span,
         ),
 
     ];
@@ -415,42 +430,48 @@ fn transform_finally_wrap<'a>(
             ),
             create_return_statement(allocator, Some(create_identifier_expression(allocator, use_var.clone(), finalizer_span)), finalizer_span),
             None,
-            try_span,
+            finalizer_span,
         ));
     }
 
-    // Add an `if` statement for each unique break label target for breaks inside the try targeting labels outside the try
-    for i in 0..target_labels.len() {
+    // Add an `if` statement for each unique break or continue target for jumps inside the try targeting labels or loops outside the try
+    for (i, target_label) in target_labels.iter().enumerate() {
         new_body.push(create_if_statement(allocator,
             create_binary_expression(allocator, BinaryOperator::StrictEquality,
                 create_identifier_expression(allocator, action_var.clone(), finalizer_span),
                 create_number_literal(
                     allocator, BREAK_ACTION_ID_OFFSET + (i as f64),
-                    allocator.alloc(Atom::from((BREAK_ACTION_ID_OFFSET+(i as f64)).to_string())).as_str(),
+                    allocator.alloc_str(&(BREAK_ACTION_ID_OFFSET+(i as f64)).to_string()),
                     finalizer_span
                 ),
                 finalizer_span
             ),
-            create_break_statement(allocator,
-                if target_labels[i] == "#looped" {
-                    None
-                } else {
-                    Some(LabelIdentifier {
-                        name: Atom::from(target_labels[i].clone()),
-                        span: finalizer_span,
-                    })
-                },
-                finalizer_span
-            ),
+            // See escaping_jump_key for the format of these keys
+            if target_label == "#looped" {
+                create_break_statement(allocator, None, finalizer_span)
+            } else if target_label == "#continue" {
+                create_continue_statement(allocator, None, finalizer_span)
+            } else if let Some(continue_label) = target_label.strip_prefix("#continue:") {
+                create_continue_statement(allocator, Some(LabelIdentifier {
+                    name: Atom::from(continue_label.to_string()),
+                    span: finalizer_span,
+                }), finalizer_span)
+            } else {
+                create_break_statement(allocator, Some(LabelIdentifier {
+                    name: Atom::from(target_label.clone()),
+                    span: finalizer_span,
+                }), finalizer_span)
+            },
             None,
-            try_span,
+            finalizer_span,
         ));
     }
 
-    (MapperAction::Revisit, create_block_statement(allocator, OxcVec::from_iter_in(new_body, allocator), try_span))
+    (MapperAction::Splice, create_block_statement(allocator, OxcVec::from_iter_in(new_body, allocator), try_span))
 }
 
 // Returns the statement and whether it (recursively) had a `return` at all
+#[allow(clippy::too_many_arguments)]
 fn transform_return_breaks_recursively<'a>(
     stmt: Statement<'a>,
     allocator: &'a Allocator,
@@ -458,10 +479,12 @@ fn transform_return_breaks_recursively<'a>(
     use_var: &str,
     new_try_label: &str,
     block_span: Span,
-    target_labels: &Vec<String>
+    target_labels: &Vec<String>,
+    local_labels: &mut Vec<String>
 ) -> (bool, Statement<'a>) {
-    // Note: after all our transforms there should only be a handful of statements left
-    // that can hold sub-statements: block, if, while, try, label, with. Ignoring functions, of course.
+    // Note: depending on which transforms are enabled, loops and switches may still be here. They are
+    // break (and continue) boundaries so we track them in `local_labels`, same as the analysis does.
+    // Functions are a hard boundary so we don't visit those.
 
     match stmt {
         Statement::ReturnStatement(ret) => {
@@ -506,35 +529,28 @@ fn transform_return_breaks_recursively<'a>(
                 Statement::BreakStatement(OxcBox(allocator.alloc(BreakStatement {
                     label: Some(LabelIdentifier {
                         name: Atom::from(new_try_label),
-                        span: block_span,
+                        span,
                     }),
-                    span: block_span,
+                    span,
                 })))
             ], allocator);
 
             (true, create_block_statement(allocator, stmts, span))
         }
 
-        Statement::BreakStatement(break_stmt) => {
-            let BreakStatement { label, span } = break_stmt.unbox();
-
-            let label = if let Some(label) = label {
-                label.name.to_string()
-            } else {
-                "#looped".to_string()
+        Statement::BreakStatement(_) | Statement::ContinueStatement(_) => {
+            let (label, is_continue, span) = match &stmt {
+                Statement::BreakStatement(break_stmt) => (break_stmt.label.as_ref().map(|label| label.name.to_string()), false, break_stmt.span),
+                Statement::ContinueStatement(continue_stmt) => (continue_stmt.label.as_ref().map(|label| label.name.to_string()), true, continue_stmt.span),
+                _ => unreachable!(),
             };
 
-            let index = target_labels.iter().position(|x| x == &label);
+            let index = escaping_jump_key(label.as_deref(), is_continue, local_labels)
+                .and_then(|key| target_labels.iter().position(|x| *x == key));
 
             let Some(index) = index else {
-                // If not found then the break targets a local label (defined inside the try) so we can ignore it
-                return (false, Statement::BreakStatement(OxcBox(allocator.alloc(BreakStatement {
-                    label: Some(LabelIdentifier {
-                        name: Atom::from(label),
-                        span: block_span,
-                    }),
-                    span: block_span,
-                }))));
+                // The jump targets a label or loop that is defined inside the try so we can leave it as is
+                return (false, stmt);
             };
 
             let stmts = OxcVec::from_iter_in([
@@ -546,7 +562,7 @@ fn transform_return_breaks_recursively<'a>(
                         action_var.to_string(),
                         Expression::NumberLiteral(OxcBox(allocator.alloc(NumberLiteral {
                             value: BREAK_ACTION_ID_OFFSET + (index as f64),
-                            raw: allocator.alloc(Atom::from((BREAK_ACTION_ID_OFFSET+(index as f64)).to_string())).as_str(),
+                            raw: allocator.alloc_str(&(BREAK_ACTION_ID_OFFSET+(index as f64)).to_string()),
                             base: NumberBase::Decimal,
                             span: block_span,
                         }))),
@@ -581,7 +597,8 @@ fn transform_return_breaks_recursively<'a>(
                     use_var,
                     new_try_label,
                     block_span,
-                    &target_labels
+                    target_labels,
+                    local_labels
                 );
                 has_return = has_return || has_return2;
                 new_body.push(stmt);
@@ -595,9 +612,9 @@ fn transform_return_breaks_recursively<'a>(
         Statement::IfStatement(if_stmt) => {
             let IfStatement { test, consequent, alternate, span } = if_stmt.unbox();
 
-            let (has_return, consequent) = transform_return_breaks_recursively(consequent, allocator, action_var, use_var, new_try_label, block_span, &target_labels);
+            let (has_return, consequent) = transform_return_breaks_recursively(consequent, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
             let (has_return2, alternate) = if let Some(alternate) = alternate {
-                let (has_return2, alternate) = transform_return_breaks_recursively(alternate, allocator, action_var, use_var, new_try_label, block_span, &target_labels);
+                let (has_return2, alternate) = transform_return_breaks_recursively(alternate, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
                 (has_return2, Some(alternate))
             } else {
                 (false, None)
@@ -613,7 +630,9 @@ fn transform_return_breaks_recursively<'a>(
 
         Statement::WhileStatement(while_stmt) => {
             let WhileStatement { test, body, span } = while_stmt.unbox();
-            let (has_return, body) = transform_return_breaks_recursively(body, allocator, action_var, use_var, new_try_label, block_span, &target_labels);
+            local_labels.push("#loop".to_string());
+            let (has_return, body) = transform_return_breaks_recursively(body, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
+            local_labels.pop();
 
             (has_return, Statement::WhileStatement(OxcBox(allocator.alloc(WhileStatement {
                 test,
@@ -621,19 +640,91 @@ fn transform_return_breaks_recursively<'a>(
                 span,
             }))))
         }
+        Statement::DoWhileStatement(do_while) => {
+            let DoWhileStatement { body, test, span } = do_while.unbox();
+            local_labels.push("#loop".to_string());
+            let (has_return, body) = transform_return_breaks_recursively(body, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
+            local_labels.pop();
+
+            (has_return, Statement::DoWhileStatement(OxcBox(allocator.alloc(DoWhileStatement {
+                body,
+                test,
+                span,
+            }))))
+        }
+        Statement::ForStatement(for_stmt) => {
+            let ForStatement { init, test, update, body, span } = for_stmt.unbox();
+            local_labels.push("#loop".to_string());
+            let (has_return, body) = transform_return_breaks_recursively(body, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
+            local_labels.pop();
+
+            (has_return, Statement::ForStatement(OxcBox(allocator.alloc(ForStatement {
+                init,
+                test,
+                update,
+                body,
+                span,
+            }))))
+        }
+        Statement::ForInStatement(for_in) => {
+            let ForInStatement { left, right, body, span } = for_in.unbox();
+            local_labels.push("#loop".to_string());
+            let (has_return, body) = transform_return_breaks_recursively(body, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
+            local_labels.pop();
+
+            (has_return, Statement::ForInStatement(OxcBox(allocator.alloc(ForInStatement {
+                left,
+                right,
+                body,
+                span,
+            }))))
+        }
+        Statement::ForOfStatement(for_of) => {
+            let ForOfStatement { left, right, body, r#await, span } = for_of.unbox();
+            local_labels.push("#loop".to_string());
+            let (has_return, body) = transform_return_breaks_recursively(body, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
+            local_labels.pop();
+
+            (has_return, Statement::ForOfStatement(OxcBox(allocator.alloc(ForOfStatement {
+                left,
+                right,
+                body,
+                r#await,
+                span,
+            }))))
+        }
+        Statement::SwitchStatement(switch_stmt) => {
+            let SwitchStatement { discriminant, cases, span } = switch_stmt.unbox();
+            local_labels.push("#switch".to_string());
+            let mut has_return = false;
+            let mut new_cases = OxcVec::with_capacity_in(cases.len(), allocator);
+            for case in cases {
+                let SwitchCase { test, consequent, span } = case;
+                let mut new_consequent = OxcVec::with_capacity_in(consequent.len(), allocator);
+                for stmt in consequent {
+                    let (has_return2, stmt) = transform_return_breaks_recursively(stmt, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
+                    has_return = has_return || has_return2;
+                    new_consequent.push(stmt);
+                }
+                new_cases.push(SwitchCase { test, consequent: new_consequent, span });
+            }
+            local_labels.pop();
+
+            (has_return, Statement::SwitchStatement(OxcBox(allocator.alloc(SwitchStatement {
+                discriminant,
+                cases: new_cases,
+                span,
+            }))))
+        }
         Statement::TryStatement(try_stmt) => {
             let TryStatement { block: try_block, handler, finalizer, span: try_span } = try_stmt.unbox();
-            if let Some(_) = finalizer {
-                panic!("We should walk upward and any finally should have already been eliminated at this point.");
-            };
-            let handler = if let Some(handler) = handler {
-                handler
-            } else {
-                panic!("Since all finally blocks have been eliminated, we should always have a handler here.");
+            // Any nested finally was eliminated on the way up (see find_unconverted_finally) so there must be a handler.
+            let (Some(handler), None) = (handler, finalizer) else {
+                unreachable!("Since all finally blocks have been eliminated, we should always have a handler here.");
             };
             let CatchClause { param: catch_param, body: catch_body, span: catch_span } = handler.unbox();
-            let (has_return, try_block) = transform_return_breaks_recursively_in_block(try_block.unbox(), allocator, action_var, use_var, new_try_label, block_span, &target_labels);
-            let (has_return2, catch_body) = transform_return_breaks_recursively_in_block(catch_body.unbox(), allocator, action_var, use_var, new_try_label, block_span, &target_labels);
+            let (has_return, try_block) = transform_return_breaks_recursively_in_block(try_block.unbox(), allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
+            let (has_return2, catch_body) = transform_return_breaks_recursively_in_block(catch_body.unbox(), allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
 
             (has_return || has_return2, Statement::TryStatement(OxcBox(allocator.alloc(TryStatement {
                 block: try_block,
@@ -650,7 +741,9 @@ fn transform_return_breaks_recursively<'a>(
         Statement::LabeledStatement(labeled) => {
             let LabeledStatement { label, body, span } = labeled.unbox();
 
-            let (has_return, body) = transform_return_breaks_recursively(body, allocator, action_var, use_var, new_try_label, block_span, &target_labels);
+            local_labels.push(label.name.to_string());
+            let (has_return, body) = transform_return_breaks_recursively(body, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
+            local_labels.pop();
             (has_return, Statement::LabeledStatement(OxcBox(allocator.alloc(LabeledStatement {
                 label,
                 body,
@@ -660,7 +753,7 @@ fn transform_return_breaks_recursively<'a>(
 
         Statement::WithStatement(_with) => {
             let WithStatement { object, body, span } = _with.unbox();
-            let (has_return, body) = transform_return_breaks_recursively(body, allocator, action_var, use_var, new_try_label, block_span, &target_labels);
+            let (has_return, body) = transform_return_breaks_recursively(body, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
             (has_return, Statement::WithStatement(OxcBox(allocator.alloc(WithStatement {
                 object,
                 body,
@@ -668,11 +761,12 @@ fn transform_return_breaks_recursively<'a>(
             }))))
         }
 
-        // Do not visit functions. Anything else should be transformed or not have sub-statements.
+        // Do not visit functions. Anything else should not have sub-statements.
         _ => (false, stmt),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn transform_return_breaks_recursively_in_block<'a>(
     block: BlockStatement<'a>, // This must unwrap to a BlockStatement!
     allocator: &'a Allocator,
@@ -680,7 +774,8 @@ fn transform_return_breaks_recursively_in_block<'a>(
     use_var: &str,
     new_try_label: &str,
     block_span: Span,
-    target_labels: &Vec<String>
+    target_labels: &Vec<String>,
+    local_labels: &mut Vec<String>
 ) -> (bool, OxcBox<'a, BlockStatement<'a>>) {
     // This works around the `try/catch` case where the children are a Statement that is guaranteed
     // to be a BlockStatement (as per JS syntax) but the type system must assume a generic Statement.
@@ -688,7 +783,7 @@ fn transform_return_breaks_recursively_in_block<'a>(
     let mut new_body = OxcVec::with_capacity_in(body.len(), allocator);
     let mut has_return = false;
     for stmt in body {
-        let (has_return2, stmt) = transform_return_breaks_recursively(stmt, allocator, action_var, use_var, new_try_label, block_span, &target_labels);
+        let (has_return2, stmt) = transform_return_breaks_recursively(stmt, allocator, action_var, use_var, new_try_label, block_span, target_labels, local_labels);
         has_return = has_return || has_return2;
         new_body.push(stmt);
     }
@@ -698,6 +793,62 @@ fn transform_return_breaks_recursively_in_block<'a>(
     })))
 }
 
+/// Returns the key to use in the target label list for a `break` or `continue` that jumps out of the try block.
+/// Returns None when it targets a label or loop that is defined inside the try block.
+///
+/// - `break label` -> `label`
+/// - `break` -> `#looped`
+/// - `continue label` -> `#continue:label`
+/// - `continue` -> `#continue`
+///
+/// Labels can't start with a `#` so these can't clash with user labels.
+fn escaping_jump_key(label: Option<&str>, is_continue: bool, local_labels: &[String]) -> Option<String> {
+    match (label, is_continue) {
+        (Some(label), false) => (!local_labels.iter().any(|local| local == label)).then(|| label.to_string()),
+        (Some(label), true) => (!local_labels.iter().any(|local| local == label)).then(|| format!("#continue:{}", label)),
+        // An unlabeled break targets the nearest loop or switch
+        (None, false) => (!local_labels.iter().any(|local| local == "#loop" || local == "#switch")).then(|| "#looped".to_string()),
+        // An unlabeled continue targets the nearest loop
+        (None, true) => (!local_labels.iter().any(|local| local == "#loop")).then(|| "#continue".to_string()),
+    }
+}
+
+/// The rewrite of abrupt completions assumes that nested try/finally statements were eliminated on the way up.
+/// That may not be the case when we had to bail on one. Return the first one found, if any. Functions are not visited.
+fn find_unconverted_finally(try_stmt: &TryStatement) -> Option<Span> {
+    find_unconverted_finally_in_block(&try_stmt.block)
+    .or_else(|| try_stmt.handler.as_ref().and_then(|handler| find_unconverted_finally_in_block(&handler.body)))
+}
+
+fn find_unconverted_finally_in_block(block: &BlockStatement) -> Option<Span> {
+    block.body.iter().find_map(find_unconverted_finally_in_statement)
+}
+
+fn find_unconverted_finally_in_statement(stmt: &Statement) -> Option<Span> {
+    match stmt {
+        Statement::TryStatement(try_stmt) if try_stmt.finalizer.is_some() || try_stmt.handler.is_none() => Some(try_stmt.span),
+        Statement::TryStatement(try_stmt) => find_unconverted_finally(try_stmt),
+
+        Statement::BlockStatement(block) => find_unconverted_finally_in_block(block),
+        Statement::IfStatement(if_stmt) => {
+            find_unconverted_finally_in_statement(&if_stmt.consequent)
+            .or_else(|| if_stmt.alternate.as_ref().and_then(find_unconverted_finally_in_statement))
+        }
+        Statement::WhileStatement(while_stmt) => find_unconverted_finally_in_statement(&while_stmt.body),
+        Statement::DoWhileStatement(do_while) => find_unconverted_finally_in_statement(&do_while.body),
+        Statement::ForStatement(for_stmt) => find_unconverted_finally_in_statement(&for_stmt.body),
+        Statement::ForInStatement(for_in) => find_unconverted_finally_in_statement(&for_in.body),
+        Statement::ForOfStatement(for_of) => find_unconverted_finally_in_statement(&for_of.body),
+        Statement::SwitchStatement(switch_stmt) => {
+            switch_stmt.cases.iter().flat_map(|case| case.consequent.iter()).find_map(find_unconverted_finally_in_statement)
+        }
+        Statement::LabeledStatement(labeled) => find_unconverted_finally_in_statement(&labeled.body),
+        Statement::WithStatement(with) => find_unconverted_finally_in_statement(&with.body),
+
+        _ => None,
+    }
+}
+
 fn _abrupt_escape_analysis(stmt: &Statement) -> (bool, Vec<String>) {
     // We need to remember the list of break labels to compile after the finally.
     // Since we only target labels that are wrapping the try-statement and JS syntax
@@ -706,98 +857,99 @@ fn _abrupt_escape_analysis(stmt: &Statement) -> (bool, Vec<String>) {
     let mut target_labels = vec!();
     // This is just for walking the tree
     let mut label_front = vec!();
-    let result = abrupt_escape_analysis_statement(stmt, &mut target_labels, &mut label_front);
+    let result = abrupt_escape_analysis_statement(stmt, &mut label_front, &mut target_labels);
 
     ( result, target_labels )
 }
 
+// Note: this must visit every statement (no short-circuiting) because every escaping jump must end up in the target label list.
 fn abrupt_escape_analysis_statement(stmt: &Statement, local_labels: &mut Vec<String>, target_labels: &mut Vec<String>) -> bool {
     match stmt {
         // This return needs to be transformed to break to the new try-parent-label
         Statement::ReturnStatement(_) => true,
         // The `try/finally` transform will wrap the block in a propagating catch regardless so we don't have to handle throws here
         Statement::ThrowStatement(_) => false,
-        // Breaks are tricky because this should only return true when they break _outside_ of the `try` block
+        // Breaks and continues are tricky because this should only return true when they jump _outside_ of the `try` block
         // For this reason we have to maintain and pass down two label vectors. Labels are statement bound and
         // guaranteed to be unique (syntax requirement) so a simple vector will suffice for us.
-        // One vector maintains all the labels local to the `try`. We need them to identify outbound breaks.
-        // The other vector maintains all the labels that are targets of outbound breaks, which we need for the
-        // final `if (action === 3) break ${labels[0]};` part. We need to know which action breaks to which label.
-        Statement::BreakStatement(stmt) => {
-            let label = if let Some(label) = stmt.label.as_ref() {
-                &label.name
-            } else {
-                // This means "break without label", which must target a loop
-                "#looped"
-            };
-
-            // Is this targeting a label that wraps the try or was defined inside the try?
-            // Using a special label for unlabeled breaks.
-            if local_labels.contains(&label.to_string()) {
-                // ie: `try { x: break x; } finally { ... }`
-                false
-            } else {
-                // ie: `x: try { break x; } finally { ... }`
-                // If we don't already have this label in the set, add it now.
-                if !target_labels.contains(&label.to_string()) {
-                    target_labels.push(label.to_string());
-                }
-                true
-            }
+        // One vector maintains all the labels (and loops and switches) local to the `try`. We need them to identify outbound jumps.
+        // The other vector maintains all the labels that are targets of outbound jumps, which we need for the
+        // final `if (action === 3) break ${labels[0]};` part. We need to know which action jumps to which label.
+        Statement::BreakStatement(break_stmt) => {
+            record_escaping_jump(escaping_jump_key(break_stmt.label.as_ref().map(|label| label.name.as_str()), false, local_labels), target_labels)
+        },
+        Statement::ContinueStatement(continue_stmt) => {
+            record_escaping_jump(escaping_jump_key(continue_stmt.label.as_ref().map(|label| label.name.as_str()), true, local_labels), target_labels)
         },
-        // We will transform away the continue statement so we should not expect it here
-        Statement::ContinueStatement(_) => panic!("ContinueStatement should have been eliminated before reaching this point"),
 
         Statement::BlockStatement(block) => {
             abrupt_escape_analysis_in_block(block, local_labels, target_labels)
         }
         Statement::IfStatement(if_stmt) => {
-            abrupt_escape_analysis_statement(&if_stmt.consequent, local_labels, target_labels) ||
-            if_stmt.alternate.as_ref().map_or(false, |alt| abrupt_escape_analysis_statement(alt, local_labels, target_labels))
+            abrupt_escape_analysis_statement(&if_stmt.consequent, local_labels, target_labels) |
+            if_stmt.alternate.as_ref().is_some_and(|alt| abrupt_escape_analysis_statement(alt, local_labels, target_labels))
         }
-        Statement::WhileStatement(while_stmt) => {
-            // We use a special #looped label to indicate that we're inside a loop such
-            // that unlabeled breaks inside won't escape the try block
-            local_labels.push("#looped".to_string());
-            let result = abrupt_escape_analysis_statement(&while_stmt.body, local_labels, target_labels);
+        Statement::WhileStatement(while_stmt) => abrupt_escape_analysis_in_loop(&while_stmt.body, local_labels, target_labels),
+        Statement::DoWhileStatement(do_while) => abrupt_escape_analysis_in_loop(&do_while.body, local_labels, target_labels),
+        Statement::ForStatement(for_stmt) => abrupt_escape_analysis_in_loop(&for_stmt.body, local_labels, target_labels),
+        Statement::ForInStatement(for_in) => abrupt_escape_analysis_in_loop(&for_in.body, local_labels, target_labels),
+        Statement::ForOfStatement(for_of) => abrupt_escape_analysis_in_loop(&for_of.body, local_labels, target_labels),
+        Statement::SwitchStatement(switch_stmt) => {
+            // Unlabeled breaks inside a switch target the switch, but unlabeled continues go through it
+            local_labels.push("#switch".to_string());
+            let mut result = false;
+            for stmt in switch_stmt.cases.iter().flat_map(|case| case.consequent.iter()) {
+                result |= abrupt_escape_analysis_statement(stmt, local_labels, target_labels);
+            }
             local_labels.pop();
             result
         }
-        Statement::DoWhileStatement(_do_while) => panic!("DoWhileStatement should have been eliminated before reaching this point"),
-        Statement::ForStatement(for_stmt) => abrupt_escape_analysis_statement(&for_stmt.body, local_labels, target_labels),
-        Statement::ForInStatement(_for_in) => panic!("ForInStatement should have been eliminated before reaching this point"),
-        Statement::ForOfStatement(_for_of) => panic!("ForOfStatement should have been eliminated before reaching this point"),
         Statement::TryStatement(try_stmt) => {
-                if abrupt_escape_analysis_in_block(&try_stmt.block, local_labels, target_labels) {
-                try_stmt.handler.as_ref().map_or(false, |h| {
-                    abrupt_escape_analysis_in_block(&h.body, local_labels, target_labels)
-                }) ||
-                try_stmt.finalizer.as_ref().map_or(false, |f| {
-                    abrupt_escape_analysis_in_block(&f, local_labels, target_labels)
-                })
-            } else {
-                false
-            }
+            abrupt_escape_analysis_in_block(&try_stmt.block, local_labels, target_labels) |
+            try_stmt.handler.as_ref().is_some_and(|h| {
+                abrupt_escape_analysis_in_block(&h.body, local_labels, target_labels)
+            }) |
+            try_stmt.finalizer.as_ref().is_some_and(|f| {
+                abrupt_escape_analysis_in_block(f, local_labels, target_labels)
+            })
         }
 
         Statement::LabeledStatement(labeled) => {
             local_labels.push(labeled.label.name.to_string());
             let result = abrupt_escape_analysis_statement(&labeled.body, local_labels, target_labels);
-            local_labels.pop(); // This is the only arm that pushes labels so we should be safe to pop it here.
+            local_labels.pop();
             result
         }
 
-        Statement::SwitchStatement(_switch) => {
-            // (This function should be used on the way back up)
-            panic!("Switch statements should have been eliminated before reaching this point");
-        }
-
+        Statement::WithStatement(with) => abrupt_escape_analysis_statement(&with.body, local_labels, target_labels),
 
         // Note: we do not visit Functions
         _ => false,
     }
 }
 
+fn record_escaping_jump(key: Option<String>, target_labels: &mut Vec<String>) -> bool {
+    let Some(key) = key else {
+        // ie: `try { x: break x; } finally { ... }`
+        return false;
+    };
+    // ie: `x: try { break x; } finally { ... }`
+    // If we don't already have this label in the set, add it now.
+    if !target_labels.contains(&key) {
+        target_labels.push(key);
+    }
+    true
+}
+
+fn abrupt_escape_analysis_in_loop(body: &Statement, local_labels: &mut Vec<String>, target_labels: &mut Vec<String>) -> bool {
+    // We use a special #loop label to indicate that we're inside a loop such
+    // that unlabeled breaks and continues inside won't escape the try block
+    local_labels.push("#loop".to_string());
+    let result = abrupt_escape_analysis_statement(body, local_labels, target_labels);
+    local_labels.pop();
+    result
+}
+
 fn abrupt_escape_analysis_in_block<'a>(block: &BlockStatement<'a>, local_labels: &mut Vec<String>, target_labels: &mut Vec<String>) -> bool {
     abrupt_escape_analysis_in_block_body(&block.body, local_labels, target_labels)
 }
@@ -805,7 +957,7 @@ fn abrupt_escape_analysis_in_block<'a>(block: &BlockStatement<'a>, local_labels:
 fn abrupt_escape_analysis_in_block_body<'a>(block: &OxcVec<'a, Statement<'a>>, local_labels: &mut Vec<String>, target_labels: &mut Vec<String>) -> bool {
     let mut result = false;
     block.iter().for_each(|stmt| {
-        result = result || abrupt_escape_analysis_statement(stmt, local_labels, target_labels);
+        result |= abrupt_escape_analysis_statement(stmt, local_labels, target_labels);
     });
     result
 }
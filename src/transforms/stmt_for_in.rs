@@ -1,13 +1,16 @@
 use oxc_allocator::Vec as OxcVec;
+use oxc_allocator::Box as OxcBox;
 use oxc_ast::ast::*;
+use oxc_span::GetSpan;
 use oxc_allocator::Allocator;
 use oxc_syntax::operator::AssignmentOperator;
 use oxc_syntax::operator::BinaryOperator;
 
+use crate::diagnostics::DiagnosticCode;
+use crate::helpers::Helper;
 use crate::mapper_state::MapperState;
 use crate::mapper::MapperAction;
-use crate::utils::example;
-use crate::utils::rule;
+use crate::trace::RuleId;
 use super::builder::create_assignment_expression;
 use super::builder::create_assignment_expression_name;
 use super::builder::create_binary_expression;
@@ -19,11 +22,10 @@ use super::builder::create_expression_statement;
 use super::builder::create_identifier_expression;
 use super::builder::create_if_statement;
 use super::builder::create_member_expression;
-use super::builder::create_variable_declaration_const;
 use super::builder::create_variable_declaration_kind;
-use super::builder::create_variable_declaration_let;
 use super::builder::create_while_statement;
 use super::for_header::transform_for_header;
+use super::for_header::SimpleForLeft;
 
 pub fn transform_for_in_statement<'a>(
     for_stmt: ForInStatement<'a>,
@@ -53,7 +55,7 @@ pub fn transform_for_in_statement<'a>(
     //   { console.log(x); }
     // }
     // ```
-    // With $forIn being defined (in JS) as simple as:
+    // With $forIn being defined (in JS) as simple as (see `Helper::ForIn`, it gets injected into the output):
     //
     // ```
     // function * $forIn(x) {
@@ -64,13 +66,27 @@ pub fn transform_for_in_statement<'a>(
     // ```
     //
 
-    rule("Eliminate for-in loop in favor of regular while");
-    example("for (x in y) { body; }", "let $tmp = $forIn(y); let $next; while ($next = $tmp.next()) { if ($next.done) break; x = $next.value; { body; } }");
+    if let ForStatementLeft::UsingDeclaration(_) = for_stmt.left {
+        // The mapper already reported this. Leave the loop as is.
+        return (MapperAction::Normal, Statement::ForInStatement(OxcBox(allocator.alloc(for_stmt))));
+    }
 
     let ForInStatement { left, right, body, span } = for_stmt;
 
+    // Generated statements map back to the part of the header they came from
+    let left_span = left.span();
+    let right_span = right.span();
+
     // Transform the header if needed
-    let (new_left, pattern_stmt) = transform_for_header(left, allocator, state, span);
+    let (new_left, pattern_stmt) = match transform_for_header(left, allocator, state, span) {
+        Ok(simplified) => simplified,
+        Err(left) => {
+            state.report(DiagnosticCode::UnsupportedTransform, "This lhs of a for-in header is not supported".to_string(), left_span);
+            return (MapperAction::Normal, Statement::ForInStatement(OxcBox(allocator.alloc(ForInStatement { left, right, body, span }))));
+        }
+    };
+
+    state.record_rule(RuleId::ForIn, span);
 
     // Create the new body with pattern assignment if needed
     let new_body = if let Some(pattern_stmt) = pattern_stmt {
@@ -86,8 +102,9 @@ pub fn transform_for_in_statement<'a>(
         body
     };
 
-    let iterator_var = state.next_ident_name();
-    let next_var = state.next_ident_name();
+    let helper_name = state.use_helper(Helper::ForIn);
+    let iterator_var = state.next_var_name(span);
+    let next_var = state.next_var_name(span);
 
     // `$next.value`
     let rhs = create_member_expression(allocator, create_identifier_expression(allocator, next_var.clone(), span), "value".to_string(), span);
@@ -95,60 +112,23 @@ pub fn transform_for_in_statement<'a>(
     // Create the `$tmp = $next.value` assignment. There are a few cases depending on the lhs in the for-in header.
     // (Wow this is annoying in Rust...)
     let next_value_stmt = match new_left {
-        ForStatementLeft::VariableDeclaration(vd) => {
-            // Note: this decl may only have one declarator (syntactic restriction)
-            let VariableDeclaration { declarations, span: decl_span, kind, modifiers: _modifiers } = vd.unbox();
-            let first = declarations.into_iter().next().unwrap();
-            let VariableDeclarator { id, init, definite: _definite, span: _span, kind: _kind } = first;
-            match id {
-                BindingPattern { kind: BindingPatternKind::BindingIdentifier(id), type_annotation: _type_annotation, optional: _optional } => {
-                    assert!(init.is_none(), "afaik for-in header lhs decl cannot have an initializer");
-                    // ie: `for (let a in b) x`
-                    create_variable_declaration_kind(allocator, kind, id.name.to_string(), Some(rhs), decl_span)
-                },
-                BindingPattern { kind: BindingPatternKind::ObjectPattern(_), .. } => {
-                    // ie: `for (let {a} in b) x`
-                    panic!("ObjectPattern in for-in header should have been transformed out");
-                },
-                BindingPattern { kind: BindingPatternKind::ArrayPattern(_), .. } => {
-                    // ie: `for (let [a] in b) x`
-                    panic!("ArrayPattern in for-in header should have been transformed out");
-                },
-                BindingPattern { kind: BindingPatternKind::AssignmentPattern(_), .. } => {
-                    // The assignment refers to a pattern default, ie: `for (let [a = b] in c) x`
-                    // We should have transformed patterns out of this header already precisely so we can cheese it here.
-                    panic!("AssignmentPattern in for-in header should have been transformed out");
-                },
-            }
-        },
-        ForStatementLeft::AssignmentTarget(AssignmentTarget::SimpleAssignmentTarget(id)) => {
-            match id {
-                SimpleAssignmentTarget::AssignmentTargetIdentifier(id) => {
-                    // ie: `for (a in c) x`
-                    create_expression_statement(
-                        allocator,
-                        create_assignment_expression(
-                            allocator,
-                            AssignmentOperator::Assign,
-                            id.unbox(),
-                            rhs,
-                            span
-                        ),
-                        span
-                    )
-                },
-                _ => {
-                    // We assume this already happened: `for (a.x in b) x` -> `for (let $tmp in b) { a.x = $tmp; { x } }`
-                    panic!("SimpleAssignmentTarget in for-in header should have been transformed to a plain `for (a in c) x`");
-                },
-            }
-        },
-        ForStatementLeft::AssignmentTarget(AssignmentTarget::AssignmentTargetPattern(_)) => {
-            // We assume this already happened: `for ([a] in b) x` -> `for (let $tmp in b) { [a] = $tmp; { x } }`
-            panic!("AssignmentTargetPattern in for-in header should have been transformed out");
+        SimpleForLeft::Declaration(kind, name, decl_span) => {
+            // ie: `for (let a in b) x`
+            create_variable_declaration_kind(allocator, kind, name, Some(rhs), decl_span)
         },
-        ForStatementLeft::UsingDeclaration(_) => {
-            panic!("UsingDeclaration is not supported by this tool");
+        SimpleForLeft::Identifier(id) => {
+            // ie: `for (a in c) x`
+            create_expression_statement(
+                allocator,
+                create_assignment_expression(
+                    allocator,
+                    AssignmentOperator::Assign,
+                    id,
+                    rhs,
+                    left_span
+                ),
+                left_span
+            )
         },
     };
 
@@ -194,18 +174,21 @@ pub fn transform_for_in_statement<'a>(
 
     let new_block_stmt = create_block_statement(allocator, OxcVec::from_iter_in([
         // `const $iterator_var = $forIn(right);`
-        create_variable_declaration_const(
+        create_variable_declaration_kind(
             allocator,
+            state.local_temp_var_kind(VariableDeclarationKind::Const),
             iterator_var.clone(),
-            Some(create_call_expression(allocator, create_identifier_expression(allocator, "$forIn".to_string(), span), OxcVec::from_iter_in([right], allocator), false, None, span)),
-            span
+            Some(create_call_expression(allocator, create_identifier_expression(allocator, helper_name, right_span), OxcVec::from_iter_in([right], allocator), false, None, right_span)),
+            right_span
         ),
         // `let $next;`
-        create_variable_declaration_let(allocator, next_var.clone(), None, span),
+        create_variable_declaration_kind(allocator, state.local_temp_var_kind(VariableDeclarationKind::Let), next_var.clone(), None, span),
         // `while ($next = $iterator_var()) { ... }`
         new_while_stmt,
     ], allocator), span);
 
 
-    ( MapperAction::Revisit, new_block_stmt )
+    // The iterator and next vars can go into the parent, unless that's the top level of a script
+    let action = if state.in_script_top_level() { MapperAction::Revisit } else { MapperAction::Splice };
+    ( action, new_block_stmt )
 }
@@ -2,19 +2,25 @@ use oxc_allocator::Vec as OxcVec;
 use oxc_allocator::Box as OxcBox;
 use oxc_ast::ast::*;
 use oxc_allocator::Allocator;
+use oxc_span::GetSpan;
 
 use crate::mapper::MapperAction;
 use crate::mapper_state::MapperState;
-use crate::utils::example;
-use crate::utils::rule;
+use crate::trace::RuleId;
 
 pub fn transform_for_n_statement<'a>(
     for_stmt: ForStatement<'a>,
     allocator: &'a Allocator,
-    _state: &mut MapperState
+    state: &mut MapperState
 ) -> (MapperAction, Statement<'a>) {
-    rule("Eliminate regular for-loop in favor of regular while");
-    example("for (x; y; z) { body; }", "x; while (y) { { body; } z; }");
+    // `for (x; y; z) { body; }` -> `x; while (y) { { body; } z; }`
+
+    if let Some(ForStatementInit::UsingDeclaration(_)) = for_stmt.init {
+        // The mapper already reported this. Leave the loop as is.
+        return (MapperAction::Normal, Statement::ForStatement(OxcBox(allocator.alloc(for_stmt))));
+    }
+
+    state.record_rule(RuleId::ForLoop, for_stmt.span);
 
     let ForStatement { init, test, update, body, span } = for_stmt;
 
@@ -28,11 +34,12 @@ pub fn transform_for_n_statement<'a>(
     let mut while_body = OxcVec::with_capacity_in(2, allocator);
     while_body.push(body);
 
-    // Add update expression if it exists
+    // Add update expression if it exists. It maps back to the update in the for-header.
     if let Some(update) = update {
+        let update_span = update.span();
         while_body.push(Statement::ExpressionStatement(OxcBox(allocator.alloc(ExpressionStatement {
             expression: update,
-            span,
+            span: update_span,
         }))));
     }
 
@@ -45,20 +52,21 @@ pub fn transform_for_n_statement<'a>(
         span,
     })));
 
-    // If there's an initializer, create a block containing it and the while loop
+    // If there's an initializer, create a block containing it and the while loop. A `let` or `const`
+    // must stay scoped to that block, anything else can be spliced into the parent.
     if let Some(init) = init {
+        let action = if init.is_lexical_declaration() { MapperAction::Revisit } else { MapperAction::Splice };
         let mut block_body = OxcVec::with_capacity_in(2, allocator);
 
         match init {
             ForStatementInit::Expression(expr) => {
+                let init_span = expr.span();
                 block_body.push(Statement::ExpressionStatement(OxcBox(allocator.alloc(ExpressionStatement {
                     expression: expr,
-                    span,
+                    span: init_span,
                 }))));
             },
-            ForStatementInit::UsingDeclaration(_) => {
-                panic!("The `using` syntax is not supported by this tool");
-            },
+            ForStatementInit::UsingDeclaration(_) => unreachable!("checked above"),
             ForStatementInit::VariableDeclaration(decl) => {
                 block_body.push(Statement::Declaration(Declaration::VariableDeclaration(decl)));
             },
@@ -66,7 +74,7 @@ pub fn transform_for_n_statement<'a>(
 
         block_body.push(while_stmt);
 
-        (MapperAction::Revisit, Statement::BlockStatement(OxcBox(allocator.alloc(BlockStatement {
+        (action, Statement::BlockStatement(OxcBox(allocator.alloc(BlockStatement {
             body: block_body,
             span,
         }))))
@@ -7,15 +7,16 @@ use oxc_ast::ast::*;
 use oxc_allocator::Allocator;
 use oxc_span::Atom;
 use oxc_span::GetSpan;
+use oxc_span::Span;
 use oxc_syntax::operator::AssignmentOperator;
 use oxc_syntax::operator::BinaryOperator;
 
-use crate::mapper::create_mapper;
+use crate::diagnostics::DiagnosticCode;
+use crate::mapper::with_sub_mapper;
 use crate::mapper_state::MapperState;
 use crate::mapper::MapperAction;
 use crate::transforms::builder::*;
-use crate::utils::example;
-use crate::utils::rule;
+use crate::trace::RuleId;
 
 /// Transform a switch statement into an if-else chain
 /// For the simple case where each case is a block you can store the discriminant in a temp var and then use that to build the if-else chain.
@@ -53,38 +54,31 @@ pub fn transform_switch_statement<'a>(
     state: &mut MapperState
 ) -> (MapperAction, Statement<'a>) {
 
-    rule("Eliminate switch statement in favor of if-else chain");
-    example(
-        "switch (test) { case a: x; break; case b: y; default: z; }",
-        "let result = 3; if (test === a) { result = 1; } else if (test === b) { result = 2; } else { result = 3; } root: { if (result <= 0) { x; break root; } if (result <= 1) { y; } if (result <= 2) { z; } }",
-    );
+    // See `RuleId::Switch` for an example.
+
+    // Step 0: Bail on toplevel declarations that we can't hoist out of the switch (yet)
+
+    if let Some((message, span)) = find_unsupported_case_declaration(&switch_stmt) {
+        state.report(DiagnosticCode::UnsupportedTransform, message.to_string(), span);
+        return (MapperAction::Normal, Statement::SwitchStatement(OxcBox(allocator.alloc(switch_stmt))));
+    }
+
+    state.record_rule(RuleId::Switch, switch_stmt.span);
 
     // Step 1: Transform unlabeled breaks that target this switch to labeled breaks
 
-    // Note: this is a dirty hack and I hate it but I'm passing on the id_counter from this mapper
-    //       so we can use it in a sub-mapper because I can't seem to mold Rust into letting me
-    //       pass a mutable reference to a sub-mapper's state instead. It just doesn't like the
-    //       mix of lifetimes and closures. So instead we pass on the id_counter + 1 to the sub-mapper
-    //       and return whether we've used it at all. If we've used it then we increment the id_counter
-    //       here too by generating the label, indicentally that's also the label that needs to wrap
-    //       the switch statement now. Double hack. Yaaay..
-    let (needs_label, switch_stmt) = update_breaks(switch_stmt, state.id_counter, allocator);
-    let switch_label = if needs_label {
-        // This bumps the id_counter in the "root mapper" state. Not multi thread safe but we don't care about that here. (Sorry, future me)
-        state.next_ident_name()
-    } else {
-        "".to_string()
-    };
+    let (switch_label, switch_stmt) = update_breaks(switch_stmt, allocator, state);
 
     // Move on with the other steps
 
     let SwitchStatement { discriminant, cases, span: switch_span } = switch_stmt;
 
     // Create a temp var to store the discriminant value
-    let discriminant_var_name = state.next_ident_name();
+    let discriminant_var_name = state.next_var_name(switch_span);
     let discriminant_span = discriminant.span();
-    let discriminant_var_decl = create_variable_declaration_const(
+    let discriminant_var_decl = create_variable_declaration_kind(
         allocator,
+        state.local_temp_var_kind(VariableDeclarationKind::Const),
         discriminant_var_name.clone(),
         Some(discriminant),
         discriminant_span // requires GetSpan import
@@ -110,75 +104,75 @@ pub fn transform_switch_statement<'a>(
     let cases = cases.into_iter().map(|case| {
         let SwitchCase { test, consequent, span } = case;
 
-        let consequent: OxcVec<Statement<'a>> = OxcVec::from_iter_in(consequent.into_iter().map(|stmt| {
-            if let Statement::Declaration(decl) = stmt {
-                match decl {
-                    Declaration::VariableDeclaration(var_decl) => {
-                        let VariableDeclaration { kind: _kind, declarations, span: _var_decl_span, modifiers: _modifiers } = var_decl.unbox();
-                        assert!(declarations.len() == 1, "Var decls should be split apart by an earlier pass above");
-                        let decl = declarations.into_iter().next().unwrap();
-                        let VariableDeclarator { id, init, span: var_decr_span, kind: _kind, definite: _definite } = decl;
-                        let BindingPatternKind::BindingIdentifier(id) = id.kind else {
-                            panic!("Var decl ids should be forced to be idents by an earlier pass above");
-                        };
-                        let BindingIdentifier { name, span: id_span, symbol_id: _symbol_id } = id.unbox();
-                        // Change `let x = y` and `const x = y` to `x = y` and remember the id. We'll move the actual decl to before the switch.
-                        // This may prevent some TDZ cases from throwing. An acceptable risk?
-
-                        names_to_predeclare.push(name.to_string());
-
-                        create_expression_statement(
+        let mut new_consequent: OxcVec<Statement<'a>> = OxcVec::with_capacity_in(consequent.len(), allocator);
+        for stmt in consequent {
+            if let Statement::Declaration(Declaration::VariableDeclaration(var_decl)) = stmt {
+                let VariableDeclaration { kind: _kind, declarations, span: _var_decl_span, modifiers: _modifiers } = var_decl.unbox();
+                // Case bodies are not blocks so the var decl transform may not have split these up.
+                for decl in declarations {
+                    let VariableDeclarator { id, init, span: var_decr_span, kind: _kind, definite: _definite } = decl;
+                    let BindingPatternKind::BindingIdentifier(id) = id.kind else {
+                        unreachable!("Patterns were checked by find_unsupported_case_declaration");
+                    };
+                    let BindingIdentifier { name, span: id_span, symbol_id: _symbol_id } = id.unbox();
+                    // Change `let x = y` and `const x = y` to `x = y` and remember the id. We'll move the actual decl to before the switch.
+                    // This may prevent some TDZ cases from throwing. An acceptable risk?
+
+                    names_to_predeclare.push(name.to_string());
+
+                    new_consequent.push(create_expression_statement(
+                        allocator,
+                        create_assignment_expression(
                             allocator,
-                            create_assignment_expression(
-                                allocator,
-                                AssignmentOperator::Assign,
-                                create_identifier_reference(name.to_string(), id_span),
-                                init.unwrap_or(create_identifier_expression(allocator, "undefined".to_string(),  var_decr_span)),
-                                var_decr_span
-                            ),
+                            AssignmentOperator::Assign,
+                            create_identifier_reference(name.to_string(), id_span),
+                            init.unwrap_or(create_identifier_expression(allocator, "undefined".to_string(),  var_decr_span)),
                             var_decr_span
-                        )
-                    }
-                    Declaration::ClassDeclaration(_cls_decl) => {
-                        todo!("Class decls should be transformed to assignments");
-                    }
-
-                    _ => Statement::Declaration(decl)
+                        ),
+                        var_decr_span
+                    ));
                 }
             } else {
-                stmt
+                new_consequent.push(stmt);
             }
-        }), allocator);
+        }
+        let consequent = new_consequent;
 
         SwitchCase { test, consequent, span }
     });
 
-    // Since in Rust we can't take the test out without taking the body we have to unzip them first
+    // Since in Rust we can't take the test out without taking the body we have to unzip them first.
+    // The case spans are kept so the generated code for each case maps back to that case.
     let mut tests = OxcVec::new_in(allocator);
     let mut consequents = OxcVec::new_in(allocator);
+    let mut case_spans = vec!();
 
     for case in cases {
-        let SwitchCase { test, consequent, span: _span } = case;
+        let SwitchCase { test, consequent, span } = case;
         tests.push(test);
         consequents.push(consequent);
+        case_spans.push(span);
     }
 
-    let switch_test_outcome_var = state.next_ident_name();
+    let switch_test_outcome_var = state.next_var_name(switch_span);
 
     let new_body = OxcVec::from_iter_in(
 
+        // The let/const decls from step 2, without init
+        names_to_predeclare.iter().map(|name| create_variable_declaration_let(allocator, name.clone(), None, switch_span)).collect::<Vec<_>>()
+        .into_iter()
+
         // This var holds the result of matching the switch discriminant to the case tests
-        vec!(
+        .chain(vec!(
             create_variable_declaration_let(
                 allocator,
                 switch_test_outcome_var.clone(),
                 // Init to total number of tests. Our JS code will check if result < current index.
                 // If there is no default and all cases miss then it shouldn't match any branch.
-                Some(create_number_literal(allocator, tests.len() as f64, allocator.alloc(tests.len().to_string()), switch_span)),
-                switch_span
+                Some(create_number_literal(allocator, tests.len() as f64, allocator.alloc_str(&tests.len().to_string()), discriminant_span)),
+                discriminant_span
             )
-        )
-        .into_iter()
+        ))
 
         // Step 3: Create the if-else chain of case tests
 
@@ -233,20 +227,17 @@ pub fn transform_switch_statement<'a>(
             // the final "else {}" of the if-else chain we're building next will assign the default's
             // case index to the result var. That's when no other case test matches.
             let default_index = tests.iter().position(|test| test.is_none());
-            let tail_default_case = match default_index {
-                Some(default_index) => Some(create_expression_statement(
+            let tail_default_case = default_index.map(|default_index| create_expression_statement(
                     allocator,
                     create_assignment_expression(
                         allocator,
                         AssignmentOperator::Assign,
-                        create_identifier_reference(switch_test_outcome_var.clone(), switch_span),
-                        create_number_literal(allocator, default_index as f64, allocator.alloc(default_index.to_string()), switch_span),
-                        switch_span
+                        create_identifier_reference(switch_test_outcome_var.clone(), case_spans[default_index]),
+                        create_number_literal(allocator, default_index as f64, allocator.alloc_str(&default_index.to_string()), case_spans[default_index]),
+                        case_spans[default_index]
                     ),
-                    switch_span
-                )),
-                None => None
-            };
+                    case_spans[default_index]
+                ));
             // Now build the if-else chain, the final else being either the default if it exists or none.
             tests.into_iter().enumerate().rev().fold(tail_default_case, |prev_if, (i, test)| {
                 if let Some(test) = test {
@@ -265,11 +256,11 @@ pub fn transform_switch_statement<'a>(
                             create_assignment_expression(
                                 allocator,
                                 AssignmentOperator::Assign,
-                                create_identifier_reference(switch_test_outcome_var.clone(), switch_span),
-                                create_number_literal(allocator, i as f64, allocator.alloc(i.to_string()), switch_span),
-                                switch_span
+                                create_identifier_reference(switch_test_outcome_var.clone(), test_span),
+                                create_number_literal(allocator, i as f64, allocator.alloc_str(&i.to_string()), test_span),
+                                test_span
                             ),
-                            switch_span
+                            test_span
                         ),
                         prev_if,
                         test_span
@@ -286,19 +277,19 @@ pub fn transform_switch_statement<'a>(
         // It does this by checking each-if whether the result is smaller than or equal the case index.
 
         .chain(
-            consequents.into_iter().enumerate().map(|(i, stmt)| {
+            consequents.into_iter().zip(case_spans.iter()).enumerate().map(|(i, (stmt, &case_span))| {
                 create_if_statement(
                     allocator,
                     create_binary_expression(
                         allocator,
                         BinaryOperator::LessEqualThan,
-                        create_identifier_expression(allocator, switch_test_outcome_var.clone(), switch_span),
-                        create_number_literal(allocator, i as f64, allocator.alloc(i.to_string()), switch_span),
-                        switch_span
+                        create_identifier_expression(allocator, switch_test_outcome_var.clone(), case_span),
+                        create_number_literal(allocator, i as f64, allocator.alloc_str(&i.to_string()), case_span),
+                        case_span
                     ),
-                    create_block_statement(allocator, stmt, switch_span),
+                    create_block_statement(allocator, stmt, case_span),
                     None,
-                    switch_span
+                    case_span
                 )
             })
         )
@@ -314,75 +305,88 @@ pub fn transform_switch_statement<'a>(
     let new_block = create_block_statement(allocator, new_body, switch_span);
 
     // If we transformed at least one `break` then we need to wrap this block in that label as well.
-    if needs_label {
+    // The predeclared names were scoped to the switch body so they must stay in a block.
+    if let Some(switch_label) = switch_label {
         (MapperAction::Revisit, create_labeled_statement(allocator, switch_label, new_block, switch_span))
-    } else {
+    } else if !names_to_predeclare.is_empty() {
         (MapperAction::Revisit, new_block)
+    } else if state.in_script_top_level() {
+        // The outcome var would end up in the scope that all scripts share
+        (MapperAction::Revisit, new_block)
+    } else {
+        (MapperAction::Splice, new_block)
     }
 }
 
-fn update_breaks<'a>(stmt: SwitchStatement<'a>, next_state_index: usize, allocator: &'a Allocator) -> (bool, SwitchStatement<'a>) {
-    let mut mapper = create_mapper(allocator);
-    let state = mapper.state.clone();
-    state.borrow_mut().id_counter = next_state_index;
-
-    let has_breaks = Rc::new(RefCell::new(false));
-    let has_breaks_closure = Rc::clone(&has_breaks);
-    let break_label_name = Rc::new(RefCell::new("".to_string()));
-
-    mapper.add_visitor_stmt(move |stmt: Statement<'a>, alloc, before: bool| {
-        if !before { return (MapperAction::Normal, stmt); }
-
-        match stmt {
-            Statement::BreakStatement(break_stmt) => {
-                if break_stmt.label.is_none() {
-                    if !*has_breaks_closure.borrow() {
-                        *break_label_name.borrow_mut() = state.borrow_mut().next_ident_name();
-                        *has_breaks_closure.borrow_mut() = true;
-                    }
+/// Class declarations and destructuring var decls in the toplevel of a case can't be hoisted out of the switch yet.
+fn find_unsupported_case_declaration(switch_stmt: &SwitchStatement) -> Option<(&'static str, Span)> {
+    switch_stmt.cases.iter().flat_map(|case| case.consequent.iter()).find_map(|stmt| match stmt {
+        Statement::Declaration(Declaration::ClassDeclaration(cls_decl)) => {
+            Some(("Class declarations in the toplevel of a switch case are not supported", cls_decl.span))
+        }
+        Statement::Declaration(Declaration::VariableDeclaration(var_decl)) => {
+            var_decl.declarations.iter()
+            .find(|decr| !matches!(decr.id.kind, BindingPatternKind::BindingIdentifier(_)))
+            .map(|decr| ("Destructuring declarations in the toplevel of a switch case are not supported", decr.span))
+        }
+        _ => None,
+    })
+}
 
-                    (MapperAction::Normal, Statement::BreakStatement(OxcBox(alloc.alloc(BreakStatement {
-                        label: Some(LabelIdentifier {
-                            name: Atom::from(break_label_name.borrow().clone()),
+/// Label the unlabeled breaks that target this switch. The label is generated when the first one is
+/// found and returned, so it's None when there were none.
+fn update_breaks<'a>(stmt: SwitchStatement<'a>, allocator: &'a Allocator, state: &mut MapperState) -> (Option<String>, SwitchStatement<'a>) {
+    with_sub_mapper(allocator, state, "switch breaks", |mapper| {
+        let label: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+        let label_closure = Rc::clone(&label);
+        let state = Rc::clone(&mapper.state);
+
+        mapper.add_visitor_stmt(move |stmt: Statement<'a>, alloc, before: bool| {
+            if !before { return (MapperAction::Normal, stmt); }
+
+            match stmt {
+                Statement::BreakStatement(break_stmt) => {
+                    if break_stmt.label.is_none() {
+                        let name = label_closure.borrow_mut().get_or_insert_with(|| state.borrow_mut().next_ident_name()).clone();
+
+                        (MapperAction::Normal, Statement::BreakStatement(OxcBox(alloc.alloc(BreakStatement {
+                            label: Some(LabelIdentifier {
+                                name: Atom::from(name),
+                                span: break_stmt.span
+                            }),
                             span: break_stmt.span
-                        }),
-                        span: break_stmt.span
-                    }))))
-                } else {
-                    (MapperAction::Normal, Statement::BreakStatement(break_stmt))
+                        }))))
+                    } else {
+                        (MapperAction::Normal, Statement::BreakStatement(break_stmt))
+                    }
                 }
-            }
 
-            // Do not enter any break boundaries.
-            // This should be on the way up (of the root mapper) and so all children of this
-            // switch should have been processed, meaning we should be able to target just
-            // the handful of statements left.
-            // Due to a limitation of the oxc mapper, we can't detect function bodies here
-            // so we just have to visit them. Worse for perf but should be okay because
-            // any breaks in there must syntactically be scoped to a statement inside
-            // that function and we wouldn't enter that statement at all.
-
-            | Statement::IfStatement(_)
-            | Statement::WhileStatement(_)
-            | Statement::TryStatement(_)
-            | Statement::Declaration(Declaration::FunctionDeclaration(_))
-            => (MapperAction::Skip, stmt),
-
-            // Sanity check: report if we are still finding certain statements because it breaks our assumptions
-            | Statement::SwitchStatement(_)
-            | Statement::ContinueStatement(_)
-            | Statement::DoWhileStatement(_)
-            | Statement::ForStatement(_)
-            | Statement::ForInStatement(_)
-            | Statement::ForOfStatement(_)
-            => panic!("This statement should have been eliminated already before exiting the parent block: {:?}", stmt),
-
-            _ => (MapperAction::Normal, stmt)
-        }
-    });
+                // Do not enter any break boundaries.
+                // Loops may still be here when their transform is disabled. Any unlabeled break inside of
+                // them targets that loop, not our switch. Same for nested switches that were left alone.
+                // Continue statements are fine. They can't target the switch and a continue inside of
+                // a labeled block is still valid.
+                // Due to a limitation of the oxc mapper, we can't detect function bodies here
+                // so we just have to visit them. Worse for perf but should be okay because
+                // any breaks in there must syntactically be scoped to a statement inside
+                // that function and we wouldn't enter that statement at all.
+
+                | Statement::WhileStatement(_)
+                | Statement::DoWhileStatement(_)
+                | Statement::ForStatement(_)
+                | Statement::ForInStatement(_)
+                | Statement::ForOfStatement(_)
+                | Statement::SwitchStatement(_)
+                | Statement::Declaration(Declaration::FunctionDeclaration(_))
+                => (MapperAction::Skip, stmt),
+
+                _ => (MapperAction::Normal, stmt)
+            }
+        });
 
-    let mapped_stmt = mapper.map_switch_statement(stmt);
+        let mapped_stmt = mapper.map_switch_statement(stmt);
 
-    let had_breaks = *has_breaks.borrow();
-    (had_breaks, mapped_stmt)
+        let label = label.borrow_mut().take();
+        (label, mapped_stmt)
+    })
 }
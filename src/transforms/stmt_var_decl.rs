@@ -7,8 +7,11 @@ use oxc_span::Span;
 use oxc_syntax::operator::AssignmentOperator;
 use oxc_syntax::operator::BinaryOperator;
 use oxc_span::GetSpan;
+use oxc_ast::syntax_directed_operations::BoundNames;
 
+use crate::diagnostics::DiagnosticCode;
 use crate::log;
+use crate::helpers::Helper;
 use crate::mapper_state::MapperState;
 use crate::mapper::MapperAction;
 use crate::transforms::builder::create_assignment_expression;
@@ -18,12 +21,12 @@ use crate::transforms::builder::create_if_statement;
 use crate::transforms::builder::create_member_expression;
 use crate::transforms::builder::create_member_expression_computed;
 use crate::transforms::builder::create_variable_declarator_pattern;
-use crate::utils::example;
-use crate::utils::rule;
+use crate::trace::RuleId;
 use super::builder::create_array_expression;
 use super::builder::create_call_expression;
 use super::builder::create_member_expression_computed_ident;
 use super::builder::create_number_literal_str;
+use super::builder::create_export_named_statement;
 use super::builder::create_expression_statement;
 use super::builder::create_identifier_expression;
 use super::builder::create_string_literal;
@@ -41,53 +44,42 @@ enum Changed {
 /// - decls with patterns -> var decls must be idents, patterns assignments on the next line
 /// - decls without init -> init to undefined
 ///
-/// Since we need to inject multiple statements, we receive the block so we can replace the
-/// var decl with multiple statements. As such, we first need to scan to see if there are
-/// any var decls to transform in the first place. Otherwise return the same block.
+/// Since we need to inject multiple statements, the result is spliced into the parent
+/// statement list. Statements that are not var decls, or already in shape, are returned as is.
+///
+/// Exported var decls (`export let {a, b} = c;`) become the transformed decls followed by an
+/// `export {a, b};`. The temporary vars are not exported that way.
 ///
 pub fn transform_var_decl_statement<'a>(
-    block: BlockStatement<'a>,
+    stmt: Statement<'a>,
     allocator: &'a Allocator,
     state: &mut MapperState
 ) -> (MapperAction, Statement<'a>) {
-    if confirm_var_decl_shape(&block.body) {
-        // log!("  - already fine, noop");
-        return (MapperAction::Normal, Statement::BlockStatement(OxcBox(allocator.alloc(block))));
-    }
-    log!("- transform_var_decl_statement");
+    let span = stmt.span();
+    let mut new_body = OxcVec::new_in(allocator);
 
+    let changed = match stmt {
+        Statement::Declaration(Declaration::VariableDeclaration(var_decl)) if !is_var_decl_in_shape(&var_decl) => {
+            log!(Debug, "- transform_var_decl_statement");
+            transform_var_decl_any_decr(var_decl.unbox(), &mut new_body, allocator, state)
+        }
+        Statement::ModuleDeclaration(module_decl) if exported_var_decl(&module_decl).is_some_and(|var_decl| !is_var_decl_in_shape(var_decl)) => {
+            log!(Debug, "- transform_var_decl_statement (exported)");
+            transform_exported_var_decl(module_decl.unbox(), &mut new_body, allocator, state)
+        }
+        // Already fine, noop
+        stmt => return (MapperAction::Normal, stmt),
+    };
 
-    let BlockStatement { body, span } = block;
-
-    let mut new_body = OxcVec::with_capacity_in(body.len(), allocator);
+    log!(Debug, "  - transform_var_decl_statement changed: {}", changed == Changed::Yes);
 
-    let mut revisit = false;
-    for stmt in body {
-        match stmt {
-            Statement::Declaration(decl) => {
-                match decl {
-                    Declaration::VariableDeclaration(var_decl) => {
-                        let changed = transform_var_decl_any_decr(var_decl.unbox(), &mut new_body, allocator, state);
-                        if changed == Changed::Yes {
-                            revisit = true;
-                        }
-                    }
-                    _ => new_body.push(Statement::Declaration(decl)),
-                }
-            }
-            _ => new_body.push(stmt),
-        }
+    // Declarations that could not be transformed (like `let x;`) come back as a single statement
+    if changed == Changed::No && new_body.len() == 1 {
+        return (MapperAction::Normal, new_body.pop().unwrap());
     }
 
-    log!("  - transform_var_decl_statement revisit: {}", revisit);
-
-    // Return the block containing everything
     (
-        if revisit {
-            MapperAction::Revisit
-        } else {
-            MapperAction::Normal
-        },
+        MapperAction::Splice,
         Statement::BlockStatement(OxcBox(allocator.alloc(BlockStatement {
             body: new_body,
             span,
@@ -97,13 +89,13 @@ pub fn transform_var_decl_statement<'a>(
 
 fn transform_var_decl_any_decr<'a>(var_decl: VariableDeclaration<'a>, new_body: &mut OxcVec<'a, Statement<'a>>, allocator: &'a Allocator, state: &mut MapperState) -> Changed {
     let VariableDeclaration { kind, declarations, span, modifiers: _modifiers } = var_decl;
-    log!("- transform_var_decl");
+    log!(Debug, "- transform_var_decl");
 
     // First split a multi-decr into a single-decr decl
 
+    // `let x = a, y = b;` -> `let x = a; let y = b;`
     if declarations.len() > 1 {
-        rule("Transform var decl with multiple declarators into multiple var decls with a single declarator");
-        example("let x = a, y = b;", "let x = a; let y = b;");
+        state.record_rule(RuleId::VarDeclDeclarators, span);
     }
 
     let decrs = declarations.into_iter().map(|decl| {
@@ -124,7 +116,7 @@ fn transform_var_decl_any_decr<'a>(var_decl: VariableDeclaration<'a>, new_body:
     // Process each decl, new or old, and convert patterns into multiple steps
 
     decrs.into_iter().for_each(|decl| {
-        log!("  - loop");
+        log!(Debug, "  - loop");
         let VariableDeclaration { kind: _kind, declarations, span: _span, modifiers: _modifiers } = decl;
         assert!(declarations.len() == 1, "caller should make sure each var decl has one decr");
         let decr = declarations.into_iter().next().unwrap();
@@ -136,53 +128,136 @@ fn transform_var_decl_any_decr<'a>(var_decl: VariableDeclaration<'a>, new_body:
     changed
 }
 
-fn confirm_var_decl_shape<'a>(body: &OxcVec<Statement<'a>>) -> bool {
-    body.iter().all(|stmt| {
-        // log!("- checking stmt: {:?}", stmt);
-        match stmt {
-            Statement::Declaration(decl) => {
-                // log!("- found var decl");
-                match &decl {
-                    Declaration::VariableDeclaration(var_decl) => {
-                        log!("- ## confirm_var_decl_shape");
-
-                        let declarations = &var_decl.declarations;
-                        if declarations.len() != 1 {
-                            log!("  - ## bad: multi var");
-                            return false; // Bad because we must transform multi vars to one var per decl
-                        }
+/// `export let ...`, `export var ...` or `export const ...`
+fn exported_var_decl<'b, 'a>(module_decl: &'b ModuleDeclaration<'a>) -> Option<&'b VariableDeclaration<'a>> {
+    match module_decl {
+        ModuleDeclaration::ExportNamedDeclaration(export_decl) => match &export_decl.declaration {
+            Some(Declaration::VariableDeclaration(var_decl)) => Some(var_decl),
+            _ => None,
+        },
+        _ => None,
+    }
+}
 
-                        return declarations.iter().any(|decl| {
-                            if decl.init.is_none() {
-                                log!("  - ## bad: missing init");
-                                return false; // Bad because we must transform missing init to init to undefined
-                            }
+/// Replace `export <var decl>` with the transformed var decl. If that's still one decl it's exported
+/// as is. Otherwise the decls are followed by an `export {...}` of the names that the original bound.
+fn transform_exported_var_decl<'a>(module_decl: ModuleDeclaration<'a>, new_body: &mut OxcVec<'a, Statement<'a>>, allocator: &'a Allocator, state: &mut MapperState) -> Changed {
+    let ModuleDeclaration::ExportNamedDeclaration(export_decl) = module_decl else { unreachable!("checked by caller") };
+    let ExportNamedDeclaration { span, declaration, specifiers, source, export_kind } = export_decl.unbox();
+    let Some(Declaration::VariableDeclaration(var_decl)) = declaration else { unreachable!("checked by caller") };
 
-                            return match decl.id.kind {
-                                BindingPatternKind::BindingIdentifier(_) => {
-                                    log!("  - ## ok: ident decl");
-                                    true // Ok because this is our target so we should be good now
-                                },
-                                _ => {
-                                    log!("  - ## bad: no ident decl");
-                                    false // Bad because we need to transform patterns to assignments on the next line
-                                }
-                            };
-                        });
-                    }
-                    _ => {
-                        true // Ok because it's not a var decl at all so not our concern here
-                    }
+    let mut names = vec!();
+    var_decl.bound_names(&mut |ident| names.push(ident.name.to_string()));
+
+    let mut decl_body = OxcVec::new_in(allocator);
+    let changed = transform_var_decl_any_decr(var_decl.unbox(), &mut decl_body, allocator, state);
+
+    if decl_body.len() == 1 && matches!(decl_body[0], Statement::Declaration(Declaration::VariableDeclaration(_))) {
+        let Some(Statement::Declaration(declaration)) = decl_body.pop() else { unreachable!("checked above") };
+        new_body.push(Statement::ModuleDeclaration(OxcBox(allocator.alloc(ModuleDeclaration::ExportNamedDeclaration(OxcBox(allocator.alloc(ExportNamedDeclaration {
+            span,
+            declaration: Some(declaration),
+            specifiers,
+            source,
+            export_kind,
+        })))))));
+    } else {
+        new_body.extend(decl_body);
+        new_body.push(create_export_named_statement(allocator, names, span));
+    }
+
+    changed
+}
+
+fn is_var_decl_in_shape(var_decl: &VariableDeclaration) -> bool {
+    log!(Debug, "- ## confirm_var_decl_shape");
+
+    let declarations = &var_decl.declarations;
+    if declarations.len() != 1 {
+        log!(Debug, "  - ## bad: multi var");
+        return false; // Bad because we must transform multi vars to one var per decl
+    }
+
+    declarations.iter().any(|decl| {
+        if decl.init.is_none() {
+            log!(Debug, "  - ## bad: missing init");
+            return false; // Bad because we must transform missing init to init to undefined
+        }
+
+        match decl.id.kind {
+            BindingPatternKind::BindingIdentifier(_) => {
+                log!(Debug, "  - ## ok: ident decl");
+                true // Ok because this is our target so we should be good now
+            },
+            _ => {
+                log!(Debug, "  - ## bad: no ident decl");
+                false // Bad because we need to transform patterns to assignments on the next line
+            }
+        }
+    })
+}
+
+/// The first part of this pattern that the transform can't deal with, if any. Only an array pattern
+/// as the rest of an array pattern is valid syntax (`let [...[a]] = y`). The parser lets a default on a
+/// rest through (`let [...a = 1] = y`) and the others can only come from a hand-built AST.
+fn unsupported_pattern(pattern: &BindingPattern) -> Option<(&'static str, Span)> {
+    match &pattern.kind {
+        BindingPatternKind::BindingIdentifier(_) => None,
+        BindingPatternKind::AssignmentPattern(assignment_pattern) => match &assignment_pattern.left.kind {
+            BindingPatternKind::AssignmentPattern(_) => Some(("Default of a default in a var declarator pattern is not supported", assignment_pattern.span)),
+            _ => unsupported_pattern(&assignment_pattern.left),
+        },
+        BindingPatternKind::ObjectPattern(object_pattern) => {
+            for prop in object_pattern.properties.iter() {
+                match &prop.key {
+                    PropertyKey::PrivateIdentifier(_) => return Some(("Private name as a key in a var declarator pattern is not supported", prop.span)),
+                    PropertyKey::Identifier(_) if prop.computed => return Some(("Computed key without expression in a var declarator pattern is not supported", prop.span)),
+                    _ => {}
+                }
+                if let Some(unsupported) = unsupported_pattern(&prop.value) {
+                    return Some(unsupported);
                 }
             }
-            _ => {
-                true // Ok because it's not a var decl at all so not our concern here
+            match &object_pattern.rest {
+                Some(rest) if !matches!(rest.argument.kind, BindingPatternKind::BindingIdentifier(_)) => Some(("Pattern as the rest of an object pattern in a var declarator is not supported", rest.span)),
+                _ => None,
+            }
         }
-    }})
+        BindingPatternKind::ArrayPattern(array_pattern) => {
+            for element in array_pattern.elements.iter().flatten() {
+                if let Some(unsupported) = unsupported_pattern(element) {
+                    return Some(unsupported);
+                }
+            }
+            match &array_pattern.rest {
+                Some(rest) => match &rest.argument.kind {
+                    BindingPatternKind::BindingIdentifier(_) => None,
+                    BindingPatternKind::ObjectPattern(_) => unsupported_pattern(&rest.argument),
+                    BindingPatternKind::ArrayPattern(_) => Some(("Array pattern as the rest of an array pattern in a var declarator is not supported", rest.span)),
+                    BindingPatternKind::AssignmentPattern(_) => Some(("Default on the rest of an array pattern in a var declarator is not supported", rest.span)),
+                },
+                None => None,
+            }
+        }
+    }
 }
 
 /// Transforms one var declarator and adds statements to the new body as necessary to deconstruct it.
 fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut OxcVec<Statement<'a>>, allocator: &'a Allocator, state: &mut MapperState) -> Changed {
+    // The parser should never give us most of these but if it does, report it and keep the declarator as is.
+    // The pattern is checked as a whole so we never bail halfway through it.
+    let unsupported = match (&declr.id.kind, &declr.init) {
+        (BindingPatternKind::AssignmentPattern(_), _) => Some(("Assignment pattern as the id of a var declarator is not supported", declr.span)),
+        (BindingPatternKind::ObjectPattern(_) | BindingPatternKind::ArrayPattern(_), None) => Some(("Destructuring var declarator without init is not supported", declr.span)),
+        _ => unsupported_pattern(&declr.id),
+    };
+    if let Some((message, message_span)) = unsupported {
+        state.report(DiagnosticCode::UnsupportedTransform, message.to_string(), message_span);
+        let span = declr.span;
+        new_body.push(create_variable_declaration_kind_declr(allocator, declr.kind, declr, span));
+        return Changed::No;
+    }
+
     let VariableDeclarator { id, init, span: decr_span, kind: decl_kind, definite: _definite } = declr;
     let BindingPattern { kind: id_kind, type_annotation: _type_annotation, optional: _optional } = id;
     match id_kind {
@@ -192,12 +267,9 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
 
             Changed::No
         }
-        BindingPatternKind::AssignmentPattern(_assignment_pattern) => {
-            todo!("What code leads to a BindingPatternKind::AssignmentPattern as id of a VariableDeclarator?");
-        }
+        BindingPatternKind::AssignmentPattern(_assignment_pattern) => unreachable!("checked above"),
         BindingPatternKind::ObjectPattern(object_pattern) => {
-            rule("Transform var decl binding pattern to var decls without binding pattern");
-            example("let {x} = a;", "let x = a.x;");
+            state.record_rule(RuleId::VarDeclObjectPattern, object_pattern.span);
 
             // `let {a} = y` -> `let a = y.a`
             // `let {a: b} = y` -> `let b = y.a`
@@ -209,7 +281,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
             // Break down the properties into a separate list of var decls, one per prop. See examples above.
             // If there's a rest prop then it also needs its own var decl and assignment. It's a special case with no trivial workaround.
 
-            let Some(init) = init else { panic!("Pattern var decls are syntactically required to have an init... so where is it"); };
+            let Some(init) = init else { unreachable!("checked above"); };
             let rhs_span = init.span();
             let rhs: String = if let Expression::Identifier(ident) = init {
                 // This is fine, don't mess with it
@@ -217,8 +289,8 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
             } else {
                 // We need to assign the rhs to a tmp var otherwise we risk introducing observable side effects when deconstructing multiple props.
                 // `let {a,b} = stuff()` -> `let tmp = stuff(); let {a,b} = tmp` (just the stuff() call part)
-                let tmp_var_name = state.next_ident_name();
-                new_body.push(create_variable_declaration_kind(allocator, VariableDeclarationKind::Let, tmp_var_name.clone(), Some(init), decr_pattern_span));
+                let tmp_var_name = state.next_var_name(decr_pattern_span);
+                new_body.push(create_variable_declaration_kind(allocator, state.temp_var_kind(), tmp_var_name.clone(), Some(init), decr_pattern_span));
                 // This is now an ident expression to the original (complex) init
                 create_identifier_expression(allocator, tmp_var_name.to_string(), decr_pattern_span);
                 tmp_var_name
@@ -247,13 +319,16 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
                         // (The name is used in both loops)
                         match expr {
                             Expression::Identifier(ident) => (ident.name.to_string(), *computed),
-                            _ => (state.next_ident_name(), *computed),
+                            _ => (state.next_var_name(expr.span()), *computed),
                         }
                     }
-                    PropertyKey::PrivateIdentifier(_) => panic!("What code leads here? It shouldn't be ident or expr and private prop is not valid syntax: {:?}", prop_lhs_key)
+                    PropertyKey::PrivateIdentifier(_) => unreachable!("checked by unsupported_pattern"),
                 }
             }).collect();
 
+            // The aliases of computed keys are never reassigned so they are constants, unless they end up in the global scope of a script
+            let key_alias_kind = if state.in_script_top_level() { VariableDeclarationKind::Var } else { state.local_temp_var_kind(VariableDeclarationKind::Const) };
+
             // First process regular props. Order is observable. Rest comes after.
             properties.into_iter().enumerate().for_each(|(prop_index, prop)| {
                 let BindingProperty { key: prop_lhs_key_only_used_for_computed, value, span: prop_span, shorthand: _shorthand, computed } = prop;
@@ -263,9 +338,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
                 let new_key_name = final_prop_names[prop_index].0.clone();
 
                 if computed {
-                    let PropertyKey::Expression(expr) = prop_lhs_key_only_used_for_computed else {
-                        panic!("What code leads here? It should be an Expression for computed keys: {:?}", prop_lhs_key_only_used_for_computed);
-                    };
+                    let PropertyKey::Expression(expr) = prop_lhs_key_only_used_for_computed else { unreachable!("checked by unsupported_pattern") };
 
                     match expr {
                         // Do not alias an ident again.
@@ -274,7 +347,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
                             // `let {[f()]: x} = y` -> `const $tmp = f(); let x = y[$tmp]`
                             new_body.push(create_variable_declaration_kind(
                                 allocator,
-                                VariableDeclarationKind::Const,
+                                key_alias_kind,
                                 new_key_name.clone(),
                                 Some(expr),
                                 prop_span
@@ -345,7 +418,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
                                 // This would have to be something like `let {a = 1 = 1} = y` where the `= 1 = 1` is a
                                 // double default, except it is not possible because it'll just be the expression
                                 // `1 = 1`, which would not be valid (but `= a = b` would lead to assignment `a=b` all the same)
-                                todo!("What code leads to a BindingPatternKind::AssignmentPattern as id of a VariableDeclarator?")
+                                unreachable!("checked by unsupported_pattern")
                             }
                         }
                     }
@@ -364,8 +437,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
 
             // Deal with the rest property. There can only be one and it is optional.
             // I'm not sure if there's a clean way of transforming this tbh. But we can abstract it into a function.
-            // The function can do it manually.
-            // `function rest(obj, props) { let o = {}; for (let p of props) { o[p] = obj[p]; } return o; }`
+            // The function can do it manually. See `Helper::Rest` for its implementation.
             if let Some(rest) = rest {
                 // `let {...b} = y` -> `let tmp = y; let {a, ...b} = tmp`
                 // `let {x, y, z, ...b} = y` -> `let tmp = y; let b = rest(tmp, ["x", "y", "z"])`
@@ -376,7 +448,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
                     //       I suspect Oxc is just a bit lazy here and using the same Rest type for arrays
                     //       and objects (a binding pattern is legal for arrays, although the default is
                     //       legal in neither).
-                    panic!("What code leads here? It shouldn't be ident or expr and private prop is not valid syntax: {:?}", argument)
+                    unreachable!("checked by unsupported_pattern")
                 };
 
                 new_body.push(
@@ -387,7 +459,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
                         Some(
                             create_call_expression(
                                 allocator,
-                                create_identifier_expression(allocator, "$rest".to_string(), decr_pattern_span),
+                                create_identifier_expression(allocator, state.use_helper(Helper::Rest), decr_pattern_span),
                                 OxcVec::from_iter_in([
                                     create_identifier_expression(allocator, rhs, rhs_span),
                                     create_array_expression(
@@ -420,8 +492,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
             Changed::Yes
         }
         BindingPatternKind::ArrayPattern(array_pattern) => {
-            rule("Transform var decl binding pattern to var decls without binding pattern");
-            example("let [x] = a;", "let x = a[0];");
+            state.record_rule(RuleId::VarDeclArrayPattern, array_pattern.span);
 
             // `let [a] = y` -> `let a = y[0]`
             // `let [a, b] = y` -> `let a = y[0]; let b = y[1]`
@@ -433,7 +504,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
 
             let ArrayPattern { elements, rest, span: _array_pattern_span } = array_pattern.unbox();
 
-            let Some(init) = init else { panic!("Pattern var decls are syntactically required to have an init... so where is it"); };
+            let Some(init) = init else { unreachable!("checked above"); };
             let rhs_span = init.span();
             let rhs = if let Expression::Identifier(ident) = init {
                 // This is fine, don't mess with it
@@ -441,8 +512,8 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
             } else {
                 // We need to assign the rhs to a tmp var otherwise we risk introducing observable side effects when deconstructing multiple props.
                 // `let {a,b} = stuff()` -> `let tmp = stuff(); let {a,b} = tmp` (just the stuff() call part)
-                let tmp_var_name = state.next_ident_name();
-                new_body.push(create_variable_declaration_kind(allocator, VariableDeclarationKind::Let, tmp_var_name.clone(), Some(init), rhs_span));
+                let tmp_var_name = state.next_var_name(rhs_span);
+                new_body.push(create_variable_declaration_kind(allocator, state.temp_var_kind(), tmp_var_name.clone(), Some(init), rhs_span));
                 // This is now an ident expression to the original (complex) init
                 create_identifier_expression(allocator, tmp_var_name.to_string(), rhs_span);
                 tmp_var_name
@@ -469,7 +540,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
                             Some(create_member_expression_computed(
                                 allocator,
                                 create_identifier_expression(allocator, rhs.clone(), decr_span),
-                                create_number_literal_str(allocator, i as f64, allocator.alloc(format!("{}", i)), decr_span),
+                                create_number_literal_str(allocator, i as f64, allocator.alloc_str(&i.to_string()), decr_span),
                                 decr_span
                             )),
                             decr_span
@@ -508,7 +579,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
                                 allocator,
                                 create_member_expression(allocator, create_identifier_expression(allocator, rhs.clone(), rest_span), "slice".to_string(), rest_span),
                                 OxcVec::from_iter_in([
-                                    create_number_literal_str(allocator, elements_len as f64, allocator.alloc(format!("{}", elements_len)), rest_span)
+                                    create_number_literal_str(allocator, elements_len as f64, allocator.alloc_str(&elements_len.to_string()), rest_span)
                                 ], allocator),
                                 false,
                                 None,
@@ -531,7 +602,7 @@ fn transform_var_decl_declr<'a>(declr: VariableDeclarator<'a>, new_body: &mut Ox
 
 
                     }
-                    _ => panic!("What code leads here? It should be an Expression for computed keys: {:?}", prop_lhs_kind)
+                    _ => unreachable!("checked by unsupported_pattern"),
                 }
             }
 
@@ -596,6 +667,7 @@ fn transform_var_decl_shorthand_with_default<'a>(
 /// `binding_pattern_kind` is the {b} part for any kind of pattern
 /// `rhs` is the `y` part
 /// `default_value_expr` is the `= 1` part
+#[allow(clippy::too_many_arguments)]
 fn transform_var_decl_obj_pattern_with_default<'a>(
     key: PropertyKey<'a>,
     binding_pattern_kind: BindingPatternKind<'a>,
@@ -606,12 +678,12 @@ fn transform_var_decl_obj_pattern_with_default<'a>(
     state: &mut MapperState,
     new_body: &mut OxcVec<Statement<'a>>
 ) {
-    let tmp_var_name = state.next_ident_name();
+    let tmp_var_name = state.next_var_name(span);
     // `let tmp = rhs.a`
     // `let tmp = rhs[a]`
     new_body.push(create_variable_declaration_kind(
         allocator,
-        VariableDeclarationKind::Let,
+        state.temp_var_kind(),
         tmp_var_name.to_string(),
         Some(match key {
             PropertyKey::Identifier(ident) => {
@@ -620,7 +692,7 @@ fn transform_var_decl_obj_pattern_with_default<'a>(
             PropertyKey::Expression(expr) => {
                 create_member_expression_computed(allocator, rhs, expr, span)
             }
-            PropertyKey::PrivateIdentifier(_) => panic!("What code leads to a PropertyKey::PrivateIdentifier as key of a var BindingProperty in the id?"),
+            PropertyKey::PrivateIdentifier(_) => unreachable!("checked by unsupported_pattern"),
         }),
         span
     ));
@@ -676,11 +748,11 @@ fn transform_var_decl_obj_pattern_no_default<'a>(
     state: &mut MapperState,
     new_body: &mut OxcVec<Statement<'a>>
 ) {
-    let tmp_var_name = state.next_ident_name();
+    let tmp_var_name = state.next_var_name(span);
     // `let tmp = rhs.a`
     new_body.push(create_variable_declaration_kind(
         allocator,
-        VariableDeclarationKind::Let,
+        state.temp_var_kind(),
         tmp_var_name.to_string(),
         Some(match key {
             PropertyKey::Identifier(ident) => {
@@ -689,7 +761,7 @@ fn transform_var_decl_obj_pattern_no_default<'a>(
             PropertyKey::Expression(expr) => {
                 create_member_expression_computed(allocator, rhs, expr, span)
             }
-            PropertyKey::PrivateIdentifier(_) => panic!("What code leads to a PropertyKey::PrivateIdentifier as key of a var BindingProperty in the id?"),
+            PropertyKey::PrivateIdentifier(_) => unreachable!("checked by unsupported_pattern"),
         }),
         span
     ));
@@ -713,6 +785,7 @@ fn transform_var_decl_obj_pattern_no_default<'a>(
 /// `binding_pattern_kind` is the {b} part for any kind of pattern
 /// `rhs` is the `y` part
 /// `default_value_expr` is the `= a` part
+#[allow(clippy::too_many_arguments)]
 fn transform_var_decl_arr_pattern_with_default<'a>(
     index: usize,
     left: BindingPattern<'a>,
@@ -723,16 +796,16 @@ fn transform_var_decl_arr_pattern_with_default<'a>(
     state: &mut MapperState,
     new_body: &mut OxcVec<Statement<'a>>
 ) {
-    let tmp_var_name = state.next_ident_name();
+    let tmp_var_name = state.next_var_name(span);
     // `let tmp = rhs[index]`
     new_body.push(create_variable_declaration_kind(
         allocator,
-        VariableDeclarationKind::Let,
+        state.temp_var_kind(),
         tmp_var_name.to_string(),
         Some(create_member_expression_computed(
             allocator,
             rhs,
-            create_number_literal_str(allocator, index as f64, allocator.alloc(format!("{}", index)), span),
+            create_number_literal_str(allocator, index as f64, allocator.alloc_str(&index.to_string()), span),
             span
         )),
         span
@@ -789,16 +862,16 @@ fn transform_var_decl_arr_pattern_no_default<'a>(
     state: &mut MapperState,
     new_body: &mut OxcVec<Statement<'a>>
 ) {
-    let tmp_var_name = state.next_ident_name();
+    let tmp_var_name = state.next_var_name(span);
     // `let tmp = rhs[index]`
     new_body.push(create_variable_declaration_kind(
         allocator,
-        VariableDeclarationKind::Let,
+        state.temp_var_kind(),
         tmp_var_name.to_string(),
         Some(create_member_expression_computed(
             allocator,
             rhs,
-            create_number_literal_str(allocator, index as f64, allocator.alloc(format!("{}", index)), span),
+            create_number_literal_str(allocator, index as f64, allocator.alloc_str(&index.to_string()), span),
             span
         )),
         span
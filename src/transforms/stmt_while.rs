@@ -0,0 +1,52 @@
+use oxc_allocator::Vec as OxcVec;
+use oxc_allocator::Box as OxcBox;
+use oxc_ast::ast::*;
+use oxc_syntax::operator::*;
+use oxc_allocator::Allocator;
+use oxc_span::GetSpan;
+
+use crate::mapper::MapperAction;
+use crate::mapper_state::MapperState;
+use crate::trace::RuleId;
+
+use super::builder::create_block_statement;
+use super::builder::create_bool;
+use super::builder::create_break_statement;
+use super::builder::create_if_statement;
+use super::builder::create_while_statement;
+
+pub fn transform_while_statement<'a>(
+    while_stmt: WhileStatement<'a>,
+    allocator: &'a Allocator,
+    state: &mut MapperState
+) -> (MapperAction, Statement<'a>) {
+    // `while (x) { y; }` -> `while (true) { if (!x) break; { y; } }`
+    //
+    // The body stays a statement of its own. Its let/const decls may shadow names in the test, which
+    // would then be in their TDZ if the test moved into the same block.
+    // A `continue` (when that transform is disabled) still works since it jumps to the test first.
+
+    if let Expression::BooleanLiteral(bool_lit) = &while_stmt.test {
+        if bool_lit.value {
+            return (MapperAction::Normal, Statement::WhileStatement(OxcBox(allocator.alloc(while_stmt))));
+        }
+    }
+
+    state.record_rule(RuleId::WhileTrue, while_stmt.span);
+
+    let WhileStatement { test, body, span } = while_stmt;
+    let test_span = test.span();
+
+    let not_test = Expression::UnaryExpression(OxcBox(allocator.alloc(UnaryExpression {
+        operator: UnaryOperator::LogicalNot,
+        argument: test,
+        span: test_span,
+    })));
+
+    let new_body = create_block_statement(allocator, OxcVec::from_iter_in([
+        create_if_statement(allocator, not_test, create_break_statement(allocator, None, test_span), None, test_span),
+        body,
+    ], allocator), span);
+
+    (MapperAction::Normal, create_while_statement(allocator, create_bool(allocator, true, span), new_body, span))
+}
@@ -0,0 +1,412 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_ast::{AstKind, Visit};
+use oxc_parser::Parser;
+use oxc_span::GetSpan;
+use oxc_span::SourceType;
+use oxc_span::Span;
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::mapper::DEFAULT_MAX_DEPTH;
+use crate::options::{Preset, TransformOptions};
+use crate::walker::{check_nesting, create_walker, Node, Walker};
+
+/// The syntax that the transforms eliminate, as far as the validator is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Construct {
+    Switch,
+    Continue,
+    Finally,
+    ForLoop,
+    ForIn,
+    ForOf,
+    DoWhile,
+    DeclarationPattern,
+    ConditionalWhile,
+    NonBlockBody,
+}
+
+impl Construct {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Construct::Switch => "`switch` statement",
+            Construct::Continue => "`continue` statement",
+            Construct::Finally => "`finally` block",
+            Construct::ForLoop => "`for` loop",
+            Construct::ForIn => "`for-in` loop",
+            Construct::ForOf => "`for-of` loop",
+            Construct::DoWhile => "`do-while` loop",
+            Construct::DeclarationPattern => "Destructuring pattern in a variable declaration",
+            Construct::ConditionalWhile => "`while` loop with a test other than `true`",
+            Construct::NonBlockBody => "Sub-statement that is not a block",
+        }
+    }
+}
+
+/// Which of the `Construct`s are allowed. Anything else is always allowed.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Profile {
+    pub switch: bool,
+    pub continue_stmt: bool,
+    pub finally: bool,
+    pub for_loop: bool,
+    pub for_in: bool,
+    pub for_of: bool,
+    pub do_while: bool,
+    pub declaration_pattern: bool,
+    pub conditional_while: bool,
+    pub non_block_body: bool,
+}
+
+#[wasm_bindgen]
+impl Profile {
+    /// What the JS0 preset eliminates is not allowed. That's every construct except for the ones of MISC.
+    pub fn js0() -> Self {
+        Self {
+            conditional_while: true,
+            non_block_body: true,
+            ..Self::misc()
+        }
+    }
+
+    /// None of the constructs that this tool eliminates are allowed (the MISC preset)
+    pub fn misc() -> Self {
+        Self {
+            switch: false,
+            continue_stmt: false,
+            finally: false,
+            for_loop: false,
+            for_in: false,
+            for_of: false,
+            do_while: false,
+            declaration_pattern: false,
+            conditional_while: false,
+            non_block_body: false,
+        }
+    }
+
+    /// What the transforms of this preset eliminate is not allowed
+    pub fn from_preset(preset: Preset) -> Self {
+        Self::from_options(&TransformOptions::from_preset(preset))
+    }
+
+    /// Everything is allowed. Only checks that the code parses.
+    pub fn any() -> Self {
+        Self {
+            switch: true,
+            continue_stmt: true,
+            finally: true,
+            for_loop: true,
+            for_in: true,
+            for_of: true,
+            do_while: true,
+            declaration_pattern: true,
+            conditional_while: true,
+            non_block_body: true,
+        }
+    }
+
+    /// Whatever the transforms enabled in these options (after `resolve()`) should have eliminated is not allowed
+    pub fn from_options(options: &TransformOptions) -> Self {
+        let options = options.resolve();
+        Self {
+            switch: !options.switch,
+            continue_stmt: !options.continue_stmt,
+            finally: !options.finally,
+            for_loop: !options.for_loop,
+            for_in: !options.for_in,
+            for_of: !options.for_of,
+            do_while: !options.do_while,
+            declaration_pattern: !options.var_decl,
+            conditional_while: !options.while_true,
+            non_block_body: !options.block_bodies,
+        }
+    }
+}
+
+impl Profile {
+    /// Everything is allowed except these
+    pub fn any_except(constructs: &[Construct]) -> Self {
+        let mut profile = Self::any();
+        for construct in constructs {
+            match construct {
+                Construct::Switch => profile.switch = false,
+                Construct::Continue => profile.continue_stmt = false,
+                Construct::Finally => profile.finally = false,
+                Construct::ForLoop => profile.for_loop = false,
+                Construct::ForIn => profile.for_in = false,
+                Construct::ForOf => profile.for_of = false,
+                Construct::DoWhile => profile.do_while = false,
+                Construct::DeclarationPattern => profile.declaration_pattern = false,
+                Construct::ConditionalWhile => profile.conditional_while = false,
+                Construct::NonBlockBody => profile.non_block_body = false,
+            }
+        }
+        profile
+    }
+
+    pub fn allows(&self, construct: Construct) -> bool {
+        match construct {
+            Construct::Switch => self.switch,
+            Construct::Continue => self.continue_stmt,
+            Construct::Finally => self.finally,
+            Construct::ForLoop => self.for_loop,
+            Construct::ForIn => self.for_in,
+            Construct::ForOf => self.for_of,
+            Construct::DoWhile => self.do_while,
+            Construct::DeclarationPattern => self.declaration_pattern,
+            Construct::ConditionalWhile => self.conditional_while,
+            Construct::NonBlockBody => self.non_block_body,
+        }
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::js0()
+    }
+}
+
+/// A node that the profile does not allow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub construct: Construct,
+    pub span: Span,
+}
+
+impl Violation {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(DiagnosticCode::ProfileViolation, format!("{} is not allowed", self.construct.description()), self.span)
+    }
+}
+
+/// Find every node in the program that the profile does not allow, in walk order
+pub fn validate_program(program: &Program, profile: Profile) -> Vec<Violation> {
+    let (walker, violations) = create_validating_walker(profile);
+    walker.walk(program);
+
+    let violations = violations.borrow().clone();
+    violations
+}
+
+/// Same as `validate_program` but only for this statement and what's inside it
+pub fn validate_statement(stmt: &Statement, profile: Profile) -> Vec<Violation> {
+    let (walker, violations) = create_validating_walker(profile);
+    walker.walk_statement(stmt);
+
+    let violations = violations.borrow().clone();
+    violations
+}
+
+fn create_validating_walker(profile: Profile) -> (Walker, Rc<RefCell<Vec<Violation>>>) {
+    let violations: Rc<RefCell<Vec<Violation>>> = Rc::new(RefCell::new(vec!()));
+    let violations_closure = Rc::clone(&violations);
+
+    let mut walker = create_walker();
+    walker.add_visitor_before(move |node| {
+        let Node::Statement(stmt) = node else { return };
+        let mut violations = violations_closure.borrow_mut();
+        find_constructs(stmt, &mut |construct: Construct, span: Span| {
+            if !profile.allows(construct) {
+                violations.push(Violation { construct, span });
+            }
+        });
+    });
+    (walker, violations)
+}
+
+/// Call `found` for each construct that this statement itself is (not what's nested in it). A loop or an
+/// `if` may also have bodies that are not a block, and a var decl can have multiple patterns.
+pub fn find_constructs(stmt: &Statement, found: &mut impl FnMut(Construct, Span)) {
+    match stmt {
+        Statement::SwitchStatement(switch_stmt) => found(Construct::Switch, switch_stmt.span),
+        Statement::ContinueStatement(continue_stmt) => found(Construct::Continue, continue_stmt.span),
+        Statement::TryStatement(try_stmt) => {
+            if let Some(finalizer) = &try_stmt.finalizer {
+                found(Construct::Finally, finalizer.span);
+            }
+        }
+        Statement::ForStatement(for_stmt) => found(Construct::ForLoop, for_stmt.span),
+        Statement::ForInStatement(for_in) => found(Construct::ForIn, for_in.span),
+        Statement::ForOfStatement(for_of) => found(Construct::ForOf, for_of.span),
+        Statement::DoWhileStatement(do_while) => found(Construct::DoWhile, do_while.span),
+        Statement::WhileStatement(while_stmt) if !matches!(&while_stmt.test, Expression::BooleanLiteral(bool_lit) if bool_lit.value) => {
+            found(Construct::ConditionalWhile, while_stmt.span);
+        }
+        Statement::Declaration(Declaration::VariableDeclaration(var_decl)) => check_declarators(var_decl, found),
+        Statement::ModuleDeclaration(module_decl) => {
+            if let ModuleDeclaration::ExportNamedDeclaration(export_decl) = &**module_decl {
+                if let Some(Declaration::VariableDeclaration(var_decl)) = &export_decl.declaration {
+                    check_declarators(var_decl, found);
+                }
+            }
+        }
+        _ => (),
+    }
+    check_bodies(stmt, found);
+}
+
+// The `else` of an `else if` and the body of a labeled statement need no block. See `transform_block_bodies`.
+fn check_bodies(stmt: &Statement, check: &mut impl FnMut(Construct, Span)) {
+    let mut check_body = |body: &Statement| {
+        if !matches!(body, Statement::BlockStatement(_)) {
+            check(Construct::NonBlockBody, body.span());
+        }
+    };
+    match stmt {
+        Statement::IfStatement(if_stmt) => {
+            check_body(&if_stmt.consequent);
+            if let Some(alternate) = &if_stmt.alternate {
+                if !matches!(alternate, Statement::IfStatement(_)) {
+                    check_body(alternate);
+                }
+            }
+        }
+        Statement::WhileStatement(while_stmt) => check_body(&while_stmt.body),
+        Statement::DoWhileStatement(do_while) => check_body(&do_while.body),
+        Statement::ForStatement(for_stmt) => check_body(&for_stmt.body),
+        Statement::ForInStatement(for_in) => check_body(&for_in.body),
+        Statement::ForOfStatement(for_of) => check_body(&for_of.body),
+        Statement::WithStatement(with_stmt) => check_body(&with_stmt.body),
+        _ => (),
+    }
+}
+
+fn check_declarators(var_decl: &VariableDeclaration, check: &mut impl FnMut(Construct, Span)) {
+    for declarator in &var_decl.declarations {
+        if !matches!(declarator.id.kind, BindingPatternKind::BindingIdentifier(_)) {
+            check(Construct::DeclarationPattern, declarator.id.kind.span());
+        }
+    }
+}
+
+/// Check that this code parses and only uses syntax that the profile allows. This is the "is this file
+/// already JS0?" check. Returns parse errors or violations, empty when the code is fine.
+pub fn validate_source(source: &str, source_type: SourceType, profile: Profile) -> Vec<Diagnostic> {
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, source, source_type).parse();
+
+    if !parsed.errors.is_empty() {
+        return parsed.errors.iter().map(|error| Diagnostic::from_parse_error(DiagnosticCode::ParseError, error)).collect();
+    }
+    if let Err(diagnostic) = check_nesting(&parsed.program, DEFAULT_MAX_DEPTH) {
+        return vec!(diagnostic);
+    }
+    let label_errors = check_labels(&parsed.program);
+    if !label_errors.is_empty() {
+        return label_errors;
+    }
+
+    validate_program(&parsed.program, profile).iter().map(Violation::to_diagnostic).collect()
+}
+
+/// The early errors of `break`, `continue` and labels, which the parser does not report: a jump to a label
+/// that is not around it, a `continue` to a label that is not a loop, a `break` or `continue` outside of
+/// anything it could jump out of, and a label inside a label of the same name.
+fn check_labels(program: &Program) -> Vec<Diagnostic> {
+    let mut checker = LabelChecker { frames: vec!(LabelFrame::default()), errors: vec!() };
+    checker.visit_program(program);
+    checker.errors
+}
+
+// A function starts over, a jump can't leave it
+#[derive(Default)]
+struct LabelFrame {
+    // The labels around the current node and whether each labels a loop
+    labels: Vec<(String, bool)>,
+    loops: usize,
+    switches: usize,
+}
+
+struct LabelChecker {
+    frames: Vec<LabelFrame>,
+    errors: Vec<Diagnostic>,
+}
+
+impl LabelChecker {
+    fn frame(&mut self) -> &mut LabelFrame {
+        self.frames.last_mut().expect("the program frame is never popped")
+    }
+
+    fn error(&mut self, message: String, span: Span) {
+        self.errors.push(Diagnostic::new(DiagnosticCode::ParseError, message, span));
+    }
+
+    fn target(&self, label: &LabelIdentifier) -> Option<bool> {
+        let frame = self.frames.last().expect("the program frame is never popped");
+        frame.labels.iter().rev().find(|(name, _)| name.as_str() == label.name.as_str()).map(|(_, is_loop)| *is_loop)
+    }
+}
+
+fn labels_loop(stmt: &Statement) -> bool {
+    match stmt {
+        Statement::LabeledStatement(labeled) => labels_loop(&labeled.body),
+        Statement::WhileStatement(_) | Statement::DoWhileStatement(_) | Statement::ForStatement(_) | Statement::ForInStatement(_) | Statement::ForOfStatement(_) => true,
+        _ => false,
+    }
+}
+
+impl<'a> Visit<'a> for LabelChecker {
+    fn enter_node(&mut self, kind: AstKind<'a>) {
+        match kind {
+            AstKind::Function(_) | AstKind::ArrowExpression(_) | AstKind::StaticBlock(_) => self.frames.push(LabelFrame::default()),
+            AstKind::LabeledStatement(labeled) => {
+                if self.target(&labeled.label).is_some() {
+                    self.error(format!("Label `{}` is already declared around this one", labeled.label.name), labeled.label.span);
+                }
+                let is_loop = labels_loop(&labeled.body);
+                self.frame().labels.push((labeled.label.name.to_string(), is_loop));
+            }
+            AstKind::WhileStatement(_) | AstKind::DoWhileStatement(_) | AstKind::ForStatement(_) | AstKind::ForInStatement(_) | AstKind::ForOfStatement(_) => {
+                self.frame().loops += 1;
+            }
+            AstKind::SwitchStatement(_) => self.frame().switches += 1,
+            AstKind::BreakStatement(break_stmt) => match &break_stmt.label {
+                Some(label) if self.target(label).is_none() => {
+                    self.error(format!("`break {}` is not inside a statement with that label", label.name), break_stmt.span);
+                }
+                Some(_) => (),
+                None => {
+                    let frame = self.frame();
+                    if frame.loops == 0 && frame.switches == 0 {
+                        self.error("`break` is not inside a loop or `switch`".to_string(), break_stmt.span);
+                    }
+                }
+            },
+            AstKind::ContinueStatement(continue_stmt) => match &continue_stmt.label {
+                Some(label) => match self.target(label) {
+                    None => self.error(format!("`continue {}` is not inside a loop with that label", label.name), continue_stmt.span),
+                    Some(false) => self.error(format!("`continue {}` targets a label that is not on a loop", label.name), continue_stmt.span),
+                    Some(true) => (),
+                },
+                None => {
+                    if self.frame().loops == 0 {
+                        self.error("`continue` is not inside a loop".to_string(), continue_stmt.span);
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn leave_node(&mut self, kind: AstKind<'a>) {
+        match kind {
+            AstKind::Function(_) | AstKind::ArrowExpression(_) | AstKind::StaticBlock(_) => {
+                self.frames.pop();
+            }
+            AstKind::LabeledStatement(_) => {
+                self.frame().labels.pop();
+            }
+            AstKind::WhileStatement(_) | AstKind::DoWhileStatement(_) | AstKind::ForStatement(_) | AstKind::ForInStatement(_) | AstKind::ForOfStatement(_) => {
+                self.frame().loops -= 1;
+            }
+            AstKind::SwitchStatement(_) => self.frame().switches -= 1,
+            _ => (),
+        }
+    }
+}
@@ -1,14 +1,33 @@
+use std::cell::Cell;
+
 use oxc_ast::ast::*;
+use oxc_span::{GetSpan, Span};
+
+use crate::diagnostics::{Diagnostic, DiagnosticCode};
+use crate::mapper::DEFAULT_MAX_DEPTH;
+
+type NodeVisitor = Box<dyn Fn(&Node)>;
 
 pub struct Walker {
-    visitors_before: Vec<Box<dyn Fn(&Node)>>,
-    visitors_after: Vec<Box<dyn Fn(&Node)>>,
+    visitors_before: Vec<NodeVisitor>,
+    visitors_after: Vec<NodeVisitor>,
+    max_depth: usize,
+    // The number of statements and expressions that are being walked right now
+    depth: Cell<usize>,
+    // The first node that was skipped for being nested deeper than `max_depth`
+    too_deep: Cell<Option<Span>>,
 }
 
 #[derive(Debug)]
-pub enum Node<'a> {
-    Statement(&'a Statement<'a>),
-    Expression(&'a Expression<'a>),
+pub enum Node<'b, 'a> {
+    Statement(&'b Statement<'a>),
+    Expression(&'b Expression<'a>),
+}
+
+impl Default for Walker {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Walker {
@@ -16,7 +35,39 @@ impl Walker {
         Self {
             visitors_before: Vec::new(),
             visitors_after: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: Cell::new(0),
+            too_deep: Cell::new(None),
+        }
+    }
+
+    /// How deep statements and expressions may be nested. Anything deeper is skipped, visitors are not
+    /// called for it, rather than running out of stack. See `too_deep`.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// The first node that the last walk skipped because it was nested too deep, if any
+    pub fn too_deep(&self) -> Option<Span> {
+        self.too_deep.get()
+    }
+
+    /// Count a level of nesting. Returns false, and records the node, when it is too deep to walk.
+    /// Every `enter_nesting` that returns true must be paired with a `leave_nesting`.
+    fn enter_nesting(&self, span: Span) -> bool {
+        let depth = self.depth.get() + 1;
+        if depth > self.max_depth {
+            if self.too_deep.get().is_none() {
+                self.too_deep.set(Some(span));
+            }
+            return false;
         }
+        self.depth.set(depth);
+        true
+    }
+
+    fn leave_nesting(&self) {
+        self.depth.set(self.depth.get() - 1);
     }
 
     pub fn add_visitor_before<F>(&mut self, visitor: F)
@@ -33,13 +84,28 @@ impl Walker {
         self.visitors_after.push(Box::new(visitor));
     }
 
-    pub fn walk<'a>(&self, program: &'a Program<'a>) {
+    pub fn walk<'a, 'b>(&self, program: &'b Program<'a>) {
+        self.too_deep.set(None);
         for stmt in &program.body {
             self.visit_statement(stmt);
         }
     }
 
-    fn visit_statement<'a>(&self, stmt: &'a Statement<'a>) {
+    /// Walk a single statement and everything in it
+    pub fn walk_statement<'a, 'b>(&self, stmt: &'b Statement<'a>) {
+        self.too_deep.set(None);
+        self.visit_statement(stmt);
+    }
+
+    fn visit_statement<'a, 'b>(&self, stmt: &'b Statement<'a>) {
+        if !self.enter_nesting(stmt.span()) {
+            return;
+        }
+        self.visit_statement_nested(stmt);
+        self.leave_nesting();
+    }
+
+    fn visit_statement_nested<'a, 'b>(&self, stmt: &'b Statement<'a>) {
         let node = Node::Statement(stmt);
         for visitor in &self.visitors_before {
             visitor(&node);
@@ -66,7 +132,7 @@ impl Walker {
                 match &for_in.left {
                     ForStatementLeft::VariableDeclaration(decl) => self.visit_variable_declaration(decl),
                     ForStatementLeft::AssignmentTarget(target) => self.visit_assignment_target(target),
-                    ForStatementLeft::UsingDeclaration(_) => panic!("UsingDeclaration (stage 3) is not supported"),
+                    ForStatementLeft::UsingDeclaration(_) => (), // Stage 3, not supported
                 }
                 self.visit_expression(&for_in.right);
                 self.visit_statement(&for_in.body);
@@ -75,7 +141,7 @@ impl Walker {
                 match &for_of.left {
                     ForStatementLeft::VariableDeclaration(decl) => self.visit_variable_declaration(decl),
                     ForStatementLeft::AssignmentTarget(target) => self.visit_assignment_target(target),
-                    ForStatementLeft::UsingDeclaration(_) => panic!("UsingDeclaration (stage 3) is not supported"),
+                    ForStatementLeft::UsingDeclaration(_) => (), // Stage 3, not supported
                 }
                 self.visit_expression(&for_of.right);
                 self.visit_statement(&for_of.body);
@@ -84,7 +150,7 @@ impl Walker {
                 match &for_stmt.init {
                     Some(ForStatementInit::Expression(expr)) => self.visit_expression(expr),
                     Some(ForStatementInit::VariableDeclaration(decl)) => self.visit_variable_declaration(decl),
-                    Some(ForStatementInit::UsingDeclaration(_)) => panic!("UsingDeclaration (stage 3) is not supported"),
+                    Some(ForStatementInit::UsingDeclaration(_)) => (), // Stage 3, not supported
                     None => {}
                 }
                 if let Some(test) = &for_stmt.test {
@@ -150,29 +216,26 @@ impl Walker {
                 self.visit_expression(&with.object);
                 self.visit_statement(&with.body);
             }
-            Statement::Declaration(decl) => match decl {
-                Declaration::VariableDeclaration(var_decl) => self.visit_variable_declaration(var_decl),
-                Declaration::FunctionDeclaration(func_decl) => self.visit_function(func_decl),
-                Declaration::ClassDeclaration(class_decl) => self.visit_class(class_decl),
-                Declaration::UsingDeclaration(_) => panic!("UsingDeclaration (stage 3) is not supported"),
-                // We don't care to visit types at this time...
-                Declaration::TSTypeAliasDeclaration(_) => (),
-                Declaration::TSInterfaceDeclaration(_) => (),
-                Declaration::TSEnumDeclaration(_) => (),
-                Declaration::TSModuleDeclaration(_) => (),
-                Declaration::TSImportEqualsDeclaration(_) => (),
-            },
+            Statement::Declaration(decl) => self.visit_declaration(decl),
 
-            Statement::ModuleDeclaration(_mod_decl) => {
-                todo!("how do we deal with the box here?");
-                // match mod_decl {
-                //     ModuleDeclaration::ImportDeclaration(_) => (),
-                //     ModuleDeclaration::ExportAllDeclaration(_) => (),
-                //     ModuleDeclaration::ExportDefaultDeclaration(_) => (),
-                //     ModuleDeclaration::ExportNamedDeclaration(_) => (),
-                //     ModuleDeclaration::TSExportAssignment(_) => (),
-                //     ModuleDeclaration::TSNamespaceExportDeclaration(_) => (),
-                // }
+            Statement::ModuleDeclaration(mod_decl) => match &**mod_decl {
+                ModuleDeclaration::ImportDeclaration(_) => (),
+                ModuleDeclaration::ExportAllDeclaration(_) => (),
+                ModuleDeclaration::ExportDefaultDeclaration(export_default) => match &export_default.declaration {
+                    ExportDefaultDeclarationKind::Expression(expr) => self.visit_expression(expr),
+                    ExportDefaultDeclarationKind::FunctionDeclaration(func) => self.visit_function(func),
+                    ExportDefaultDeclarationKind::ClassDeclaration(class) => self.visit_class(class),
+                    // We don't care to visit types at this time...
+                    ExportDefaultDeclarationKind::TSInterfaceDeclaration(_) => (),
+                    ExportDefaultDeclarationKind::TSEnumDeclaration(_) => (),
+                },
+                ModuleDeclaration::ExportNamedDeclaration(export_named) => {
+                    if let Some(decl) = &export_named.declaration {
+                        self.visit_declaration(decl);
+                    }
+                }
+                ModuleDeclaration::TSExportAssignment(_) => (),
+                ModuleDeclaration::TSNamespaceExportDeclaration(_) => (),
             }
         }
 
@@ -181,7 +244,15 @@ impl Walker {
         }
     }
 
-    fn visit_expression<'a>(&self, expr: &'a Expression<'a>) {
+    fn visit_expression<'a, 'b>(&self, expr: &'b Expression<'a>) {
+        if !self.enter_nesting(expr.span()) {
+            return;
+        }
+        self.visit_expression_nested(expr);
+        self.leave_nesting();
+    }
+
+    fn visit_expression_nested<'a, 'b>(&self, expr: &'b Expression<'a>) {
         let node = Node::Expression(expr);
         for visitor in &self.visitors_before {
             visitor(&node);
@@ -204,12 +275,9 @@ impl Walker {
                     self.visit_binding_pattern(&param.pattern);
                 }
 
-                if arrow.body.is_empty() {
-                    self.visit_expression(arrow.get_expression().unwrap());
-                } else {
-                    for stmt in &arrow.body.statements {
-                        self.visit_statement(stmt);
-                    }
+                // `() => x` has the `x` as the only (expression) statement of its body, `() => {}` has none
+                for stmt in &arrow.body.statements {
+                    self.visit_statement(stmt);
                 }
             }
             Expression::AssignmentExpression(assign) => {
@@ -253,9 +321,8 @@ impl Walker {
                                 // "static" being the opposite of computed, not related to the "static" keyword
                                 self.visit_expression(&static_member.object);
                             }
-                            MemberExpression::PrivateFieldExpression(_private_field) => {
-                                todo!("TODO: not sure how to walk this properly :D");
-                                // self.visit_expression(&private_field.object);
+                            MemberExpression::PrivateFieldExpression(private_field) => {
+                                self.visit_expression(&private_field.object);
                             }
                         }
                     }
@@ -303,10 +370,7 @@ impl Walker {
                 for prop in &object.properties {
                     match prop {
                         ObjectPropertyKind::ObjectProperty(prop) => {
-                            match &prop.key {
-                                PropertyKey::Expression(expr) => self.visit_expression(expr),
-                                _ => (),
-                            }
+                            if let PropertyKey::Expression(expr) = &prop.key { self.visit_expression(expr) }
                             self.visit_expression(&prop.value);
                         }
                         ObjectPropertyKind::SpreadProperty(spread) => {
@@ -322,7 +386,9 @@ impl Walker {
             }
             Expression::TaggedTemplateExpression(tagged) => {
                 self.visit_expression(&tagged.tag);
-                // Template literals are handled implicitly
+                for expr in &tagged.quasi.expressions {
+                    self.visit_expression(expr);
+                }
             }
             Expression::ThisExpression(_) => (), // No children to visit
             Expression::UnaryExpression(unary) => {
@@ -348,9 +414,8 @@ impl Walker {
                                 // Do we visit the object of a static member expression when it's an assignment target?
                                 // self.visit_expression(&static_member.property);
                             }
-                            MemberExpression::PrivateFieldExpression(_private_field) => {
-                                todo!("TODO: not sure how to walk this properly :D");
-                                // self.visit_expression(&private_field.object);
+                            MemberExpression::PrivateFieldExpression(private_field) => {
+                                self.visit_expression(&private_field.object);
                             }
                         }
                     }
@@ -376,7 +441,12 @@ impl Walker {
             Expression::TSTypeAssertion(_) => (),
             Expression::TSNonNullExpression(_) => (),
             Expression::TSInstantiationExpression(_) => (),
-            Expression::ImportExpression(_) => (),
+            Expression::ImportExpression(import) => {
+                self.visit_expression(&import.source);
+                for arg in &import.arguments {
+                    self.visit_expression(arg);
+                }
+            }
             Expression::Super(_) => (),
             Expression::MetaProperty(_) => (),
             Expression::Identifier(_) => (),
@@ -386,8 +456,14 @@ impl Walker {
             Expression::BigintLiteral(_) => (),
             Expression::RegExpLiteral(_) => (),
             Expression::StringLiteral(_) => (),
-            Expression::TemplateLiteral(_) => (),
-            Expression::ParenthesizedExpression(_) => (),
+            Expression::TemplateLiteral(template) => {
+                for expr in &template.expressions {
+                    self.visit_expression(expr);
+                }
+            }
+            Expression::ParenthesizedExpression(paren) => {
+                self.visit_expression(&paren.expression);
+            }
         }
 
         for visitor in &self.visitors_after {
@@ -395,7 +471,7 @@ impl Walker {
         }
     }
 
-    fn visit_variable_declaration<'a>(&self, decl: &'a VariableDeclaration<'a>) {
+    fn visit_variable_declaration<'a, 'b>(&self, decl: &'b VariableDeclaration<'a>) {
         for declarator in &decl.declarations {
             self.visit_binding_pattern(&declarator.id);
             if let Some(init) = &declarator.init {
@@ -404,7 +480,7 @@ impl Walker {
         }
     }
 
-    fn visit_binding_pattern<'a>(&self, pattern: &'a BindingPattern<'a>) {
+    fn visit_binding_pattern<'a, 'b>(&self, pattern: &'b BindingPattern<'a>) {
         match &pattern.kind {
             BindingPatternKind::ObjectPattern(obj_pattern) => {
                 for prop in &obj_pattern.properties {
@@ -412,10 +488,8 @@ impl Walker {
                 }
             }
             BindingPatternKind::ArrayPattern(array_pattern) => {
-                for elem in &array_pattern.elements {
-                    if let Some(elem) = elem {
-                        self.visit_binding_pattern(elem);
-                    }
+                for elem in (&array_pattern.elements).into_iter().flatten() {
+                    self.visit_binding_pattern(elem);
                 }
             }
             BindingPatternKind::AssignmentPattern(assign_pattern) => {
@@ -426,7 +500,7 @@ impl Walker {
         }
     }
 
-    fn visit_assignment_target<'a>(&self, target: &'a AssignmentTarget<'a>) {
+    fn visit_assignment_target<'a, 'b>(&self, target: &'b AssignmentTarget<'a>) {
         match target {
             AssignmentTarget::SimpleAssignmentTarget(simple) => match simple {
                 SimpleAssignmentTarget::MemberAssignmentTarget(member) => {
@@ -443,7 +517,11 @@ impl Walker {
                         }
                     }
                 }
-                _ => (), // Identifier targets don't need further visiting
+                SimpleAssignmentTarget::AssignmentTargetIdentifier(_) => (), // Identifier targets don't need further visiting
+                SimpleAssignmentTarget::TSAsExpression(_) => (),
+                SimpleAssignmentTarget::TSSatisfiesExpression(_) => (),
+                SimpleAssignmentTarget::TSNonNullExpression(_) => (),
+                SimpleAssignmentTarget::TSTypeAssertion(_) => (),
             },
             AssignmentTarget::AssignmentTargetPattern(pattern) => match pattern {
                 AssignmentTargetPattern::ObjectAssignmentTarget(_obj) => {
@@ -456,8 +534,24 @@ impl Walker {
         }
     }
 
-    fn visit_function<'a>(&self, func: &'a Function<'a>) {
+    fn visit_declaration<'a, 'b>(&self, decl: &'b Declaration<'a>) {
+        match decl {
+            Declaration::VariableDeclaration(var_decl) => self.visit_variable_declaration(var_decl),
+            Declaration::FunctionDeclaration(func_decl) => self.visit_function(func_decl),
+            Declaration::ClassDeclaration(class_decl) => self.visit_class(class_decl),
+            Declaration::UsingDeclaration(_) => (), // Stage 3, not supported
+            // We don't care to visit types at this time...
+            Declaration::TSTypeAliasDeclaration(_) => (),
+            Declaration::TSInterfaceDeclaration(_) => (),
+            Declaration::TSEnumDeclaration(_) => (),
+            Declaration::TSModuleDeclaration(_) => (),
+            Declaration::TSImportEqualsDeclaration(_) => (),
+        }
+    }
+
+    fn visit_function<'a, 'b>(&self, func: &'b Function<'a>) {
         for param in &func.params.items {
+            self.visit_decorators(&param.decorators);
             self.visit_binding_pattern(&param.pattern);
         }
 
@@ -469,7 +563,14 @@ impl Walker {
         }
     }
 
-    fn visit_class<'a>(&self, class: &'a Class<'a>) {
+    fn visit_decorators<'a, 'b>(&self, decorators: &'b [Decorator<'a>]) {
+        for decorator in decorators {
+            self.visit_expression(&decorator.expression);
+        }
+    }
+
+    fn visit_class<'a, 'b>(&self, class: &'b Class<'a>) {
+        self.visit_decorators(&class.decorators);
         if let Some(super_class) = &class.super_class {
             self.visit_expression(super_class);
         }
@@ -477,19 +578,49 @@ impl Walker {
         for element in &class.body.body {
             match element {
                 ClassElement::PropertyDefinition(prop) => {
+                    self.visit_decorators(&prop.decorators);
                     if let Some(value) = &prop.value {
                         self.visit_expression(value);
                     }
                 }
                 ClassElement::MethodDefinition(method) => {
+                    self.visit_decorators(&method.decorators);
                     self.visit_function(&method.value);
                 }
-                _ => panic!("Unsupported class element type (Stage 3 or lower)"),
+                ClassElement::StaticBlock(block) => {
+                    for stmt in &block.body {
+                        self.visit_statement(stmt);
+                    }
+                }
+                ClassElement::AccessorProperty(accessor) => {
+                    if let Some(value) = &accessor.value {
+                        self.visit_expression(value);
+                    }
+                }
+                ClassElement::TSAbstractMethodDefinition(_) => (),
+                ClassElement::TSAbstractPropertyDefinition(_) => (),
+                ClassElement::TSIndexSignature(_) => (),
             }
         }
     }
 }
 
+/// Check that no statement or expression in the program is nested deeper than `max_depth`. The transforms,
+/// the scope analysis and the validator all recurse into the nodes, so this runs before any of them.
+pub fn check_nesting(program: &Program, max_depth: usize) -> Result<(), Diagnostic> {
+    let mut walker = create_walker();
+    walker.set_max_depth(max_depth);
+    walker.walk(program);
+    match walker.too_deep() {
+        Some(span) => Err(Diagnostic::new(
+            DiagnosticCode::NestingLimit,
+            format!("This is nested more than {} levels deep. The input is not processed.", max_depth),
+            span,
+        )),
+        None => Ok(()),
+    }
+}
+
 // Simple builder pattern for creating walkers
 pub fn create_walker() -> Walker {
     Walker::new()
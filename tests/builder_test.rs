@@ -0,0 +1,41 @@
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_parser::Parser;
+use oxc_span::{SourceType, Span};
+
+use zero_sugar::transforms::builder::{create_arr_assignment_pattern_from_binding_pattern, create_expression_statement, create_identifier_expression, create_obj_assignment_pattern_from_binding_pattern};
+
+/// Turn the pattern of the var decl in the source into an assignment of that pattern to `y`
+fn assign_pattern(source: &str) -> String {
+    let allocator = Allocator::default();
+    let mut program = Parser::new(&allocator, source, SourceType::default().with_module(true)).parse().program;
+    let Some(Statement::Declaration(Declaration::VariableDeclaration(var_decl))) = program.body.pop() else { panic!("Expected a var decl") };
+    let declarator = var_decl.unbox().declarations.into_iter().next().unwrap();
+    let right = create_identifier_expression(&allocator, "y".to_string(), Span::default());
+    let expr = match declarator.id.kind {
+        BindingPatternKind::ObjectPattern(pattern) => create_obj_assignment_pattern_from_binding_pattern(&allocator, pattern.unbox(), right, Span::default()),
+        BindingPatternKind::ArrayPattern(pattern) => create_arr_assignment_pattern_from_binding_pattern(&allocator, pattern.unbox(), right, Span::default()),
+        _ => panic!("Expected a pattern"),
+    };
+    program.body.push(create_expression_statement(&allocator, expr, Span::default()));
+
+    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions);
+    codegen.build(&program)
+}
+
+#[test]
+fn test_obj_pattern_with_defaults_to_assignment() {
+    assert_snapshot!(assign_pattern("let {a, b = 1, c: d = 2, e: [f = 3] = [], ...g} = x;"), @r#"
+    ({a, b=1, c:d=2, e:[f=3]=[],...g} = y);
+    "#);
+}
+
+#[test]
+fn test_arr_pattern_with_defaults_to_assignment() {
+    assert_snapshot!(assign_pattern("let [a = 1, , {b = 2, c: [d] = []}, ...e] = x;"), @r#"
+    [a=1, , {b=2, c:[d]=[]},...e] = y;
+    "#);
+}
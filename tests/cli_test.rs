@@ -0,0 +1,306 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use insta::assert_snapshot;
+
+fn zero_sugar() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_zero-sugar"))
+}
+
+// A fresh dir per test so they can run in parallel
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("zero-sugar-cli-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run_stdin(source: &str) -> (String, String, i32) {
+    run_stdin_with_args(&[], source)
+}
+
+fn run_stdin_with_args(args: &[&str], source: &str) -> (String, String, i32) {
+    let mut child = zero_sugar()
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(source.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+        output.status.code().unwrap(),
+    )
+}
+
+#[test]
+fn test_cli_stdin_to_stdout() {
+    let (stdout, stderr, code) = run_stdin("do { f(); } while (x);");
+
+    assert_eq!(code, 0);
+    assert_eq!(stderr, "");
+    assert_snapshot!(stdout, @r#"
+    let $zeroSugar0 = true;
+    while($zeroSugar0){
+    	{
+    		f();
+    	}
+    	$zeroSugar0 = x;
+    }
+    "#);
+}
+
+#[test]
+fn test_cli_verbose_logs_to_stderr() {
+    let (stdout, stderr, code) = run_stdin_with_args(&["--verbose"], "do { f(); } while (x);");
+
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("let $zeroSugar0 = true;"), "stdout should only have the code: {}", stdout);
+    assert_snapshot!(stderr, @r#"
+    Rule: Eliminate do-while in favor of regular while
+    "#);
+}
+
+#[test]
+fn test_cli_check() {
+    let (stdout, stderr, code) = run_stdin_with_args(&["--check"], "while (x) f();\ndo { f(); } while (x);\n");
+
+    assert_eq!(code, 1);
+    assert_eq!(stdout, "");
+    assert_snapshot!(stderr, @r#"
+    <stdin>:2:1: error[ZS006]: `do-while` loop is not allowed
+    "#);
+}
+
+#[test]
+fn test_cli_stdin_diagnostics() {
+    let (stdout, stderr, code) = run_stdin("f();\nswitch (x) {\n  case 1: class A {}\n}\n");
+
+    assert_eq!(code, 1);
+    assert_snapshot!(stderr, @"<stdin>:3:11: error[ZS003]: Class declarations in the toplevel of a switch case are not supported");
+    // The code is still written, with the offending switch left as is
+    assert!(stdout.contains("switch"));
+}
+
+#[test]
+fn test_cli_parse_error_writes_nothing() {
+    let (stdout, stderr, code) = run_stdin("x = ;");
+
+    assert_eq!(code, 1);
+    assert_eq!(stdout, "");
+    assert_snapshot!(stderr, @"<stdin>:1:5: error[ZS001]: Unexpected token");
+}
+
+#[test]
+fn test_cli_source_type_from_extension() {
+    let dir = temp_dir("source-type");
+    fs::write(dir.join("a.cjs"), "<!-- sloppy\nwith (o) f();\n").unwrap();
+    fs::write(dir.join("b.mjs"), "<!-- sloppy\nwith (o) f();\n").unwrap();
+
+    let output = zero_sugar().arg(&dir).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(dir.join("a.zs.cjs").exists());
+    assert!(!dir.join("b.zs.mjs").exists());
+    let stderr = String::from_utf8_lossy(&output.stderr).replace(&dir.display().to_string(), "<dir>");
+    assert_snapshot!(stderr, @r#"
+    <dir>/b.mjs:1:1: error[ZS001]: Unexpected token
+    "#);
+
+    // Unless the source type is forced
+    let output = zero_sugar().arg(dir.join("b.mjs")).args(["--source-type", "script"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_file_next_to_input() {
+    let dir = temp_dir("next-to-input");
+    fs::write(dir.join("a.js"), "for (let i = 0; i < 2; i++) f(i);\n").unwrap();
+
+    let output = zero_sugar().arg(dir.join("a.js")).arg("--source-map").output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let code = fs::read_to_string(dir.join("a.zs.js")).unwrap();
+    assert_snapshot!(code, @r#"
+    {
+    	let i = 0;
+    	while(i < 2)	{
+    		f(i);
+    		i++;
+    	}
+    }
+
+    //# sourceMappingURL=a.zs.js.map
+    "#);
+    let map = fs::read_to_string(dir.join("a.zs.js.map")).unwrap();
+    assert!(map.starts_with(r#"{"version":3,"sources":[""#), "{}", map);
+    assert!(map.contains("a.js"), "{}", map);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_dir_to_out_dir() {
+    let dir = temp_dir("out-dir");
+    fs::create_dir_all(dir.join("src/sub")).unwrap();
+    fs::create_dir_all(dir.join("src/node_modules")).unwrap();
+    fs::write(dir.join("src/a.js"), "a: for (;;) continue a;").unwrap();
+    fs::write(dir.join("src/sub/b.mjs"), "try { f(); } finally { g(); }").unwrap();
+    fs::write(dir.join("src/sub/readme.txt"), "not js").unwrap();
+    fs::write(dir.join("src/node_modules/c.js"), "for (;;);").unwrap();
+
+    let output = zero_sugar().arg(dir.join("src")).arg("-o").arg(dir.join("out")).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let mut written: Vec<String> = vec!();
+    for entry in ["a.js", "sub/b.mjs", "sub/readme.txt", "node_modules/c.js"] {
+        if dir.join("out").join(entry).exists() {
+            written.push(entry.to_string());
+        }
+    }
+    assert_eq!(written, vec!("a.js", "sub/b.mjs"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_out_dir_inside_input_dir() {
+    let dir = temp_dir("out-dir-inside");
+    fs::write(dir.join("a.js"), "do f(); while (x);").unwrap();
+
+    // The second run must not pick up the output of the first
+    for _ in 0..2 {
+        let output = zero_sugar().arg(&dir).arg("-o").arg(dir.join("out")).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+    assert!(dir.join("out/a.js").exists());
+    assert!(!dir.join("out/out").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_empty_suffix_without_out_dir() {
+    let dir = temp_dir("empty-suffix");
+    fs::write(dir.join("a.js"), "do f(); while (x);").unwrap();
+
+    let output = zero_sugar().arg(dir.join("a.js")).args(["--suffix", ""]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: An empty `--suffix` needs an `--out-dir`"));
+    assert_eq!(fs::read_to_string(dir.join("a.js")).unwrap(), "do f(); while (x);");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_out_dir_is_input_dir() {
+    let dir = temp_dir("out-dir-is-input");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/a.js"), "do f(); while (x);").unwrap();
+    fs::write(dir.join("b.js"), "do g(); while (y);").unwrap();
+
+    for args in [["src", "-o", "src"], ["b.js", "-o", "."]] {
+        let output = zero_sugar().current_dir(&dir).args(args).output().unwrap();
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: The output for "), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+    assert_eq!(fs::read_to_string(dir.join("src/a.js")).unwrap(), "do f(); while (x);");
+    assert_eq!(fs::read_to_string(dir.join("b.js")).unwrap(), "do g(); while (y);");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cli_source_map_for_stdin() {
+    // Fails before reading stdin
+    let output = zero_sugar().arg("--source-map").stdin(Stdio::null()).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(output.stdout, b"");
+    assert_snapshot!(String::from_utf8_lossy(&output.stderr), @r#"
+    error: Can not write a source map for stdin, pass a file instead
+    "#);
+}
+
+#[test]
+fn test_cli_unknown_option() {
+    let output = zero_sugar().arg("--nope").output().unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: Unknown option `--nope`"));
+}
+
+#[test]
+fn test_cli_unknown_log_level() {
+    let output = zero_sugar().args(["--log-level", "loud"]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: Unknown log level `loud`"));
+}
+
+#[test]
+fn test_cli_preset() {
+    let source = "do x(); while (y);\nfor (const [a] of b) if (a) f();\n";
+    let (stdout, stderr, code) = run_stdin_with_args(&["--preset", "es5", "--helpers", "none"], source);
+
+    assert_eq!(code, 0);
+    assert_eq!(stderr, "");
+    assert!(stdout.starts_with("do \n\tx();\nwhile(y);"), "es5 keeps the do-while: {}", stdout);
+    assert!(!stdout.contains("for ("), "es5 removes the for-of: {}", stdout);
+
+    // The check uses the profile of the preset
+    let (_, stderr, code) = run_stdin_with_args(&["--preset", "misc", "--check"], "while (x) { f(); }\nif (x) f();\n");
+    assert_eq!(code, 1);
+    assert_snapshot!(stderr, @r#"
+    <stdin>:1:1: error[ZS006]: `while` loop with a test other than `true` is not allowed
+    <stdin>:2:8: error[ZS006]: Sub-statement that is not a block is not allowed
+    "#);
+}
+
+#[test]
+fn test_cli_invalid_name_prefix() {
+    let output = zero_sugar().args(["--name-prefix", "1x"]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: The name prefix `1x` is not a valid identifier"));
+}
+
+#[test]
+fn test_cli_unknown_preset() {
+    let output = zero_sugar().args(["--preset", "es3"]).output().unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: Unknown preset `es3`"));
+}
+
+#[test]
+fn test_cli_inventory() {
+    let (stdout, stderr, code) = run_stdin_with_args(&["--inventory", "table"], "for (const x of y) if (x) continue;\n");
+
+    assert_eq!(code, 0);
+    assert_eq!(stderr, "");
+    assert_snapshot!(stdout, @r#"
+    <stdin>
+      Feature   Count  Where
+      continue      1  1:27
+      for-of        1  1:1
+      const         1  1:6
+
+    Total (1 file)
+      Feature   Files  Count  Preset
+      continue      1      1  js0
+      for-of        1      1  es5
+      const         1      1  -
+    "#);
+
+    let (stdout, _, code) = run_stdin_with_args(&["--inventory", "json"], "x = ;");
+    assert_eq!(code, 1);
+    assert!(stdout.contains("\"errors\": [{ \"code\": \"ZS001\""), "The parse error is in the report: {}", stdout);
+}
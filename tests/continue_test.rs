@@ -3,7 +3,7 @@ use insta::assert_snapshot;
 use zero_sugar::transform_code;
 
 fn parse_and_map(source: &str) -> String {
-    // Must use the global transform because the transform needs an up to date loop stack and the general handler deals with that.
+    // Must use the global transform because the transform needs the loops and labels around the continue, which the mapper tracks.
     let transformed_code = transform_code(source);
     transformed_code.unwrap().transformed_code
 }
@@ -83,16 +83,14 @@ fn test_continue_in_do_while() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar1 = true;
-    	while($zeroSugar1)	{
-    		$zeroSugar0:		{
-    			if (x) 			break $zeroSugar0;
+    let $zeroSugar1 = true;
+    while($zeroSugar1){
+    	$zeroSugar0:	{
+    		if (x) 		break $zeroSugar0;
 
-    			console.log(x);
-    		}
-    		$zeroSugar1 = x;
+    		console.log(x);
     	}
+    	$zeroSugar1 = x;
     }
     "#);
 }
@@ -289,3 +287,25 @@ fn test_continue_nested_labeled() {
 
 
 
+
+#[test]
+fn test_continue_to_outer_of_two_labels() {
+    let result = parse_and_map(r#"
+        a: b: while (x) {
+            while (y) {
+                if (z) continue a;
+                continue b;
+            }
+        }
+    "#);
+
+    assert_snapshot!(result, @r#"
+    a:b:while(x)$zeroSugar0:{
+    	while(y)	{
+    		if (z) 		break $zeroSugar0;
+
+    		break $zeroSugar0;
+    	}
+    }
+    "#);
+}
@@ -0,0 +1,164 @@
+use insta::assert_snapshot;
+
+use zero_sugar::transform_code;
+
+fn transform_with_errors(source: &str) -> String {
+    let result = transform_code(source).unwrap();
+    let errors = result.errors.iter().map(|error| {
+        format!("{} {}:{} [{}, {}] {}", error.code, error.line, error.column, error.start, error.end, error.message)
+    }).collect::<Vec<String>>().join("\n");
+    format!("had_error: {}\n{}\n---\n{}", result.had_error, errors, result.transformed_code)
+}
+
+#[test]
+fn test_no_errors() {
+    let result = transform_with_errors(r#"
+        while (x) { if (y) continue; z(); }
+    "#);
+
+    assert_snapshot!(result, @r#"
+    had_error: false
+
+    ---
+    while(x)$zeroSugar0:{
+    	if (y) 	break $zeroSugar0;
+
+    	z();
+    }
+    "#);
+}
+
+#[test]
+fn test_parse_error() {
+    let result = transform_with_errors(r#"
+        let x = ;
+    "#);
+
+    assert_snapshot!(result, @r#"
+    had_error: true
+    ZS001 2:17 [17, 18] Unexpected token
+    ---
+    "#);
+}
+
+#[test]
+fn test_parse_error_message() {
+    let result = transform_code("x = ").unwrap();
+
+    assert!(result.had_error);
+    assert_eq!(result.error_message.as_deref(), result.errors.first().map(|error| error.message.as_str()));
+}
+
+#[test]
+fn test_for_await_is_left_alone() {
+    let result = transform_with_errors(r#"
+        async function f() {
+            for await (const x of y) foo(x);
+        }
+    "#);
+
+    assert_snapshot!(result, @r#"
+    had_error: true
+    ZS003 3:13 [42, 74] `for await` loops are not supported
+    ---
+    async function f() {
+    	for  await(const x of  y) 	foo(x);
+    }
+    "#);
+}
+
+#[test]
+fn test_class_in_switch_case() {
+    let result = transform_with_errors(r#"
+        switch (x) {
+            case 1:
+                class A {}
+        }
+    "#);
+
+    assert_snapshot!(result, @r#"
+    had_error: true
+    ZS003 4:17 [58, 68] Class declarations in the toplevel of a switch case are not supported
+    ---
+    switch(x){
+    	case 1:
+    		class A {
+    		}
+    }
+    "#);
+}
+
+#[test]
+fn test_pattern_in_switch_case() {
+    let result = transform_with_errors(r#"
+        switch (x) {
+            case 1:
+                let {a} = b;
+        }
+    "#);
+
+    assert_snapshot!(result, @r#"
+    had_error: false
+
+    ---
+    {
+    	let a;
+    	let $zeroSugar1 = 1;
+    	if ($zeroSugar1 === 1) 	$zeroSugar1 = 0;
+
+    	if ($zeroSugar1 <= 0) {
+    		a = b.a;
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_multiple_declarators_in_switch_case() {
+    let result = transform_with_errors(r#"
+        switch (x) {
+            case 1:
+                let a = 1, b;
+        }
+    "#);
+
+    assert_snapshot!(result, @r#"
+    had_error: false
+
+    ---
+    {
+    	let a;
+    	let b;
+    	let $zeroSugar1 = 1;
+    	if ($zeroSugar1 === 1) 	$zeroSugar1 = 0;
+
+    	if ($zeroSugar1 <= 0) {
+    		a = 1;
+    		b = undefined;
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_private_field_in_optional_chain() {
+    let result = transform_with_errors(r#"
+        class A {
+            #x;
+            f(a) { return a?.#x; }
+        }
+    "#);
+
+    assert_snapshot!(result, @r#"
+    had_error: false
+
+    ---
+    class A {
+    	#x;
+
+    	f(a){
+    		return a?.#x;
+    	}
+    }
+    "#);
+}
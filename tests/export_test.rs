@@ -0,0 +1,138 @@
+use insta::assert_snapshot;
+
+use zero_sugar::transform_code_with_options;
+use zero_sugar::HelperMode;
+use zero_sugar::TransformOptions;
+
+fn parse_and_map(source: &str) -> String {
+    let options = TransformOptions { helpers: HelperMode::None, ..TransformOptions::default() };
+    let result = transform_code_with_options(source, &options).unwrap();
+    assert!(!result.had_error, "Unexpected errors: {:?}", result.errors);
+    result.transformed_code
+}
+
+#[test]
+fn test_export_function() {
+    let result = parse_and_map(r#"
+        export function f() {
+            do { g(); } while (x);
+        }
+    "#);
+
+    assert_snapshot!(result, @r#"
+    export function f() {
+    	let $zeroSugar0 = true;
+    	while($zeroSugar0)	{
+    		{
+    			g();
+    		}
+    		$zeroSugar0 = x;
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_export_default_class() {
+    let result = parse_and_map(r#"
+        export default class {
+            m() {
+                for (;;) { if (x) continue; break; }
+            }
+        }
+    "#);
+
+    assert_snapshot!(result, @r#"
+    export default class {
+    	m(){
+    		while(true)		{
+    			$zeroSugar0:			{
+    				if (x) 				break $zeroSugar0;
+
+    				break;
+    			}
+    		}
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_export_default_expression() {
+    let result = parse_and_map(r#"
+        export default function () {
+            switch (x) { case 1: f(); }
+        };
+        export const g = () => { try { f(); } finally { g(); } };
+    "#);
+
+    assert_snapshot!(result, @r#"
+    export default function() {
+    	let $zeroSugar4 = 1;
+    	if ($zeroSugar4 === 1) 	$zeroSugar4 = 0;
+
+    	if ($zeroSugar4 <= 0) {
+    		f();
+    	}
+    };export const g = () => {
+    	let $zeroSugar0 = 0;
+    	let $zeroSugar1;
+    	$zeroSugar2:	try{
+    		f();
+    	}catch(e){
+    		$zeroSugar0 = 1;
+    		$zeroSugar1 = e;
+    	}	{
+    		g();
+    	}
+    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
+
+    };
+    "#);
+}
+
+#[test]
+fn test_export_pattern_decl() {
+    let result = parse_and_map(r#"
+        export const {a, b: [c, d]} = obj;
+        f(a, c);
+    "#);
+
+    assert_snapshot!(result, @r#"
+    const a = obj.a;
+    let $zeroSugar0 = obj.b;
+    let c = $zeroSugar0[0];
+    let d = $zeroSugar0[1];
+    export { a, c, d };f(a, c);
+    "#);
+}
+
+#[test]
+fn test_export_multi_decl() {
+    let result = parse_and_map(r#"
+        export let x = 1, y;
+        export var z;
+    "#);
+
+    assert_snapshot!(result, @r#"
+    let x = 1;
+    let y;
+    export { x, y };export var z;
+    "#);
+}
+
+#[test]
+fn test_export_untouched() {
+    let result = parse_and_map(r#"
+        import a, {b as c} from 'a';
+        export * from 'b';
+        export {a, c as d};
+        export const e = 1;
+    "#);
+
+    assert_snapshot!(result, @r#"
+    import a,{b as c} from 'a';
+    export* from'b';
+    export { a, c as d };export const e = 1;
+    "#);
+}
@@ -30,7 +30,7 @@ fn parse_and_map(source: &str) -> String {
     });
 
     let transformed = mapper.map(parsed.program);
-    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions::default());
+    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions);
 
 	let out = codegen.build(&transformed);
 
@@ -58,20 +58,17 @@ fn test_basic_try_finally() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar0 = 0;
-    	let $zeroSugar1;
-    	$zeroSugar2:	try{
-    		a();
-    	}catch(e){
-    		$zeroSugar0 = 1;
-    		$zeroSugar1 = e;
-    	}	{
-    		b();
-    	}
-    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
-
+    let $zeroSugar0 = 0;
+    let $zeroSugar1;
+    $zeroSugar2:try{
+    	a();
+    }catch(e){
+    	$zeroSugar0 = 1;
+    	$zeroSugar1 = e;
+    }{
+    	b();
     }
+    if ($zeroSugar0 === 1) throw $zeroSugar1;
     "#);
 }
 
@@ -89,26 +86,24 @@ fn test_try_finally_with_return() {
 
     assert_snapshot!(result, @r#"
     function f() {
-    	{
-    		let $zeroSugar0 = 0;
-    		let $zeroSugar1;
-    		$zeroSugar2:		try{
-    			{
-    				$zeroSugar0 = 2;
-    				$zeroSugar1 = a();
-    				break $zeroSugar2;
-    			}
-    		}catch(e){
-    			$zeroSugar0 = 1;
-    			$zeroSugar1 = e;
-    		}		{
-    			b();
+    	let $zeroSugar0 = 0;
+    	let $zeroSugar1;
+    	$zeroSugar2:	try{
+    		{
+    			$zeroSugar0 = 2;
+    			$zeroSugar1 = a();
+    			break $zeroSugar2;
     		}
-    		if ($zeroSugar0 === 1) 		throw $zeroSugar1;
+    	}catch(e){
+    		$zeroSugar0 = 1;
+    		$zeroSugar1 = e;
+    	}	{
+    		b();
+    	}
+    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
 
-    		if ($zeroSugar0 === 2) 		return $zeroSugar1;
+    	if ($zeroSugar0 === 2) 	return $zeroSugar1;
 
-    	}
     }
     "#);
 }
@@ -127,26 +122,24 @@ fn test_try_finally_with_return_in_both() {
 
     assert_snapshot!(result, @r#"
     function f() {
-    	{
-    		let $zeroSugar0 = 0;
-    		let $zeroSugar1;
-    		$zeroSugar2:		try{
-    			{
-    				$zeroSugar0 = 2;
-    				$zeroSugar1 = a();
-    				break $zeroSugar2;
-    			}
-    		}catch(e){
-    			$zeroSugar0 = 1;
-    			$zeroSugar1 = e;
-    		}		{
-    			return b();
+    	let $zeroSugar0 = 0;
+    	let $zeroSugar1;
+    	$zeroSugar2:	try{
+    		{
+    			$zeroSugar0 = 2;
+    			$zeroSugar1 = a();
+    			break $zeroSugar2;
     		}
-    		if ($zeroSugar0 === 1) 		throw $zeroSugar1;
+    	}catch(e){
+    		$zeroSugar0 = 1;
+    		$zeroSugar1 = e;
+    	}	{
+    		return b();
+    	}
+    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
 
-    		if ($zeroSugar0 === 2) 		return $zeroSugar1;
+    	if ($zeroSugar0 === 2) 	return $zeroSugar1;
 
-    	}
     }
     "#);
 }
@@ -162,20 +155,17 @@ fn test_try_finally_with_throw() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar0 = 0;
-    	let $zeroSugar1;
-    	$zeroSugar2:	try{
-    		throw new Error();
-    	}catch(e){
-    		$zeroSugar0 = 1;
-    		$zeroSugar1 = e;
-    	}	{
-    		cleanup();
-    	}
-    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
-
+    let $zeroSugar0 = 0;
+    let $zeroSugar1;
+    $zeroSugar2:try{
+    	throw new Error();
+    }catch(e){
+    	$zeroSugar0 = 1;
+    	$zeroSugar1 = e;
+    }{
+    	cleanup();
     }
+    if ($zeroSugar0 === 1) throw $zeroSugar1;
     "#);
 }
 
@@ -192,23 +182,20 @@ fn test_try_catch_finally() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar0 = 0;
-    	let $zeroSugar1;
-    	$zeroSugar2:	try{
-    		a();
-    	}catch(err){
-    		try{
-    			handleError(err);
-    		}catch($zeroSugar3){
-    			$zeroSugar0 = 1;
-    			$zeroSugar1 = $zeroSugar3;
-    		}	}	{
-    		cleanup();
-    	}
-    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
-
+    let $zeroSugar0 = 0;
+    let $zeroSugar1;
+    $zeroSugar2:try{
+    	a();
+    }catch(err){
+    	try{
+    		handleError(err);
+    	}catch($zeroSugar3){
+    		$zeroSugar0 = 1;
+    		$zeroSugar1 = $zeroSugar3;
+    	}}{
+    	cleanup();
     }
+    if ($zeroSugar0 === 1) throw $zeroSugar1;
     "#);
 }
 
@@ -227,33 +214,28 @@ fn test_nested_try_finally() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar3 = 0;
-    	let $zeroSugar4;
-    	$zeroSugar5:	try{
-    		{
-    			let $zeroSugar0 = 0;
-    			let $zeroSugar1;
-    			$zeroSugar2:			try{
-    				a();
-    			}catch(e){
-    				$zeroSugar0 = 1;
-    				$zeroSugar1 = e;
-    			}			{
-    				b();
-    			}
-    			if ($zeroSugar0 === 1) 			throw $zeroSugar1;
-
-    		}
+    let $zeroSugar3 = 0;
+    let $zeroSugar4;
+    $zeroSugar5:try{
+    	let $zeroSugar0 = 0;
+    	let $zeroSugar1;
+    	$zeroSugar2:	try{
+    		a();
     	}catch(e){
-    		$zeroSugar3 = 1;
-    		$zeroSugar4 = e;
+    		$zeroSugar0 = 1;
+    		$zeroSugar1 = e;
     	}	{
-    		c();
+    		b();
     	}
-    	if ($zeroSugar3 === 1) 	throw $zeroSugar4;
+    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
 
+    }catch(e){
+    	$zeroSugar3 = 1;
+    	$zeroSugar4 = e;
+    }{
+    	c();
     }
+    if ($zeroSugar3 === 1) throw $zeroSugar4;
     "#);
 }
 
@@ -271,26 +253,24 @@ fn test_try_finally_with_return_value() {
 
     assert_snapshot!(result, @r#"
     function f() {
-    	{
-    		let $zeroSugar0 = 0;
-    		let $zeroSugar1;
-    		$zeroSugar2:		try{
-    			{
-    				$zeroSugar0 = 2;
-    				$zeroSugar1 = getValue();
-    				break $zeroSugar2;
-    			}
-    		}catch(e){
-    			$zeroSugar0 = 1;
-    			$zeroSugar1 = e;
-    		}		{
-    			cleanup();
+    	let $zeroSugar0 = 0;
+    	let $zeroSugar1;
+    	$zeroSugar2:	try{
+    		{
+    			$zeroSugar0 = 2;
+    			$zeroSugar1 = getValue();
+    			break $zeroSugar2;
     		}
-    		if ($zeroSugar0 === 1) 		throw $zeroSugar1;
+    	}catch(e){
+    		$zeroSugar0 = 1;
+    		$zeroSugar1 = e;
+    	}	{
+    		cleanup();
+    	}
+    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
 
-    		if ($zeroSugar0 === 2) 		return $zeroSugar1;
+    	if ($zeroSugar0 === 2) 	return $zeroSugar1;
 
-    	}
     }
     "#);
 }
@@ -306,20 +286,17 @@ fn test_try_finally_without_catch() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar0 = 0;
-    	let $zeroSugar1;
-    	$zeroSugar2:	try{
-    		mayThrow();
-    	}catch(e){
-    		$zeroSugar0 = 1;
-    		$zeroSugar1 = e;
-    	}	{
-    		cleanup();
-    	}
-    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
-
+    let $zeroSugar0 = 0;
+    let $zeroSugar1;
+    $zeroSugar2:try{
+    	mayThrow();
+    }catch(e){
+    	$zeroSugar0 = 1;
+    	$zeroSugar1 = e;
+    }{
+    	cleanup();
     }
+    if ($zeroSugar0 === 1) throw $zeroSugar1;
     "#);
 }
 
@@ -338,26 +315,24 @@ fn test_try_finally_with_break() {
 
     assert_snapshot!(result, @r#"
     while(true){
-    	{
-    		let $zeroSugar0 = 0;
-    		let $zeroSugar1;
-    		$zeroSugar2:		try{
-    			if (x) {
-    				$zeroSugar0 = 3;
-    				break $zeroSugar2;
-    			}
-    			a();
-    		}catch(e){
-    			$zeroSugar0 = 1;
-    			$zeroSugar1 = e;
-    		}		{
-    			cleanup();
+    	let $zeroSugar0 = 0;
+    	let $zeroSugar1;
+    	$zeroSugar2:	try{
+    		if (x) {
+    			$zeroSugar0 = 3;
+    			break $zeroSugar2;
     		}
-    		if ($zeroSugar0 === 1) 		throw $zeroSugar1;
+    		a();
+    	}catch(e){
+    		$zeroSugar0 = 1;
+    		$zeroSugar1 = e;
+    	}	{
+    		cleanup();
+    	}
+    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
 
-    		if ($zeroSugar0 === 3) 		break;
+    	if ($zeroSugar0 === 3) 	break;
 
-    	}
     }
     "#);
 }
@@ -383,51 +358,47 @@ fn test_try_finally_with_nested_return() {
 
     assert_snapshot!(result, @r#"
     function f() {
-    	{
-    		let $zeroSugar3 = 0;
-    		let $zeroSugar4;
-    		$zeroSugar5:		try{
-    			if (x) {
+    	let $zeroSugar3 = 0;
+    	let $zeroSugar4;
+    	$zeroSugar5:	try{
+    		if (x) {
+    			let $zeroSugar0 = 0;
+    			let $zeroSugar1;
+    			$zeroSugar2:			try{
     				{
-    					let $zeroSugar0 = 0;
-    					let $zeroSugar1;
-    					$zeroSugar2:					try{
-    						{
-    							$zeroSugar0 = 2;
-    							$zeroSugar1 = inner();
-    							break $zeroSugar2;
-    						}
-    					}catch(e){
-    						$zeroSugar0 = 1;
-    						$zeroSugar1 = e;
-    					}					{
-    						cleanup1();
-    					}
-    					if ($zeroSugar0 === 1) 					throw $zeroSugar1;
-
-    					if ($zeroSugar0 === 2) {
-    						$zeroSugar3 = 2;
-    						$zeroSugar4 = $zeroSugar1;
-    						break $zeroSugar5;
-    					}
+    					$zeroSugar0 = 2;
+    					$zeroSugar1 = inner();
+    					break $zeroSugar2;
     				}
+    			}catch(e){
+    				$zeroSugar0 = 1;
+    				$zeroSugar1 = e;
+    			}			{
+    				cleanup1();
     			}
-    			{
+    			if ($zeroSugar0 === 1) 			throw $zeroSugar1;
+
+    			if ($zeroSugar0 === 2) {
     				$zeroSugar3 = 2;
-    				$zeroSugar4 = outer();
+    				$zeroSugar4 = $zeroSugar1;
     				break $zeroSugar5;
     			}
-    		}catch(e){
-    			$zeroSugar3 = 1;
-    			$zeroSugar4 = e;
-    		}		{
-    			cleanup2();
     		}
-    		if ($zeroSugar3 === 1) 		throw $zeroSugar4;
+    		{
+    			$zeroSugar3 = 2;
+    			$zeroSugar4 = outer();
+    			break $zeroSugar5;
+    		}
+    	}catch(e){
+    		$zeroSugar3 = 1;
+    		$zeroSugar4 = e;
+    	}	{
+    		cleanup2();
+    	}
+    	if ($zeroSugar3 === 1) 	throw $zeroSugar4;
 
-    		if ($zeroSugar3 === 2) 		return $zeroSugar4;
+    	if ($zeroSugar3 === 2) 	return $zeroSugar4;
 
-    	}
     }
     "#);
 }
@@ -449,28 +420,26 @@ fn test_try_finally_with_labeled_break() {
 
     assert_snapshot!(result, @r#"
     outer:while(true){
-    	{
-    		let $zeroSugar0 = 0;
-    		let $zeroSugar1;
-    		$zeroSugar2:		try{
-    			while(true)			{
-    				if (x) {
-    					$zeroSugar0 = 3;
-    					break $zeroSugar2;
-    				}
-    				a();
+    	let $zeroSugar0 = 0;
+    	let $zeroSugar1;
+    	$zeroSugar2:	try{
+    		while(true)		{
+    			if (x) {
+    				$zeroSugar0 = 3;
+    				break $zeroSugar2;
     			}
-    		}catch(e){
-    			$zeroSugar0 = 1;
-    			$zeroSugar1 = e;
-    		}		{
-    			cleanup();
+    			a();
     		}
-    		if ($zeroSugar0 === 1) 		throw $zeroSugar1;
+    	}catch(e){
+    		$zeroSugar0 = 1;
+    		$zeroSugar1 = e;
+    	}	{
+    		cleanup();
+    	}
+    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
 
-    		if ($zeroSugar0 === 3) 		break outer;
+    	if ($zeroSugar0 === 3) 	break outer;
 
-    	}
     }
     "#);
 }
@@ -500,53 +469,53 @@ fn test_try_finally_with_nested_break_and_return() {
     assert_snapshot!(result, @r#"
     function f() {
     	loop1:	while(true)	{
-    		{
-    			let $zeroSugar3 = 0;
-    			let $zeroSugar4;
-    			$zeroSugar5:			try{
-    				loop2:				while(true)				{
-    					{
-    						let $zeroSugar0 = 0;
-    						let $zeroSugar1;
-    						$zeroSugar2:						try{
-    							if (x) {
-    								$zeroSugar0 = 3;
-    								break $zeroSugar2;
-    							}
-    							if (y) {
-    								$zeroSugar0 = 2;
-    								$zeroSugar1 = value;
-    								break $zeroSugar2;
-    							}
-    							a();
-    						}catch(e){
-    							$zeroSugar0 = 1;
-    							$zeroSugar1 = e;
-    						}						{
-    							cleanup1();
-    						}
-    						if ($zeroSugar0 === 1) 						throw $zeroSugar1;
-
-    						if ($zeroSugar0 === 2) {
-    							$zeroSugar3 = 2;
-    							$zeroSugar4 = $zeroSugar1;
-    							break $zeroSugar5;
-    						}
-    						if ($zeroSugar0 === 3) 						break loop1;
-
+    		let $zeroSugar3 = 0;
+    		let $zeroSugar4;
+    		$zeroSugar5:		try{
+    			loop2:			while(true)			{
+    				let $zeroSugar0 = 0;
+    				let $zeroSugar1;
+    				$zeroSugar2:				try{
+    					if (x) {
+    						$zeroSugar0 = 3;
+    						break $zeroSugar2;
+    					}
+    					if (y) {
+    						$zeroSugar0 = 2;
+    						$zeroSugar1 = value;
+    						break $zeroSugar2;
     					}
+    					a();
+    				}catch(e){
+    					$zeroSugar0 = 1;
+    					$zeroSugar1 = e;
+    				}				{
+    					cleanup1();
+    				}
+    				if ($zeroSugar0 === 1) 				throw $zeroSugar1;
+
+    				if ($zeroSugar0 === 2) {
+    					$zeroSugar3 = 2;
+    					$zeroSugar4 = $zeroSugar1;
+    					break $zeroSugar5;
+    				}
+    				if ($zeroSugar0 === 3) {
+    					$zeroSugar3 = 3;
+    					break $zeroSugar5;
     				}
-    			}catch(e){
-    				$zeroSugar3 = 1;
-    				$zeroSugar4 = e;
-    			}			{
-    				cleanup2();
     			}
-    			if ($zeroSugar3 === 1) 			throw $zeroSugar4;
+    		}catch(e){
+    			$zeroSugar3 = 1;
+    			$zeroSugar4 = e;
+    		}		{
+    			cleanup2();
+    		}
+    		if ($zeroSugar3 === 1) 		throw $zeroSugar4;
 
-    			if ($zeroSugar3 === 2) 			return $zeroSugar4;
+    		if ($zeroSugar3 === 2) 		return $zeroSugar4;
+
+    		if ($zeroSugar3 === 3) 		break loop1;
 
-    		}
     	}
     }
     "#);
@@ -567,30 +536,28 @@ fn test_try_finally_with_multiple_returns() {
 
     assert_snapshot!(result, @r#"
     function f() {
-    	{
-    		let $zeroSugar0 = 0;
-    		let $zeroSugar1;
-    		$zeroSugar2:		try{
-    			if (x) {
-    				$zeroSugar0 = 2;
-    				$zeroSugar1 = 'a';
-    				break $zeroSugar2;
-    			} else {
-    				$zeroSugar0 = 2;
-    				$zeroSugar1 = 'b';
-    				break $zeroSugar2;
-    			}
-    		}catch(e){
-    			$zeroSugar0 = 1;
-    			$zeroSugar1 = e;
-    		}		{
-    			cleanup();
+    	let $zeroSugar0 = 0;
+    	let $zeroSugar1;
+    	$zeroSugar2:	try{
+    		if (x) {
+    			$zeroSugar0 = 2;
+    			$zeroSugar1 = 'a';
+    			break $zeroSugar2;
+    		} else {
+    			$zeroSugar0 = 2;
+    			$zeroSugar1 = 'b';
+    			break $zeroSugar2;
     		}
-    		if ($zeroSugar0 === 1) 		throw $zeroSugar1;
+    	}catch(e){
+    		$zeroSugar0 = 1;
+    		$zeroSugar1 = e;
+    	}	{
+    		cleanup();
+    	}
+    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
 
-    		if ($zeroSugar0 === 2) 		return $zeroSugar1;
+    	if ($zeroSugar0 === 2) 	return $zeroSugar1;
 
-    	}
     }
     "#);
 }
@@ -608,22 +575,19 @@ fn test_try_finally_with_internal_break() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar0 = 0;
-    	let $zeroSugar1;
-    	$zeroSugar2:	try{
-    		a:		{
-    			break a;
-    		}
-    	}catch(e){
-    		$zeroSugar0 = 1;
-    		$zeroSugar1 = e;
-    	}	{
-    		cleanup();
+    let $zeroSugar0 = 0;
+    let $zeroSugar1;
+    $zeroSugar2:try{
+    	a:	{
+    		break a;
     	}
-    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
-
+    }catch(e){
+    	$zeroSugar0 = 1;
+    	$zeroSugar1 = e;
+    }{
+    	cleanup();
     }
+    if ($zeroSugar0 === 1) throw $zeroSugar1;
     "#);
 }
 
@@ -680,40 +644,38 @@ fn test_try_finally_with_nested_returns_in_blocks() {
 
     assert_snapshot!(result, @r#"
     function f() {
-    	{
-    		let $zeroSugar0 = 0;
-    		let $zeroSugar1;
-    		$zeroSugar2:		try{
-    			if (x) {
-    				if (y) {
-    					{
-    						$zeroSugar0 = 2;
-    						$zeroSugar1 = 'a';
-    						break $zeroSugar2;
-    					}
-    				}
+    	let $zeroSugar0 = 0;
+    	let $zeroSugar1;
+    	$zeroSugar2:	try{
+    		if (x) {
+    			if (y) {
     				{
     					$zeroSugar0 = 2;
-    					$zeroSugar1 = 'b';
+    					$zeroSugar1 = 'a';
     					break $zeroSugar2;
     				}
     			}
     			{
     				$zeroSugar0 = 2;
-    				$zeroSugar1 = 'c';
+    				$zeroSugar1 = 'b';
     				break $zeroSugar2;
     			}
-    		}catch(e){
-    			$zeroSugar0 = 1;
-    			$zeroSugar1 = e;
-    		}		{
-    			cleanup();
     		}
-    		if ($zeroSugar0 === 1) 		throw $zeroSugar1;
+    		{
+    			$zeroSugar0 = 2;
+    			$zeroSugar1 = 'c';
+    			break $zeroSugar2;
+    		}
+    	}catch(e){
+    		$zeroSugar0 = 1;
+    		$zeroSugar1 = e;
+    	}	{
+    		cleanup();
+    	}
+    	if ($zeroSugar0 === 1) 	throw $zeroSugar1;
 
-    		if ($zeroSugar0 === 2) 		return $zeroSugar1;
+    	if ($zeroSugar0 === 2) 	return $zeroSugar1;
 
-    	}
     }
     "#);
 }
@@ -740,37 +702,35 @@ fn test_try_finally_with_mixed_breaks_and_returns() {
     assert_snapshot!(result, @r#"
     function f() {
     	outer:	{
-    		{
-    			let $zeroSugar0 = 0;
-    			let $zeroSugar1;
-    			$zeroSugar2:			try{
-    				inner:				{
-    					if (x) 					break inner;
+    		let $zeroSugar0 = 0;
+    		let $zeroSugar1;
+    		$zeroSugar2:		try{
+    			inner:			{
+    				if (x) 				break inner;
 
-    					if (y) {
-    						$zeroSugar0 = 3;
-    						break $zeroSugar2;
-    					}
-    					{
-    						$zeroSugar0 = 2;
-    						$zeroSugar1 = value;
-    						break $zeroSugar2;
-    					}
+    				if (y) {
+    					$zeroSugar0 = 3;
+    					break $zeroSugar2;
+    				}
+    				{
+    					$zeroSugar0 = 2;
+    					$zeroSugar1 = value;
+    					break $zeroSugar2;
     				}
-    				moreCode();
-    			}catch(e){
-    				$zeroSugar0 = 1;
-    				$zeroSugar1 = e;
-    			}			{
-    				cleanup();
     			}
-    			if ($zeroSugar0 === 1) 			throw $zeroSugar1;
+    			moreCode();
+    		}catch(e){
+    			$zeroSugar0 = 1;
+    			$zeroSugar1 = e;
+    		}		{
+    			cleanup();
+    		}
+    		if ($zeroSugar0 === 1) 		throw $zeroSugar1;
 
-    			if ($zeroSugar0 === 2) 			return $zeroSugar1;
+    		if ($zeroSugar0 === 2) 		return $zeroSugar1;
 
-    			if ($zeroSugar0 === 3) 			break outer;
+    		if ($zeroSugar0 === 3) 		break outer;
 
-    		}
     	}
     }
     "#);
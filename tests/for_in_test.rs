@@ -30,7 +30,7 @@ fn parse_and_map(source: &str) -> String {
     });
 
     let transformed = mapper.map(parsed.program);
-    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions::default());
+    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions);
 
 	let out = codegen.build(&transformed);
 
@@ -56,16 +56,14 @@ fn test_basic_for_in() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar0 = $forIn(obj);
-    	let $zeroSugar1;
-    	while($zeroSugar1 = $zeroSugar0.next())	{
-    		if ($zeroSugar1.done === true) 		break;
+    const $zeroSugar0 = $forIn(obj);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
 
-    		let x = $zeroSugar1.value;
-    		{
-    			console.log(x);
-    		}
+    	let x = $zeroSugar1.value;
+    	{
+    		console.log(x);
     	}
     }
     "#);
@@ -80,16 +78,14 @@ fn test_for_in_bare_identifier() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar0 = $forIn(obj);
-    	let $zeroSugar1;
-    	while($zeroSugar1 = $zeroSugar0.next())	{
-    		if ($zeroSugar1.done === true) 		break;
+    const $zeroSugar0 = $forIn(obj);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
 
-    		x = $zeroSugar1.value;
-    		{
-    			console.log(x);
-    		}
+    	x = $zeroSugar1.value;
+    	{
+    		console.log(x);
     	}
     }
     "#);
@@ -106,16 +102,14 @@ fn test_for_in_var() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar0 = $forIn(obj);
-    	let $zeroSugar1;
-    	while($zeroSugar1 = $zeroSugar0.next())	{
-    		if ($zeroSugar1.done === true) 		break;
+    const $zeroSugar0 = $forIn(obj);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
 
-    		var x = $zeroSugar1.value;
-    		{
-    			console.log(x);
-    		}
+    	var x = $zeroSugar1.value;
+    	{
+    		console.log(x);
     	}
     }
     "#);
@@ -130,16 +124,14 @@ fn test_for_in_const() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar0 = $forIn(obj);
-    	let $zeroSugar1;
-    	while($zeroSugar1 = $zeroSugar0.next())	{
-    		if ($zeroSugar1.done === true) 		break;
+    const $zeroSugar0 = $forIn(obj);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
 
-    		const x = $zeroSugar1.value;
-    		{
-    			console.log(x);
-    		}
+    	const x = $zeroSugar1.value;
+    	{
+    		console.log(x);
     	}
     }
     "#);
@@ -152,15 +144,13 @@ fn test_for_in_no_block() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar0 = $forIn(obj);
-    	let $zeroSugar1;
-    	while($zeroSugar1 = $zeroSugar0.next())	{
-    		if ($zeroSugar1.done === true) 		break;
+    const $zeroSugar0 = $forIn(obj);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
 
-    		let x = $zeroSugar1.value;
-    		console.log(x);
-    	}
+    	let x = $zeroSugar1.value;
+    	console.log(x);
     }
     "#);
 }
@@ -176,25 +166,21 @@ fn test_for_in_nested() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar2 = $forIn(obj1);
-    	let $zeroSugar3;
-    	while($zeroSugar3 = $zeroSugar2.next())	{
-    		if ($zeroSugar3.done === true) 		break;
+    const $zeroSugar2 = $forIn(obj1);
+    let $zeroSugar3;
+    while($zeroSugar3 = $zeroSugar2.next()){
+    	if ($zeroSugar3.done === true) 	break;
 
-    		let x = $zeroSugar3.value;
-    		{
+    	let x = $zeroSugar3.value;
+    	{
+    		const $zeroSugar0 = $forIn(obj2);
+    		let $zeroSugar1;
+    		while($zeroSugar1 = $zeroSugar0.next())		{
+    			if ($zeroSugar1.done === true) 			break;
+
+    			let y = $zeroSugar1.value;
     			{
-    				const $zeroSugar0 = $forIn(obj2);
-    				let $zeroSugar1;
-    				while($zeroSugar1 = $zeroSugar0.next())				{
-    					if ($zeroSugar1.done === true) 					break;
-
-    					let y = $zeroSugar1.value;
-    					{
-    						console.log(x, y);
-    					}
-    				}
+    				console.log(x, y);
     			}
     		}
     	}
@@ -211,16 +197,14 @@ fn test_for_in_complex_right() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar0 = $forIn(foo.bar().baz);
-    	let $zeroSugar1;
-    	while($zeroSugar1 = $zeroSugar0.next())	{
-    		if ($zeroSugar1.done === true) 		break;
+    const $zeroSugar0 = $forIn(foo.bar().baz);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
 
-    		let x = $zeroSugar1.value;
-    		{
-    			console.log(x);
-    		}
+    	let x = $zeroSugar1.value;
+    	{
+    		console.log(x);
     	}
     }
     "#);
@@ -236,18 +220,16 @@ fn test_for_in_with_continue() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar0 = $forIn(obj);
-    	let $zeroSugar1;
-    	while($zeroSugar1 = $zeroSugar0.next())	{
-    		if ($zeroSugar1.done === true) 		break;
+    const $zeroSugar0 = $forIn(obj);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
 
-    		let x = $zeroSugar1.value;
-    		{
-    			if (x === 'skip') 			continue;
+    	let x = $zeroSugar1.value;
+    	{
+    		if (x === 'skip') 		continue;
 
-    			console.log(x);
-    		}
+    		console.log(x);
     	}
     }
     "#);
@@ -263,18 +245,16 @@ fn test_for_in_with_break() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar0 = $forIn(obj);
-    	let $zeroSugar1;
-    	while($zeroSugar1 = $zeroSugar0.next())	{
-    		if ($zeroSugar1.done === true) 		break;
+    const $zeroSugar0 = $forIn(obj);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
 
-    		let x = $zeroSugar1.value;
-    		{
-    			if (x === 'stop') 			break;
+    	let x = $zeroSugar1.value;
+    	{
+    		if (x === 'stop') 		break;
 
-    			console.log(x);
-    		}
+    		console.log(x);
     	}
     }
     "#);
@@ -300,18 +280,16 @@ fn test_for_in_with_labeled_continue() {
 
     		let x = $zeroSugar3.value;
     		{
-    			{
-    				const $zeroSugar0 = $forIn(obj2);
-    				let $zeroSugar1;
-    				while($zeroSugar1 = $zeroSugar0.next())				{
-    					if ($zeroSugar1.done === true) 					break;
+    			const $zeroSugar0 = $forIn(obj2);
+    			let $zeroSugar1;
+    			while($zeroSugar1 = $zeroSugar0.next())			{
+    				if ($zeroSugar1.done === true) 				break;
 
-    					let y = $zeroSugar1.value;
-    					{
-    						if (y === 'skip') 						continue outer;
+    				let y = $zeroSugar1.value;
+    				{
+    					if (y === 'skip') 					continue outer;
 
-    						console.log(x, y);
-    					}
+    					console.log(x, y);
     				}
     			}
     		}
@@ -340,18 +318,16 @@ fn test_for_in_with_labeled_break() {
 
     		let x = $zeroSugar3.value;
     		{
-    			{
-    				const $zeroSugar0 = $forIn(obj2);
-    				let $zeroSugar1;
-    				while($zeroSugar1 = $zeroSugar0.next())				{
-    					if ($zeroSugar1.done === true) 					break;
+    			const $zeroSugar0 = $forIn(obj2);
+    			let $zeroSugar1;
+    			while($zeroSugar1 = $zeroSugar0.next())			{
+    				if ($zeroSugar1.done === true) 				break;
 
-    					let y = $zeroSugar1.value;
-    					{
-    						if (y === 'stop') 						break outer;
+    				let y = $zeroSugar1.value;
+    				{
+    					if (y === 'stop') 					break outer;
 
-    						console.log(x, y);
-    					}
+    					console.log(x, y);
     				}
     			}
     		}
@@ -370,19 +346,17 @@ fn test_for_in_with_function_declaration() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar0 = $forIn(obj);
-    	let $zeroSugar1;
-    	while($zeroSugar1 = $zeroSugar0.next())	{
-    		if ($zeroSugar1.done === true) 		break;
+    const $zeroSugar0 = $forIn(obj);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
 
-    		let x = $zeroSugar1.value;
-    		{
-    			function f() {
-    				return x;
-    			}
-    			console.log(f());
+    	let x = $zeroSugar1.value;
+    	{
+    		function f() {
+    			return x;
     		}
+    		console.log(f());
     	}
     }
     "#);
@@ -402,21 +376,19 @@ fn test_for_in_with_try_catch() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar0 = $forIn(obj);
-    	let $zeroSugar1;
-    	while($zeroSugar1 = $zeroSugar0.next())	{
-    		if ($zeroSugar1.done === true) 		break;
+    const $zeroSugar0 = $forIn(obj);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
 
-    		let x = $zeroSugar1.value;
-    		{
-    			try{
-    				risky(x);
-    			}catch(e){
-    				console.error(e);
-    				continue;
-    			}		}
-    	}
+    	let x = $zeroSugar1.value;
+    	{
+    		try{
+    			risky(x);
+    		}catch(e){
+    			console.error(e);
+    			continue;
+    		}	}
     }
     "#);
 }
@@ -434,18 +406,16 @@ fn test_for_in_with_return() {
 
     assert_snapshot!(result, @r#"
     function f() {
-    	{
-    		const $zeroSugar0 = $forIn(obj);
-    		let $zeroSugar1;
-    		while($zeroSugar1 = $zeroSugar0.next())		{
-    			if ($zeroSugar1.done === true) 			break;
+    	const $zeroSugar0 = $forIn(obj);
+    	let $zeroSugar1;
+    	while($zeroSugar1 = $zeroSugar0.next())	{
+    		if ($zeroSugar1.done === true) 		break;
 
-    			let x = $zeroSugar1.value;
-    			{
-    				if (x === 'special') 				return x;
+    		let x = $zeroSugar1.value;
+    		{
+    			if (x === 'special') 			return x;
 
-    				console.log(x);
-    			}
+    			console.log(x);
     		}
     	}
     }
@@ -461,18 +431,16 @@ fn test_for_in_with_static_member_left() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar1 = $forIn(source);
-    	let $zeroSugar2;
-    	while($zeroSugar2 = $zeroSugar1.next())	{
-    		if ($zeroSugar2.done === true) 		break;
+    const $zeroSugar1 = $forIn(source);
+    let $zeroSugar2;
+    while($zeroSugar2 = $zeroSugar1.next()){
+    	if ($zeroSugar2.done === true) 	break;
 
-    		$zeroSugar0 = $zeroSugar2.value;
+    	$zeroSugar0 = $zeroSugar2.value;
+    	{
+    		obj.key = $zeroSugar0;
     		{
-    			obj.key = $zeroSugar0;
-    			{
-    				console.log(obj.key);
-    			}
+    			console.log(obj.key);
     		}
     	}
     }
@@ -488,18 +456,16 @@ fn test_for_in_with_computed_member_left() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	const $zeroSugar1 = $forIn(source);
-    	let $zeroSugar2;
-    	while($zeroSugar2 = $zeroSugar1.next())	{
-    		if ($zeroSugar2.done === true) 		break;
+    const $zeroSugar1 = $forIn(source);
+    let $zeroSugar2;
+    while($zeroSugar2 = $zeroSugar1.next()){
+    	if ($zeroSugar2.done === true) 	break;
 
-    		$zeroSugar0 = $zeroSugar2.value;
+    	$zeroSugar0 = $zeroSugar2.value;
+    	{
+    		obj[key] = $zeroSugar0;
     		{
-    			obj[key] = $zeroSugar0;
-    			{
-    				console.log(obj[key]);
-    			}
+    			console.log(obj[key]);
     		}
     	}
     }
@@ -520,27 +486,25 @@ fn test_for_in_with_private_field_member_left() {
 		}
     "#);
 
-    assert_snapshot!(result, @r##"
+    assert_snapshot!(result, @r#"
     class C {
     	#x;
 
     	constructor(x){
-    		{
-    			const $zeroSugar1 = $forIn(source);
-    			let $zeroSugar2;
-    			while($zeroSugar2 = $zeroSugar1.next())			{
-    				if ($zeroSugar2.done === true) 				break;
+    		const $zeroSugar1 = $forIn(source);
+    		let $zeroSugar2;
+    		while($zeroSugar2 = $zeroSugar1.next())		{
+    			if ($zeroSugar2.done === true) 			break;
 
-    				$zeroSugar0 = $zeroSugar2.value;
+    			$zeroSugar0 = $zeroSugar2.value;
+    			{
+    				this.#x = $zeroSugar0;
     				{
-    					this.#x = $zeroSugar0;
-    					{
-    						console.log(this.#x);
-    					}
+    					console.log(this.#x);
     				}
     			}
     		}
     	}
     }
-    "##);
+    "#);
 }
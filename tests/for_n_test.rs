@@ -30,7 +30,7 @@ fn parse_and_map(source: &str) -> String {
     });
 
     let transformed = mapper.map(parsed.program);
-    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions::default());
+    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions);
 
     let out = codegen.build(&transformed);
 
@@ -180,14 +180,12 @@ fn test_for_loop_with_expression_init() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	x = 0;
-    	while(x < 5)	{
-    		{
-    			console.log(x);
-    		}
-    		x++;
+    x = 0;
+    while(x < 5){
+    	{
+    		console.log(x);
     	}
+    	x++;
     }
     "#);
 }
@@ -201,14 +199,12 @@ fn test_for_loop_with_expression_sequence_init() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	x = 0,x = 2;
-    	while(x < 5)	{
-    		{
-    			console.log(x);
-    		}
-    		x++;
+    x = 0,x = 2;
+    while(x < 5){
+    	{
+    		console.log(x);
     	}
+    	x++;
     }
     "#);
 }
@@ -0,0 +1,265 @@
+use insta::assert_snapshot;
+
+use zero_sugar::transform_code_with_options;
+use zero_sugar::HelperMode;
+use zero_sugar::TransformOptions;
+
+fn parse_and_map(source: &str, helpers: HelperMode) -> String {
+    let options = TransformOptions {
+        helpers,
+        helpers_module: "./runtime.js".to_string(),
+        ..TransformOptions::default()
+    };
+    let result = transform_code_with_options(source, &options).unwrap();
+    assert!(!result.had_error, "Unexpected errors: {:?}", result.errors);
+    result.transformed_code
+}
+
+#[test]
+fn test_helpers_not_used() {
+    let result = parse_and_map(r#"
+        for (let i = 0; i < 10; i++) f(i);
+    "#, HelperMode::Inline);
+
+    assert_snapshot!(result, @r#"
+    {
+    	let i = 0;
+    	while(i < 10)	{
+    		f(i);
+    		i++;
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_helpers_inline() {
+    let result = parse_and_map(r#"
+        let {a, ...b} = y;
+        for (const x in b) f(x);
+        for (const x of a) f(x);
+    "#, HelperMode::Inline);
+
+    assert_snapshot!(result, @r#"
+    function $forIn(obj) {
+    	var keys = [];
+    	var from = obj === null || obj === undefined ? null : Object(obj);
+    	var seen = Object.create(null);
+    	var target = from;
+    	while(true)	{
+    		if (target === null) {
+    			break;
+    		}
+    		var names = Object.getOwnPropertyNames(target);
+    		var i = 0;
+    		while(true)		{
+    			if (i >= names.length) {
+    				break;
+    			}
+    			if (seen[names[i]] !== true) {
+    				seen[names[i]] = true;
+    				if (Object.prototype.propertyIsEnumerable.call(target, names[i])) {
+    					keys.push(names[i]);
+    				}
+    			}
+    			i++;
+    		}
+    		target = Object.getPrototypeOf(target);
+    	}
+    	var index = 0;
+    	return {
+    		next:function() {
+    			while(true)			{
+    				if (index >= keys.length) {
+    					return {
+    						done:true,
+    						value:undefined
+    					};
+    				}
+    				var key = keys[index];
+    				index++;
+    				if (key in  from) {
+    					return {
+    						done:false,
+    						value:key
+    					};
+    				}
+    			}
+    		}
+    	};
+    }
+    function $forOf(obj) {
+    	if (typeof Symbol === 'function') {
+    		return obj[Symbol.iterator]();
+    	}
+    	var from = Object(obj);
+    	var index = 0;
+    	return {
+    		next:function() {
+    			if (index >= from.length) {
+    				return {
+    					done:true,
+    					value:undefined
+    				};
+    			}
+    			index++;
+    			return {
+    				done:false,
+    				value:from[index - 1]
+    			};
+    		}
+    	};
+    }
+    function $rest(obj, keys) {
+    	if (obj === null || obj === undefined) {
+    		throw new TypeError('Cannot destructure \'' + obj + '\' as it is ' + obj + '.');
+    	}
+    	var from = Object(obj);
+    	var excluded = [];
+    	var i = 0;
+    	while(true)	{
+    		if (i >= keys.length) {
+    			break;
+    		}
+    		excluded.push(typeof keys[i] === 'symbol' ? keys[i] : String(keys[i]));
+    		i++;
+    	}
+    	var result = {};
+    	var own = Object.getOwnPropertyNames(from);
+    	if (typeof Object.getOwnPropertySymbols === 'function') {
+    		own = own.concat(Object.getOwnPropertySymbols(from));
+    	}
+    	var j = 0;
+    	while(true)	{
+    		if (j >= own.length) {
+    			break;
+    		}
+    		if (excluded.indexOf(own[j]) ===  -1 && Object.prototype.propertyIsEnumerable.call(from, own[j])) {
+    			result[own[j]] = from[own[j]];
+    		}
+    		j++;
+    	}
+    	return result;
+    }
+    let a = y.a;
+    let b = $rest(y, ['a']);
+    const $zeroSugar0 = $forIn(b);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
+
+    	const x = $zeroSugar1.value;
+    	f(x);
+    }
+    const $zeroSugar2 = $forOf(a);
+    let $zeroSugar3;
+    while($zeroSugar3 = $zeroSugar2.next()){
+    	if ($zeroSugar3.done === true) 	break;
+
+    	const x = $zeroSugar3.value;
+    	f(x);
+    }
+    "#);
+}
+
+#[test]
+fn test_helpers_import() {
+    let result = parse_and_map(r#"
+        for (const x of a) f(x);
+        for (const x in b) f(x);
+    "#, HelperMode::Import);
+
+    assert_snapshot!(result, @r#"
+    import {$forIn,$forOf} from './runtime.js';
+    const $zeroSugar0 = $forOf(a);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
+
+    	const x = $zeroSugar1.value;
+    	f(x);
+    }
+    const $zeroSugar2 = $forIn(b);
+    let $zeroSugar3;
+    while($zeroSugar3 = $zeroSugar2.next()){
+    	if ($zeroSugar3.done === true) 	break;
+
+    	const x = $zeroSugar3.value;
+    	f(x);
+    }
+    "#);
+}
+
+#[test]
+fn test_helpers_none() {
+    let result = parse_and_map(r#"
+        for (const x of a) f(x);
+    "#, HelperMode::None);
+
+    assert_snapshot!(result, @r#"
+    const $zeroSugar0 = $forOf(a);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
+
+    	const x = $zeroSugar1.value;
+    	f(x);
+    }
+    "#);
+}
+
+#[test]
+fn test_helpers_after_directives() {
+    let result = parse_and_map(r#"
+        "use strict";
+        for (const x of a) f(x);
+    "#, HelperMode::Inline);
+
+    assert_snapshot!(result, @r#"
+    'use strict';function $forOf(obj) {
+    	if (typeof Symbol === 'function') {
+    		return obj[Symbol.iterator]();
+    	}
+    	var from = Object(obj);
+    	var index = 0;
+    	return {
+    		next:function() {
+    			if (index >= from.length) {
+    				return {
+    					done:true,
+    					value:undefined
+    				};
+    			}
+    			index++;
+    			return {
+    				done:false,
+    				value:from[index - 1]
+    			};
+    		}
+    	};
+    }
+    const $zeroSugar0 = $forOf(a);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
+
+    	const x = $zeroSugar1.value;
+    	f(x);
+    }
+    "#);
+}
+
+#[test]
+fn test_helpers_module_is_escaped() {
+    let options = TransformOptions {
+        helpers: HelperMode::Import,
+        helpers_module: "./a\"b\\c\nd.js".to_string(),
+        ..TransformOptions::default()
+    };
+    let result = transform_code_with_options("for (const x of a) f(x);", &options).unwrap();
+
+    assert!(!result.had_error, "Unexpected errors: {:?}", result.errors);
+    assert_snapshot!(result.transformed_code.lines().next().unwrap(), @r#"
+    import {$forOf} from './a\"b\\c\nd.js';
+    "#);
+}
@@ -0,0 +1,177 @@
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use zero_sugar::inventory::{find_features, Feature};
+use zero_sugar::{Inventory, Preset, TransformOptions};
+
+fn features(source: &str) -> String {
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, source, SourceType::default().with_module(true)).parse();
+    assert!(parsed.errors.is_empty(), "Parse errors: {:?}", parsed.errors);
+
+    find_features(&parsed.program).iter()
+        .map(|(feature, span)| format!("{} [{}, {}] {}", feature.as_str(), span.start, span.end, &source[span.start as usize..span.end as usize]))
+        .collect::<Vec<String>>().join("\n")
+}
+
+#[test]
+fn test_statement_features() {
+    assert_snapshot!(features(r#"
+a: for (let i = 0; i < n; i++) { if (i) continue a; }
+switch (k) { case 1: do f(); while (x); }
+try { f(); } catch ({message}) {} finally { g(); }
+for (const [p, q] of list) for (k in obj) while (true) break;
+for ([x, y] of pairs);
+with (obj) { debugger; }
+"#), @r#"
+    label [1, 54] a: for (let i = 0; i < n; i++) { if (i) continue a; }
+    for [4, 54] for (let i = 0; i < n; i++) { if (i) continue a; }
+    let [9, 18] let i = 0
+    continue [41, 52] continue a;
+    switch [55, 96] switch (k) { case 1: do f(); while (x); }
+    do-while [76, 94] do f(); while (x);
+    destructuring-parameter [117, 126] {message}
+    finally [139, 147] { g(); }
+    for-of [148, 209] for (const [p, q] of list) for (k in obj) while (true) break;
+    const [153, 165] const [p, q]
+    destructuring-declaration [159, 165] [p, q]
+    for-in [175, 209] for (k in obj) while (true) break;
+    while-true [190, 209] while (true) break;
+    for-of [210, 232] for ([x, y] of pairs);
+    destructuring-assignment [215, 221] [x, y]
+    with [233, 257] with (obj) { debugger; }
+    debugger [246, 255] debugger;
+    "#);
+}
+
+#[test]
+fn test_function_and_expression_features() {
+    assert_snapshot!(features(r#"
+async function* gen(first = 1, {second}, ...others) { yield await z; }
+class C { async m() {} *n() {} }
+const f = async ([a, ...b]) => o?.p ?? [...a];
+x ||= y ** 2; eval(`${x}`); tag`t`; import("m"); new.target; 10n;
+(function () { g(...args); })();
+"#), @r#"
+    async [1, 71] async function* gen(first = 1, {second}, ...others) { yield await z; }
+    generator [1, 71] async function* gen(first = 1, {second}, ...others) { yield await z; }
+    default-parameter [21, 30] first = 1
+    destructuring-parameter [32, 40] {second}
+    rest [42, 51] ...others
+    yield [55, 68] yield await z
+    await [61, 68] await z
+    class [72, 104] class C { async m() {} *n() {} }
+    async [89, 94] () {}
+    generator [97, 102] () {}
+    const [105, 151] const f = async ([a, ...b]) => o?.p ?? [...a];
+    arrow [115, 150] async ([a, ...b]) => o?.p ?? [...a]
+    async [115, 150] async ([a, ...b]) => o?.p ?? [...a]
+    destructuring-parameter [122, 131] [a, ...b]
+    rest [126, 130] ...b
+    nullish-coalescing [136, 150] o?.p ?? [...a]
+    optional-chaining [136, 140] o?.p
+    spread [145, 149] ...a
+    logical-assignment [152, 164] x ||= y ** 2
+    exponentiation [158, 164] y ** 2
+    eval [166, 178] eval(`${x}`)
+    template-literal [171, 177] `${x}`
+    tagged-template [180, 186] tag`t`
+    dynamic-import [188, 199] import("m")
+    meta-property [201, 211] new.target
+    bigint [213, 216] 10n
+    spread [235, 242] ...args
+    "#);
+}
+
+#[test]
+fn test_report_per_file_and_in_total() {
+    let options = TransformOptions::new();
+    let mut inventory = Inventory::new();
+    inventory.add_source("a.js", "for (const x of y) f(x);\nswitch (x) {}\n", &options);
+    inventory.add_source("b.js", "for (const [a] of b) {}\n", &options);
+    inventory.add_source("c.js", "x = ;", &options);
+
+    assert_eq!(inventory.totals().get(&Feature::ForOf), Some(&(2, 2)));
+    assert_snapshot!(inventory.to_table(), @r#"
+    a.js
+      Feature                    Count  Where
+      switch                         1  2:1
+      for-of                         1  1:1
+      const                          1  1:6
+
+    b.js
+      Feature                    Count  Where
+      for-of                         1  1:1
+      const                          1  1:6
+      destructuring-declaration      1  1:12
+
+    c.js
+      Does not parse
+
+    Total (3 files)
+      Feature                    Files  Count  Preset
+      switch                         1      1  js0
+      for-of                         2      2  es5
+      const                          2      2  -
+      destructuring-declaration      1      1  es5
+    "#);
+    assert_snapshot!(inventory.to_json(), @r#"
+    {
+      "files": [
+        {
+          "name": "a.js",
+          "errors": [],
+          "features": {
+            "switch": { "count": 1, "occurrences": [{ "start": 25, "end": 38, "line": 2, "column": 1 }] },
+            "for-of": { "count": 1, "occurrences": [{ "start": 0, "end": 24, "line": 1, "column": 1 }] },
+            "const": { "count": 1, "occurrences": [{ "start": 5, "end": 12, "line": 1, "column": 6 }] }
+          }
+        },
+        {
+          "name": "b.js",
+          "errors": [],
+          "features": {
+            "for-of": { "count": 1, "occurrences": [{ "start": 0, "end": 23, "line": 1, "column": 1 }] },
+            "const": { "count": 1, "occurrences": [{ "start": 5, "end": 14, "line": 1, "column": 6 }] },
+            "destructuring-declaration": { "count": 1, "occurrences": [{ "start": 11, "end": 14, "line": 1, "column": 12 }] }
+          }
+        },
+        {
+          "name": "c.js",
+          "errors": [{ "code": "ZS001", "message": "Unexpected token", "line": 1, "column": 5 }],
+          "features": {}
+        }
+      ],
+      "total": {
+        "switch": { "count": 1, "files": 1 },
+        "for-of": { "count": 2, "files": 2 },
+        "const": { "count": 2, "files": 2 },
+        "destructuring-declaration": { "count": 1, "files": 1 }
+      }
+    }
+    "#);
+}
+
+#[test]
+fn test_only_conditional_while_loops_need_misc() {
+    let options = TransformOptions::new();
+    let mut inventory = Inventory::new();
+    inventory.add_source("a.js", "while (x) f();\nwhile (true) break;\nwhile (false);\n", &options);
+
+    assert_eq!(Feature::While.preset(), Some(Preset::Misc));
+    assert_eq!(Feature::WhileTrue.preset(), None);
+    assert_snapshot!(inventory.to_table(), @r#"
+    a.js
+      Feature     Count  Where
+      while           2  1:1, 3:1
+      while-true      1  2:1
+
+    Total (1 file)
+      Feature     Files  Count  Preset
+      while           1      2  misc
+      while-true      1      1  -
+    "#);
+}
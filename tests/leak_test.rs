@@ -0,0 +1,54 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use oxc_allocator::Allocator;
+
+use zero_sugar::{transform_source, TransformOptions, Transformer};
+
+// Counts the bytes that are currently allocated. This is the only test in this binary so nothing else allocates meanwhile.
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LIVE_BYTES.fetch_add(new_size, Ordering::SeqCst);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_repeated_transforms_do_not_leak() {
+    let source = "for (const [a, b] of c) { try { if (a) continue; } finally { f(b); } }\n".repeat(20);
+    let options = TransformOptions::new();
+    let transformer = Transformer::new(&options);
+    let run = || {
+        transform_source(&source, "input.js", &options);
+        let allocator = Allocator::default();
+        transformer.transform_str(&allocator, &source);
+    };
+
+    // Anything that is set up once happens here
+    run();
+    let before = LIVE_BYTES.load(Ordering::SeqCst);
+    for _ in 0..10 {
+        run();
+    }
+    let after = LIVE_BYTES.load(Ordering::SeqCst);
+
+    assert!(after <= before + source.len(), "{} bytes more after 10 runs, the source is {} bytes", after as isize - before as isize, source.len());
+}
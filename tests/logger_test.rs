@@ -0,0 +1,48 @@
+use std::sync::{Arc, Mutex};
+
+use insta::assert_snapshot;
+
+use zero_sugar::transform_code;
+use zero_sugar::{set_log_level, set_log_sink, LogLevel};
+
+// The level and sink are global so this is all one test, otherwise parallel tests would see each other's logs
+#[test]
+fn test_logger_levels_and_sink() {
+    let messages = Arc::new(Mutex::new(vec!()));
+    let sink_messages = messages.clone();
+    set_log_sink(Some(Arc::new(move |level: LogLevel, message: &str| {
+        sink_messages.lock().unwrap().push(format!("[{}] {}", level.as_str(), message));
+    })));
+    let source = "do { if (a) continue; } while (x);";
+
+    // Silent by default
+    transform_code(source).unwrap();
+    assert_eq!(messages.lock().unwrap().len(), 0);
+
+    set_log_level(LogLevel::Info);
+    transform_code(source).unwrap();
+    assert_snapshot!(messages.lock().unwrap().join("\n"), @r#"
+    [info] Rule: Eliminate continue statement in favor of a break statement
+    [info] Rule: Eliminate do-while in favor of regular while
+    "#);
+
+    // Debug includes info but not the mapper trace
+    messages.lock().unwrap().clear();
+    set_log_level(LogLevel::Debug);
+    transform_code(source).unwrap();
+    let debug = messages.lock().unwrap().clone();
+    assert!(debug.iter().any(|message| message.starts_with("[info]")));
+    assert!(debug.iter().any(|message| message.starts_with("[debug]")));
+    assert!(!debug.iter().any(|message| message.starts_with("[trace]")));
+
+    messages.lock().unwrap().clear();
+    set_log_level(LogLevel::Trace);
+    transform_code(source).unwrap();
+    assert!(messages.lock().unwrap().iter().any(|message| message.starts_with("[trace] continue: Enter statement")));
+
+    messages.lock().unwrap().clear();
+    set_log_level(LogLevel::Off);
+    transform_code(source).unwrap();
+    set_log_sink(None);
+    assert_eq!(messages.lock().unwrap().len(), 0);
+}
@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use zero_sugar::mapper::{create_mapper, MapperAction};
+use zero_sugar::mapper_context::{AncestorKind, MapperContext};
+
+fn describe(name: &str, context: &MapperContext) -> String {
+    let ancestors: Vec<String> = context.ancestors().iter().map(|ancestor| match &ancestor.kind {
+        AncestorKind::Label(label) => format!("Label({})", label),
+        AncestorKind::Loop(kind) => format!("{:?}", kind),
+        AncestorKind::Function { is_arrow: true, .. } => "Arrow".to_string(),
+        AncestorKind::Function { .. } => "Function".to_string(),
+        kind => format!("{:?}", kind),
+    }).collect();
+    format!(
+        "{}: [{}] loop={:?} labels={:?} generator={} async={} strict={}",
+        name,
+        ancestors.join(" > "),
+        context.nearest_loop().map(|ancestor| &ancestor.kind),
+        context.labels(),
+        context.in_generator(),
+        context.in_async(),
+        context.is_strict(),
+    )
+}
+
+/// Map the source and describe the context at every `probe*` identifier
+fn contexts(source: &str, source_type: SourceType) -> String {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source = Box::leak(source.to_string().into_boxed_str());
+    let parsed = Parser::new(allocator, source, source_type).parse();
+    assert!(parsed.errors.is_empty(), "Parse errors: {:?}", parsed.errors);
+
+    let seen: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec!()));
+    let seen_closure = Rc::clone(&seen);
+
+    let mut mapper = create_mapper(allocator);
+    let state = mapper.state.clone();
+    mapper.add_visitor_expr(move |expr: Expression<'_>, _alloc, before: bool| {
+        if before {
+            if let Expression::Identifier(ident) = &expr {
+                if ident.name.starts_with("probe") {
+                    seen_closure.borrow_mut().push(describe(&ident.name, &state.borrow().context));
+                }
+            }
+        }
+        (MapperAction::Normal, expr)
+    });
+    mapper.map(parsed.program);
+
+    assert!(mapper.state.borrow().context.ancestors().is_empty(), "Every ancestor should be popped again");
+
+    let seen = seen.borrow().join("\n");
+    seen
+}
+
+#[test]
+fn test_loops_and_labels() {
+    let result = contexts(r#"
+        probeTop;
+        outer: while (x) {
+            probeWhile;
+            for (const y of probeForOfRight) {
+                inner: { probeForOfBody; }
+            }
+            switch (probeDiscriminant) {
+                case 1: probeCase;
+            }
+        }
+        try { probeTry; } catch (e) { probeCatch; } finally { probeFinally; }
+    "#, SourceType::default().with_module(true));
+
+    assert_snapshot!(result, @r#"
+    probeTop: [Program] loop=None labels=[] generator=false async=false strict=true
+    probeWhile: [Program > Label(outer) > While] loop=Some(Loop(While)) labels=["outer"] generator=false async=false strict=true
+    probeForOfRight: [Program > Label(outer) > While > ForOf] loop=Some(Loop(ForOf)) labels=["outer"] generator=false async=false strict=true
+    probeForOfBody: [Program > Label(outer) > While > ForOf > Label(inner)] loop=Some(Loop(ForOf)) labels=["inner", "outer"] generator=false async=false strict=true
+    probeDiscriminant: [Program > Label(outer) > While > Switch] loop=Some(Loop(While)) labels=["outer"] generator=false async=false strict=true
+    probeCase: [Program > Label(outer) > While > Switch] loop=Some(Loop(While)) labels=["outer"] generator=false async=false strict=true
+    probeTry: [Program > Try] loop=None labels=[] generator=false async=false strict=true
+    probeCatch: [Program > Try > Catch] loop=None labels=[] generator=false async=false strict=true
+    probeFinally: [Program > Try > Finally] loop=None labels=[] generator=false async=false strict=true
+    "#);
+}
+
+#[test]
+fn test_functions_reset_loops_and_labels() {
+    let result = contexts(r#"
+        label: while (x) {
+            function* gen() { probeGenerator; }
+            async () => probeAsyncArrow;
+            async function f() {
+                const g = () => { probeArrowInAsync; };
+                do { probeDoWhile; } while (y);
+            }
+        }
+    "#, SourceType::default().with_module(true));
+
+    assert_snapshot!(result, @r#"
+    probeGenerator: [Program > Label(label) > While > Function] loop=None labels=[] generator=true async=false strict=true
+    probeAsyncArrow: [Program > Label(label) > While > Arrow] loop=None labels=[] generator=false async=true strict=true
+    probeArrowInAsync: [Program > Label(label) > While > Function > Arrow] loop=None labels=[] generator=false async=false strict=true
+    probeDoWhile: [Program > Label(label) > While > Function > DoWhile] loop=Some(Loop(DoWhile)) labels=[] generator=false async=true strict=true
+    "#);
+}
+
+#[test]
+fn test_strict_mode() {
+    let result = contexts(r#"
+        probeSloppy;
+        function f() { "use strict"; probeUseStrict; }
+        function g() { probeStillSloppy; }
+        class C { m() { probeClassBody; } static { probeStaticBlock; } }
+    "#, SourceType::default().with_script(true));
+
+    assert_snapshot!(result, @r#"
+    probeSloppy: [Program] loop=None labels=[] generator=false async=false strict=false
+    probeUseStrict: [Program > Function] loop=None labels=[] generator=false async=false strict=true
+    probeStillSloppy: [Program > Function] loop=None labels=[] generator=false async=false strict=false
+    probeClassBody: [Program > Class > Function] loop=None labels=[] generator=false async=false strict=true
+    probeStaticBlock: [Program > Class > StaticBlock] loop=None labels=[] generator=false async=false strict=true
+    "#);
+}
@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use zero_sugar::mapper::{create_mapper, MapperAction};
+
+/// Map the source and return the names of all `probe*` identifiers that the expression visitors saw, in visit order
+fn visited_probes(source: &str) -> Vec<String> {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source = Box::leak(source.to_string().into_boxed_str());
+    let parsed = Parser::new(allocator, source, SourceType::default().with_module(true)).parse();
+    assert!(parsed.errors.is_empty(), "Parse errors: {:?}", parsed.errors);
+
+    let seen: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec!()));
+    let seen_closure = Rc::clone(&seen);
+
+    let mut mapper = create_mapper(allocator);
+    mapper.add_visitor_expr(move |expr: Expression<'_>, _alloc, before: bool| {
+        if before {
+            if let Expression::Identifier(ident) = &expr {
+                if ident.name.starts_with("probe") {
+                    seen_closure.borrow_mut().push(ident.name.to_string());
+                }
+            }
+        }
+        (MapperAction::Normal, expr)
+    });
+    mapper.map(parsed.program);
+
+    let seen = seen.borrow().clone();
+    seen
+}
+
+#[test]
+fn test_every_expression_position_is_visited() {
+    let source = r#"
+        function f(a = probeParamDefault, {b = probeObjectParamDefault, [probeParamKey]: c}, [d = probeArrayParamDefault], ...{e = probeRestParamDefault}) {}
+        const g = (a = probeArrowParamDefault, ...[b = probeArrowRestDefault]) => probeArrowExpressionBody;
+        const h = function(a = probeFunctionExpressionDefault) { return probeFunctionExpressionBody; };
+        class K {
+            [probeComputedMethodKey]() {}
+            method(a = probeMethodParamDefault) { probeMethodBody; }
+            [probeComputedPropertyKey] = probePropertyValue;
+            accessor [probeAccessorKey] = probeAccessorValue;
+            static { probeStaticBlock; }
+        }
+        ({x = probeAssignTargetDefault, [probeAssignTargetKey]: y = probeAssignTargetPropertyDefault, ...z} = probeAssignRight);
+        [u = probeArrayAssignTargetDefault, ...w[probeArrayAssignTargetRest]] = [];
+        try {} catch ({message = probeCatchParamDefault}) { probeCatchBody; }
+        `template ${probeTemplateExpression}`;
+        import(probeImportSource, probeImportOptions);
+        const {[probeDeclarationKey]: v = probeDeclarationDefault, ...r} = {};
+        export default function(a = probeExportDefaultParam) {}
+        @probeClassDecorator(probeClassDecoratorArgument) class L extends probeSuperClass {
+            @probeMethodDecorator method() {}
+            @probePropertyDecorator(probePropertyDecoratorArgument) property = 1;
+        }
+        (@probeClassExpressionDecorator class extends probeClassExpressionSuperClass {});
+    "#;
+
+    let seen = visited_probes(source);
+
+    assert_snapshot!(seen.join("\n"), @r#"
+    probeParamDefault
+    probeObjectParamDefault
+    probeParamKey
+    probeArrayParamDefault
+    probeRestParamDefault
+    probeArrowParamDefault
+    probeArrowRestDefault
+    probeArrowExpressionBody
+    probeFunctionExpressionDefault
+    probeFunctionExpressionBody
+    probeComputedMethodKey
+    probeMethodParamDefault
+    probeMethodBody
+    probeComputedPropertyKey
+    probePropertyValue
+    probeAccessorKey
+    probeAccessorValue
+    probeStaticBlock
+    probeAssignTargetDefault
+    probeAssignTargetKey
+    probeAssignTargetPropertyDefault
+    probeAssignRight
+    probeArrayAssignTargetDefault
+    probeArrayAssignTargetRest
+    probeCatchParamDefault
+    probeCatchBody
+    probeTemplateExpression
+    probeImportSource
+    probeImportOptions
+    probeDeclarationKey
+    probeDeclarationDefault
+    probeExportDefaultParam
+    probeClassDecorator
+    probeClassDecoratorArgument
+    probeSuperClass
+    probeMethodDecorator
+    probePropertyDecorator
+    probePropertyDecoratorArgument
+    probeClassExpressionDecorator
+    probeClassExpressionSuperClass
+    "#);
+
+    let expected = source.split(|c: char| !c.is_alphanumeric()).filter(|word| word.starts_with("probe")).count();
+    assert_eq!(seen.len(), expected, "Every probe should be visited exactly once");
+}
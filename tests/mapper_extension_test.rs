@@ -0,0 +1,79 @@
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_parser::Parser;
+use oxc_span::{Atom, SourceType};
+
+use zero_sugar::mapper::{create_mapper, MapperAction};
+use zero_sugar::mapper_state::MapperState;
+
+// The state of a transform that could live in another crate
+#[derive(Default)]
+struct FunctionDepth {
+    current: usize,
+    deepest: usize,
+}
+
+#[derive(Default, Debug, PartialEq)]
+struct Renamed(Vec<String>);
+
+#[test]
+fn test_transform_keeps_its_own_state() {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source = "function a() { function b() { function c() {} } } function d() {}";
+    let parsed = Parser::new(allocator, source, SourceType::default().with_module(true)).parse();
+
+    let mut mapper = create_mapper(allocator);
+    let state = mapper.state.clone();
+    mapper.add_visitor_function(move |mut func: Function<'_>, _alloc, before: bool| {
+        let mut state = state.borrow_mut();
+        let depth = state.extension_mut::<FunctionDepth>();
+        if before {
+            depth.current += 1;
+            depth.deepest = depth.deepest.max(depth.current);
+            return (MapperAction::Normal, func);
+        }
+        let suffix = depth.current;
+        depth.current -= 1;
+        if let Some(id) = &mut func.id {
+            state.extension_mut::<Renamed>().0.push(id.name.to_string());
+            id.name = Atom::from(format!("{}{}", id.name, suffix));
+        }
+        (MapperAction::Normal, func)
+    });
+    let program = mapper.map(parsed.program);
+
+    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions);
+    assert_snapshot!(codegen.build(&program), @r#"
+    function a1() {
+    	function b2() {
+    		function c3() {
+    		}
+    	}
+    }
+    function d1() {
+    }
+    "#);
+
+    let state = mapper.state.borrow();
+    assert_eq!(state.extension::<FunctionDepth>().unwrap().deepest, 3);
+    assert_eq!(state.extension::<FunctionDepth>().unwrap().current, 0);
+    assert_eq!(state.extension::<Renamed>(), Some(&Renamed(vec!("c".into(), "b".into(), "a".into(), "d".into()))));
+}
+
+#[test]
+fn test_extensions_are_keyed_by_type() {
+    let mut state = MapperState::new();
+    assert!(state.extension::<Renamed>().is_none());
+
+    assert_eq!(state.insert_extension(Renamed(vec!("x".into()))), None);
+    assert_eq!(state.insert_extension(Renamed(vec!("y".into()))), Some(Renamed(vec!("x".into()))));
+    state.extension_mut::<FunctionDepth>().current = 2;
+
+    assert_eq!(state.extension::<Renamed>(), Some(&Renamed(vec!("y".into()))));
+    assert_eq!(state.remove_extension::<Renamed>(), Some(Renamed(vec!("y".into()))));
+    assert!(state.extension::<Renamed>().is_none());
+    assert_eq!(state.extension::<FunctionDepth>().unwrap().current, 2);
+}
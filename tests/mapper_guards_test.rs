@@ -0,0 +1,133 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use zero_sugar::mapper::{create_mapper, Mapper, MapperAction, DEFAULT_MAX_DEPTH};
+use zero_sugar::inventory::FileInventory;
+use zero_sugar::names::collect_used_names;
+use zero_sugar::scope::ScopeTree;
+use zero_sugar::walker::create_walker;
+use zero_sugar::{transform_code, validate_code, Profile, TransformError, TransformOptions};
+
+fn parse(allocator: &'static Allocator, source: &'static str) -> Program<'static> {
+    let parsed = Parser::new(allocator, source, SourceType::default().with_module(true)).parse();
+    assert!(parsed.errors.is_empty(), "Parse errors: {:?}", parsed.errors);
+    parsed.program
+}
+
+fn diagnostics(mapper: &Mapper) -> String {
+    mapper.state.borrow().diagnostics.iter().map(|diagnostic| {
+        format!("{} [{}, {}] {}", diagnostic.code.as_str(), diagnostic.span.start, diagnostic.span.end, diagnostic.message)
+    }).collect::<Vec<String>>().join("\n")
+}
+
+fn errors(errors: &[TransformError]) -> String {
+    errors.iter().map(|error| format!("{} {}:{} {}", error.code, error.line, error.column, error.message)).collect::<Vec<String>>().join("\n")
+}
+
+#[test]
+fn test_endless_revisits_are_reported() {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source = "stuck; fine; function f() {} g(() => stuck);";
+
+    let mut mapper = create_mapper(allocator);
+    mapper.set_max_revisits(5);
+    let calls = Rc::new(Cell::new(0));
+    let calls_closure = Rc::clone(&calls);
+    mapper.add_visitor_stmt(|stmt: Statement<'_>, _alloc, _before: bool| (MapperAction::Normal, stmt));
+    mapper.add_visitor_stmt(move |stmt: Statement<'_>, _alloc, before: bool| {
+        // Returns the statement as is, so asking for a revisit never ends
+        let stuck = matches!(&stmt, Statement::ExpressionStatement(expr_stmt) if matches!(&expr_stmt.expression, Expression::Identifier(ident) if ident.name == "stuck"));
+        if stuck && !before {
+            calls_closure.set(calls_closure.get() + 1);
+            return (MapperAction::Revisit, stmt);
+        }
+        (MapperAction::Normal, stmt)
+    });
+    mapper.add_visitor_expr(|expr: Expression<'_>, _alloc, before: bool| {
+        let stuck = matches!(&expr, Expression::Identifier(ident) if ident.name == "stuck");
+        (if stuck && before { MapperAction::Revisit } else { MapperAction::Normal }, expr)
+    });
+    mapper.add_visitor_function(|func: Function<'_>, _alloc, _before: bool| (MapperAction::Revisit, func));
+    let program = mapper.map(parse(allocator, source));
+
+    // The first revisit is the sixth visit
+    assert_eq!(calls.get(), 6);
+    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions);
+    assert_snapshot!(codegen.build(&program), @r#"
+    stuck;
+    fine;
+    function f() {
+    }
+    g(() => stuck);
+    "#);
+    assert_snapshot!(diagnostics(&mapper), @r#"
+    ZS008 [0, 5] The expression visitor at index 0 kept asking to revisit this node. Stopped after 5 revisits.
+    ZS008 [0, 6] The statement visitor at index 1 kept asking to revisit this node. Stopped after 5 revisits.
+    ZS008 [13, 28] The function visitor at index 0 kept asking to revisit this node. Stopped after 5 revisits.
+    ZS008 [37, 42] The expression visitor at index 0 kept asking to revisit this node. Stopped after 5 revisits.
+    "#);
+}
+
+#[test]
+fn test_deep_nesting_is_reported() {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source: &'static str = Box::leak(format!("x = {};\n[[[[[[[[[[[[y]]]]]]]]]]]];", vec!("a"; 30).join(" + ")).into_boxed_str());
+
+    let mut mapper = create_mapper(allocator);
+    mapper.set_max_depth(10);
+    let visited = Rc::new(Cell::new(0));
+    let visited_closure = Rc::clone(&visited);
+    mapper.add_visitor_expr(move |expr: Expression<'_>, _alloc, before: bool| {
+        if before && matches!(expr, Expression::Identifier(_)) {
+            visited_closure.set(visited_closure.get() + 1);
+        }
+        (MapperAction::Normal, expr)
+    });
+    mapper.map(parse(allocator, source));
+
+    // The program body, the statement, the assignment and the first few additions are mapped. The deepest `a`s and `y` are not.
+    assert_eq!(visited.get(), 6);
+    assert_snapshot!(diagnostics(&mapper), @r#"
+    ZS009 [4, 93] This is nested more than 10 levels deep. It is left as is.
+    ZS009 [96, 97] This is nested more than 10 levels deep. It is left as is.
+    ZS009 [131, 140] This is nested more than 10 levels deep. It is left as is.
+    "#);
+}
+
+#[test]
+fn test_deep_input_does_not_crash() {
+    // Everything that recurses (the scope analysis, the name collection, the mapper, the walker) would run out
+    // of stack on this, so the input is rejected up front. That check does not need more than the test thread has.
+    let source = format!("x = {};", vec!("a"; 10000).join(" + "));
+    let options = TransformOptions::default();
+
+    let result = transform_code(&source).unwrap();
+    assert_eq!(result.transformed_code, "");
+    assert_snapshot!(errors(&result.errors), @r#"
+    ZS009 1:5 This is nested more than 500 levels deep. The input is not processed.
+    "#);
+
+    assert_snapshot!(errors(&validate_code(&source, &options, &Profile::js0())), @r#"
+    ZS009 1:5 This is nested more than 500 levels deep. The input is not processed.
+    "#);
+    assert_snapshot!(errors(&FileInventory::from_source("deep.js", &source, &options).errors), @r#"
+    ZS009 1:5 This is nested more than 500 levels deep. The input is not processed.
+    "#);
+
+    // The parts that are public stop at the same depth on their own
+    let allocator = Allocator::default();
+    let program = Parser::new(&allocator, &source, SourceType::default().with_module(true)).parse().program;
+    let walker = create_walker();
+    walker.walk(&program);
+    assert_eq!(walker.too_deep().map(|span| span.start), Some(4));
+    assert!(ScopeTree::analyze(&program).references().count() <= DEFAULT_MAX_DEPTH);
+    assert!(collect_used_names(&program).contains("x"));
+}
@@ -0,0 +1,168 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_parser::Parser;
+use oxc_span::{Atom, GetSpan, SourceType, Span};
+
+use zero_sugar::mapper::{create_mapper, Mapper, MapperAction};
+
+fn parse<'a>(allocator: &'a Allocator, source: &'a str) -> Program<'a> {
+    let parsed = Parser::new(allocator, source, SourceType::default().with_module(true)).parse();
+    assert!(parsed.errors.is_empty(), "Parse errors: {:?}", parsed.errors);
+    parsed.program
+}
+
+fn generate(program: &Program, source: &str) -> String {
+    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions);
+    codegen.build(program)
+}
+
+/// Register a hook of every kind that logs the node it gets, in the after phase
+fn log_hooks(mapper: &mut Mapper<'static>, source: &'static str) -> Rc<RefCell<Vec<String>>> {
+    let seen: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec!()));
+    let text = move |span: Span| source[span.start as usize..span.end as usize].to_string();
+
+    macro_rules! log_hook {
+        ($add:ident, $name:literal, $span:expr) => {{
+            let seen = Rc::clone(&seen);
+            mapper.$add(move |node, _alloc, before: bool| {
+                if !before {
+                    seen.borrow_mut().push(format!("{} `{}`", $name, text($span(&node))));
+                }
+                (MapperAction::Normal, node)
+            });
+        }};
+    }
+    log_hook!(add_visitor_function, "function", |node: &Function| node.span);
+    log_hook!(add_visitor_arrow, "arrow", |node: &ArrowExpression| node.span);
+    log_hook!(add_visitor_class, "class", |node: &Class| node.span);
+    log_hook!(add_visitor_params, "params", |node: &FormalParameters| node.span);
+    log_hook!(add_visitor_binding_pattern, "pattern", |node: &BindingPattern| node.kind.span());
+    log_hook!(add_visitor_object_property, "property", |node: &ObjectProperty| node.span);
+    log_hook!(add_visitor_switch_case, "case", |node: &SwitchCase| node.span);
+
+    seen
+}
+
+#[test]
+fn test_hooks_apply_to_declarations_and_expressions() {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source = r#"function f({a}, b = 1) {}
+const g = function () {};
+const h = (c) => c;
+class K { m(d) {} }
+const L = class {};
+const o = { p: 1, q() {} };
+switch (x) { case 1: default: }
+"#;
+    let mut mapper = create_mapper(allocator);
+    let seen = log_hooks(&mut mapper, source);
+    mapper.map(parse(allocator, source));
+
+    assert_snapshot!(seen.borrow().join("\n"), @r#"
+    pattern `a`
+    pattern `{a}`
+    pattern `b`
+    pattern `b = 1`
+    params `({a}, b = 1)`
+    function `function f({a}, b = 1) {}`
+    pattern `g`
+    params `()`
+    function `function () {}`
+    pattern `h`
+    pattern `c`
+    params `(c)`
+    arrow `(c) => c`
+    pattern `d`
+    params `(d)`
+    function `(d) {}`
+    class `class K { m(d) {} }`
+    pattern `L`
+    class `class {}`
+    pattern `o`
+    property `p: 1`
+    params `()`
+    function `() {}`
+    property `q() {}`
+    case `case 1:`
+    case `default:`
+    "#);
+}
+
+#[test]
+fn test_hook_can_replace_the_node() {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source = "function f() {} const g = function named() {}; class K { m() {} }";
+    let mut mapper = create_mapper(allocator);
+    mapper.add_visitor_function(|mut func: Function<'_>, _alloc, before: bool| {
+        if !before {
+            if let Some(id) = &mut func.id {
+                id.name = Atom::from(format!("{}_renamed", id.name));
+            }
+        }
+        (MapperAction::Normal, func)
+    });
+    let program = mapper.map(parse(allocator, source));
+
+    assert_snapshot!(generate(&program, source), @r#"
+    function f_renamed() {
+    }
+    const g = function named_renamed() {
+    };
+    class K {
+    	m(){
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_hook_skip_and_revisit() {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source = "class A { m() { inside; } } class B { m() { inside; } }";
+    let seen: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec!()));
+
+    let mut mapper = create_mapper(allocator);
+    let seen_class = Rc::clone(&seen);
+    mapper.add_visitor_class(move |class: Class<'_>, _alloc, before: bool| {
+        let name = class.id.as_ref().map(|id| id.name.to_string()).unwrap_or_default();
+        seen_class.borrow_mut().push(format!("class {} before={}", name, before));
+        match (before, name.as_str()) {
+            // Don't enter A at all
+            (true, "A") => (MapperAction::Skip, class),
+            // Visit B twice by renaming it after the first visit
+            (false, "B") => {
+                let mut class = class;
+                class.id.as_mut().unwrap().name = Atom::from("C");
+                (MapperAction::Revisit, class)
+            }
+            _ => (MapperAction::Normal, class),
+        }
+    });
+    let seen_expr = Rc::clone(&seen);
+    mapper.add_visitor_expr(move |expr: Expression<'_>, _alloc, before: bool| {
+        if before {
+            if let Expression::Identifier(ident) = &expr {
+                seen_expr.borrow_mut().push(format!("identifier {}", ident.name));
+            }
+        }
+        (MapperAction::Normal, expr)
+    });
+    mapper.map(parse(allocator, source));
+
+    assert_snapshot!(seen.borrow().join("\n"), @r#"
+    class A before=true
+    class A before=false
+    class B before=true
+    identifier inside
+    class B before=false
+    class C before=true
+    identifier inside
+    class C before=false
+    "#);
+}
@@ -0,0 +1,174 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, SourceType};
+
+use zero_sugar::mapper::{create_mapper, MapperAction};
+use zero_sugar::transforms::builder::{create_identifier_expression, create_variable_declaration_let};
+
+/// Map the source with a visitor that moves the argument of every `hoist(x)` call into a temporary
+/// variable in the prelude. When that's not possible, the call is kept and the reason is listed.
+/// With `force` the visitor ignores `can_hoist` and always tries to hoist.
+fn hoist(source: &str, force: bool) -> String {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source: &'static str = Box::leak(source.to_string().into_boxed_str());
+    let parsed = Parser::new(allocator, source, SourceType::default().with_module(true)).parse();
+    assert!(parsed.errors.is_empty(), "Parse errors: {:?}", parsed.errors);
+
+    let blocked: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec!()));
+    let blocked_closure = Rc::clone(&blocked);
+    let counter = Cell::new(0);
+
+    let mut mapper = create_mapper(allocator);
+    mapper.add_visitor_expr_with_prelude(move |expr: Expression<'_>, alloc, before: bool, prelude| {
+        let is_hoist_call = matches!(&expr, Expression::CallExpression(call) if matches!(&call.callee, Expression::Identifier(ident) if ident.name == "hoist"));
+        if before || !is_hoist_call {
+            return (MapperAction::Normal, expr);
+        }
+        if !prelude.can_hoist() && !force {
+            blocked_closure.borrow_mut().push(format!("{}: {}", &source[expr.span().start as usize..expr.span().end as usize], prelude.blocked_by().unwrap()));
+            return (MapperAction::Normal, expr);
+        }
+
+        let expr = prelude.hoist(expr, |expr| {
+            let Expression::CallExpression(call) = expr else { unreachable!("checked above") };
+            let span = call.span;
+            let Some(Argument::Expression(argument)) = call.unbox().arguments.into_iter().next() else { panic!("hoist() needs an argument") };
+            let name = format!("$t{}", counter.get());
+            counter.set(counter.get() + 1);
+            (vec!(create_variable_declaration_let(alloc, name.clone(), Some(argument), span)), create_identifier_expression(alloc, name, span))
+        });
+        (MapperAction::Normal, expr)
+    });
+    let program = mapper.map(parsed.program);
+
+    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions);
+    let code = codegen.build(&program);
+    let diagnostics: Vec<String> = mapper.state.borrow().diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect();
+    format!("{}---\nblocked:\n{}\ndiagnostics:\n{}", code, blocked.borrow().join("\n"), diagnostics.join("\n"))
+}
+
+#[test]
+fn test_prelude_goes_before_the_statement() {
+    let result = hoist(r#"
+f(hoist(a), hoist(b));
+function g() { return hoist(c); }
+if (x) f(hoist(d));
+label: for (let i = hoist(e); i < 1; i++) {}
+"#, false);
+
+    assert_snapshot!(result, @r#"
+    let $t0 = a;
+    let $t1 = b;
+    f($t0, $t1);
+    function g() {
+    	let $t2 = c;
+    	return $t2;
+    }
+    if (x) {
+    	let $t3 = d;
+    	f($t3);
+    }
+    let $t4 = e;
+    label:for (let i = $t4; i < 1; i++){
+    }
+    ---
+    blocked:
+
+    diagnostics:
+    "#);
+}
+
+#[test]
+fn test_prelude_restructures_loop_tests_and_arrows() {
+    let result = hoist(r#"
+while (hoist(a)) { f(); }
+for (;hoist(b);) { continue; }
+g(() => hoist(c));
+"#, false);
+
+    assert_snapshot!(result, @r#"
+    while(true){
+    	let $t0 = a;
+    	if ( !$t0) 	break;
+
+    	{
+    		f();
+    	}
+    }
+    for (; ; ){
+    	let $t1 = b;
+    	if ( !$t1) 	break;
+
+    	{
+    		continue;
+    	}
+    }
+    g(() => {
+    	let $t2 = c;
+    	return $t2;
+    });
+    ---
+    blocked:
+
+    diagnostics:
+    "#);
+}
+
+#[test]
+fn test_prelude_blocked_positions() {
+    let result = hoist(r#"
+x && hoist(a);
+y ? hoist(b) : 0;
+do {} while (hoist(c));
+for (;; hoist(d)) {}
+f(g(), hoist(e));
+function h(p = hoist(f)) {}
+switch (x) { case hoist(g): }
+"#, false);
+
+    assert_snapshot!(result, @r#"
+    x && hoist(a);
+    y ? hoist(b) : 0;
+    do {
+    }while(hoist(c));
+    for (; ; hoist(d)){
+    }
+    f(g(), hoist(e));
+    function h(p=hoist(f)) {
+    }
+    switch(x){
+    	case hoist(g):
+    }
+    ---
+    blocked:
+    hoist(a): the right side of a logical operator
+    hoist(b): a branch of a conditional expression
+    hoist(c): a `do-while` test
+    hoist(d): a `for` loop update
+    hoist(e): an expression that must run after other side effects in the same statement
+    hoist(f): a default value
+    hoist(g): a `case` test
+    diagnostics:
+    "#);
+}
+
+#[test]
+fn test_prelude_pushed_when_blocked_is_reported() {
+    let result = hoist("x || hoist(a);", true);
+
+    assert_snapshot!(result, @r#"
+    x || hoist(a);
+    ---
+    blocked:
+
+    diagnostics:
+    Cannot move statements out of the right side of a logical operator
+    "#);
+}
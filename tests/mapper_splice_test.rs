@@ -0,0 +1,121 @@
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_allocator::Box as OxcBox;
+use oxc_allocator::Vec as OxcVec;
+use oxc_ast::ast::*;
+use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, SourceType};
+
+use zero_sugar::mapper::{create_mapper, MapperAction};
+
+/// Map the source with a visitor that splits every sequence expression statement into one statement per expression
+fn split_sequences(source: &str) -> String {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source = Box::leak(source.to_string().into_boxed_str());
+    let parsed = Parser::new(allocator, source, SourceType::default().with_module(true)).parse();
+    assert!(parsed.errors.is_empty(), "Parse errors: {:?}", parsed.errors);
+
+    let mut mapper = create_mapper(allocator);
+    mapper.add_visitor_stmt(|stmt: Statement<'_>, alloc, before: bool| match (before, stmt) {
+        (true, Statement::ExpressionStatement(expr_stmt)) if matches!(expr_stmt.expression, Expression::SequenceExpression(_)) => {
+            let ExpressionStatement { expression, span } = expr_stmt.unbox();
+            let Expression::SequenceExpression(sequence) = expression else { unreachable!("checked above") };
+            let mut body = OxcVec::new_in(alloc);
+            for expression in sequence.unbox().expressions {
+                let expression = match expression {
+                    Expression::ParenthesizedExpression(parens) => parens.unbox().expression,
+                    expression => expression,
+                };
+                let span = expression.span();
+                body.push(Statement::ExpressionStatement(OxcBox(alloc.alloc(ExpressionStatement { expression, span }))));
+            }
+            (MapperAction::Splice, Statement::BlockStatement(OxcBox(alloc.alloc(BlockStatement { body, span }))))
+        }
+        (_, stmt) => (MapperAction::Normal, stmt),
+    });
+    let program = mapper.map(parsed.program);
+
+    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions);
+    codegen.build(&program)
+}
+
+#[test]
+fn test_splice_into_statement_lists() {
+    let result = split_sequences(r#"
+a, b;
+function f() { c, d; }
+{ e, f; }
+switch (x) { case 1: g, h; }
+try { i, j; } catch { k, l; } finally { m, n; }
+"#);
+
+    assert_snapshot!(result, @r#"
+    a;
+    b;
+    function f() {
+    	c;
+    	d;
+    }
+    {
+    	e;
+    	f;
+    }
+    switch(x){
+    	case 1:
+    		g;
+    		h;
+    }
+    try{
+    	i;
+    	j;
+    }catch{
+    	k;
+    	l;
+    }finally{
+    	m;
+    	n;
+    }
+    "#);
+}
+
+#[test]
+fn test_splice_keeps_block_in_single_statement_context() {
+    let result = split_sequences(r#"
+if (x) a, b; else c, d;
+while (x) e, f;
+label: g, h;
+"#);
+
+    assert_snapshot!(result, @r#"
+    if (x) {
+    	a;
+    	b;
+    } else {
+    	c;
+    	d;
+    }
+    while(x){
+    	e;
+    	f;
+    }
+    label:{
+    	g;
+    	h;
+    }
+    "#);
+}
+
+#[test]
+fn test_spliced_statements_are_visited() {
+    // The nested sequence only becomes a statement after the outer one is spliced
+    let result = split_sequences("a, (b, c), d;");
+
+    assert_snapshot!(result, @r#"
+    a;
+    b;
+    c;
+    d;
+    "#);
+}
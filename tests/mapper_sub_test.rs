@@ -0,0 +1,74 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_parser::Parser;
+use oxc_span::{Atom, SourceType, Span};
+
+use zero_sugar::diagnostics::DiagnosticCode;
+use zero_sugar::mapper::{create_mapper, with_sub_mapper, MapperAction};
+
+#[test]
+fn test_sub_mapper_shares_the_state() {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source = "function f() { a + b; } c;";
+    let parsed = Parser::new(allocator, source, SourceType::default().with_module(true)).parse();
+    let seen: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(vec!()));
+
+    let mut mapper = create_mapper(allocator);
+    let state = mapper.state.clone();
+    let seen_outer = Rc::clone(&seen);
+    mapper.add_visitor_stmt(move |stmt, alloc, before: bool| {
+        if before || !matches!(stmt, Statement::ExpressionStatement(_)) {
+            return (MapperAction::Normal, stmt);
+        }
+        let mut state = state.borrow_mut();
+        seen_outer.borrow_mut().push(format!("outer before: {}", state.next_ident_name()));
+
+        // Rename every identifier in the statement to a generated name
+        let seen_inner = Rc::clone(&seen_outer);
+        let stmt = with_sub_mapper(alloc, &mut state, "rename", |sub| {
+            let sub_state = Rc::clone(&sub.state);
+            sub.add_visitor_expr(move |expr, _alloc, before: bool| match (before, expr) {
+                (false, Expression::Identifier(mut ident)) => {
+                    let mut sub_state = sub_state.borrow_mut();
+                    let name = sub_state.next_ident_name();
+                    let in_function = sub_state.context.enclosing_function().is_some();
+                    seen_inner.borrow_mut().push(format!("inner: {} -> {} in function: {}", ident.name, name, in_function));
+                    sub_state.report(DiagnosticCode::UnsupportedTransform, format!("renamed {}", ident.name), Span::default());
+                    ident.name = Atom::from(name);
+                    (MapperAction::Normal, Expression::Identifier(ident))
+                }
+                (_, expr) => (MapperAction::Normal, expr),
+            });
+            sub.map_statement(stmt)
+        });
+
+        seen_outer.borrow_mut().push(format!("outer after: {}", state.next_ident_name()));
+        (MapperAction::Normal, stmt)
+    });
+    let program = mapper.map(parsed.program);
+
+    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions);
+    assert_snapshot!(codegen.build(&program), @r#"
+    function f() {
+    	$zeroSugar1 + $zeroSugar2;
+    }
+    $zeroSugar5;
+    "#);
+    assert_snapshot!(seen.borrow().join("\n"), @r#"
+    outer before: $zeroSugar0
+    inner: a -> $zeroSugar1 in function: true
+    inner: b -> $zeroSugar2 in function: true
+    outer after: $zeroSugar3
+    outer before: $zeroSugar4
+    inner: c -> $zeroSugar5 in function: false
+    outer after: $zeroSugar6
+    "#);
+    let diagnostics: Vec<String> = mapper.state.borrow().diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect();
+    assert_eq!(diagnostics, vec!("renamed a", "renamed b", "renamed c"));
+}
@@ -128,7 +128,7 @@ fn test_dowhile_to_while_mapping_serialized() {
     let source = "do { console.log('test'); } while (x > 0);";
     let result = parse_and_map(&allocator, source, Some(mapper));
 
-    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions::default());
+    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions);
     let code = codegen.build(&result);
 
     // Note: the transform is incorrect but the test is only testing the mapper, not the validity of the transform :)
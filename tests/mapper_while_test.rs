@@ -114,7 +114,7 @@ fn parse_and_map(source: &str) -> String {
     });
 
     let transformed = mapper.map(parsed.program);
-    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions::default());
+    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions);
 
     let out = codegen.build(&transformed);
 
@@ -0,0 +1,180 @@
+use insta::assert_snapshot;
+
+use zero_sugar::transform_code_with_options;
+use zero_sugar::HelperMode;
+use zero_sugar::TransformOptions;
+
+fn parse_and_map(source: &str, options: TransformOptions) -> String {
+    let result = transform_code_with_options(source, &options).unwrap();
+    assert!(!result.had_error, "Unexpected errors: {:?}", result.errors);
+    result.transformed_code
+}
+
+fn default_options() -> TransformOptions {
+    TransformOptions { helpers: HelperMode::None, ..TransformOptions::default() }
+}
+
+#[test]
+fn test_names_skip_used_bindings_and_labels() {
+    let result = parse_and_map(r#"
+        let $zeroSugar0 = 1;
+        $zeroSugar1: do {
+            f($zeroSugar0);
+        } while (x);
+    "#, default_options());
+
+    assert_snapshot!(result, @r#"
+    let $zeroSugar0 = 1;
+    $zeroSugar1:{
+    	let $zeroSugar2 = true;
+    	while($zeroSugar2)	{
+    		{
+    			f($zeroSugar0);
+    		}
+    		$zeroSugar2 = x;
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_names_rerun_on_own_output() {
+    let once = parse_and_map(r#"
+        do {
+            if (a) continue;
+            f();
+        } while (x);
+    "#, default_options());
+    let twice = parse_and_map(&format!("do {{ {} }} while (y);", once), default_options());
+
+    assert_snapshot!(twice, @r#"
+    let $zeroSugar2 = true;
+    while($zeroSugar2){
+    	{
+    		let $zeroSugar1 = true;
+    		while($zeroSugar1)		{
+    			$zeroSugar0:			{
+    				if (a) 				break $zeroSugar0;
+
+    				f();
+    			}
+    			$zeroSugar1 = x;
+    		}
+    	}
+    	$zeroSugar2 = y;
+    }
+    "#);
+}
+
+#[test]
+fn test_names_switch_label_skips_used_name() {
+    let result = parse_and_map(r#"
+        switch (x) {
+            case 1:
+                $zeroSugar0();
+                break;
+        }
+    "#, default_options());
+
+    assert_snapshot!(result, @r#"
+    $zeroSugar1:{
+    	let $zeroSugar3 = 1;
+    	if ($zeroSugar3 === 1) 	$zeroSugar3 = 0;
+
+    	if ($zeroSugar3 <= 0) {
+    		$zeroSugar0();
+    		break $zeroSugar1;
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_names_custom_prefix() {
+    let result = parse_and_map(r#"
+        let _t0;
+        for (let i = 0; i < 2; i++) {
+            if (i) continue;
+            f(i);
+        }
+    "#, TransformOptions { name_prefix: "_t".to_string(), ..default_options() });
+
+    assert_snapshot!(result, @r#"
+    let _t0;
+    {
+    	let i = 0;
+    	while(i < 2)	{
+    		_t1:		{
+    			if (i) 			break _t1;
+
+    			f(i);
+    		}
+    		i++;
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_names_helper_name_in_use() {
+    let result = parse_and_map(r#"
+        function $forOf() {}
+        for (const x of y) $forOf(x);
+    "#, TransformOptions::default());
+
+    assert_snapshot!(result, @r#"
+    function $forOf1(obj) {
+    	if (typeof Symbol === 'function') {
+    		return obj[Symbol.iterator]();
+    	}
+    	var from = Object(obj);
+    	var index = 0;
+    	return {
+    		next:function() {
+    			if (index >= from.length) {
+    				return {
+    					done:true,
+    					value:undefined
+    				};
+    			}
+    			index++;
+    			return {
+    				done:false,
+    				value:from[index - 1]
+    			};
+    		}
+    	};
+    }
+    function $forOf() {
+    }
+    const $zeroSugar0 = $forOf1(y);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
+
+    	const x = $zeroSugar1.value;
+    	$forOf(x);
+    }
+    "#);
+}
+
+#[test]
+fn test_names_helper_name_in_use_import() {
+    let result = parse_and_map(r#"
+        const $forOf = 1;
+        for (const x of y) f(x);
+    "#, TransformOptions { helpers: HelperMode::Import, ..TransformOptions::default() });
+
+    assert_snapshot!(result, @r#"
+    import {$forOf as $forOf1} from 'zero-sugar/helpers';
+    const $forOf = 1;
+    const $zeroSugar0 = $forOf1(y);
+    let $zeroSugar1;
+    while($zeroSugar1 = $zeroSugar0.next()){
+    	if ($zeroSugar1.done === true) 	break;
+
+    	const x = $zeroSugar1.value;
+    	f(x);
+    }
+    "#);
+}
@@ -0,0 +1,98 @@
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use zero_sugar::{HelperMode, TransformOptions, TransformOutput, Transformer};
+
+fn summary(output: &TransformOutput) -> String {
+    let rules: Vec<&str> = output.trace.iter().map(|entry| entry.rule.as_str()).collect();
+    let diagnostics: Vec<String> = output.diagnostics.iter().map(|diagnostic| {
+        format!("{} [{}, {}] {}", diagnostic.code.as_str(), diagnostic.span.start, diagnostic.span.end, diagnostic.message)
+    }).collect();
+    format!("{}---\nstatements: {}\nrules: {}\ndiagnostics:\n{}", output.code, output.program.body.len(), rules.join(", "), diagnostics.join("\n"))
+}
+
+#[test]
+fn test_transform_str_borrows_the_source() {
+    let mut options = TransformOptions::new();
+    options.helpers = HelperMode::None;
+    let transformer = Transformer::new(&options);
+
+    let allocator = Allocator::default();
+    // Not 'static, the output only needs to live as long as the source and the allocator
+    let source = String::from("do { f(); } while (x);\nswitch (y) { default: g(); }");
+    let output = transformer.transform_str(&allocator, &source);
+
+    assert_snapshot!(summary(&output), @r#"
+    let $zeroSugar0 = true;
+    while($zeroSugar0){
+    	{
+    		f();
+    	}
+    	$zeroSugar0 = x;
+    }
+    let $zeroSugar2 = 1;
+    $zeroSugar2 = 0;
+    if ($zeroSugar2 <= 0) {
+    	g();
+    }
+    ---
+    statements: 5
+    rules: do-while, switch
+    diagnostics:
+    "#);
+    assert!(output.source_map(&source, "input.js").contains("\"sources\":[\"input.js\"]"));
+}
+
+#[test]
+fn test_transform_str_reports_parse_errors() {
+    let transformer = Transformer::new(&TransformOptions::new());
+    let allocator = Allocator::default();
+    let source = String::from("do {");
+    let output = transformer.transform_str(&allocator, &source);
+
+    assert_snapshot!(summary(&output), @r#"
+    ---
+    statements: 0
+    rules: 
+    diagnostics:
+    ZS001 [4, 4] Expected `}` but found `EOF`
+    "#);
+    assert_eq!(output.source_map(&source, "input.js"), "");
+}
+
+#[test]
+fn test_transform_program_takes_a_parsed_program() {
+    let mut options = TransformOptions::new();
+    options.helpers = HelperMode::None;
+    let transformer = Transformer::new(&options);
+
+    let allocator = Allocator::default();
+    let source = String::from("const $zeroSugar0 = 1;\nfor (let i = 0; i < 2; i++) { if (i) continue; }");
+    let program = Parser::new(&allocator, &source, SourceType::default().with_module(true)).parse().program;
+    let output = transformer.transform_program(&allocator, program);
+
+    // The name in the input is skipped
+    assert_snapshot!(summary(&output), @r#"
+    const $zeroSugar0 = 1;
+    {
+    	let i = 0;
+    	while(i < 2)	{
+    		$zeroSugar1:		{
+    			if (i) 			break $zeroSugar1;
+
+    		}
+    		i++;
+    	}
+    }
+    ---
+    statements: 2
+    rules: continue, for-loop
+    diagnostics:
+    "#);
+    assert!(matches!(output.program.body.last(), Some(Statement::BlockStatement(_))));
+    assert!(!output.source_map(&source, "input.js").is_empty());
+}
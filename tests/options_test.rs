@@ -0,0 +1,209 @@
+use insta::assert_snapshot;
+
+use zero_sugar::transform_code_with_options;
+use zero_sugar::TransformOptions;
+
+fn parse_and_map(source: &str, options: TransformOptions) -> String {
+    let result = transform_code_with_options(source, &options).unwrap();
+    assert!(!result.had_error, "Unexpected errors: {:?}", result.errors);
+    result.transformed_code
+}
+
+#[test]
+fn test_none_is_noop() {
+    let result = parse_and_map(r#"
+        for (let i = 0; i < 10; i++) {
+            if (i) continue;
+            switch (i) { case 1: f(); }
+        }
+    "#, TransformOptions::none());
+
+    assert_snapshot!(result, @r#"
+    for (let i = 0; i < 10; i++){
+    	if (i) 	continue;
+
+    	switch(i){
+    		case 1:
+    			f();
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_only_switch_keeps_loops() {
+    let mut options = TransformOptions::none();
+    options.switch = true;
+
+    let result = parse_and_map(r#"
+        for (const x of y) {
+            switch (x) {
+                case 1:
+                    for (;;) break;
+                    break;
+                case 2:
+                    continue;
+            }
+        }
+    "#, options);
+
+    assert_snapshot!(result, @r#"
+    for (const x of  y) {
+    	$zeroSugar0:	{
+    		let $zeroSugar2 = 2;
+    		if ($zeroSugar2 === 1) 		$zeroSugar2 = 0;
+     else if ($zeroSugar2 === 2) 		$zeroSugar2 = 1;
+
+    		if ($zeroSugar2 <= 0) {
+    			for (; ; )			break;
+    			break $zeroSugar0;
+    		}
+    		if ($zeroSugar2 <= 1) {
+    			continue;
+    		}
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_switch_and_finally_keep_for_loops() {
+    let mut options = TransformOptions::none();
+    options.switch = true;
+    options.finally = true;
+
+    let result = parse_and_map(r#"
+        function f() {
+            for (let i = 0; i < 10; i++) {
+                try {
+                    if (a) continue;
+                    if (b) break;
+                    for (const x of y) {
+                        if (x) break;
+                        if (!x) continue;
+                    }
+                    switch (i) {
+                        case 1: return i;
+                    }
+                } finally {
+                    g();
+                }
+            }
+        }
+    "#, options);
+
+    assert_snapshot!(result, @r#"
+    function f() {
+    	for (let i = 0; i < 10; i++)	{
+    		let $zeroSugar2 = 0;
+    		let $zeroSugar3;
+    		$zeroSugar4:		try{
+    			if (a) {
+    				$zeroSugar2 = 3;
+    				break $zeroSugar4;
+    			}
+    			if (b) {
+    				$zeroSugar2 = 4;
+    				break $zeroSugar4;
+    			}
+    			for (const x of  y) 			{
+    				if (x) 				break;
+
+    				if ( !x) 				continue;
+
+    			}
+    			let $zeroSugar1 = 1;
+    			if ($zeroSugar1 === 1) 			$zeroSugar1 = 0;
+
+    			if ($zeroSugar1 <= 0) {
+    				{
+    					$zeroSugar2 = 2;
+    					$zeroSugar3 = i;
+    					break $zeroSugar4;
+    				}
+    			}
+    		}catch(e){
+    			$zeroSugar2 = 1;
+    			$zeroSugar3 = e;
+    		}		{
+    			g();
+    		}
+    		if ($zeroSugar2 === 1) 		throw $zeroSugar3;
+
+    		if ($zeroSugar2 === 2) 		return $zeroSugar3;
+
+    		if ($zeroSugar2 === 3) 		continue;
+
+    		if ($zeroSugar2 === 4) 		break;
+
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_for_loop_enables_continue() {
+    let mut options = TransformOptions::none();
+    options.for_loop = true;
+
+    assert!(options.resolve().continue_stmt);
+
+    let result = parse_and_map(r#"
+        for (let i = 0; i < 10; i++) {
+            if (i) continue;
+            f(i);
+        }
+        while (x) {
+            continue;
+        }
+    "#, options);
+
+    assert_snapshot!(result, @r#"
+    {
+    	let i = 0;
+    	while(i < 10)	{
+    		$zeroSugar0:		{
+    			if (i) 			break $zeroSugar0;
+
+    			f(i);
+    		}
+    		i++;
+    	}
+    }
+    while(x)$zeroSugar1:{
+    	break $zeroSugar1;
+    }
+    "#);
+}
+
+#[test]
+fn test_without_var_decl() {
+    let mut options = TransformOptions::new();
+    options.var_decl = false;
+
+    let result = parse_and_map(r#"
+        let {a, b: [c]} = d, e;
+    "#, options);
+
+    assert_snapshot!(result, @r#"
+    let {a:a, b:[c]} = d, e;
+    "#);
+}
+
+#[test]
+fn test_invalid_name_prefix() {
+    for prefix in ["a-b", "1x", ""] {
+        let options = TransformOptions { name_prefix: prefix.to_string(), ..TransformOptions::default() };
+        assert!(options.check().is_err(), "`{}` is not an identifier", prefix);
+    }
+    let options = TransformOptions { name_prefix: "_tmp$".to_string(), ..TransformOptions::default() };
+    assert_eq!(options.check(), Ok(()));
+
+    let options = TransformOptions { name_prefix: "a-b".to_string(), ..TransformOptions::default() };
+    let result = transform_code_with_options("do f(); while (x);", &options).unwrap();
+    assert!(result.had_error);
+    assert_eq!(result.transformed_code, "");
+    assert_snapshot!(format!("{} {}", result.errors[0].code, result.errors[0].message), @r#"
+    ZS011 The name prefix `a-b` is not a valid identifier
+    "#);
+}
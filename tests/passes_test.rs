@@ -0,0 +1,232 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use zero_sugar::mapper::{Mapper, MapperAction};
+use zero_sugar::mapper_state::MapperState;
+use zero_sugar::passes::*;
+use zero_sugar::scope::BindingKind;
+use zero_sugar::{Preset, Transform, TransformOptions};
+
+/// Run the passes over the source and return the output, the rules that were applied and the diagnostics
+fn run(manager: &PassManager, source: &str) -> String {
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, source, SourceType::default().with_module(true)).parse();
+    assert!(parsed.errors.is_empty(), "Parse errors: {:?}", parsed.errors);
+
+    let state = Rc::new(RefCell::new(MapperState::new()));
+    let program = manager.run(parsed.program, &allocator, &state);
+
+    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions);
+    let state = state.borrow();
+    let rules: Vec<&str> = state.trace.iter().map(|entry| entry.rule.as_str()).collect();
+    let diagnostics: Vec<String> = state.diagnostics.iter().map(|diagnostic| {
+        format!("{} [{}, {}] {}", diagnostic.code.as_str(), diagnostic.span.start, diagnostic.span.end, diagnostic.message)
+    }).collect();
+    format!("{}---\nrules: {}\ndiagnostics:\n{}", codegen.build(&program), rules.join(", "), diagnostics.join("\n"))
+}
+
+fn manager_of(passes: &[Pass]) -> PassManager {
+    let mut manager = PassManager::new();
+    for pass in passes {
+        manager.add(*pass);
+    }
+    manager
+}
+
+fn problems(manager: &PassManager) -> String {
+    manager.validate().iter().map(|diagnostic| format!("{} {}", diagnostic.code.as_str(), diagnostic.message)).collect::<Vec<String>>().join("\n")
+}
+
+#[test]
+fn test_default_order_validates() {
+    let all = PassManager::from_options(&TransformOptions::new().resolve());
+    let names: Vec<&str> = all.transforms().map(|transform| transform.name()).collect();
+    assert_eq!(names, vec!("finally", "continue", "do-while", "for-loop", "for-in", "for-of", "var-decl", "switch"));
+    assert!(all.validate().is_empty());
+    assert_eq!(all.traversal_names(), vec!("finally", "continue", "do-while+for-loop+for-in+for-of", "var-decl", "switch"));
+
+    let misc = PassManager::from_options(&TransformOptions::from_preset(Preset::Misc).resolve());
+    assert!(misc.validate().is_empty());
+    assert_eq!(misc.traversal_names(), vec!("finally", "continue", "do-while+for-loop+for-in+for-of", "var-decl", "switch+while-true", "block-body"));
+
+    // Without var-decl nothing removes the patterns that the switch pass can't deal with. That's up to the input.
+    let mut options = TransformOptions::new();
+    options.var_decl = false;
+    assert!(PassManager::from_options(&options.resolve()).validate().is_empty());
+}
+
+#[test]
+fn test_bad_order_is_reported() {
+    assert_snapshot!(problems(&manager_of(&[FOR_LOOP_PASS, DO_WHILE_PASS, CONTINUE_PASS])), @r#"
+    ZS010 `continue` statement must be gone before the `for-loop` pass so that must run after the `continue` pass
+    ZS010 `continue` statement must be gone before the `do-while` pass so that must run after the `continue` pass
+    "#);
+    assert_snapshot!(problems(&manager_of(&[CONTINUE_PASS, FINALLY_PASS, FOR_LOOP_PASS, VAR_DECL_PASS, FOR_OF_PASS, SWITCH_PASS])), @r#"
+    ZS010 `continue` statement must be gone before the `for-loop` pass but the `finally` pass before it may introduce one
+    ZS010 Destructuring pattern in a variable declaration must be gone before the `switch` pass but the `for-of` pass before it may introduce one
+    "#);
+
+    // Nothing runs when the order is wrong
+    assert_snapshot!(run(&manager_of(&[FOR_LOOP_PASS, CONTINUE_PASS]), "for (;;) { if (a) continue; }"), @r#"
+    for (; ; ){
+    	if (a) 	continue;
+
+    }
+    ---
+    rules: 
+    diagnostics:
+    ZS010 [0, 0] `continue` statement must be gone before the `for-loop` pass so that must run after the `continue` pass
+    "#);
+}
+
+#[test]
+fn test_precondition_is_checked_per_statement() {
+    // Nothing removes the `continue` so the for-loop pass skips the loop that has one, but not the other loops
+    let result = run(&manager_of(&[DO_WHILE_PASS, FOR_LOOP_PASS]), r#"
+for (let i = 0; i < 3; i++) { if (i) continue; }
+for (;;) { f(); }
+do { while (x) continue; } while (y);
+"#);
+
+    assert_snapshot!(result, @r#"
+    for (let i = 0; i < 3; i++){
+    	if (i) 	continue;
+
+    }
+    while(true){
+    	{
+    		f();
+    	}
+    }
+    do {
+    	while(x)	continue;
+    }while(y);
+    ---
+    rules: for-loop
+    diagnostics:
+    ZS004 [83, 92] `continue` statement must be gone before the `do-while` pass. This one is still here so the `do-while` loop around it is left as is.
+    ZS004 [38, 47] `continue` statement must be gone before the `for-loop` pass. This one is still here so the `for` loop around it is left as is.
+    "#);
+}
+
+#[test]
+fn test_fixpoint_runs_passes_again() {
+    // The for-loop pass moves the pattern out of the header after the var-decl pass already ran
+    let source = "for (let [a, b] = c; a; a = b) f();";
+    let mut manager = manager_of(&[VAR_DECL_PASS, FOR_LOOP_PASS]);
+
+    assert_snapshot!(run(&manager, source), @r#"
+    {
+    	let [a,b] = c;
+    	while(a)	{
+    		f();
+    		a = b;
+    	}
+    }
+    ---
+    rules: for-loop
+    diagnostics:
+    "#);
+
+    manager.set_max_rounds(5);
+    assert_snapshot!(run(&manager, source), @r#"
+    {
+    	let a = c[0];
+    	let b = c[1];
+    	while(a)	{
+    		f();
+    		a = b;
+    	}
+    }
+    ---
+    rules: for-loop, var-decl-array-pattern
+    diagnostics:
+    "#);
+}
+
+#[test]
+fn test_scopes_are_analyzed_again_after_a_change() {
+    // The second traversal sees the variable that the first one generated as the `let` it became
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, "do { f(); } while (x);", SourceType::default().with_module(true)).parse();
+    let state = Rc::new(RefCell::new(MapperState::new()));
+    manager_of(&[DO_WHILE_PASS, VAR_DECL_PASS]).run(parsed.program, &allocator, &state);
+
+    let state = state.borrow();
+    let kinds: Vec<(String, BindingKind)> = state.scopes.bindings().map(|(_, binding)| (binding.name.clone(), binding.kind)).collect();
+    assert_eq!(kinds, vec!(("$zeroSugar0".to_string(), BindingKind::Let)));
+}
+
+#[test]
+fn test_scopes_analysis_is_kept_until_a_change() {
+    let allocator = Allocator::default();
+    let state = Rc::new(RefCell::new(MapperState::new()));
+    let manager = manager_of(&[DO_WHILE_PASS]);
+
+    let parsed = Parser::new(&allocator, "while (x) f();", SourceType::default().with_module(true)).parse();
+    manager.run(parsed.program, &allocator, &state);
+    assert!(state.borrow().scopes_analyzed);
+
+    let parsed = Parser::new(&allocator, "do f(); while (x);", SourceType::default().with_module(true)).parse();
+    state.borrow_mut().scopes_analyzed = false;
+    manager.run(parsed.program, &allocator, &state);
+    assert!(!state.borrow().scopes_analyzed);
+}
+
+/// Resolves the argument of each `f(x)` call in the scope of that call, after the built-in passes
+struct ResolveArguments;
+
+#[derive(Default)]
+struct Resolved(Vec<(String, Option<BindingKind>)>);
+
+impl Transform for ResolveArguments {
+    fn name(&self) -> &str {
+        "resolve-arguments"
+    }
+
+    fn install<'a>(&self, mapper: &mut Mapper<'a>) {
+        let state = mapper.state.clone();
+        mapper.add_visitor_expr(move |expr, _alloc, before: bool| {
+            if before {
+                return (MapperAction::Normal, expr);
+            }
+            if let Expression::CallExpression(call) = &expr {
+                if let Some(Argument::Expression(Expression::Identifier(ident))) = call.arguments.first() {
+                    let mut state = state.borrow_mut();
+                    let scope = state.context.current_scope().unwrap_or(state.scopes.root());
+                    let kind = state.scopes.resolve(scope, &ident.name).map(|symbol| state.scopes.binding(symbol).kind);
+                    state.extension_mut::<Resolved>().0.push((ident.name.to_string(), kind));
+                }
+            }
+            (MapperAction::Normal, expr)
+        });
+    }
+}
+
+#[test]
+fn test_scopes_resolve_after_the_builtin_passes() {
+    // The `for` scope of `i` is a block by now, that must not make it resolve to the outer `var i`
+    let allocator = Allocator::default();
+    let source = "var i = 0; for (let i = 1; i < 3; i++) { f(i); } try { g(i); } finally { h(i); }";
+    let parsed = Parser::new(&allocator, source, SourceType::default().with_module(true)).parse();
+    let state = Rc::new(RefCell::new(MapperState::new()));
+    let mut manager = PassManager::from_options(&TransformOptions::new());
+    manager.add(ResolveArguments);
+    manager.run(parsed.program, &allocator, &state);
+
+    let state = state.borrow();
+    let resolved = &state.extension::<Resolved>().unwrap().0;
+    assert_eq!(resolved, &vec!(
+        ("i".to_string(), Some(BindingKind::Let)),
+        ("i".to_string(), Some(BindingKind::Var)),
+        ("i".to_string(), Some(BindingKind::Var)),
+    ));
+}
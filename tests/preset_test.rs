@@ -0,0 +1,224 @@
+use insta::assert_snapshot;
+
+use zero_sugar::{transform_code_with_options, validate_code};
+use zero_sugar::{HelperMode, Preset, Profile, SourceKind, TransformOptions};
+
+fn transform(source: &str, preset: Preset) -> String {
+    let mut options = TransformOptions::from_preset(preset);
+    options.helpers = HelperMode::None;
+    options.validate = true;
+    let result = transform_code_with_options(source, &options).unwrap();
+    assert!(!result.had_error, "Unexpected errors: {:?}", result.errors);
+    result.transformed_code
+}
+
+#[test]
+fn test_presets_match_their_profile() {
+    assert_eq!(TransformOptions::from_preset(Preset::Js0), TransformOptions::new());
+    assert_eq!(Profile::from_preset(Preset::Js0), Profile::js0());
+    assert_eq!(Profile::from_preset(Preset::Misc), Profile::misc());
+
+    let es5 = Profile::from_preset(Preset::Es5);
+    assert_eq!(es5, Profile { for_of: false, declaration_pattern: false, continue_stmt: false, ..Profile::any() });
+
+    // Only the transforms are set, the rest is left alone
+    let mut options = TransformOptions::none();
+    options.name_prefix = "$x".to_string();
+    options.apply_preset(Preset::Misc);
+    assert!(options.switch && options.while_true && options.block_bodies);
+    assert_eq!(options.name_prefix, "$x");
+}
+
+#[test]
+fn test_es5_only_removes_es6_statements() {
+    let result = transform(r#"
+        do x(); while (y);
+        for (const v of w) if (v) continue;
+        const {a, b: [c]} = d;
+    "#, Preset::Es5);
+
+    assert_snapshot!(result, @r#"
+    do 
+    	x();
+    while(y);
+    var $zeroSugar1 = $forOf(w);
+    var $zeroSugar2;
+    while($zeroSugar2 = $zeroSugar1.next()){
+    	if ($zeroSugar2.done === true) 	break;
+
+    	const v = $zeroSugar2.value;
+    	$zeroSugar0:	if (v) 	break $zeroSugar0;
+
+    }
+    const a = d.a;
+    var $zeroSugar3 = d.b;
+    let c = $zeroSugar3[0];
+    "#);
+}
+
+#[test]
+fn test_es5_retargets_labeled_continue_in_for_of() {
+    // The loop ends up in a labeled block, where a `continue outer` is a syntax error
+    let result = transform(r#"
+        outer: for (const x of a) {
+            for (const y of b) {
+                if (y) continue outer;
+                f(y);
+            }
+        }
+    "#, Preset::Es5);
+
+    assert_snapshot!(result, @r#"
+    outer:{
+    	var $zeroSugar3 = $forOf(a);
+    	var $zeroSugar4;
+    	while($zeroSugar4 = $zeroSugar3.next())	{
+    		if ($zeroSugar4.done === true) 		break;
+
+    		const x = $zeroSugar4.value;
+    		$zeroSugar0:		{
+    			var $zeroSugar1 = $forOf(b);
+    			var $zeroSugar2;
+    			while($zeroSugar2 = $zeroSugar1.next())			{
+    				if ($zeroSugar2.done === true) 				break;
+
+    				const y = $zeroSugar2.value;
+    				{
+    					if (y) 					break $zeroSugar0;
+
+    					f(y);
+    				}
+    			}
+    		}
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_es5_declares_temporaries_with_var() {
+    let source = r#"
+        function g(o, y) {
+            var {a, [k()]: b, ...r} = o;
+            for (var x of y) {
+                if (x) continue;
+                f(x);
+            }
+        }
+    "#;
+    assert_snapshot!(transform(source, Preset::Es5), @r#"
+    function g(o, y) {
+    	var a = o.a;
+    	var $zeroSugar3 = k();
+    	var b = o[$zeroSugar3];
+    	var r = $rest(o, ['a', $zeroSugar3]);
+    	var $zeroSugar1 = $forOf(y);
+    	var $zeroSugar2;
+    	while($zeroSugar2 = $zeroSugar1.next())	{
+    		if ($zeroSugar2.done === true) 		break;
+
+    		var x = $zeroSugar2.value;
+    		$zeroSugar0:		{
+    			if (x) 			break $zeroSugar0;
+
+    			f(x);
+    		}
+    	}
+    }
+    "#);
+
+    // Nor do the helpers bring any ES6 along
+    let mut options = TransformOptions::from_preset(Preset::Es5);
+    options.helpers = HelperMode::Inline;
+    let result = transform_code_with_options(source, &options).unwrap();
+    for es6 in ["let ", "const ", "=>", "Reflect"] {
+        assert!(!result.transformed_code.contains(es6), "{} in:\n{}", es6, result.transformed_code);
+    }
+}
+
+#[test]
+fn test_misc_makes_every_loop_while_true() {
+    let result = transform(r#"
+        while (x) { let x = 1; }
+        out: while (a) if (b) break out; else if (c) f(); else g();
+        for (;;) h();
+    "#, Preset::Misc);
+
+    assert_snapshot!(result, @r#"
+    while(true){
+    	if ( !x) {
+    		break;
+    	}
+    	{
+    		let x = 1;
+    	}
+    }
+    out:while(true){
+    	if ( !a) {
+    		break;
+    	}
+    	if (b) {
+    		break out;
+    	} else if (c) {
+    		f();
+    	} else {
+    		g();
+    	}
+    }
+    while(true){
+    	h();
+    }
+    "#);
+}
+
+#[test]
+fn test_misc_wraps_generated_bodies() {
+    let result = transform(r#"
+        switch (x) { case 1: f(); break; default: g(); }
+        with (o) f();
+    "#, Preset::Misc);
+
+    assert_snapshot!(result, @r#"
+    $zeroSugar0:{
+    	let $zeroSugar2 = 2;
+    	if ($zeroSugar2 === 1) {
+    		$zeroSugar2 = 0;
+    	} else {
+    		$zeroSugar2 = 1;
+    	}
+    	if ($zeroSugar2 <= 0) {
+    		f();
+    		break $zeroSugar0;
+    	}
+    	if ($zeroSugar2 <= 1) {
+    		g();
+    	}
+    }
+    with(o){
+    	f();
+    }
+    "#);
+}
+
+#[test]
+fn test_helpers_match_every_preset() {
+    // The helpers are injected after the transforms so they must already be in the target subset
+    let source = r#"
+        for (const k in o) f(k);
+        for (const v of w) f(v);
+        const {a, ...b} = c;
+    "#;
+    for preset in [Preset::Es5, Preset::Js0, Preset::Misc] {
+        for (source_kind, helpers) in [(SourceKind::Module, HelperMode::Inline), (SourceKind::Script, HelperMode::Inline), (SourceKind::Script, HelperMode::Import)] {
+            let mut options = TransformOptions::from_preset(preset);
+            options.source_kind = source_kind;
+            options.helpers = helpers;
+            options.validate = true;
+            let result = transform_code_with_options(source, &options).unwrap();
+            assert!(!result.had_error, "Unexpected errors for {:?} {:?}: {:?}", preset, helpers, result.errors);
+
+            let violations = validate_code(&result.transformed_code, &options, &Profile::from_preset(preset));
+            assert!(violations.is_empty(), "{:?} {:?}: {:?}\n{}", preset, helpers, violations, result.transformed_code);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use zero_sugar::mapper::{create_mapper, MapperAction};
+use zero_sugar::scope::{BindingKind, ScopeTree};
+use zero_sugar::transforms::stmt_do_while::transform_do_while_statement;
+
+/// One line per binding: name, kind, the kind of scope it's in and how many references resolve to it
+fn describe_bindings(source: &str, tree: &ScopeTree) -> String {
+    let mut lines: Vec<String> = tree.bindings().map(|(_, binding)| {
+        let reads = binding.references.iter().filter(|id| !tree.reference(**id).is_write).count();
+        let writes = binding.references.len() - reads;
+        let scope = tree.scope(binding.scope);
+        format!(
+            "{} {:?} in {:?} `{}` reads={} writes={}",
+            binding.name,
+            binding.kind,
+            scope.kind,
+            source[scope.span.start as usize..scope.span.end as usize].split('\n').next().unwrap_or("").trim(),
+            reads,
+            writes,
+        )
+    }).collect();
+    let mut globals: Vec<String> = tree.references().filter(|(_, reference)| reference.binding.is_none()).map(|(_, reference)| reference.name.clone()).collect();
+    globals.dedup();
+    lines.push(format!("globals: {}", globals.join(", ")));
+    lines.join("\n")
+}
+
+fn analyze(source: &str) -> String {
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, source, SourceType::default().with_module(true)).parse();
+    assert!(parsed.errors.is_empty(), "Parse errors: {:?}", parsed.errors);
+    describe_bindings(source, &ScopeTree::analyze(&parsed.program))
+}
+
+#[test]
+fn test_declarations_and_references() {
+    let result = analyze(r#"import { a } from "a";
+let b = a;
+const c = b + d;
+function f(p, { q = c }, ...r) {
+    var v = p;
+    g(v, q, r);
+}
+class K { m() { return K; } }
+try { b = 2; } catch (e) { e; }
+[b] = [1];
+"#);
+
+    assert_snapshot!(result, @r#"
+    a Import in Program `import { a } from "a";` reads=1 writes=0
+    b Let in Program `import { a } from "a";` reads=1 writes=2
+    c Const in Program `import { a } from "a";` reads=1 writes=0
+    f Function in Program `import { a } from "a";` reads=0 writes=0
+    p Param in Function `function f(p, { q = c }, ...r) {` reads=1 writes=0
+    q Param in Function `function f(p, { q = c }, ...r) {` reads=1 writes=0
+    r Param in Function `function f(p, { q = c }, ...r) {` reads=1 writes=0
+    v Var in Function `function f(p, { q = c }, ...r) {` reads=1 writes=0
+    K Class in Program `import { a } from "a";` reads=1 writes=0
+    e CatchParam in Catch `catch (e) { e; }` reads=1 writes=0
+    globals: d, g
+    "#);
+}
+
+#[test]
+fn test_hoisting_and_shadowing() {
+    let result = analyze(r#"x;
+{
+    var x = 1;
+    let y = 2;
+    {
+        let y = 3;
+        y;
+    }
+    y;
+}
+for (let i = 0; i < 1; i++) { i; }
+const fe = function named() { named; };
+const ce = class Named { m() { Named; } };
+"#);
+
+    assert_snapshot!(result, @r#"
+    x Var in Program `x;` reads=1 writes=0
+    y Let in Block `{` reads=1 writes=0
+    y Let in Block `{` reads=1 writes=0
+    i Let in For `for (let i = 0; i < 1; i++) { i; }` reads=2 writes=1
+    fe Const in Program `x;` reads=0 writes=0
+    named Function in Function `function named() { named; }` reads=1 writes=0
+    ce Const in Program `x;` reads=0 writes=0
+    Named Class in Class `class Named { m() { Named; } }` reads=1 writes=0
+    globals:
+    "#);
+}
+
+#[test]
+fn test_ids_are_stamped_on_the_ast() {
+    let allocator = Allocator::default();
+    let source = "let a = 1; { let a = 2; a; } a;";
+    let parsed = Parser::new(&allocator, source, SourceType::default().with_module(true)).parse();
+    let tree = ScopeTree::analyze(&parsed.program);
+
+    let Statement::Declaration(Declaration::VariableDeclaration(decl)) = &parsed.program.body[0] else { panic!("Expected a declaration") };
+    let BindingPatternKind::BindingIdentifier(outer) = &decl.declarations[0].id.kind else { panic!("Expected an identifier") };
+    let Statement::ExpressionStatement(outer_ref) = &parsed.program.body[2] else { panic!("Expected an expression statement") };
+    let Expression::Identifier(outer_ref) = &outer_ref.expression else { panic!("Expected an identifier") };
+    let Statement::BlockStatement(block) = &parsed.program.body[1] else { panic!("Expected a block") };
+    let Statement::ExpressionStatement(inner_ref) = &block.body[1] else { panic!("Expected an expression statement") };
+    let Expression::Identifier(inner_ref) = &inner_ref.expression else { panic!("Expected an identifier") };
+
+    let outer = tree.symbol_of(outer).unwrap();
+    assert_eq!(tree.binding_of(outer_ref).unwrap().span, outer.span);
+    assert_ne!(tree.binding_of(inner_ref).unwrap().span, outer.span);
+}
+
+#[test]
+fn test_generated_names_are_declared() {
+    let allocator = Box::leak(Box::new(Allocator::default()));
+    let source = "let a = 1; function f() { do { a++; } while (a < 10); }";
+    let parsed = Parser::new(allocator, source, SourceType::default().with_module(true)).parse();
+
+    let mut mapper = create_mapper(allocator);
+    let state = mapper.state.clone();
+    mapper.add_visitor_stmt(move |stmt, allocator, before: bool| match (before, stmt) {
+        (false, Statement::DoWhileStatement(do_while)) => transform_do_while_statement(do_while.unbox(), allocator, &mut state.borrow_mut()),
+        (_, stmt) => (MapperAction::Normal, stmt),
+    });
+    mapper.map(parsed.program);
+
+    let state = mapper.state.borrow();
+    let tree = &state.scopes;
+    let (_, generated) = tree.bindings().find(|(_, binding)| binding.kind == BindingKind::Generated).expect("The do-while test variable should be declared");
+    assert_eq!(generated.name, "$zeroSugar0");
+    // The scope of the statement that was transformed, which is the function body
+    assert_eq!(tree.scope(generated.scope).span, parsed_span_of(source, "function f()"));
+    assert_eq!(tree.resolve(generated.scope, "a"), tree.resolve(tree.root(), "a"));
+}
+
+fn parsed_span_of(source: &str, start: &str) -> oxc_span::Span {
+    let start = source.find(start).unwrap() as u32;
+    oxc_span::Span::new(start, source.len() as u32)
+}
@@ -0,0 +1,247 @@
+use insta::assert_snapshot;
+
+use zero_sugar::transform_code;
+
+// Decode the mappings and print each one as `generated -> original`, with a snippet of the code at both
+// positions. Lines and columns are one-based here to make it easier to read.
+fn parse_and_map(source: &str) -> String {
+    let result = transform_code(source).unwrap();
+    assert!(!result.had_error, "Unexpected errors: {:?}", result.errors);
+
+    let mappings = extract_mappings(&result.source_map);
+    let generated_lines: Vec<&str> = result.transformed_code.lines().collect();
+    let source_lines: Vec<&str> = source.lines().collect();
+
+    let mut out = String::new();
+    for (generated_line, generated_column, original_line, original_column) in decode_mappings(&mappings) {
+        out.push_str(&format!(
+            "{}:{} `{}` -> {}:{} `{}`\n",
+            generated_line + 1,
+            generated_column + 1,
+            snippet(&generated_lines, generated_line, generated_column),
+            original_line + 1,
+            original_column + 1,
+            snippet(&source_lines, original_line, original_column),
+        ));
+    }
+    out
+}
+
+fn extract_mappings(source_map: &str) -> String {
+    let start = source_map.find(r#""mappings":""#).expect("source map should have mappings") + r#""mappings":""#.len();
+    let end = source_map[start..].find('"').unwrap() + start;
+    source_map[start..end].to_string()
+}
+
+// The column is in UTF-16 code units
+fn snippet(lines: &[&str], line: i64, column: i64) -> String {
+    let mut units = 0;
+    let text: String = lines[line as usize].chars().skip_while(|c| {
+        units += c.len_utf16() as i64;
+        units <= column
+    }).take(20).collect();
+    text.trim_end().to_string()
+}
+
+fn decode_mappings(mappings: &str) -> Vec<(i64, i64, i64, i64)> {
+    let mut result = vec!();
+    let mut original_line = 0;
+    let mut original_column = 0;
+    for (generated_line, line) in mappings.split(';').enumerate() {
+        let mut generated_column = 0;
+        for segment in line.split(',').filter(|segment| !segment.is_empty()) {
+            let fields = decode_vlq(segment);
+            assert_eq!(fields.len(), 4, "Expecting four fields per segment");
+            assert_eq!(fields[1], 0, "There is only one source");
+            generated_column += fields[0];
+            original_line += fields[2];
+            original_column += fields[3];
+            result.push((generated_line as i64, generated_column, original_line, original_column));
+        }
+    }
+    result
+}
+
+fn decode_vlq(segment: &str) -> Vec<i64> {
+    const BASE64_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut values = vec!();
+    let mut value = 0;
+    let mut shift = 0;
+    for c in segment.chars() {
+        let digit = BASE64_CHARS.find(c).unwrap() as i64;
+        value += (digit & 0b11111) << shift;
+        if digit & 0b100000 != 0 {
+            shift += 5;
+        } else {
+            values.push(if value & 1 == 1 { -(value >> 1) } else { value >> 1 });
+            value = 0;
+            shift = 0;
+        }
+    }
+    values
+}
+
+#[test]
+fn test_source_map_shape() {
+    let result = transform_code("f();").unwrap();
+
+    assert_snapshot!(result.source_map, @r#"
+    {"version":3,"sources":["input.js"],"sourcesContent":["f();"],"names":[],"mappings":"AAAA"}
+    "#);
+}
+
+#[test]
+fn test_source_map_untransformed() {
+    let result = parse_and_map(r#"
+let a = 1;
+if (a) {
+  f(a);
+}
+    "#);
+
+    assert_snapshot!(result, @r#"
+    1:1 `let a = 1;` -> 2:1 `let a = 1;`
+    2:1 `if (a) {` -> 3:1 `if (a) {`
+    2:8 `{` -> 3:8 `{`
+    3:2 `f(a);` -> 4:3 `f(a);`
+    "#);
+}
+
+#[test]
+fn test_source_map_for_loop() {
+    let result = parse_and_map(r#"
+for (let i = 0; i < 10; i++) {
+  f(i);
+}
+    "#);
+
+    assert_snapshot!(result, @r#"
+    1:1 `{` -> 2:1 `for (let i = 0; i <`
+    2:2 `let i = 0;` -> 2:6 `let i = 0; i < 10; i`
+    3:2 `while(i < 10)	{` -> 2:1 `for (let i = 0; i <`
+    3:16 `{` -> 2:1 `for (let i = 0; i <`
+    4:3 `{` -> 2:30 `{`
+    5:4 `f(i);` -> 3:3 `f(i);`
+    7:3 `i++;` -> 2:25 `i++) {`
+    "#);
+}
+
+#[test]
+fn test_source_map_do_while() {
+    let result = parse_and_map(r#"
+do {
+  f();
+} while (x);
+    "#);
+
+    assert_snapshot!(result, @r#"
+    1:1 `let $zeroSugar0 = tr` -> 2:1 `do {`
+    2:1 `while($zeroSugar0){` -> 2:1 `do {`
+    2:19 `{` -> 2:1 `do {`
+    3:2 `{` -> 2:4 `{`
+    4:3 `f();` -> 3:3 `f();`
+    6:2 `$zeroSugar0 = x;` -> 4:10 `x);`
+    "#);
+}
+
+#[test]
+fn test_source_map_for_of() {
+    let result = parse_and_map(r#"
+for (const x of arr) {
+  f(x);
+}
+    "#);
+
+    assert_snapshot!(result, @r#"
+    23:1 `const $zeroSugar0 =` -> 2:17 `arr) {`
+    24:1 `let $zeroSugar1;` -> 2:1 `for (const x of arr)`
+    25:1 `while($zeroSugar1 =` -> 2:1 `for (const x of arr)`
+    25:40 `{` -> 2:1 `for (const x of arr)`
+    26:2 `if ($zeroSugar1.done` -> 2:1 `for (const x of arr)`
+    26:34 `break;` -> 2:1 `for (const x of arr)`
+    28:2 `const x = $zeroSugar` -> 2:6 `const x of arr) {`
+    29:2 `{` -> 2:22 `{`
+    30:3 `f(x);` -> 3:3 `f(x);`
+    "#);
+}
+
+#[test]
+fn test_source_map_switch() {
+    let result = parse_and_map(r#"
+switch (x) {
+  case 1:
+    f();
+    break;
+  case 2:
+    g();
+  default:
+    h();
+}
+    "#);
+
+    assert_snapshot!(result, @r#"
+    1:1 `$zeroSugar0:{` -> 2:1 `switch (x) {`
+    1:13 `{` -> 2:1 `switch (x) {`
+    2:2 `let $zeroSugar2 = 3;` -> 2:9 `x) {`
+    3:2 `if ($zeroSugar2 ===` -> 3:8 `1:`
+    3:26 `$zeroSugar2 = 0;` -> 3:8 `1:`
+    4:7 `if ($zeroSugar2 ===` -> 6:8 `2:`
+    4:31 `$zeroSugar2 = 1;` -> 6:8 `2:`
+    6:3 `$zeroSugar2 = 2;` -> 8:3 `default:`
+    7:2 `if ($zeroSugar2 <= 0` -> 3:3 `case 1:`
+    7:24 `{` -> 3:3 `case 1:`
+    8:3 `f();` -> 4:5 `f();`
+    9:3 `break $zeroSugar0;` -> 5:5 `break;`
+    11:2 `if ($zeroSugar2 <= 1` -> 6:3 `case 2:`
+    11:24 `{` -> 6:3 `case 2:`
+    12:3 `g();` -> 7:5 `g();`
+    14:2 `if ($zeroSugar2 <= 2` -> 8:3 `default:`
+    14:24 `{` -> 8:3 `default:`
+    15:3 `h();` -> 9:5 `h();`
+    "#);
+}
+
+#[test]
+fn test_source_map_finally() {
+    let result = parse_and_map(r#"
+function f() {
+  try {
+    return g();
+  } finally {
+    h();
+  }
+}
+    "#);
+
+    assert_snapshot!(result, @r#"
+    1:1 `function f() {` -> 2:1 `function f() {`
+    2:2 `let $zeroSugar0 = 0;` -> 3:3 `try {`
+    3:2 `let $zeroSugar1;` -> 3:3 `try {`
+    4:2 `$zeroSugar2:	try{` -> 3:3 `try {`
+    4:15 `try{` -> 3:3 `try {`
+    5:3 `{` -> 4:5 `return g();`
+    6:4 `$zeroSugar0 = 2;` -> 4:5 `return g();`
+    7:4 `$zeroSugar1 = g();` -> 4:5 `return g();`
+    8:4 `break $zeroSugar2;` -> 4:5 `return g();`
+    11:3 `$zeroSugar0 = 1;` -> 3:3 `try {`
+    12:3 `$zeroSugar1 = e;` -> 3:3 `try {`
+    13:4 `{` -> 5:13 `{`
+    14:3 `h();` -> 6:5 `h();`
+    16:2 `if ($zeroSugar0 ===` -> 5:13 `{`
+    16:26 `throw $zeroSugar1;` -> 5:13 `{`
+    18:2 `if ($zeroSugar0 ===` -> 5:13 `{`
+    18:26 `return $zeroSugar1;` -> 5:13 `{`
+    "#);
+}
+
+#[test]
+fn test_source_map_utf16_columns() {
+    let result = parse_and_map(r#"
+let s = "😀"; f(s);
+    "#);
+
+    assert_snapshot!(result, @r#"
+    1:1 `let s = '\u{1f600}';` -> 2:1 `let s = "😀"; f(s);`
+    2:1 `f(s);` -> 2:15 `f(s);`
+    "#);
+}
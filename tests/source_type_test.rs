@@ -0,0 +1,212 @@
+use insta::assert_snapshot;
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::*;
+use oxc_parser::Parser;
+use oxc_span::SourceType;
+
+use zero_sugar::transform_code_with_options;
+use zero_sugar::HelperMode;
+use zero_sugar::SourceKind;
+use zero_sugar::SourceLanguage;
+use zero_sugar::TransformOptions;
+
+fn parse_and_map(source: &str, options: TransformOptions) -> String {
+    let result = transform_code_with_options(source, &options).unwrap();
+    if result.had_error {
+        return result.errors.iter().map(|error| format!("{}:{}: {} {}", error.line, error.column, error.code, error.message)).collect::<Vec<_>>().join("\n");
+    }
+    result.transformed_code
+}
+
+fn script() -> TransformOptions {
+    TransformOptions { source_kind: SourceKind::Script, helpers: HelperMode::None, ..TransformOptions::default() }
+}
+
+#[test]
+fn test_source_type_sloppy_script() {
+    let result = parse_and_map(r#"
+        <!-- html comment
+        with (obj) {
+            do { f(010); } while (x);
+        }
+    "#, script());
+
+    assert_snapshot!(result, @r#"
+    with(obj){
+    	let $zeroSugar0 = true;
+    	while($zeroSugar0)	{
+    		{
+    			f(010);
+    		}
+    		$zeroSugar0 = x;
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_source_type_module_rejects_sloppy() {
+    let result = parse_and_map(r#"
+        <!-- html comment
+        f();
+    "#, TransformOptions::default());
+
+    assert_snapshot!(result, @r#"
+    2:9: ZS001 Unexpected token
+    "#);
+}
+
+#[test]
+fn test_source_type_jsx() {
+    let result = parse_and_map(r#"
+        do { f(<div />); } while (x);
+    "#, TransformOptions { language: SourceLanguage::Jsx, ..TransformOptions::default() });
+
+    assert_snapshot!(result, @r#"
+    2:16: ZS002 JSXElement is not supported
+    "#);
+}
+
+#[test]
+fn test_source_type_typescript() {
+    let result = parse_and_map(r#"
+        let x: number = 1;
+        do { f(x as any); } while (x);
+    "#, TransformOptions { language: SourceLanguage::TypeScript, ..TransformOptions::default() });
+
+    assert_snapshot!(result, @r#"
+    3:16: ZS002 TSAsExpression is not supported
+    "#);
+}
+
+#[test]
+fn test_source_type_script_requires_helpers() {
+    let result = parse_and_map(r#"
+        const $forIn = 1;
+        for (const x in a) for (const y of x) f(y);
+    "#, TransformOptions { helpers: HelperMode::Import, ..script() });
+
+    assert_snapshot!(result, @r#"
+    var $forIn1 = require('zero-sugar/helpers').$forIn;
+    var $forOf = require('zero-sugar/helpers').$forOf;
+    const $forIn = 1;
+    {
+    	const $zeroSugar2 = $forIn1(a);
+    	let $zeroSugar3;
+    	while($zeroSugar3 = $zeroSugar2.next())	{
+    		if ($zeroSugar3.done === true) 		break;
+
+    		const x = $zeroSugar3.value;
+    		{
+    			const $zeroSugar0 = $forOf(x);
+    			let $zeroSugar1;
+    			while($zeroSugar1 = $zeroSugar0.next())			{
+    				if ($zeroSugar1.done === true) 				break;
+
+    				const y = $zeroSugar1.value;
+    				f(y);
+    			}
+    		}
+    	}
+    }
+    "#);
+}
+
+#[test]
+fn test_source_type_infer_from_file_name() {
+    let mut options = TransformOptions::default();
+
+    assert!(options.infer_source_type("a/b.cjs"));
+    assert_eq!((options.source_kind, options.language), (SourceKind::Script, SourceLanguage::JavaScript));
+    // .js keeps whatever it was
+    assert!(options.infer_source_type("b.js"));
+    assert_eq!((options.source_kind, options.language), (SourceKind::Script, SourceLanguage::JavaScript));
+    assert!(options.infer_source_type("c.d.mts"));
+    assert_eq!((options.source_kind, options.language), (SourceKind::Module, SourceLanguage::TypeScript));
+    assert!(options.infer_source_type("d.tsx"));
+    assert_eq!((options.source_kind, options.language), (SourceKind::Module, SourceLanguage::Tsx));
+    assert!(!options.infer_source_type("e.json"));
+    assert_eq!((options.source_kind, options.language), (SourceKind::Module, SourceLanguage::Tsx));
+}
+
+#[test]
+fn test_for_in_initializer_in_sloppy_script() {
+    let result = parse_and_map(r#"
+        for (var a = 0 in b) f(a);
+    "#, script());
+
+    assert_snapshot!(result, @r#"
+    2:14: ZS003 This lhs of a for-in header is not supported
+    "#);
+}
+
+#[test]
+fn test_ts_assertion_in_for_header() {
+    let options = TransformOptions { language: SourceLanguage::TypeScript, helpers: HelperMode::None, ..TransformOptions::default() };
+    let result = parse_and_map(r#"
+        for (a! in b) f(a);
+        for ((a as any) of b) f(a);
+    "#, options);
+
+    assert_snapshot!(result, @r#"
+    2:14: ZS002 TSNonNullExpression is not supported
+    3:15: ZS002 TSAsExpression is not supported
+    2:14: ZS003 This lhs of a for-in header is not supported
+    3:15: ZS003 This lhs of a for-of header is not supported
+    "#);
+}
+
+#[test]
+fn test_script_top_level_temporaries_are_var() {
+    let result = parse_and_map(r#"
+        let {a: {b}, [k()]: c} = d();
+        function f() { let {a: {b}} = d(); }
+    "#, script());
+
+    assert_snapshot!(result, @r#"
+    var $zeroSugar0 = d();
+    var $zeroSugar2 = $zeroSugar0.a;
+    let b = $zeroSugar2.b;
+    var $zeroSugar1 = k();
+    let c = $zeroSugar0[$zeroSugar1];
+    function f() {
+    	let $zeroSugar3 = d();
+    	let $zeroSugar4 = $zeroSugar3.a;
+    	let b = $zeroSugar4.b;
+    }
+    "#);
+}
+
+#[test]
+fn test_scripts_can_be_concatenated() {
+    // Scripts on the same page share their top level scope. A `let` or `const` with the same name in
+    // two of them is a redeclaration error, so the generated ones must not end up there.
+    let source = r#"
+        for (const x of a) f(x);
+        for (const x in b) f(x);
+        try { f(); } finally { g(); }
+        do { f(); } while (x);
+        switch (x) { case 1: f(); }
+        var {p: q, [k()]: r} = s();
+    "#;
+    let both = format!("{}\n{}", parse_and_map(source, script()), parse_and_map(source, script()));
+
+    let allocator = Allocator::default();
+    let parsed = Parser::new(&allocator, &both, SourceType::default()).parse();
+    assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+
+    let mut lexical_names: Vec<String> = vec!();
+    for stmt in &parsed.program.body {
+        if let Statement::Declaration(Declaration::VariableDeclaration(decl)) = stmt {
+            if decl.kind.is_lexical() {
+                for declarator in &decl.declarations {
+                    if let BindingPatternKind::BindingIdentifier(id) = &declarator.id.kind {
+                        lexical_names.push(id.name.to_string());
+                    }
+                }
+            }
+        }
+    }
+    assert_eq!(lexical_names, Vec::<String>::new(), "{}", both);
+}
@@ -260,22 +260,20 @@ fn test_switch_with_return() {
 
     assert_snapshot!(result, @r#"
     function f() {
-    	{
-    		let $zeroSugar1 = 3;
-    		if ($zeroSugar1 === 1) 		$zeroSugar1 = 0;
-     else if ($zeroSugar1 === 2) 		$zeroSugar1 = 1;
+    	let $zeroSugar1 = 3;
+    	if ($zeroSugar1 === 1) 	$zeroSugar1 = 0;
+     else if ($zeroSugar1 === 2) 	$zeroSugar1 = 1;
      else 
-    			$zeroSugar1 = 2;
-    		if ($zeroSugar1 <= 0) {
-    			return 'one';
-    		}
-    		if ($zeroSugar1 <= 1) {
-    			console.log('two');
-    			return 'two';
-    		}
-    		if ($zeroSugar1 <= 2) {
-    			return 'other';
-    		}
+    		$zeroSugar1 = 2;
+    	if ($zeroSugar1 <= 0) {
+    		return 'one';
+    	}
+    	if ($zeroSugar1 <= 1) {
+    		console.log('two');
+    		return 'two';
+    	}
+    	if ($zeroSugar1 <= 2) {
+    		return 'other';
     	}
     }
     "#);
@@ -301,6 +299,9 @@ fn test_switch_with_declarations() {
 
     assert_snapshot!(result, @r#"
     $zeroSugar0:{
+    	let y;
+    	let z;
+    	let w;
     	let $zeroSugar2 = 3;
     	if ($zeroSugar2 === 1) 	$zeroSugar2 = 0;
      else if ($zeroSugar2 === 2) 	$zeroSugar2 = 1;
@@ -390,7 +391,7 @@ fn test_switch_with_while_break() {
     	}
     	if ($zeroSugar2 <= 1) {
     		while(true)		{
-    			break $zeroSugar0;
+    			break;
     		}
     		break $zeroSugar0;
     	}
@@ -414,6 +415,7 @@ fn test_switch_transform_nested_do_first() {
 
     assert_snapshot!(result, @r#"
     $zeroSugar1:{
+    	let $zeroSugar0;
     	let $zeroSugar3 = 2;
     	if ($zeroSugar3 === a) 	$zeroSugar3 = 0;
      else if ($zeroSugar3 === b) 	$zeroSugar3 = 1;
@@ -421,14 +423,12 @@ fn test_switch_transform_nested_do_first() {
     	if ($zeroSugar3 <= 0) {
     	}
     	if ($zeroSugar3 <= 1) {
-    		{
-    			let $zeroSugar0 = true;
-    			while($zeroSugar0)			{
-    				{
-    					break $zeroSugar1;
-    				}
-    				$zeroSugar0 = true;
+    		$zeroSugar0 = true;
+    		while($zeroSugar0)		{
+    			{
+    				break;
     			}
+    			$zeroSugar0 = true;
     		}
     		break $zeroSugar1;
     	}
@@ -0,0 +1,79 @@
+use insta::assert_snapshot;
+
+use zero_sugar::transform_code_with_options;
+use zero_sugar::HelperMode;
+use zero_sugar::TransformOptions;
+
+// One line per rule: the rule, the first line of the input it rewrote and the first line of what it became
+fn trace(source: &str, options: TransformOptions) -> String {
+    let result = transform_code_with_options(source, &options).unwrap();
+    assert!(!result.had_error, "Unexpected errors: {:?}", result.errors);
+    result.trace.iter().map(|entry| {
+        let original = &source[entry.start as usize..entry.end as usize];
+        let generated = match (entry.generated_start, entry.generated_end) {
+            (Some(start), Some(end)) => result.transformed_code[start as usize..end as usize].lines().next().unwrap_or("").to_string(),
+            _ => "?".to_string(),
+        };
+        format!("{} `{}` -> `{}`", entry.rule, original.lines().next().unwrap_or(""), generated)
+    }).collect::<Vec<_>>().join("\n")
+}
+
+fn default_options() -> TransformOptions {
+    TransformOptions { helpers: HelperMode::None, ..TransformOptions::default() }
+}
+
+#[test]
+fn test_trace_nothing_to_do() {
+    let result = trace(r#"
+        while (x) f();
+    "#, default_options());
+
+    assert_snapshot!(result, @"");
+}
+
+#[test]
+fn test_trace_order_is_application_order() {
+    let result = trace(r#"
+        for (let i = 0; i < 2; i++) {
+            do { if (a) continue; } while (b);
+        }
+        let {x, y: [z]} = obj;
+    "#, default_options());
+
+    assert_snapshot!(result, @r#"
+    continue `continue;` -> `break $zeroSugar0;`
+    do-while `do { if (a) continue; } while (b);` -> `let $zeroSugar1 = true;`
+    for-loop `for (let i = 0; i < 2; i++) {` -> `{`
+    var-decl-object-pattern `{x, y: [z]}` -> `let x = obj.x;`
+    var-decl-array-pattern `[z]` -> `?`
+    "#);
+}
+
+#[test]
+fn test_trace_finally_and_switch() {
+    let result = trace(r#"
+        try { a(); } finally { b(); }
+        try { a(); } catch { c(); } finally { b(); }
+        switch (x) { case 1: f(); }
+    "#, default_options());
+
+    assert_snapshot!(result, @r#"
+    try-finally `try { a(); } finally { b(); }` -> `let $zeroSugar0 = 0;`
+    try-catch-finally `try { a(); } catch { c(); } finally { b(); }` -> `let $zeroSugar3 = 0;`
+    switch `switch (x) { case 1: f(); }` -> `let $zeroSugar8 = 1;`
+    "#);
+}
+
+#[test]
+fn test_trace_skips_disabled_and_bailed_transforms() {
+    let options = TransformOptions { do_while: false, ..default_options() };
+    let result = transform_code_with_options(r#"
+        do { f(); } while (x);
+        for (const a of b) g(a);
+        switch (x) { case 1: class Y {} }
+    "#, &options).unwrap();
+
+    assert_eq!(result.rule_count("do-while"), 0);
+    assert_eq!(result.rule_count("for-of"), 1);
+    assert_eq!(result.rule_count("switch"), 0);
+}
@@ -0,0 +1,154 @@
+use insta::assert_snapshot;
+
+use oxc_allocator::Vec as OxcVec;
+use oxc_ast::ast::*;
+
+use zero_sugar::mapper::{Mapper, MapperAction};
+use zero_sugar::passes::{FOR_OF_PASS, SWITCH_PASS};
+use zero_sugar::trace::RuleId;
+use zero_sugar::transforms::builder::{create_identifier_expression, create_member_expression, create_string_literal};
+use zero_sugar::validator::Construct;
+use zero_sugar::{Transform, TransformOptions, TransformResult, Transformer};
+
+/// Rewrites `LOG(x)` to `console.log("<prefix> #<n>", x)`, where n counts the calls
+struct LogMacro {
+    prefix: String,
+}
+
+#[derive(Default)]
+struct LogCount(usize);
+
+impl Transform for LogMacro {
+    fn name(&self) -> &str {
+        "log-macro"
+    }
+
+    fn install<'a>(&self, mapper: &mut Mapper<'a>) {
+        let prefix = self.prefix.clone();
+        let state = mapper.state.clone();
+        mapper.add_visitor_expr(move |expr, alloc, before: bool| {
+            let is_log = matches!(&expr, Expression::CallExpression(call) if matches!(&call.callee, Expression::Identifier(ident) if ident.name == "LOG"));
+            if before || !is_log {
+                return (MapperAction::Normal, expr);
+            }
+            let Expression::CallExpression(mut call) = expr else { unreachable!("checked above") };
+            let mut state = state.borrow_mut();
+            state.record_rule(RuleId::Custom("log-macro"), call.span);
+            let count = state.extension_mut::<LogCount>();
+            count.0 += 1;
+
+            let span = call.span;
+            call.callee = create_member_expression(alloc, create_identifier_expression(alloc, "console".to_string(), span), "log".to_string(), span);
+            let mut arguments = OxcVec::new_in(alloc);
+            arguments.push(Argument::Expression(create_string_literal(alloc, format!("{} #{}", prefix, count.0), span)));
+            arguments.extend(call.arguments.drain(..));
+            call.arguments = arguments;
+            (MapperAction::Normal, Expression::CallExpression(call))
+        });
+    }
+}
+
+/// Needs every `switch` to be gone, like a transform that only knows about `if`
+struct AfterSwitch;
+
+impl Transform for AfterSwitch {
+    fn name(&self) -> &str {
+        "after-switch"
+    }
+
+    fn requires_absent(&self) -> &[Construct] {
+        &[Construct::Switch]
+    }
+
+    fn install<'a>(&self, _mapper: &mut Mapper<'a>) {}
+}
+
+fn result_of(result: TransformResult) -> String {
+    let rules: Vec<String> = result.trace.iter().map(|entry| entry.rule.clone()).collect();
+    let errors: Vec<String> = result.errors.iter().map(|error| format!("{} {}", error.code, error.message)).collect();
+    format!("{}---\nrules: {}\nerrors:\n{}", result.transformed_code, rules.join(", "), errors.join("\n"))
+}
+
+#[test]
+fn test_custom_transform_runs_with_the_builtins() {
+    let mut options = TransformOptions::new();
+    options.helpers = zero_sugar::HelperMode::None;
+    let mut transformer = Transformer::new(&options);
+    transformer.add_to_last_traversal(LogMacro { prefix: "[app]".to_string() });
+
+    let names: Vec<&str> = transformer.passes().transforms().map(|transform| transform.name()).collect();
+    assert_eq!(names, vec!("finally", "continue", "do-while", "for-loop", "for-in", "for-of", "var-decl", "switch", "log-macro"));
+
+    let result = transformer.transform_source(r#"
+switch (x) {
+  case 1: LOG(a); break;
+  default: for (const y of z) LOG(y, 2);
+}
+"#, "input.js");
+
+    assert_snapshot!(result_of(result), @r#"
+    $zeroSugar2:{
+    	let $zeroSugar0;
+    	let $zeroSugar1;
+    	let $zeroSugar4 = 2;
+    	if ($zeroSugar4 === 1) 	$zeroSugar4 = 0;
+     else 
+    		$zeroSugar4 = 1;
+    	if ($zeroSugar4 <= 0) {
+    		console.log('[app] #1', a);
+    		break $zeroSugar2;
+    	}
+    	if ($zeroSugar4 <= 1) {
+    		$zeroSugar0 = $forOf(z);
+    		$zeroSugar1 = undefined;
+    		while($zeroSugar1 = $zeroSugar0.next())		{
+    			if ($zeroSugar1.done === true) 			break;
+
+    			const y = $zeroSugar1.value;
+    			console.log('[app] #2', y, 2);
+    		}
+    	}
+    }
+    ---
+    rules: for-of, log-macro, log-macro, switch
+    errors:
+    "#);
+}
+
+#[test]
+fn test_custom_transform_order_is_validated() {
+    let mut transformer = Transformer::empty(&TransformOptions::none());
+    transformer.add(AfterSwitch);
+    transformer.add(SWITCH_PASS);
+    transformer.add(FOR_OF_PASS);
+    transformer.add_to_last_traversal(LogMacro { prefix: "".to_string() });
+    assert_snapshot!(result_of(transformer.transform_source("switch (x) { default: LOG(); }", "input.js")), @r#"
+    switch(x){
+    	default:
+    		LOG();
+    }
+    ---
+    rules: 
+    errors:
+    ZS010 `switch` statement must be gone before the `after-switch` pass so that must run after the `switch` pass
+    "#);
+
+    let mut transformer = Transformer::empty(&TransformOptions::none());
+    transformer.add(SWITCH_PASS);
+    transformer.add_to_last_traversal(AfterSwitch);
+    assert_snapshot!(result_of(transformer.transform_source("switch (x) { default: LOG(); }", "input.js")), @r#"
+    switch(x){
+    	default:
+    		LOG();
+    }
+    ---
+    rules: 
+    errors:
+    ZS010 `switch` statement must be gone before the `after-switch` pass so that can't share a traversal with the `switch` pass
+    "#);
+
+    let mut transformer = Transformer::empty(&TransformOptions::none());
+    transformer.add(SWITCH_PASS);
+    transformer.add(AfterSwitch);
+    assert!(transformer.passes().validate().is_empty());
+}
@@ -0,0 +1,157 @@
+use insta::assert_snapshot;
+
+use zero_sugar::transform_code_with_options;
+use zero_sugar::validate_code;
+use zero_sugar::Profile;
+use zero_sugar::TransformError;
+use zero_sugar::TransformOptions;
+
+fn format_errors(errors: &[TransformError]) -> String {
+    errors.iter().map(|error| format!("{}:{}: {} {}", error.line, error.column, error.code, error.message)).collect::<Vec<_>>().join("\n")
+}
+
+fn validate(source: &str, profile: Profile) -> String {
+    format_errors(&validate_code(source, &TransformOptions::default(), &profile))
+}
+
+fn transform_and_validate(source: &str, options: TransformOptions) -> String {
+    let result = transform_code_with_options(source, &TransformOptions { validate: true, ..options }).unwrap();
+    format_errors(&result.errors)
+}
+
+#[test]
+fn test_validator_reports_every_violation() {
+    let result = validate(r#"
+        switch (x) { case 1: f(); }
+        for (let i = 0; i < n; i++) if (i) continue;
+        for (const k in o) for (const v of k) f(v);
+        do { try { f(); } finally { g(); } } while (x);
+        let a = 1, {b, c: [d]} = e;
+        function h() { let [z] = y; }
+        export const {w} = v;
+        export function g() { for (;;); }
+    "#, Profile::js0());
+
+    assert_snapshot!(result, @r#"
+    2:9: ZS006 `switch` statement is not allowed
+    3:9: ZS006 `for` loop is not allowed
+    3:44: ZS006 `continue` statement is not allowed
+    4:9: ZS006 `for-in` loop is not allowed
+    4:28: ZS006 `for-of` loop is not allowed
+    5:9: ZS006 `do-while` loop is not allowed
+    5:35: ZS006 `finally` block is not allowed
+    6:20: ZS006 Destructuring pattern in a variable declaration is not allowed
+    7:28: ZS006 Destructuring pattern in a variable declaration is not allowed
+    8:22: ZS006 Destructuring pattern in a variable declaration is not allowed
+    9:31: ZS006 `for` loop is not allowed
+    "#);
+}
+
+#[test]
+fn test_validator_already_js0() {
+    let result = validate(r#"
+        let i = 0;
+        while (i < n) {
+            try { f(i); } catch (e) {}
+            i++;
+        }
+    "#, Profile::js0());
+
+    assert_snapshot!(result, @"");
+}
+
+#[test]
+fn test_validator_profile_allows() {
+    let result = validate(r#"
+        switch (x) { case 1: f(); }
+        for (;;) continue;
+    "#, Profile { switch: true, continue_stmt: true, ..Profile::js0() });
+
+    assert_snapshot!(result, @r#"
+    3:9: ZS006 `for` loop is not allowed
+    "#);
+}
+
+#[test]
+fn test_validator_looks_inside_parens_and_templates() {
+    let result = validate(r#"
+        (function () { switch (x) {} })();
+        f(() => {}, `${(() => { do f(); while (x); })()}`);
+    "#, Profile::js0());
+
+    assert_snapshot!(result, @r#"
+    2:24: ZS006 `switch` statement is not allowed
+    3:33: ZS006 `do-while` loop is not allowed
+    "#);
+}
+
+#[test]
+fn test_validator_looks_inside_decorators_and_extends() {
+    let result = validate(r#"
+        @dec(() => { for (;;) {} }) class B {}
+        class C extends (() => { do f(); while (x); })() {
+            @dec(function () { switch (x) {} }) method() {}
+        }
+    "#, Profile::js0());
+
+    assert_snapshot!(result, @r#"
+    2:22: ZS006 `for` loop is not allowed
+    3:34: ZS006 `do-while` loop is not allowed
+    4:32: ZS006 `switch` statement is not allowed
+    "#);
+}
+
+#[test]
+fn test_validator_parse_error() {
+    let result = validate(r#"
+        let x = ;
+    "#, Profile::js0());
+
+    assert_snapshot!(result, @r#"
+    2:17: ZS001 Unexpected token
+    "#);
+}
+
+#[test]
+fn test_validator_label_errors() {
+    // These parse, but an engine rejects them before running anything
+    let result = validate(r#"
+        outer: { while (x) { continue outer; } }
+        while (x) { (() => { break; })(); }
+        a: while (x) { a: for (;;) break a; }
+        b: while (x) { function f() { continue b; } }
+        c: d: for (;;) { if (x) continue c; else break d; }
+        switch (x) { case 1: break; }
+    "#, Profile::any());
+
+    assert_snapshot!(result, @r#"
+    2:30: ZS001 `continue outer` targets a label that is not on a loop
+    3:30: ZS001 `break` is not inside a loop or `switch`
+    4:24: ZS001 Label `a` is already declared around this one
+    5:39: ZS001 `continue b` is not inside a loop with that label
+    "#);
+}
+
+#[test]
+fn test_validator_post_check_clean() {
+    // The helpers use for-in and for-of but those are not checked
+    let result = transform_and_validate(r#"
+        for (const x of y) { if (x) continue; f(x); }
+        let {a, ...b} = c;
+    "#, TransformOptions::default());
+
+    assert_snapshot!(result, @"");
+}
+
+#[test]
+fn test_validator_post_check_leftovers() {
+    let result = transform_and_validate(r#"
+        switch (x) { case 1: class A {} }
+        do { f(); } while (x);
+    "#, TransformOptions { do_while: false, ..TransformOptions::default() });
+
+    assert_snapshot!(result, @r#"
+    2:30: ZS003 Class declarations in the toplevel of a switch case are not supported
+    2:9: ZS006 `switch` statement is not allowed
+    "#);
+}
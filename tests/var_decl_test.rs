@@ -1,13 +1,33 @@
 use insta::assert_snapshot;
 
-use zero_sugar::transform_code;
+use oxc_allocator::Allocator;
+use oxc_allocator::Box as OxcBox;
+use oxc_ast::ast::*;
+use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_parser::Parser;
+use oxc_span::{Atom, GetSpan, SourceType};
+
+use zero_sugar::mapper_state::MapperState;
+use zero_sugar::transforms::stmt_var_decl::transform_var_decl_statement;
+use zero_sugar::transform_code_with_options;
+use zero_sugar::HelperMode;
+use zero_sugar::TransformOptions;
 
 fn parse_and_map(source: &str) -> String {
-    // Must use `transform_code` because the var decl transform has two steps
-    let transformed_code = transform_code(source);
+    // Must use `transform_code` because the var decl transform has two steps.
+    // The `$rest` helper is tested in helpers_test.rs, leave it out here.
+    let options = TransformOptions { helpers: HelperMode::None, ..TransformOptions::default() };
+    let transformed_code = transform_code_with_options(source, &options);
     transformed_code.unwrap().transformed_code
 }
 
+fn parse_and_map_with_errors(source: &str) -> String {
+    let options = TransformOptions { helpers: HelperMode::None, ..TransformOptions::default() };
+    let result = transform_code_with_options(source, &options).unwrap();
+    let errors: Vec<String> = result.errors.iter().map(|error| format!("{} {}:{} {}", error.code, error.line, error.column, error.message)).collect();
+    format!("{}\n---\n{}", errors.join("\n"), result.transformed_code)
+}
+
 #[test]
 fn test_basic_var_decl() {
     let result = parse_and_map(r#"
@@ -301,10 +321,12 @@ fn test_var_decl_func_decl() {
     assert_snapshot!(result, @r#"
     {
     	let x = function() {
-    		var a = 1, b = 2;
+    		var a = 1;
+    		var b = 2;
     	};
     	let y = function() {
-    		var c = 3, d = 4;
+    		var c = 3;
+    		var d = 4;
     	};
     }
     "#);
@@ -323,15 +345,14 @@ fn test_complex_func_let_decl() {
 
     assert_snapshot!(result, @r#"
     function example() {
-    	let a = 1, [b] = window;
-    	{
-    		let $zeroSugar0 = true;
-    		while($zeroSugar0)		{
-    			{
-    				console.log('hello', a, b);
-    			}
-    			$zeroSugar0 = true;
+    	let a = 1;
+    	let b = window[0];
+    	let $zeroSugar0 = true;
+    	while($zeroSugar0)	{
+    		{
+    			console.log('hello', a, b);
     		}
+    		$zeroSugar0 = true;
     	}
     }
     "#);
@@ -349,14 +370,12 @@ fn test_complex_let_decl() {
     assert_snapshot!(result, @r#"
     let a = 1;
     let b = window[0];
-    {
-    	let $zeroSugar0 = true;
-    	while($zeroSugar0)	{
-    		{
-    			console.log('hello', a, b);
-    		}
-    		$zeroSugar0 = true;
+    let $zeroSugar0 = true;
+    while($zeroSugar0){
+    	{
+    		console.log('hello', a, b);
     	}
+    	$zeroSugar0 = true;
     }
     "#);
 }
@@ -697,3 +716,58 @@ fn test_arr_pattern_rest_with_pattern_complex() {
     let b = $zeroSugar0.length;
     "#);
 }
+
+#[test]
+fn test_arr_pattern_rest_with_arr_pattern_is_reported() {
+    let result = parse_and_map_with_errors(r#"
+        let [a, ...[b, c]] = arr;
+        let {x: [...[y]]} = obj;
+    "#);
+
+    assert_snapshot!(result, @r#"
+    ZS003 2:17 Array pattern as the rest of an array pattern in a var declarator is not supported
+    ZS003 3:18 Array pattern as the rest of an array pattern in a var declarator is not supported
+    ---
+    let [a,...[b,c]] = arr;
+    let {x:[...[y]]} = obj;
+    "#);
+}
+
+#[test]
+fn test_arr_pattern_rest_with_default_is_reported() {
+    // This is a syntax error but the parser lets it through
+    let result = parse_and_map_with_errors(r#"
+        let [a, ...b = 1] = arr;
+    "#);
+
+    assert_snapshot!(result, @r#"
+    ZS003 2:17 Default on the rest of an array pattern in a var declarator is not supported
+    ---
+    let [a,...b=1] = arr;
+    "#);
+}
+
+#[test]
+fn test_obj_pattern_private_key_is_reported() {
+    // The parser rejects `let {#a: b} = obj` so put the private name in by hand
+    let allocator = Allocator::default();
+    let source = "let {a: b} = obj;";
+    let mut program = Parser::new(&allocator, source, SourceType::default().with_module(true)).parse().program;
+    let Some(Statement::Declaration(Declaration::VariableDeclaration(var_decl))) = program.body.first_mut() else { panic!("Expected a var decl") };
+    let BindingPatternKind::ObjectPattern(object_pattern) = &mut var_decl.declarations.first_mut().unwrap().id.kind else { panic!("Expected an object pattern") };
+    let key = &mut object_pattern.properties.first_mut().unwrap().key;
+    *key = PropertyKey::PrivateIdentifier(OxcBox(allocator.alloc(PrivateIdentifier { name: Atom::from("a"), span: key.span() })));
+
+    let mut state = MapperState::new();
+    let stmt = program.body.pop().unwrap();
+    let (_action, stmt) = transform_var_decl_statement(stmt, &allocator, &mut state);
+    program.body.push(stmt);
+
+    let codegen: Codegen<false> = Codegen::new(source.len(), CodegenOptions);
+    let diagnostics: Vec<String> = state.diagnostics.iter().map(|diagnostic| format!("{} {}", diagnostic.code.as_str(), diagnostic.message)).collect();
+    assert_snapshot!(format!("{}\n---\n{}", diagnostics.join("\n"), codegen.build(&program)), @r#"
+    ZS003 Private name as a key in a var declarator pattern is not supported
+    ---
+    let {#a:b} = obj;
+    "#);
+}
@@ -30,7 +30,7 @@ fn parse_and_map(source: &str) -> String {
     });
 
     let transformed = mapper.map(parsed.program);
-    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions::default());
+    let codegen: Codegen<false> = Codegen::new(transformed.span.end as usize, CodegenOptions);
 
     let out = codegen.build(&transformed);
 
@@ -57,15 +57,13 @@ fn test_do_while_loop() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar0 = true;
-    	while($zeroSugar0)	{
-    		{
-    			console.log(x);
-    			x++;
-    		}
-    		$zeroSugar0 = x;
+    let $zeroSugar0 = true;
+    while($zeroSugar0){
+    	{
+    		console.log(x);
+    		x++;
     	}
+    	$zeroSugar0 = x;
     }
     "#);
 }
@@ -80,15 +78,13 @@ fn test_non_ident_test() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar0 = true;
-    	while($zeroSugar0)	{
-    		{
-    			console.log(x);
-    			x++;
-    		}
-    		$zeroSugar0 = 'infinite';
+    let $zeroSugar0 = true;
+    while($zeroSugar0){
+    	{
+    		console.log(x);
+    		x++;
     	}
+    	$zeroSugar0 = 'infinite';
     }
     "#);
 }
@@ -103,15 +99,13 @@ fn test_binexpr_test() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar0 = true;
-    	while($zeroSugar0)	{
-    		{
-    			console.log(x);
-    			x++;
-    		}
-    		$zeroSugar0 = 1 + 1;
+    let $zeroSugar0 = true;
+    while($zeroSugar0){
+    	{
+    		console.log(x);
+    		x++;
     	}
+    	$zeroSugar0 = 1 + 1;
     }
     "#);
 }
@@ -125,12 +119,10 @@ fn test_not_block_body() {
     "#);
 
     assert_snapshot!(result, @r#"
-    {
-    	let $zeroSugar0 = true;
-    	while($zeroSugar0)	{
-    		console.log(x);
-    		$zeroSugar0 = x < 5;
-    	}
+    let $zeroSugar0 = true;
+    while($zeroSugar0){
+    	console.log(x);
+    	$zeroSugar0 = x < 5;
     }
     "#);
 }